@@ -0,0 +1,37 @@
+#![feature(generic_const_exprs)]
+#![allow(incomplete_features)]
+use ferrum_hdl::prelude::*;
+
+pub fn accumulator<D: ClockDomain>(
+    clk: Clock<D>,
+    rst: Reset<D>,
+    x: Signal<D, U<8>>,
+) -> Signal<D, U<8>> {
+    x.and_then(|x| reg0(&clk, &rst, move |acc: U<8>| acc + x.value()))
+}
+
+// Per-cycle outputs of the behavioral model, one value per line, which can be
+// compared with the outputs of the synthesized verilog dumped by a simulator
+pub fn model_outputs<D: ClockDomain>(inputs: &[u8]) -> Vec<String> {
+    let clk = Clock::<D>::new();
+    let xs = inputs.iter().copied().map(Cast::cast::<U<8>>);
+
+    eval_inputs(&clk, (xs,), |(x,)| {
+        accumulator(clk.clone(), Reset::reset(), x).boxed()
+    })
+    .map(|acc| acc.to_string())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs() {
+        // the register sees the input at the next cycle and wraps at 8 bits
+        assert_eq!(model_outputs::<TD4>(&[1, 2, 3, 250, 10]), [
+            "0", "1", "3", "6", "0"
+        ]);
+    }
+}
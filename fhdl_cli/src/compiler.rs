@@ -1,4 +1,7 @@
-use std::io::{self, IsTerminal};
+use std::{
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
 
 use clap::{Args, ValueEnum};
 use fhdl_netlist::cfg::NetListCfg;
@@ -18,10 +21,71 @@ pub struct CompilerArgs {
     /// Dump MIR
     #[arg(long)]
     pub dump_mir: bool,
+    /// Directory of the generated verilog file (default: synth/verilog in the crate root)
+    #[arg(long)]
+    pub out_dir: Option<PathBuf>,
+    /// Name of the generated verilog file without extension (default: top)
+    #[arg(long)]
+    pub out_name: Option<String>,
+    /// Value of a const generic parameter of the top module, e.g. `WIDTH=8`. Values
+    /// without names are assigned to the parameters in declaration order
+    #[arg(long = "top-param", value_name = "[NAME=]VALUE", value_parser = parse_top_param)]
+    #[serde(default)]
+    pub top_params: Vec<(Option<String>, u128)>,
+    /// Generate a simulation wrapper `wrapper.sv` (`<out-name>_wrapper.sv` if the name is
+    /// set) next to the verilog file
+    #[arg(long)]
+    #[serde(default)]
+    pub wrapper: bool,
     #[command(flatten)]
     pub netlist: NetListCfg,
 }
 
+impl CompilerArgs {
+    // Relative output directories are resolved against the crate root
+    pub fn verilog_path(&self, root_dir: &Path) -> PathBuf {
+        let out_dir = match &self.out_dir {
+            Some(out_dir) => root_dir.join(out_dir),
+            None => root_dir.join("synth").join("verilog"),
+        };
+
+        let mut path = out_dir.join(self.out_name.as_deref().unwrap_or("top"));
+        path.set_extension("v");
+        path
+    }
+
+    pub fn wrapper_path(&self, root_dir: &Path) -> PathBuf {
+        let name = match &self.out_name {
+            Some(out_name) => format!("{out_name}_wrapper.sv"),
+            None => "wrapper.sv".to_string(),
+        };
+        self.verilog_path(root_dir).with_file_name(name)
+    }
+
+    // The manifest is put into the output directory (default: synth in the crate root)
+    pub fn manifest_path(&self, root_dir: &Path) -> PathBuf {
+        let out_dir = match &self.out_dir {
+            Some(out_dir) => root_dir.join(out_dir),
+            None => root_dir.join("synth"),
+        };
+
+        out_dir.join("manifest.json")
+    }
+}
+
+fn parse_top_param(param: &str) -> Result<(Option<String>, u128), String> {
+    let (name, value) = match param.split_once('=') {
+        Some((name, value)) => (Some(name.trim().to_string()), value),
+        None => (None, param),
+    };
+    let value = value.trim().parse().map_err(|_| match &name {
+        Some(name) => format!("invalid value of parameter `{name}`: `{value}`"),
+        None => format!("invalid parameter value: `{value}`"),
+    })?;
+
+    Ok((name, value))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
 pub enum Color {
     Never,
@@ -38,3 +102,103 @@ impl Color {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: CompilerArgs,
+    }
+
+    fn verilog_path(cli_args: &[&str]) -> PathBuf {
+        let cli = Cli::parse_from(iter::once("fhdl").chain(cli_args.iter().copied()));
+        cli.args.verilog_path(Path::new("/project"))
+    }
+
+    #[test]
+    fn out_dir_and_name() {
+        assert_eq!(verilog_path(&[]), Path::new("/project/synth/verilog/top.v"));
+        assert_eq!(
+            verilog_path(&["--out-dir", "gen", "--out-name", "blinky"]),
+            Path::new("/project/gen/blinky.v")
+        );
+        assert_eq!(
+            verilog_path(&["--out-dir", "/tmp/out"]),
+            Path::new("/tmp/out/top.v")
+        );
+
+        let cli = Cli::parse_from(["fhdl", "--out-dir", "gen", "--wrapper"]);
+        assert!(cli.args.wrapper);
+        assert_eq!(
+            cli.args.wrapper_path(Path::new("/project")),
+            Path::new("/project/gen/wrapper.sv")
+        );
+        assert_eq!(
+            cli.args.manifest_path(Path::new("/project")),
+            Path::new("/project/gen/manifest.json")
+        );
+
+        let cli = Cli::parse_from(["fhdl", "--out-name", "blinky"]);
+        assert_eq!(
+            cli.args.wrapper_path(Path::new("/project")),
+            Path::new("/project/synth/verilog/blinky_wrapper.sv")
+        );
+        assert_eq!(
+            cli.args.manifest_path(Path::new("/project")),
+            Path::new("/project/synth/manifest.json")
+        );
+    }
+
+    #[test]
+    fn args_without_new_options() {
+        // the options added later take their default values
+        let args = serde_json::from_str::<CompilerArgs>(
+            r#"{
+                "color": "Never",
+                "dump_netlist": false,
+                "dump_tr_netlist": false,
+                "dump_mir": false,
+                "netlist": {
+                    "inline_mod": "Auto",
+                    "no_embed_muxs": false,
+                    "no_eliminate_const": false,
+                    "max_inlines": null
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(!args.netlist.no_reconnect_pass);
+        assert!(!args.netlist.no_fuse_splitter_merger);
+        assert!(!args.netlist.no_simplify_dff);
+        assert!(!args.netlist.clock_gating);
+        assert!(!args.netlist.preserve_names);
+        assert!(args.top_params.is_empty());
+    }
+
+    #[test]
+    fn top_params() {
+        let cli =
+            Cli::parse_from(["fhdl", "--top-param", "WIDTH=8", "--top-param", "N = 2"]);
+        assert_eq!(cli.args.top_params, [
+            (Some("WIDTH".to_string()), 8),
+            (Some("N".to_string()), 2)
+        ]);
+
+        let cli = Cli::parse_from(["fhdl", "--top-param", "8", "--top-param", "2"]);
+        assert_eq!(cli.args.top_params, [(None, 8), (None, 2)]);
+
+        let cli = Cli::parse_from(["fhdl"]);
+        assert!(cli.args.top_params.is_empty());
+
+        assert!(Cli::try_parse_from(["fhdl", "--top-param", "WIDTH"]).is_err());
+        assert!(Cli::try_parse_from(["fhdl", "--top-param", "WIDTH=x"]).is_err());
+    }
+}
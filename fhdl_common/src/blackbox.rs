@@ -6,10 +6,37 @@ pub enum BlackboxKind {
     ArrayMakeIdx,
     ArrayMap,
     ArrayMapIdx,
+    ArrayRotateLeft,
+    ArrayRotateRight,
+    ArrayPosition,
+    ArraySplitFirst,
+    ArraySplitLast,
+    ArrayAll,
+    ArrayAny,
+    ArrayFromIterExact,
 
     BitPackPack,
     BitPackUnpack,
 
+    BitVecMask,
+    BitVecInterleave,
+    BitVecDeinterleave,
+    BitVecToLeBytes,
+    BitVecToBeBytes,
+    BitVecFromLeBytes,
+    BitVecFromBeBytes,
+    BitVecParity,
+    BitVecCrcStep,
+
+    UnsignedClog2,
+    UnsignedRotateThroughCarry,
+    UnsignedAbsDiff,
+    UnsignedPow,
+
+    BarrelShift,
+
+    ClockGate,
+
     Bundle,
     Unbundle,
 
@@ -19,6 +46,7 @@ pub enum BlackboxKind {
     OpLe,
     OpGt,
     OpGe,
+    OpCmp,
     OpBitAnd,
     OpBitOr,
     OpBitXor,
@@ -43,16 +71,27 @@ pub enum BlackboxKind {
 
     SignalAndThen,
     SignalApply2,
+    SignalConstant,
+    SignalCrossDomain,
     SignalDff,
     SignalDffComb,
+    SignalDffFull,
     SignalMap,
     SignalValue,
     IntoSignal,
 
     StdClone,
+    StdIdentity,
     StdIntoIter,
     StdIterEnum,
+    StdIterFind,
+    StdIterMap,
     StdIterNext,
+    StdIterNonStaticLen,
+    StdIterPosition,
+    StdIterRepeat,
+    StdIterTake,
+    StdOptionMap,
 }
 
 #[derive(Display, Debug, Clone, Copy, EnumString, PartialEq, Eq, Hash)]
@@ -8,4 +8,7 @@ pub enum LangItem {
     DomFreq,
     DomRstKind,
     DomRstPol,
+    BitSize,
+    BitSizeBits,
+    AxiStream,
 }
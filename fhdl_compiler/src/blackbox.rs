@@ -4,6 +4,7 @@ pub mod bitpack;
 pub mod bitvec;
 pub mod cast;
 pub mod loop_gen;
+pub mod option;
 pub mod reg;
 pub mod signal;
 pub mod un_op;
@@ -89,10 +90,37 @@ eval_expr!(
     ArrayMakeIdx => array::Make { with_idx: true },
     ArrayMap => array::Map { with_idx: false },
     ArrayMapIdx => array::Map { with_idx: true },
+    ArrayRotateLeft => array::Rotate { left: true },
+    ArrayRotateRight => array::Rotate { left: false },
+    ArrayPosition => array::Position,
+    ArraySplitFirst => array::Split { first: true },
+    ArraySplitLast => array::Split { first: false },
+    ArrayAll => array::Reduce { all: true },
+    ArrayAny => array::Reduce { all: false },
+    ArrayFromIterExact => array::FromIterExact,
 
     BitPackPack => bitpack::Pack,
     BitPackUnpack => bitpack::Unpack,
 
+    BitVecMask => bitvec::Mask,
+    BitVecInterleave => bitvec::Interleave,
+    BitVecDeinterleave => bitvec::Deinterleave,
+    BitVecToLeBytes => bitvec::ToBytes { le: true },
+    BitVecToBeBytes => bitvec::ToBytes { le: false },
+    BitVecFromLeBytes => bitvec::FromBytes { le: true },
+    BitVecFromBeBytes => bitvec::FromBytes { le: false },
+    BitVecParity => bitvec::Parity,
+    BitVecCrcStep => bitvec::CrcStep,
+
+    UnsignedClog2 => bitvec::Clog2,
+    UnsignedRotateThroughCarry => bitvec::RotateThroughCarry,
+    UnsignedAbsDiff => bin_op::AbsDiff,
+    UnsignedPow => bin_op::Pow,
+
+    BarrelShift => bitvec::BarrelShift,
+
+    ClockGate => reg::ClockGate,
+
     Bundle => PassReceiver,
     Unbundle => PassReceiver,
 
@@ -102,6 +130,7 @@ eval_expr!(
     OpLe => bin_op::BinOp(BinOp::Le),
     OpGt => bin_op::BinOp(BinOp::Gt),
     OpGe => bin_op::BinOp(BinOp::Ge),
+    OpCmp => bin_op::Cmp,
     OpBitAnd => bin_op::BinOp(BinOp::BitAnd),
     OpBitOr => bin_op::BinOp(BinOp::BitOr),
     OpBitXor => bin_op::BinOp(BinOp::BitXor),
@@ -126,14 +155,26 @@ eval_expr!(
 
     SignalAndThen => signal::AndThen,
     SignalApply2 => signal::Apply2,
+    // the value is passed as is, so a constant stays foldable
+    SignalConstant => PassReceiver,
+    SignalCrossDomain => PassReceiver,
     SignalMap => signal::Map,
-    SignalDff => signal::SignalDff { comb: false },
-    SignalDffComb => signal::SignalDff { comb: true },
+    SignalDff => signal::SignalDff { comb: false, rst_val: false },
+    SignalDffComb => signal::SignalDff { comb: true, rst_val: false },
+    SignalDffFull => signal::SignalDff { comb: false, rst_val: true },
     SignalValue => PassReceiver,
     IntoSignal => PassReceiver,
 
     StdClone => PassReceiver,
+    StdIdentity => PassReceiver,
     StdIntoIter => loop_gen::IntoIter,
     StdIterEnum => loop_gen::IterEnum,
+    StdIterFind => loop_gen::IterPosition { find: true },
+    StdIterMap => loop_gen::IterMap,
     StdIterNext => loop_gen::IterNext,
+    StdIterNonStaticLen => loop_gen::IterNonStaticLen,
+    StdIterPosition => loop_gen::IterPosition { find: false },
+    StdIterRepeat => loop_gen::IterRepeat,
+    StdIterTake => loop_gen::IterTake,
+    StdOptionMap => option::Map,
 );
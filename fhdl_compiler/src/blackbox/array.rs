@@ -1,13 +1,24 @@
+use std::iter;
+
+use fhdl_data_structures::graph::Port;
+use fhdl_netlist::{
+    const_val::ConstVal,
+    netlist::Module,
+    node::{BinOp, BinOpArgs, BinOpNode, Switch, SwitchArgs},
+    node_ty::NodeTy,
+};
 use rustc_middle::ty::{List, Ty};
 use rustc_span::Span;
+use rustc_target::abi::VariantIdx;
 
 use super::{args, EvalExpr};
 use crate::{
     compiler::{
-        item::{Group, Item, ModuleExt},
-        Compiler, Context,
+        item::{Group, Item, ItemKind, ModuleExt},
+        item_ty::ItemTy,
+        Compiler, Context, SymIdent,
     },
-    error::Error,
+    error::{Error, SpanError, SpanErrorKind},
 };
 
 pub struct Make {
@@ -50,6 +61,43 @@ impl<'tcx> EvalExpr<'tcx> for Make {
     }
 }
 
+pub struct FromIterExact;
+
+impl<'tcx> EvalExpr<'tcx> for FromIterExact {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        _: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as iter);
+
+        let array_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let count = array_ty.array_ty().count() as usize;
+
+        let items = match iter.loop_gen_opt() {
+            Some(loop_gen) => loop_gen.take_items(count + 1),
+            None => match &iter.kind {
+                ItemKind::Group(group) => group.items().to_vec(),
+                _ => {
+                    return Err(SpanError::new(SpanErrorKind::NotSynthExpr, span).into());
+                }
+            },
+        };
+        if items.len() != count {
+            return Err(SpanError::new(
+                SpanErrorKind::IterLenMismatch(count as u128, items.len() as u128),
+                span,
+            )
+            .into());
+        }
+
+        Ok(Item::new(array_ty, Group::new(items)))
+    }
+}
+
 pub struct Map {
     pub with_idx: bool,
 }
@@ -90,3 +138,270 @@ impl<'tcx> EvalExpr<'tcx> for Map {
         ))
     }
 }
+
+pub struct Rotate {
+    pub left: bool,
+}
+
+impl<'tcx> EvalExpr<'tcx> for Rotate {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, k);
+
+        let array_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+
+        let items = rec.group().items().to_vec();
+        let len = items.len();
+        let rotate = |k: usize| {
+            let mut items = items.clone();
+            if self.left {
+                items.rotate_left(k % len);
+            } else {
+                items.rotate_right(k % len);
+            }
+            items
+        };
+
+        // constant rotation is just a rewiring of items
+        if len <= 1 {
+            return Ok(Item::new(array_ty, Group::new(items)));
+        }
+        if let Some(k) = ctx.module.to_const_val(k) {
+            let k = (k % len as u128) as usize;
+            return Ok(Item::new(array_ty, Group::new(rotate(k))));
+        }
+
+        // runtime rotation selects one of the `N` rewirings by `k % N`
+        let k = ctx.module.to_bitvec(k, span)?.port();
+        let k_ty = ctx.module[k].ty;
+        let len_port = ctx.module.const_val(k_ty, len as u128);
+        let sel = ctx.module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: k_ty,
+            bin_op: BinOp::Rem,
+            lhs: k,
+            rhs: len_port,
+            sym: None,
+        });
+
+        let mut variants = Vec::with_capacity(len - 1);
+        for k in 1 .. len {
+            let rotated = Item::new(array_ty, Group::new(rotate(k)));
+            let rotated = ctx.module.to_bitvec(&rotated, span)?.port();
+            variants.push((ConstVal::new(k as u128, k_ty.width()), iter::once(rotated)));
+        }
+        let default = ctx.module.to_bitvec(rec, span)?.port();
+
+        let rotated = ctx.module.add_and_get_port::<_, Switch>(SwitchArgs {
+            outputs: iter::once((array_ty.to_bitvec(), SymIdent::Mux.into())),
+            sel,
+            variants,
+            default: Some(iter::once(default)),
+        });
+
+        ctx.module.from_bitvec(rotated, array_ty, span)
+    }
+}
+
+pub struct Split {
+    pub first: bool,
+}
+
+impl<'tcx> EvalExpr<'tcx> for Split {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        _: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let struct_ty = output_ty.struct_ty();
+
+        // splitting is just a regrouping of items
+        let group = rec.group();
+        let len = group.len();
+        let items = if self.first {
+            let rest = Item::new(struct_ty.by_idx(1), group.slice(1, len - 1));
+            [group.by_idx(0), rest]
+        } else {
+            let rest = Item::new(struct_ty.by_idx(0), group.slice(0, len - 1));
+            [rest, group.by_idx(len - 1)]
+        };
+
+        Ok(Item::new(output_ty, Group::new(items)))
+    }
+}
+
+pub struct Position;
+
+impl<'tcx> EvalExpr<'tcx> for Position {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, closure);
+
+        let opt_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let idx_ty = opt_some_ty(opt_ty);
+
+        let mut candidates = vec![];
+        for (idx, item) in rec.group().items().iter().enumerate() {
+            let found =
+                compiler.instantiate_closure(closure, &[item.clone()], ctx, span)?;
+            let idx = ctx.module.const_val(idx_ty.to_bitvec(), idx as u128);
+            let idx = ctx.module.from_bitvec(idx, idx_ty, span)?;
+
+            candidates.push((found, idx));
+        }
+
+        priority_select(candidates, opt_ty, ctx, span)
+    }
+}
+
+// The type of the `Some` value of `opt_ty`
+pub fn opt_some_ty(opt_ty: ItemTy<'_>) -> ItemTy<'_> {
+    let some_ty = *opt_ty
+        .enum_ty()
+        .by_variant_idx(VariantIdx::from_usize(1))
+        .ty;
+    some_ty.struct_ty().by_idx(0)
+}
+
+// Priority encoder: returns `Some` of the value of the first candidate which is found,
+// or `None` if there is no such candidate
+pub fn priority_select<'tcx>(
+    candidates: Vec<(Item<'tcx>, Item<'tcx>)>,
+    opt_ty: ItemTy<'tcx>,
+    ctx: &mut Context<'tcx>,
+    span: Span,
+) -> Result<Item<'tcx>, Error> {
+    let (none_idx, some_idx) = (VariantIdx::from_usize(0), VariantIdx::from_usize(1));
+    let some_ty = *opt_ty.enum_ty().by_variant_idx(some_idx).ty;
+
+    let none = ctx
+        .module
+        .enum_variant_to_bitvec(None, opt_ty, none_idx, span)?;
+
+    // the lowest candidate wins, so the chain of muxes is built from the last
+    // candidate to the first one
+    let mut pos = none.port();
+    for (found, value) in candidates.into_iter().rev() {
+        let found = ctx.module.to_bitvec(&found, span)?.port();
+
+        let some = Item::new(some_ty, ItemKind::Group(Group::new([value])));
+        let some =
+            ctx.module
+                .enum_variant_to_bitvec(Some(some), opt_ty, some_idx, span)?;
+
+        pos = ctx.module.add_and_get_port::<_, Switch>(SwitchArgs {
+            outputs: iter::once((opt_ty.to_bitvec(), SymIdent::Mux.into())),
+            sel: found,
+            variants: [(ConstVal::new(1, 1), iter::once(some.port()))],
+            default: Some(iter::once(pos)),
+        });
+    }
+
+    Ok(Item::new(opt_ty, pos))
+}
+
+pub struct Reduce {
+    pub all: bool,
+}
+
+impl<'tcx> EvalExpr<'tcx> for Reduce {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, closure);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+
+        let items = rec.group().items().to_vec();
+        let mut preds = Vec::with_capacity(items.len());
+        for item in items {
+            let pred = compiler.instantiate_closure(closure, &[item], ctx, span)?;
+            preds.push(pred.port());
+        }
+
+        let bin_op = if self.all { BinOp::And } else { BinOp::Or };
+        let res = reduce_tree(&mut ctx.module, preds, bin_op, self.all);
+
+        Ok(Item::new(output_ty, res))
+    }
+}
+
+// Reduces the bits into a balanced tree of `bin_op` nodes, an empty list is reduced
+// to `empty`
+pub(super) fn reduce_tree(
+    module: &mut Module,
+    mut ports: Vec<Port>,
+    bin_op: BinOp,
+    empty: bool,
+) -> Port {
+    if ports.is_empty() {
+        return module.const_val(NodeTy::Bit, empty as u128);
+    }
+
+    while ports.len() > 1 {
+        ports = ports
+            .chunks(2)
+            .map(|chunk| match *chunk {
+                [lhs, rhs] => module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                    ty: NodeTy::Bit,
+                    bin_op,
+                    lhs,
+                    rhs,
+                    sym: None,
+                }),
+                [port] => port,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    ports[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduce_balanced_tree() {
+        let mut module = Module::new("top", true);
+        let bits = (0 .. 4)
+            .map(|idx| module.add_input(NodeTy::Bit, Some(format!("b{idx}"))))
+            .collect::<Vec<_>>();
+
+        let root = reduce_tree(&mut module, bits.clone(), BinOp::And, true);
+
+        // (b0 & b1) & (b2 & b3)
+        let children = module.incoming_iter(root.node).collect::<Vec<_>>();
+        assert_eq!(children.len(), 2);
+        for (child, leaves) in children.into_iter().zip(bits.chunks(2)) {
+            assert_eq!(module.incoming_iter(child.node).collect::<Vec<_>>(), leaves);
+        }
+        assert_eq!(module.node_count(), 4 + 3);
+
+        let empty = reduce_tree(&mut module, vec![], BinOp::Or, false);
+        assert_eq!(module.to_const(empty), Some(ConstVal::new(0, 1)));
+    }
+}
@@ -1,4 +1,16 @@
-use fhdl_netlist::node::{BinOp as NodeBinOp, BinOpArgs, BinOpNode};
+use std::{cmp::Ordering, iter};
+
+use fhdl_data_structures::graph::Port;
+use fhdl_netlist::{
+    cfg::ConstOverflow,
+    const_val::ConstVal,
+    netlist::Module,
+    node::{
+        BinOp as NodeBinOp, BinOpArgs, BinOpNode, Merger, MergerArgs, Reduce, ReduceArgs,
+        ReduceOp, Splitter, SplitterArgs, Switch, SwitchArgs,
+    },
+    node_ty::NodeTy,
+};
 use rustc_middle::{mir::BinOp as MirBinOp, ty::Ty};
 use rustc_span::Span;
 
@@ -6,8 +18,8 @@ use super::{args, cast::CastFrom, EvalExpr};
 use crate::{
     compiler::{
         item::{Item, ModuleExt},
-        item_ty::ItemTy,
-        Compiler, Context,
+        item_ty::{ItemTy, ItemTyKind},
+        Compiler, Context, SymIdent,
     },
     error::{Error, SpanError, SpanErrorKind},
 };
@@ -51,6 +63,7 @@ impl BinOp {
         lhs: &Item<'tcx>,
         rhs: &Item<'tcx>,
         output_ty: ItemTy<'tcx>,
+        overflow: ConstOverflow,
         ctx: &mut Context<'tcx>,
         span: Span,
     ) -> Result<Item<'tcx>, Error> {
@@ -75,8 +88,33 @@ impl BinOp {
         let rhs = subnode(rhs, rhs.ty)?;
         let bin_op = self.0;
 
-        if let (Some(&lhs), Some(&rhs)) = (lhs.const_opt(), rhs.const_opt()) {
-            Ok(Item::new(output_ty, lhs.eval_bin_op(rhs, bin_op)))
+        if let (Some(&lhs_cons), Some(&rhs_cons)) = (lhs.const_opt(), rhs.const_opt()) {
+            let signed = lhs.ty.is_signed();
+            let res = eval_const(lhs_cons, rhs_cons, bin_op, signed, overflow, span)?;
+
+            Ok(Item::new(output_ty, res))
+        } else if let Some(res) = (!lhs.ty.is_signed() && !rhs.ty.is_signed())
+            .then(|| {
+                unsigned_cmp_with_bound(
+                    bin_op,
+                    lhs.const_opt().copied(),
+                    rhs.const_opt().copied(),
+                    lhs.width(),
+                )
+            })
+            .flatten()
+        {
+            Ok(Item::new(output_ty, ConstVal::from(res)))
+        } else if let Some(res) = fold_const_nodes(
+            &mut ctx.module,
+            &lhs,
+            &rhs,
+            bin_op,
+            output_ty,
+            overflow,
+            span,
+        ) {
+            res
         } else {
             let lhs = ctx.module.to_bitvec(&lhs, span)?.port();
             let rhs = ctx.module.to_bitvec(&rhs, span)?.port();
@@ -95,6 +133,82 @@ impl BinOp {
     }
 }
 
+fn eval_const(
+    lhs: ConstVal,
+    rhs: ConstVal,
+    bin_op: NodeBinOp,
+    signed: bool,
+    overflow: ConstOverflow,
+    span: Span,
+) -> Result<ConstVal, Error> {
+    lhs.eval_bin_op_with(rhs, bin_op, signed, overflow)
+        .ok_or_else(|| {
+            SpanError::new(
+                SpanErrorKind::ConstOverflow(format!("{lhs} {bin_op} {rhs}")),
+                span,
+            )
+            .into()
+        })
+}
+
+// Folds operands driven by constant nodes (e.g. const generics) into a constant node,
+// so that branches on the result are resolved while visiting MIR and the dead
+// branch is never synthesized.
+fn fold_const_nodes<'tcx>(
+    module: &mut Module,
+    lhs: &Item<'tcx>,
+    rhs: &Item<'tcx>,
+    bin_op: NodeBinOp,
+    output_ty: ItemTy<'tcx>,
+    overflow: ConstOverflow,
+    span: Span,
+) -> Option<Result<Item<'tcx>, Error>> {
+    if lhs.ty.is_signed() || rhs.ty.is_signed() {
+        return None;
+    }
+
+    let lhs = module.to_const_opt(lhs)?;
+    let rhs = module.to_const_opt(rhs)?;
+
+    Some(
+        eval_const(lhs, rhs, bin_op, false, overflow, span).map(|res| {
+            Item::new(output_ty, module.const_val(output_ty.node_ty(), res.val()))
+        }),
+    )
+}
+
+// Folds comparisons of an unsigned value with the bounds of its type
+// (e.g. `0 <= n` or `n <= MAX`), which MIR emits for range patterns.
+fn unsigned_cmp_with_bound(
+    bin_op: NodeBinOp,
+    lhs: Option<ConstVal>,
+    rhs: Option<ConstVal>,
+    width: u128,
+) -> Option<bool> {
+    use NodeBinOp::*;
+
+    let is_min = |cons: Option<ConstVal>| cons.is_some_and(|cons| cons.val() == 0);
+    let is_max = |cons: Option<ConstVal>| {
+        cons.is_some_and(|cons| cons.val() == ConstVal::new(u128::MAX, width).val())
+    };
+
+    match bin_op {
+        // 0 <= n, n >= 0
+        Le if is_min(lhs) => Some(true),
+        Ge if is_min(rhs) => Some(true),
+        // n < 0, 0 > n
+        Lt if is_min(rhs) => Some(false),
+        Gt if is_min(lhs) => Some(false),
+        // n <= MAX, MAX >= n
+        Le if is_max(rhs) => Some(true),
+        Ge if is_max(lhs) => Some(true),
+        // MAX < n, n > MAX
+        Lt if is_max(lhs) => Some(false),
+        Gt if is_max(rhs) => Some(false),
+        _ => None,
+    }
+}
+
 impl<'tcx> EvalExpr<'tcx> for BinOp {
     fn eval(
         &self,
@@ -107,6 +221,420 @@ impl<'tcx> EvalExpr<'tcx> for BinOp {
         args!(args as lhs, rhs);
 
         let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
-        self.bin_op(lhs, rhs, output_ty, ctx, span)
+        let overflow = compiler.netlist.cfg().const_overflow;
+
+        // a primitive literal is converted to the type of the result implicitly
+        // (e.g. `a + 20` for `a: U<4>`), so its truncation is reported
+        if self.0.should_convert_operands() {
+            for operand in [lhs, rhs] {
+                if let Some(kind) = ctx
+                    .module
+                    .to_const_opt(operand)
+                    .filter(|_| operand.ty != output_ty)
+                    .and_then(|cons| literal_overflow(cons.val(), output_ty))
+                {
+                    compiler.emit_warn(SpanError::new(kind, span));
+                }
+            }
+        }
+
+        self.bin_op(lhs, rhs, output_ty, overflow, ctx, span)
+    }
+}
+
+fn literal_overflow(value: u128, to_ty: ItemTy<'_>) -> Option<SpanErrorKind> {
+    match to_ty.kind() {
+        ItemTyKind::Node(node_ty) if node_ty.is_unsigned() => {
+            let width = node_ty.width();
+            let truncated = ConstVal::new(value, width).val();
+
+            (truncated != value)
+                .then_some(SpanErrorKind::LiteralOverflow(value, width, truncated))
+        }
+        _ => None,
+    }
+}
+
+pub struct Cmp;
+
+impl<'tcx> EvalExpr<'tcx> for Cmp {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: rustc_span::Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as lhs, rhs);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        if output_ty.width() != 2 {
+            return Err(SpanError::new(SpanErrorKind::NotSynthExpr, span).into());
+        }
+
+        // Ordering is encoded as a 2-bit signed discriminant:
+        // Less = 0b11, Equal = 0b00, Greater = 0b01
+        if let (Some(lhs), Some(rhs)) = (lhs.const_opt(), rhs.const_opt()) {
+            let discr = match lhs.val().cmp(&rhs.val()) {
+                Ordering::Less => 0b11,
+                Ordering::Equal => 0b00,
+                Ordering::Greater => 0b01,
+            };
+
+            return Ok(Item::new(output_ty, ConstVal::new(discr, 2)));
+        }
+
+        let lhs = ctx.module.to_bitvec(lhs, span)?.port();
+        let rhs = ctx.module.to_bitvec(rhs, span)?.port();
+        let discr = cmp(&mut ctx.module, lhs, rhs);
+
+        Ok(Item::new(output_ty, discr))
+    }
+}
+
+// The discriminant of `Ordering` is produced by a single comparator, the subtractor
+// `{0, lhs} - {0, rhs}`: its borrow (the MSB) is `lhs < rhs` and the difference is
+// not zero if `lhs != rhs`.
+fn cmp(module: &mut Module, lhs: Port, rhs: Port) -> Port {
+    let width = module[lhs].width().max(module[rhs].width()) + 1;
+    let ty = NodeTy::BitVec(width);
+
+    let mut zero_extend = |port: Port| {
+        let zero = module.const_zero(NodeTy::BitVec(width - module[port].width()));
+        module.add_and_get_port::<_, Merger>(MergerArgs {
+            inputs: [zero, port].into_iter(),
+            rev: false,
+            sym: None,
+        })
+    };
+    let lhs = zero_extend(lhs);
+    let rhs = zero_extend(rhs);
+
+    let diff = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+        ty,
+        bin_op: NodeBinOp::Sub,
+        lhs,
+        rhs,
+        sym: None,
+    });
+
+    let lt = module.add_and_get_port::<_, Splitter>(SplitterArgs {
+        input: diff,
+        outputs: iter::once((NodeTy::Bit, None)),
+        start: Some(width - 1),
+        rev: false,
+    });
+    let ne = module.add_and_get_port::<_, Reduce>(ReduceArgs {
+        op: ReduceOp::Or,
+        input: diff,
+        sym: None,
+    });
+
+    module.add_and_get_port::<_, Merger>(MergerArgs {
+        inputs: [lt, ne].into_iter(),
+        rev: false,
+        sym: SymIdent::Discr.into(),
+    })
+}
+
+pub struct AbsDiff;
+
+impl<'tcx> EvalExpr<'tcx> for AbsDiff {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: rustc_span::Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as lhs, rhs);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let lhs = ctx.module.to_bitvec(lhs, span)?.port();
+        let rhs = ctx.module.to_bitvec(rhs, span)?.port();
+
+        let diff = abs_diff(&mut ctx.module, lhs, rhs, output_ty.to_bitvec());
+        ctx.module.from_bitvec(diff, output_ty, span)
+    }
+}
+
+// `lhs >= rhs ? lhs - rhs : rhs - lhs`
+fn abs_diff(module: &mut Module, lhs: Port, rhs: Port, ty: NodeTy) -> Port {
+    let ge = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+        ty: NodeTy::Bit,
+        bin_op: NodeBinOp::Ge,
+        lhs,
+        rhs,
+        sym: None,
+    });
+
+    let mut sub = |lhs, rhs| {
+        module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty,
+            bin_op: NodeBinOp::Sub,
+            lhs,
+            rhs,
+            sym: None,
+        })
+    };
+    let lhs_sub_rhs = sub(lhs, rhs);
+    let rhs_sub_lhs = sub(rhs, lhs);
+
+    module.add_and_get_port::<_, Switch>(SwitchArgs {
+        outputs: iter::once((ty, SymIdent::Mux.into())),
+        sel: ge,
+        variants: [(ConstVal::new(1, 1), iter::once(lhs_sub_rhs))],
+        default: Some(iter::once(rhs_sub_lhs)),
+    })
+}
+
+pub struct Pow;
+
+impl<'tcx> EvalExpr<'tcx> for Pow {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as base);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let exp = ctx.fn_generic_const(compiler, 0, span)?.unwrap();
+
+        match exp {
+            0 => Ok(Item::new(
+                output_ty,
+                ctx.module.const_val(output_ty.node_ty(), 1),
+            )),
+            1 => Ok(base.clone()),
+            _ => {
+                let base = ctx.module.to_bitvec(base, span)?.port();
+                let pow = pow(&mut ctx.module, base, exp, output_ty.to_bitvec());
+                ctx.module.from_bitvec(pow, output_ty, span)
+            }
+        }
+    }
+}
+
+// `base * base * ... * base` with `exp - 1` multipliers
+fn pow(module: &mut Module, base: Port, exp: u128, ty: NodeTy) -> Port {
+    (1 .. exp).fold(base, |acc, _| {
+        module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty,
+            bin_op: NodeBinOp::Mul,
+            lhs: acc,
+            rhs: base,
+            sym: None,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use fhdl_data_structures::cursor::Cursor;
+    use fhdl_netlist::node::NodeKind;
+    use rustc_span::DUMMY_SP;
+
+    use super::*;
+    use crate::compiler::item_ty::WithTypeInfo;
+
+    #[test]
+    fn literal_overflow_u4() {
+        let ty = WithTypeInfo::new(ItemTyKind::Node(NodeTy::Unsigned(4)), None);
+        let ty = ItemTy::new(&ty);
+
+        assert!(literal_overflow(15, ty).is_none());
+        assert_eq!(
+            literal_overflow(20, ty).unwrap().to_string(),
+            "literal 20 does not fit into 4 bits and is truncated to 4"
+        );
+    }
+
+    #[test]
+    fn range_pattern_bounds() {
+        let n = None;
+        let zero = Some(ConstVal::new(0, 8));
+        let three = Some(ConstVal::new(3, 8));
+        let max = Some(ConstVal::new(255, 8));
+
+        // `0..=3 =>` lowers into `0 <= n && n <= 3`
+        assert_eq!(
+            unsigned_cmp_with_bound(NodeBinOp::Le, zero, n, 8),
+            Some(true)
+        );
+        assert_eq!(unsigned_cmp_with_bound(NodeBinOp::Le, n, three, 8), None);
+
+        assert_eq!(
+            unsigned_cmp_with_bound(NodeBinOp::Le, n, max, 8),
+            Some(true)
+        );
+        assert_eq!(
+            unsigned_cmp_with_bound(NodeBinOp::Lt, n, zero, 8),
+            Some(false)
+        );
+        assert_eq!(
+            unsigned_cmp_with_bound(NodeBinOp::Gt, n, max, 8),
+            Some(false)
+        );
+        assert_eq!(unsigned_cmp_with_bound(NodeBinOp::Ge, three, n, 8), None);
+        assert_eq!(unsigned_cmp_with_bound(NodeBinOp::Eq, zero, n, 8), None);
+    }
+
+    #[test]
+    fn const_generic_cmp() {
+        let bit_ty = WithTypeInfo::new(ItemTyKind::Node(NodeTy::Bit), None);
+        let bit_ty = ItemTy::new(&bit_ty);
+        let ty = WithTypeInfo::new(ItemTyKind::Node(NodeTy::Unsigned(64)), None);
+        let ty = ItemTy::new(&ty);
+
+        let mut module = Module::new("test", false);
+        let n = Item::new(ty, module.const_val(NodeTy::Unsigned(64), 8));
+        let four = Item::new(ty, module.const_val(NodeTy::Unsigned(64), 4));
+        let input = Item::new(ty, module.add_input(NodeTy::Unsigned(64), Some("a")));
+
+        let fold = |module: &mut Module, lhs, rhs, bin_op, ty, overflow| {
+            fold_const_nodes(module, lhs, rhs, bin_op, ty, overflow, DUMMY_SP)
+        };
+
+        // `N > 4`
+        let res = fold(
+            &mut module,
+            &n,
+            &four,
+            NodeBinOp::Gt,
+            bit_ty,
+            ConstOverflow::Wrap,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(module.to_const_opt(&res), Some(ConstVal::new(1, 1)));
+
+        let res = fold(
+            &mut module,
+            &four,
+            &n,
+            NodeBinOp::Gt,
+            bit_ty,
+            ConstOverflow::Wrap,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(module.to_const_opt(&res), Some(ConstVal::new(0, 1)));
+
+        assert!(fold(
+            &mut module,
+            &input,
+            &four,
+            NodeBinOp::Gt,
+            bit_ty,
+            ConstOverflow::Wrap
+        )
+        .is_none());
+
+        // `4 - N` underflows
+        let res = fold(
+            &mut module,
+            &four,
+            &n,
+            NodeBinOp::Sub,
+            ty,
+            ConstOverflow::Wrap,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            module.to_const_opt(&res),
+            Some(ConstVal::new(u64::MAX as u128 - 3, 64))
+        );
+
+        let res = fold(
+            &mut module,
+            &four,
+            &n,
+            NodeBinOp::Sub,
+            ty,
+            ConstOverflow::Saturate,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(module.to_const_opt(&res), Some(ConstVal::new(0, 64)));
+
+        assert!(fold(
+            &mut module,
+            &four,
+            &n,
+            NodeBinOp::Sub,
+            ty,
+            ConstOverflow::Error
+        )
+        .unwrap()
+        .is_err());
+    }
+
+    #[test]
+    fn abs_diff_nodes() {
+        let mut module = Module::new("test", false);
+        let ty = NodeTy::Unsigned(8);
+        let a = module.add_input(ty, Some("a"));
+        let b = module.add_input(ty, Some("b"));
+
+        abs_diff(&mut module, a, b, ty);
+
+        let mut bin_ops = vec![];
+        let mut muxs = 0;
+        let mut nodes = module.nodes();
+        while let Some(node_id) = nodes.next_(&module) {
+            let node = module.node(node_id);
+            match node.kind() {
+                NodeKind::BinOp(bin_op) => bin_ops.push(bin_op.bin_op),
+                NodeKind::Switch(_) => muxs += 1,
+                _ => {}
+            }
+        }
+
+        assert_eq!(bin_ops, [NodeBinOp::Ge, NodeBinOp::Sub, NodeBinOp::Sub]);
+        assert_eq!(muxs, 1);
+    }
+
+    #[test]
+    fn pow_mul_chain() {
+        let mut module = Module::new("test", false);
+        let ty = NodeTy::Unsigned(8);
+        let x = module.add_input(ty, Some("x"));
+
+        let x3 = pow(&mut module, x, 3, ty);
+
+        let is_mul = |port: Port| {
+            matches!(
+                module.node(port.node).kind(),
+                NodeKind::BinOp(bin_op) if bin_op.bin_op == NodeBinOp::Mul
+            )
+        };
+
+        // x^3 = (x * x) * x
+        assert!(is_mul(x3));
+        let mut incoming = module.incoming(x3.node);
+        let lhs = incoming.next_(&module).unwrap();
+        assert_eq!(incoming.next_(&module), Some(x));
+
+        assert!(is_mul(lhs));
+        let mut incoming = module.incoming(lhs.node);
+        assert_eq!(incoming.next_(&module), Some(x));
+        assert_eq!(incoming.next_(&module), Some(x));
+
+        let mut bin_ops = 0;
+        let mut nodes = module.nodes();
+        while let Some(node_id) = nodes.next_(&module) {
+            if let NodeKind::BinOp(_) = module.node(node_id).kind() {
+                bin_ops += 1;
+            }
+        }
+        assert_eq!(bin_ops, 2);
+        assert_eq!(pow(&mut module, x, 1, ty), x);
     }
 }
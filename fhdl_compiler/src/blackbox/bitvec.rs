@@ -1,19 +1,27 @@
-use std::iter::{self};
+use std::{
+    iter::{self},
+    num::NonZeroU128,
+};
 
+use ferrum_hdl::const_functions::clog2_len;
 use fhdl_data_structures::graph::Port;
 use fhdl_netlist::{
     const_val::ConstVal,
     netlist::Module,
-    node::{Splitter, SplitterArgs, Switch, SwitchArgs},
+    node::{
+        BinOp, BinOpArgs, BinOpNode, Extend, ExtendArgs, Memory, MemoryArgs, Merger,
+        MergerArgs, Splitter, SplitterArgs, Switch, SwitchArgs,
+    },
     node_ty::NodeTy,
+    symbol::Symbol,
 };
 use rustc_middle::ty::Ty;
 use rustc_span::Span;
 
-use super::{args, EvalExpr};
+use super::{args, array::reduce_tree, EvalExpr};
 use crate::{
     compiler::{
-        item::{Item, ModuleExt},
+        item::{Group, Item, ItemKind, ModuleExt},
         item_ty::{ItemTy, ItemTyKind},
         Compiler, Context, SymIdent,
     },
@@ -56,6 +64,10 @@ impl<'tcx> EvalExpr<'tcx> for Slice {
                     span,
                 )
             }
+            ItemTyKind::Array(_) if self.only_one => {
+                let rom_threshold = compiler.netlist.cfg().rom_threshold;
+                index_array(&mut ctx.module, rec, idx, rom_threshold, span)
+            }
             ItemTyKind::Array(array_ty) => {
                 let group = rec.group();
 
@@ -96,6 +108,490 @@ impl<'tcx> EvalExpr<'tcx> for Slice {
     }
 }
 
+pub struct Mask;
+
+impl<'tcx> EvalExpr<'tcx> for Mask {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let width = output_ty.width();
+        let len = ctx.fn_generic_const(compiler, 0, span)?.unwrap();
+        assert!(len <= width);
+
+        if len == width {
+            return Ok(rec.clone());
+        }
+
+        if let Some(cons) = rec.const_opt() {
+            return Ok(Item::new(output_ty, cons.convert(len).convert(width)));
+        }
+
+        if len == 0 {
+            return Ok(Item::new(output_ty, ConstVal::zero(width)));
+        }
+
+        let rec = ctx.module.to_bitvec(rec, span)?.port();
+        let slice = slice(&mut ctx.module, rec, 0, NodeTy::Unsigned(len));
+
+        Ok(Item::new(
+            output_ty,
+            ctx.module.add_and_get_port::<_, Extend>(ExtendArgs {
+                ty: output_ty.node_ty(),
+                input: slice,
+                sym: None,
+                is_sign: false,
+            }),
+        ))
+    }
+}
+
+pub struct Clog2;
+
+impl<'tcx> EvalExpr<'tcx> for Clog2 {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let node_ty = output_ty.node_ty();
+        let width = rec.width();
+
+        if let Some(cons) = rec.const_opt() {
+            let bits = (u128::BITS - cons.val().leading_zeros()).max(1);
+            return Ok(Item::new(
+                output_ty,
+                ConstVal::new(bits as u128, node_ty.width()),
+            ));
+        }
+
+        let rec = ctx.module.to_bitvec(rec, span)?.port();
+
+        // priority encoder: the highest set bit overrides the lower ones,
+        // zero input gives 1 as clog2(0) does
+        let mut value = ctx.module.const_val(node_ty, 1);
+        for idx in 1 .. width {
+            let sel = slice(&mut ctx.module, rec, idx, NodeTy::Bit);
+            let bits = ctx.module.const_val(node_ty, idx + 1);
+
+            value = ctx.module.add_and_get_port::<_, Switch>(SwitchArgs {
+                outputs: iter::once((node_ty, SymIdent::Mux.into())),
+                sel,
+                variants: [(ConstVal::new(1, 1), iter::once(bits))],
+                default: Some(iter::once(value)),
+            });
+        }
+
+        Ok(Item::new(output_ty, value))
+    }
+}
+
+pub struct Interleave;
+
+impl<'tcx> EvalExpr<'tcx> for Interleave {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, other);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let width = rec.ty.width();
+        if width == 0 {
+            return Ok(Item::new(output_ty, ConstVal::zero(0)));
+        }
+
+        let rec = ctx.module.to_bitvec(rec, span)?.port();
+        let other = ctx.module.to_bitvec(other, span)?.port();
+
+        let rec = split_bits(&mut ctx.module, rec, width);
+        let other = split_bits(&mut ctx.module, other, width);
+
+        // inputs of merger go from the MSB
+        let bits = rec
+            .into_iter()
+            .zip(other)
+            .rev()
+            .flat_map(|(even, odd)| [odd, even]);
+
+        Ok(Item::new(
+            output_ty,
+            ctx.module.add_and_get_port::<_, Merger>(MergerArgs {
+                inputs: bits,
+                rev: false,
+                sym: None,
+            }),
+        ))
+    }
+}
+
+pub struct Deinterleave;
+
+impl<'tcx> EvalExpr<'tcx> for Deinterleave {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let width = rec.ty.width();
+        if width == 0 {
+            return Ok(Item::new(
+                output_ty,
+                ItemKind::Group(Group::new([
+                    Item::new(output_ty.struct_ty().by_idx(0), ConstVal::zero(0)),
+                    Item::new(output_ty.struct_ty().by_idx(1), ConstVal::zero(0)),
+                ])),
+            ));
+        }
+
+        let rec = ctx.module.to_bitvec(rec, span)?.port();
+        let bits = split_bits(&mut ctx.module, rec, width);
+
+        let mut merge = |parity: usize| {
+            let inputs = bits.iter().copied().skip(parity).step_by(2).rev();
+            ctx.module.add_and_get_port::<_, Merger>(MergerArgs {
+                inputs,
+                rev: false,
+                sym: None,
+            })
+        };
+        let (even, odd) = (merge(0), merge(1));
+
+        let struct_ty = output_ty.struct_ty();
+        Ok(Item::new(
+            output_ty,
+            ItemKind::Group(Group::new([
+                Item::new(struct_ty.by_idx(0), even),
+                Item::new(struct_ty.by_idx(1), odd),
+            ])),
+        ))
+    }
+}
+
+pub struct ToBytes {
+    pub le: bool,
+}
+
+impl<'tcx> EvalExpr<'tcx> for ToBytes {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let array_ty = output_ty.array_ty();
+        let byte_ty = array_ty.ty();
+        let count = array_ty.count();
+        if count == 0 {
+            return Ok(Item::new(output_ty, ItemKind::Group(Group::new([]))));
+        }
+
+        let rec = ctx.module.to_bitvec(rec, span)?.port();
+
+        // the splitter goes from the LSB, the reversed one from the MSB
+        let node_id = ctx.module.add::<_, Splitter>(SplitterArgs {
+            input: rec,
+            outputs: (0 .. count).map(|_| (byte_ty.to_bitvec(), SymIdent::Slice.into())),
+            start: None,
+            rev: !self.le,
+        });
+        let bytes = (0 .. count as u32)
+            .map(|idx| {
+                ctx.module
+                    .from_bitvec(Port::new(node_id, idx), byte_ty, span)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Item::new(output_ty, ItemKind::Group(Group::new(bytes))))
+    }
+}
+
+pub struct FromBytes {
+    pub le: bool,
+}
+
+impl<'tcx> EvalExpr<'tcx> for FromBytes {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as bytes);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        if output_ty.width() == 0 {
+            return Ok(Item::new(output_ty, ConstVal::zero(0)));
+        }
+
+        let mut bytes = bytes
+            .group()
+            .items()
+            .iter()
+            .map(|byte| Ok(ctx.module.to_bitvec(byte, span)?.port()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        // inputs of merger go from the MSB
+        if self.le {
+            bytes.reverse();
+        }
+
+        Ok(Item::new(
+            output_ty,
+            ctx.module.add_and_get_port::<_, Merger>(MergerArgs {
+                inputs: bytes.into_iter(),
+                rev: false,
+                sym: None,
+            }),
+        ))
+    }
+}
+
+pub struct RotateThroughCarry;
+
+impl<'tcx> EvalExpr<'tcx> for RotateThroughCarry {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, carry_in);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let struct_ty = output_ty.struct_ty();
+        let (value_ty, bit_ty) = (struct_ty.by_idx(0), struct_ty.by_idx(1));
+        let width = value_ty.width();
+
+        if width == 0 {
+            return Ok(Item::new(
+                output_ty,
+                ItemKind::Group(Group::new([rec.clone(), carry_in.clone()])),
+            ));
+        }
+
+        let rec = ctx.module.to_bitvec(rec, span)?.port();
+        let carry_in = ctx.module.to_bitvec(carry_in, span)?.port();
+
+        let carry_out = slice(&mut ctx.module, rec, width - 1, NodeTy::Bit);
+        let rotated = if width == 1 {
+            carry_in
+        } else {
+            // drop the MSB and append the carry as the new LSB
+            let rest = slice(&mut ctx.module, rec, 0, NodeTy::BitVec(width - 1));
+            ctx.module.add_and_get_port::<_, Merger>(MergerArgs {
+                inputs: [rest, carry_in].into_iter(),
+                rev: false,
+                sym: None,
+            })
+        };
+
+        let rotated = ctx.module.from_bitvec(rotated, value_ty, span)?;
+        let carry_out = ctx.module.from_bitvec(carry_out, bit_ty, span)?;
+
+        Ok(Item::new(
+            output_ty,
+            ItemKind::Group(Group::new([rotated, carry_out])),
+        ))
+    }
+}
+
+pub struct BarrelShift;
+
+impl<'tcx> EvalExpr<'tcx> for BarrelShift {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as value, amount, dir);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let node_ty = output_ty.node_ty();
+        let width = node_ty.width();
+
+        let is_left = ctx
+            .module
+            .to_const_val(dir)
+            .map(|dir| dir == 0)
+            .ok_or_else(|| SpanError::new(SpanErrorKind::NotSynthExpr, span))?;
+
+        let mut value = ctx.module.to_bitvec(value, span)?.port();
+        let amount = ctx.module.to_bitvec(amount, span)?.port();
+
+        // each bit of the amount controls one stage which shifts by the power of two
+        for stage in 0 .. ctx.module[amount].width() {
+            let shift = 1 << stage;
+            let sel = slice(&mut ctx.module, amount, stage, NodeTy::Bit);
+
+            let shifted = if shift >= width {
+                ctx.module.const_zero(node_ty)
+            } else {
+                let zeros = ctx.module.const_zero(NodeTy::BitVec(shift));
+                let rest = NodeTy::BitVec(width - shift);
+                let inputs = if is_left {
+                    [slice(&mut ctx.module, value, 0, rest), zeros]
+                } else {
+                    [zeros, slice(&mut ctx.module, value, shift, rest)]
+                };
+
+                ctx.module.add_and_get_port::<_, Merger>(MergerArgs {
+                    inputs: inputs.into_iter(),
+                    rev: false,
+                    sym: None,
+                })
+            };
+
+            value = ctx.module.add_and_get_port::<_, Switch>(SwitchArgs {
+                outputs: iter::once((node_ty, SymIdent::Mux.into())),
+                sel,
+                variants: [(ConstVal::new(1, 1), iter::once(shifted))],
+                default: Some(iter::once(value)),
+            });
+        }
+
+        Ok(Item::new(output_ty, value))
+    }
+}
+
+pub struct Parity;
+
+impl<'tcx> EvalExpr<'tcx> for Parity {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let width = rec.ty.width();
+        let bits = match width {
+            0 => vec![],
+            _ => {
+                let rec = ctx.module.to_bitvec(rec, span)?.port();
+                split_bits(&mut ctx.module, rec, width)
+            }
+        };
+
+        Ok(Item::new(
+            output_ty,
+            reduce_tree(&mut ctx.module, bits, BinOp::BitXor, false),
+        ))
+    }
+}
+
+pub struct CrcStep;
+
+impl<'tcx> EvalExpr<'tcx> for CrcStep {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, data_bit);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let width = output_ty.width();
+        if width == 0 {
+            return Ok(rec.clone());
+        }
+        let poly = ctx.fn_generic_const(compiler, 0, span)?.unwrap();
+
+        let rec = ctx.module.to_bitvec(rec, span)?.port();
+        let data_bit = ctx.module.to_bitvec(data_bit, span)?.port();
+        let bits = split_bits(&mut ctx.module, rec, width);
+
+        let xor = |module: &mut Module, lhs, rhs| {
+            module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                ty: NodeTy::Bit,
+                bin_op: BinOp::BitXor,
+                lhs,
+                rhs,
+                sym: None,
+            })
+        };
+
+        // the feedback bit is XORed into the taps of the shifted register, the LSB
+        // has nothing shifted into it
+        let fb = xor(&mut ctx.module, bits[width as usize - 1], data_bit);
+        let mut next = Vec::with_capacity(width as usize);
+        for idx in 0 .. width {
+            let tap = (poly >> idx) & 1 == 1;
+            next.push(match (idx.checked_sub(1), tap) {
+                (Some(prev), true) => xor(&mut ctx.module, bits[prev as usize], fb),
+                (Some(prev), false) => bits[prev as usize],
+                (None, true) => fb,
+                (None, false) => ctx.module.const_val(NodeTy::Bit, 0),
+            });
+        }
+
+        // inputs of merger go from the MSB
+        Ok(Item::new(
+            output_ty,
+            ctx.module.add_and_get_port::<_, Merger>(MergerArgs {
+                inputs: next.into_iter().rev(),
+                rev: false,
+                sym: None,
+            }),
+        ))
+    }
+}
+
+// Splits into single bits starting from the LSB
+fn split_bits(module: &mut Module, value: Port, width: u128) -> Vec<Port> {
+    let node_id = module.add::<_, Splitter>(SplitterArgs {
+        input: value,
+        outputs: (0 .. width).map(|_| (NodeTy::Bit, SymIdent::Bit.into())),
+        start: None,
+        rev: false,
+    });
+
+    (0 .. width as u32)
+        .map(|idx| Port::new(node_id, idx))
+        .collect()
+}
+
 fn slice(module: &mut Module, value: Port, idx: u128, node_ty: NodeTy) -> Port {
     module.add_and_get_port::<_, Splitter>(SplitterArgs {
         input: value,
@@ -112,6 +608,88 @@ fn slice(module: &mut Module, value: Port, idx: u128, node_ty: NodeTy) -> Port {
     })
 }
 
+// Selects an element of the array by the index known only at runtime, arrays of
+// constants with at least `rom_threshold` elements are lowered to a ROM
+pub fn index_array<'tcx>(
+    module: &mut Module,
+    array: &Item<'tcx>,
+    idx: &Item<'tcx>,
+    rom_threshold: usize,
+    span: Span,
+) -> Result<Item<'tcx>, Error> {
+    let array_ty = array.ty.array_ty();
+    let group = array.group();
+    let count = array_ty.count();
+
+    let mut sel = module.to_bitvec(idx, span)?.port();
+    let mut sel_width = idx.width();
+
+    // e.g. `usize` indices, out-of-bounds indices are rejected by the bounds check
+    let idx_width = clog2_len(count as usize) as u128;
+    if sel_width > idx_width {
+        sel = Compiler::trunc_or_extend(
+            module,
+            sel,
+            NodeTy::Unsigned(sel_width),
+            NodeTy::Unsigned(idx_width),
+            SymIdent::Sel.into(),
+            false,
+        );
+        sel_width = idx_width;
+    }
+
+    if let Some(rom) = make_rom(module, array, sel, sel_width, rom_threshold) {
+        return Ok(Item::new(array_ty.ty(), rom));
+    }
+
+    make_mux_by_sel(
+        module,
+        sel,
+        sel_width,
+        count,
+        array_ty.ty(),
+        |_, case| group.by_idx(case as usize).ports(),
+        span,
+    )
+}
+
+// The ROM is padded with zero words up to the range of `addr`, so every address
+// reads the same word in the generated code and in the constant folding
+fn make_rom(
+    module: &mut Module,
+    array: &Item<'_>,
+    addr: Port,
+    addr_width: u128,
+    rom_threshold: usize,
+) -> Option<Port> {
+    let array_ty = array.ty.array_ty();
+    let count = array_ty.count();
+    let node_ty = match array_ty.ty().kind() {
+        ItemTyKind::Node(node_ty) if node_ty.width() != 0 => *node_ty,
+        _ => return None,
+    };
+    if count == 0 || count < rom_threshold as u128 {
+        return None;
+    }
+
+    let group = array.group();
+    let init = (0 .. count)
+        .map(|idx| {
+            let val = module.to_const_opt(&group.by_idx(idx as usize))?;
+            Some((idx, val))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(module.add_and_get_port::<_, Memory>(MemoryArgs {
+        ty: node_ty,
+        dim: NonZeroU128::new(1 << addr_width).unwrap(),
+        addr,
+        init,
+        name: Some(Symbol::intern("rom")),
+        data_sym: None,
+    }))
+}
+
 fn make_mux<'tcx, I>(
     module: &mut Module,
     idx: &Item<'tcx>,
@@ -124,8 +702,21 @@ where
     I: Iterator<Item = Port>,
 {
     let sel = module.to_bitvec(idx, span)?.port();
-    let sel_width = idx.width();
+    make_mux_by_sel(module, sel, idx.width(), count, output_ty, mk_variant, span)
+}
 
+fn make_mux_by_sel<'tcx, I>(
+    module: &mut Module,
+    sel: Port,
+    sel_width: u128,
+    count: u128,
+    output_ty: ItemTy<'tcx>,
+    mk_variant: impl Fn(&mut Module, u128) -> I,
+    span: Span,
+) -> Result<Item<'tcx>, Error>
+where
+    I: Iterator<Item = Port>,
+{
     let variants = (0 .. count)
         .map(|case| {
             let variant = mk_variant(module, case);
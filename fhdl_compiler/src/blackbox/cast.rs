@@ -6,7 +6,7 @@ use super::{args, EvalExpr};
 use crate::{
     compiler::{
         item::{Item, ItemKind},
-        item_ty::{ItemTy, ItemTyKind},
+        item_ty::{EnumTy, ItemTy, ItemTyKind},
         Compiler, Context, SymIdent,
     },
     error::{Error, SpanError, SpanErrorKind},
@@ -41,6 +41,27 @@ impl CastFrom {
                 assert_convert::<S<1>, S<2>>();
                 Ok(Self::trunc_or_extend(from.clone(), to_ty, ctx, true))
             }
+            (ItemTyKind::Enum(from_ty_), ItemTyKind::Enum(to_ty_)) => {
+                if same_enum_layout(*from_ty_, *to_ty_) {
+                    // the enums have the same bit representation
+                    Ok(Item::new(to_ty, from.port()))
+                } else {
+                    let ty_name = |ty: ItemTy<'tcx>| {
+                        ty.rust_ty()
+                            .map(|ty| ty.to_string())
+                            .unwrap_or_else(|| format!("{ty:?}"))
+                    };
+
+                    Err(SpanError::new(
+                        SpanErrorKind::IncompatibleEnumCast(
+                            ty_name(from.ty),
+                            ty_name(to_ty),
+                        ),
+                        span,
+                    )
+                    .into())
+                }
+            }
             _ => {
                 tracing::error!("from {:?} => to {:?}", from.ty, to_ty);
 
@@ -71,6 +92,28 @@ impl CastFrom {
 
 fn assert_convert<F, T: cast::CastFrom<F>>() {}
 
+// Discriminant widths, discriminants and field widths of all variants are the same
+fn same_enum_layout(from: EnumTy<'_>, to: EnumTy<'_>) -> bool {
+    fn field_widths(ty: ItemTy<'_>) -> Vec<u128> {
+        match ty.kind() {
+            ItemTyKind::Struct(struct_ty) => {
+                struct_ty.tys().map(|ty| ty.width()).collect()
+            }
+            _ => vec![ty.width()],
+        }
+    }
+
+    from.discr_width() == to.discr_width()
+        && from.data_width() == to.data_width()
+        && from.discriminants().count() == to.discriminants().count()
+        && from
+            .discriminants()
+            .zip(to.discriminants())
+            .all(|(from, to)| {
+                from.discr == to.discr && field_widths(*from.ty) == field_widths(*to.ty)
+            })
+}
+
 impl<'tcx> EvalExpr<'tcx> for CastFrom {
     fn eval(
         &self,
@@ -86,3 +129,48 @@ impl<'tcx> EvalExpr<'tcx> for CastFrom {
         Self::convert(from, output_ty, ctx, span)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use fhdl_netlist::{node_ty::NodeTy, symbol::Symbol};
+
+    use super::*;
+    use crate::compiler::item_ty::{Named, StructTy, WithTypeInfo};
+
+    fn leak_ty(kind: ItemTyKind<'static>) -> ItemTy<'static> {
+        ItemTy::new(Box::leak(Box::new(WithTypeInfo::new(kind, None))))
+    }
+
+    // enum with a unit variant and a variant with fields of the specified widths
+    fn enum_ty(fields: &[u128]) -> EnumTy<'static> {
+        let fields = fields
+            .iter()
+            .map(|width| {
+                let ty = leak_ty(ItemTyKind::Node(NodeTy::Unsigned(*width)));
+                Named::new(ty, Symbol::intern("field"))
+            })
+            .collect::<Vec<_>>();
+
+        let unit = leak_ty(ItemTyKind::Struct(StructTy::new(&[])));
+        let data = leak_ty(ItemTyKind::Struct(StructTy::new(fields.leak())));
+        let variants = vec![
+            Named::new(unit, Symbol::intern("A")),
+            Named::new(data, Symbol::intern("B")),
+        ];
+
+        EnumTy::new(
+            variants.leak(),
+            None,
+            leak_ty(ItemTyKind::Node(NodeTy::Unsigned(1))),
+        )
+    }
+
+    #[test]
+    fn enum_layouts() {
+        assert!(same_enum_layout(enum_ty(&[4]), enum_ty(&[4])));
+        assert!(same_enum_layout(enum_ty(&[2, 2]), enum_ty(&[2, 2])));
+        // the same width, but different fields
+        assert!(!same_enum_layout(enum_ty(&[4]), enum_ty(&[2, 2])));
+        assert!(!same_enum_layout(enum_ty(&[4]), enum_ty(&[3])));
+    }
+}
@@ -3,8 +3,12 @@ use rustc_span::Span;
 
 use super::EvalExpr;
 use crate::{
-    blackbox::args,
-    compiler::{item::Item, item_ty::ItemTyKind, Compiler, Context, LoopGen},
+    blackbox::{args, array},
+    compiler::{
+        item::{Item, ModuleExt},
+        item_ty::ItemTyKind,
+        Compiler, Context, LoopGen,
+    },
     error::{Error, SpanError, SpanErrorKind},
 };
 
@@ -77,6 +81,145 @@ impl<'tcx> EvalExpr<'tcx> for IterEnum {
     }
 }
 
+pub struct IterMap;
+
+impl<'tcx> EvalExpr<'tcx> for IterMap {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        _: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, closure);
+
+        if let Some(loop_gen) = rec.loop_gen_opt() {
+            if loop_gen.is_unbounded() {
+                return Err(SpanError::new(SpanErrorKind::UnboundedIter, span).into());
+            }
+
+            let closure_ty = closure.ty.closure_ty();
+            let output_ty = compiler.fn_output(closure_ty.fn_did, closure_ty.fn_generics);
+            let iter_item_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+
+            loop_gen.map(compiler, closure, iter_item_ty, ctx, span)
+        } else {
+            Err(SpanError::new(SpanErrorKind::NotSynthExpr, span).into())
+        }
+    }
+}
+
+// `position` and `find` are lowered to a priority encoder over all items
+pub struct IterPosition {
+    pub find: bool,
+}
+
+impl<'tcx> EvalExpr<'tcx> for IterPosition {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, closure);
+
+        let loop_gen = rec
+            .loop_gen_opt()
+            .ok_or_else(|| SpanError::new(SpanErrorKind::NotSynthExpr, span))?;
+        if loop_gen.is_unbounded() {
+            return Err(SpanError::new(SpanErrorKind::UnboundedIter, span).into());
+        }
+
+        let opt_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let idx_ty = array::opt_some_ty(opt_ty);
+
+        let mut candidates = vec![];
+        for (idx, item) in loop_gen.items().into_iter().enumerate() {
+            let found =
+                compiler.instantiate_closure(closure, &[item.clone()], ctx, span)?;
+            let value = if self.find {
+                item
+            } else {
+                let idx = ctx.module.const_val(idx_ty.to_bitvec(), idx as u128);
+                ctx.module.from_bitvec(idx, idx_ty, span)?
+            };
+
+            candidates.push((found, value));
+        }
+
+        array::priority_select(candidates, opt_ty, ctx, span)
+    }
+}
+
+pub struct IterRepeat;
+
+impl<'tcx> EvalExpr<'tcx> for IterRepeat {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        _: Ty<'tcx>,
+        _: &mut Context<'tcx>,
+        _: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as item);
+
+        Ok(LoopGen::repeat(compiler, item.ty, item.clone()))
+    }
+}
+
+pub struct IterTake;
+
+impl<'tcx> EvalExpr<'tcx> for IterTake {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as rec, count);
+
+        let loop_gen = rec
+            .loop_gen_opt()
+            .ok_or_else(|| SpanError::new(SpanErrorKind::NotSynthExpr, span))?;
+        let count = ctx
+            .module
+            .to_const_val(count)
+            .ok_or_else(|| SpanError::new(non_static_len(compiler, output_ty), span))?;
+
+        Ok(loop_gen.take(compiler, count as usize))
+    }
+}
+
+pub struct IterNonStaticLen;
+
+impl<'tcx> EvalExpr<'tcx> for IterNonStaticLen {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        _: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        _: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        Err(SpanError::new(non_static_len(compiler, output_ty), span).into())
+    }
+}
+
+fn non_static_len<'tcx>(compiler: &Compiler<'tcx>, output_ty: Ty<'tcx>) -> SpanErrorKind {
+    let adapter = match output_ty.ty_adt_def() {
+        Some(adt) => compiler.tcx.item_name(adt.did()).to_string(),
+        None => output_ty.to_string(),
+    };
+
+    SpanErrorKind::NonStaticIterLen(adapter)
+}
+
 pub struct IterNext;
 
 impl<'tcx> EvalExpr<'tcx> for IterNext {
@@ -0,0 +1,89 @@
+use std::iter;
+
+use either::Either;
+use fhdl_netlist::node::{Merger, MergerArgs, Splitter, SplitterArgs};
+use rustc_middle::ty::Ty;
+use rustc_span::Span;
+use rustc_target::abi::VariantIdx;
+
+use super::{args, EvalExpr};
+use crate::{
+    compiler::{
+        item::{Group, Item, ModuleExt},
+        Compiler, Context, SymIdent,
+    },
+    error::Error,
+};
+
+const NONE_IDX: usize = 0;
+const SOME_IDX: usize = 1;
+
+pub struct Map;
+
+impl<'tcx> EvalExpr<'tcx> for Map {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as opt, closure);
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+        let enum_ty = opt.ty.enum_ty();
+        let discr_ty = enum_ty.discr_ty().node_ty();
+
+        let opt = ctx.module.to_bitvec(opt, span)?.port();
+        let discr = ctx.module.add_and_get_port::<_, Splitter>(SplitterArgs {
+            input: opt,
+            outputs: iter::once((discr_ty, SymIdent::Discr.into())),
+            start: None,
+            rev: true,
+        });
+
+        // the splitter is not folded until the netlist transforms, so the
+        // discriminant of a constant option is sliced from its value
+        if let Some(opt) = ctx.module.to_const(opt) {
+            let discr = opt.slice(enum_ty.data_width(), enum_ty.discr_width());
+            if discr.val() == enum_ty.by_idx(NONE_IDX).discr {
+                return ctx.module.enum_variant_to_bitvec(
+                    None,
+                    output_ty,
+                    VariantIdx::from_usize(NONE_IDX),
+                    span,
+                );
+            }
+        }
+
+        let some_idx = VariantIdx::from_usize(SOME_IDX);
+        let value = ctx
+            .module
+            .enum_variant_from_bitvec(opt, enum_ty, some_idx, span)?
+            .by_idx(0);
+        let value = compiler.instantiate_closure(closure, &[value], ctx, span)?;
+
+        // the closure output is valid only when the input option is valid,
+        // so the discriminant of the input is passed to the output as is
+        let output_enum_ty = output_ty.enum_ty();
+        let inputs = if output_enum_ty.data_width() == 0 {
+            Either::Left(iter::once(discr))
+        } else {
+            let some_ty = *output_enum_ty.by_variant_idx(some_idx).ty;
+            let data_part = Item::new(some_ty, Group::new(iter::once(value)));
+            let data_part = ctx.module.to_bitvec(&data_part, span)?.port();
+
+            Either::Right([discr, data_part].into_iter())
+        };
+
+        Ok(Item::new(
+            output_ty,
+            ctx.module.add_and_get_port::<_, Merger>(MergerArgs {
+                inputs,
+                rev: false,
+                sym: None,
+            }),
+        ))
+    }
+}
@@ -1,4 +1,6 @@
-use fhdl_netlist::node::{DFFArgs, TyOrData, DFF};
+use fhdl_netlist::node::{
+    ClockGate as NodeClockGate, ClockGateArgs, DFFArgs, TyOrData, DFF,
+};
 use rustc_middle::ty::Ty;
 use rustc_span::Span;
 
@@ -8,7 +10,7 @@ use crate::{
         item::{Group, Item, ItemKind, ModuleExt},
         Compiler, Context, SymIdent,
     },
-    error::Error,
+    error::{Error, SpanError, SpanErrorKind},
 };
 
 pub struct RegEn {
@@ -50,9 +52,12 @@ impl<'tcx> EvalExpr<'tcx> for RegEn {
             rst_pol: domain.rst_pol,
             en: Some(en),
             init,
+            rst_val: None,
             data: TyOrData::Ty(dff_ty.to_bitvec()),
             sym: SymIdent::Reg.into(),
         });
+        let dff_span = compiler.span_to_string(span, ctx.fn_did);
+        ctx.module.add_span(dff.node, dff_span);
         let dff_out = ctx.module.from_bitvec(dff, dff_ty, span)?;
         let comb = compiler.instantiate_closure(comb, &[dff_out.clone()], ctx, span)?;
 
@@ -69,3 +74,37 @@ impl<'tcx> EvalExpr<'tcx> for RegEn {
         })
     }
 }
+
+pub struct ClockGate;
+
+impl<'tcx> EvalExpr<'tcx> for ClockGate {
+    fn eval(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        args: &[Item<'tcx>],
+        output_ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        args!(args as clk, en);
+
+        if !compiler.netlist.cfg().clock_gating {
+            compiler.emit_warn(SpanError::new(SpanErrorKind::ClockGatingDisabled, span));
+        }
+
+        let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
+
+        let clk = clk.port();
+        let en = ctx.module.to_bitvec(en, span)?.port();
+
+        let gclk = ctx
+            .module
+            .add_and_get_port::<_, NodeClockGate>(ClockGateArgs {
+                clk,
+                en,
+                sym: SymIdent::Gclk.into(),
+            });
+
+        Ok(Item::new(output_ty, ItemKind::Port(gclk)))
+    }
+}
@@ -14,6 +14,7 @@ use crate::{
 
 pub struct SignalDff {
     pub comb: bool,
+    pub rst_val: bool,
 }
 
 impl<'tcx> EvalExpr<'tcx> for SignalDff {
@@ -25,7 +26,13 @@ impl<'tcx> EvalExpr<'tcx> for SignalDff {
         ctx: &mut Context<'tcx>,
         span: Span,
     ) -> Result<Item<'tcx>, Error> {
-        args!(args as clk, rst, en, init, comb, rst_kind, rst_pol);
+        args!(args as clk, rst, en, init);
+        let (rst_val, args) = if self.rst_val {
+            (Some(&args[4]), &args[5 ..])
+        } else {
+            (None, &args[4 ..])
+        };
+        args!(args as comb, rst_kind, rst_pol);
 
         let output_ty = compiler.resolve_fn_out_ty(output_ty, span)?;
 
@@ -33,6 +40,9 @@ impl<'tcx> EvalExpr<'tcx> for SignalDff {
         let rst = ctx.module.to_bitvec(rst, span)?.port();
         let en = ctx.module.to_bitvec(en, span)?.port();
         let init = ctx.module.to_bitvec(init, span)?.port();
+        let rst_val = rst_val
+            .map(|rst_val| ctx.module.to_bitvec(rst_val, span).map(|item| item.port()))
+            .transpose()?;
 
         let (dff_ty, comb_ty) = if self.comb {
             let struct_ty = output_ty.struct_ty();
@@ -60,9 +70,12 @@ impl<'tcx> EvalExpr<'tcx> for SignalDff {
             rst_pol,
             en: Some(en),
             init,
+            rst_val,
             data: TyOrData::Ty(dff_ty.to_bitvec()),
             sym: SymIdent::Reg.into(),
         });
+        let dff_span = compiler.span_to_string(span, ctx.fn_did);
+        ctx.module.add_span(dff.node, dff_span);
         let dff_out = ctx.module.from_bitvec(dff, dff_ty, span)?;
 
         let comb = compiler.instantiate_closure(comb, &[dff_out.clone()], ctx, span)?;
@@ -4,7 +4,10 @@ use rustc_span::Span;
 
 use super::{args, EvalExpr};
 use crate::{
-    compiler::{item::Item, Compiler, Context},
+    compiler::{
+        item::{Item, ModuleExt},
+        Compiler, Context,
+    },
     error::Error,
 };
 
@@ -18,6 +21,13 @@ impl BitNot {
     ) -> Result<Item<'tcx>, Error> {
         let ty = expr.ty;
 
+        if let Some(cons) = ctx.module.to_const_opt(expr) {
+            return Ok(Item::new(
+                ty,
+                ctx.module.const_val(ty.node_ty(), (!cons).val()),
+            ));
+        }
+
         Ok(Item::new(
             ty,
             ctx.module.add_and_get_port::<_, BitNotNode>(BitNotArgs {
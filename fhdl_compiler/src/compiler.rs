@@ -10,6 +10,7 @@ mod locals;
 mod mir;
 // mod pins;
 mod loop_gen;
+mod manifest;
 mod post_dominator;
 pub mod switch;
 mod switch_tuple;
@@ -36,6 +37,7 @@ use fhdl_netlist::{
     node::{Extend, ExtendArgs, Splitter, SplitterArgs},
     node_ty::NodeTy,
     symbol::Symbol,
+    visitor::WrapperCfg,
 };
 pub use loop_gen::LoopGen;
 use rustc_data_structures::fx::FxHashMap;
@@ -48,9 +50,14 @@ use rustc_interface::{interface::Compiler as RustCompiler, Queries};
 use rustc_middle::{
     dep_graph::DepContext,
     mir::BasicBlock,
-    ty::{GenericArgs, GenericArgsRef, Ty, TyCtxt},
+    ty::{
+        self, Const, GenericArgs, GenericArgsRef, GenericParamDefKind, ParamEnv, Ty,
+        TyCtxt,
+    },
 };
 use rustc_span::{def_id::CrateNum, FileName, Span, StableSourceFileId};
+use rustc_target::abi::FieldIdx;
+use smallvec::SmallVec;
 pub use sym_ident::SymIdent;
 
 use self::{
@@ -61,7 +68,7 @@ use self::{
     post_dominator::PostDominator,
     switch_tuple::SwitchTupleRef,
 };
-use crate::error::{Error, SpanError};
+use crate::error::{Error, SpanError, SpanErrorKind};
 
 pub struct CompilerCallbacks {
     pub args: CompilerArgs,
@@ -184,6 +191,7 @@ struct LangItems {
     freq: DefId,
     rst_kind: DefId,
     rst_pol: DefId,
+    bit_size_bits: DefId,
 }
 
 impl LangItems {
@@ -199,6 +207,12 @@ impl LangItems {
             .unwrap_or_default()
     }
 
+    fn is_bit_size(tcx: TyCtxt<'_>, def_id: DefId) -> bool {
+        find_lang_item(tcx, def_id)
+            .map(|lang_item| matches!(lang_item, LangItem::BitSize))
+            .unwrap_or_default()
+    }
+
     fn collect(tcx: TyCtxt<'_>, ferrum_hdl: CrateNum) -> Self {
         let traits = tcx.traits(ferrum_hdl);
 
@@ -242,6 +256,19 @@ impl LangItems {
             }
         }
 
+        let bit_size = traits
+            .iter()
+            .find(|item| Self::is_bit_size(tcx, **item))
+            .copied()
+            .expect("BitSize trait expected");
+
+        let mut bit_size_bits = None;
+        for item in tcx.associated_items(bit_size).in_definition_order() {
+            if let Some(LangItem::BitSizeBits) = find_lang_item(tcx, item.def_id) {
+                bit_size_bits = Some(item.def_id);
+            }
+        }
+
         Self {
             module,
             mod_logic: mod_logic.expect("Module::logic expected"),
@@ -249,6 +276,7 @@ impl LangItems {
             freq: freq.expect("ClockDomain::FREQ expected"),
             rst_kind: rst_kind.expect("ClockDomain::RST_KIND expected"),
             rst_pol: rst_pol.expect("ClockDomain::RST_POLARITY expected"),
+            bit_size_bits: bit_size_bits.expect("BitSize::BITS expected"),
         }
     }
 }
@@ -262,9 +290,15 @@ pub struct Compiler<'tcx> {
     lang_items: LangItems,
     blackbox: FxHashMap<DefId, Option<BlackboxKind>>,
     evaluated_modules: FxHashMap<MonoItem<'tcx>, ModuleId>,
+    // Captures assigned by closure modules (e.g. of `FnMut` closures),
+    // their new values are returned after the closure output
+    closure_state: FxHashMap<ModuleId, SmallVec<[FieldIdx; 1]>>,
+    extern_files: Vec<(ModuleId, PathBuf)>,
     item_ty: FxHashMap<Ty<'tcx>, ItemTy<'tcx>>,
     allocated_ty: FxHashMap<ItemTyKind<'tcx>, ItemTy<'tcx>>,
     file_names: FxHashMap<StableSourceFileId, Option<PathBuf>>,
+    // Spans of the node span strings, to report the netlist warnings
+    spans: FxHashMap<String, Span>,
     // pin_constr: FxHashMap<NonEmptyStr, PinConstraints>,
     post_dominator: FxHashMap<DefId, PostDominator>,
     switch_tuples: FxHashMap<(DefId, BasicBlock), Option<SwitchTupleRef<'tcx>>>,
@@ -288,9 +322,12 @@ impl<'tcx> Compiler<'tcx> {
             lang_items,
             blackbox: Default::default(),
             evaluated_modules: Default::default(),
+            closure_state: Default::default(),
+            extern_files: Default::default(),
             item_ty: Default::default(),
             allocated_ty: Default::default(),
             file_names: Default::default(),
+            spans: Default::default(),
             // pin_constr: Default::default(),
             post_dominator: Default::default(),
             switch_tuples: Default::default(),
@@ -369,18 +406,127 @@ impl<'tcx> Compiler<'tcx> {
         Err(Error::MissingTop)
     }
 
+    // Const generic parameters of the top module (including the ones of its `impl`)
+    // are set by `--top-param` options by name or positionally if names are omitted
+    fn top_generics(&self, top: DefId) -> Result<GenericArgsRef<'tcx>, Error> {
+        let span = self.tcx.def_span(top);
+        let const_args = self
+            .args
+            .top_params
+            .iter()
+            .filter(|(name, _)| name.is_none())
+            .map(|(_, value)| *value)
+            .collect::<Vec<_>>();
+        if !const_args.is_empty() && const_args.len() != self.args.top_params.len() {
+            return Err(SpanError::new(SpanErrorKind::MixedTopParams, span).into());
+        }
+
+        let mut params = Vec::new();
+        let mut const_params = 0;
+        let mut err = None;
+
+        let generics = GenericArgs::for_item(self.tcx, top, |param, _| {
+            let name = param.name.as_str();
+            params.push(name.to_string());
+
+            match param.kind {
+                GenericParamDefKind::Lifetime => self.tcx.lifetimes.re_erased.into(),
+                GenericParamDefKind::Const { .. } => {
+                    let value = if const_args.is_empty() {
+                        self.args
+                            .top_params
+                            .iter()
+                            .find(|(param, _)| param.as_deref() == Some(name))
+                            .map(|(_, value)| value)
+                    } else {
+                        const_args.get(const_params)
+                    };
+                    const_params += 1;
+
+                    match value {
+                        Some(value) => {
+                            let ty =
+                                self.tcx.type_of(param.def_id).instantiate_identity();
+                            if self.is_valid_top_param(ty, *value) {
+                                Const::from_bits(
+                                    self.tcx,
+                                    *value,
+                                    ParamEnv::empty().and(ty),
+                                )
+                                .into()
+                            } else {
+                                err.get_or_insert(SpanErrorKind::InvalidTopParam(
+                                    name.to_string(),
+                                    *value,
+                                ));
+                                self.tcx.mk_param_from_def(param)
+                            }
+                        }
+                        None => {
+                            err.get_or_insert(SpanErrorKind::MissingTopParam(
+                                name.to_string(),
+                            ));
+                            self.tcx.mk_param_from_def(param)
+                        }
+                    }
+                }
+                GenericParamDefKind::Type { .. } => {
+                    err.get_or_insert(SpanErrorKind::NotSynthGenParam);
+                    self.tcx.mk_param_from_def(param)
+                }
+            }
+        });
+
+        if let Some(name) = self
+            .args
+            .top_params
+            .iter()
+            .filter_map(|(name, _)| name.as_ref())
+            .find(|name| !params.contains(name))
+        {
+            err.get_or_insert(SpanErrorKind::UnknownTopParam(name.clone()));
+        }
+
+        if !const_args.is_empty() && const_args.len() != const_params {
+            err = Some(SpanErrorKind::TopParamsCount(
+                const_params,
+                const_args.len(),
+            ));
+        }
+
+        match err {
+            Some(kind) => Err(SpanError::new(kind, span).into()),
+            None => Ok(generics),
+        }
+    }
+
+    // Checks that the value of a top module parameter fits into its type
+    fn is_valid_top_param(&self, ty: Ty<'tcx>, value: u128) -> bool {
+        match ty.kind() {
+            ty::TyKind::Bool => value <= 1,
+            ty::TyKind::Char => {
+                u32::try_from(value).ok().and_then(char::from_u32).is_some()
+            }
+            _ => self
+                .tcx
+                .layout_of(ParamEnv::empty().and(ty))
+                .is_ok_and(|layout| {
+                    let bits = layout.size.bits();
+                    bits >= 128 || value >> bits == 0
+                }),
+        }
+    }
+
     fn synth_inner(&mut self) -> Result<(), Error> {
         let crate_name = self.tcx.crate_name(LOCAL_CRATE);
 
         let root_dir = &env::var("CARGO_MANIFEST_DIR").unwrap();
         let root_dir = StdPath::new(&root_dir);
-        let name = "top";
-
-        let synth_path = root_dir.join("synth").join("verilog");
-        fs::create_dir_all(&synth_path)?;
 
-        let mut path = synth_path.join(name);
-        path.set_extension("v");
+        let path = self.args.verilog_path(root_dir);
+        if let Some(synth_path) = path.parent() {
+            fs::create_dir_all(synth_path)?;
+        }
 
         self.print_message(
             &"Synthesizing",
@@ -394,17 +540,54 @@ impl<'tcx> Compiler<'tcx> {
         let elapsed = Instant::now();
 
         let top = self.find_top_module()?;
-        self.visit_fn(top.into(), GenericArgs::empty(), true)?;
+        let top_generics = self.top_generics(top)?;
+        self.visit_fn(top.into(), top_generics, true)?;
 
         if self.args.dump_netlist {
             self.netlist.dump(false);
         }
         self.netlist.run_visitors();
+        for warning in self.netlist.warnings() {
+            // the innermost span of the chain in the user crate points at the node
+            // (e.g. at the call of `reg` instead of the register in `ferrum_hdl`)
+            let sm = self.tcx.sess.source_map();
+            match warning.span.as_deref().and_then(|span| {
+                span.split(" <- ")
+                    .filter_map(|span| self.spans.get(span))
+                    .find(|span| !sm.is_imported(**span))
+            }) {
+                Some(span) => self.emit_warn(SpanError::new(
+                    SpanErrorKind::NetListWarning(warning.msg.clone()),
+                    *span,
+                )),
+                None => {
+                    self.tcx.sess.dcx().warn(warning.to_string());
+                }
+            }
+        }
+        for error in self.netlist.errors() {
+            self.tcx.sess.dcx().err(error.clone());
+        }
+        self.tcx.sess.dcx().abort_if_errors();
         if self.args.dump_tr_netlist {
             self.netlist.dump(false);
         }
 
-        self.netlist.synth_verilog_into_file(path)?;
+        self.netlist.synth_verilog_into_file(&path)?;
+        self.copy_extern_files(&path)?;
+
+        if self.args.wrapper {
+            let mut cfg = WrapperCfg::default();
+            if let Some(domain) = self.top_domain(top, top_generics) {
+                cfg.freq = domain.freq;
+                cfg.rst_pol = domain.rst_pol;
+            }
+            self.netlist
+                .synth_wrapper_into_file(self.args.wrapper_path(root_dir), cfg)?;
+        }
+
+        self.manifest()
+            .write_into_file(self.args.manifest_path(root_dir))?;
 
         self.print_message(
             &"Synthesized",
@@ -460,6 +643,10 @@ impl<'tcx> Compiler<'tcx> {
         self.tcx.sess.dcx().abort_if_errors();
     }
 
+    pub fn emit_warn(&self, SpanError { kind, span }: SpanError) {
+        self.tcx.sess.dcx().span_warn(span, kind.to_string());
+    }
+
     pub fn type_of(&self, def_id: DefId, generics: GenericArgsRef<'tcx>) -> Ty<'tcx> {
         self.tcx.type_of(def_id).instantiate(self.tcx, generics)
     }
@@ -540,7 +727,14 @@ impl<'tcx> Compiler<'tcx> {
                 file_name.to_string_lossy()
             };
 
-            Some(format!("{file_name}: {lo_line}"))
+            let span_str = format!("{file_name}: {lo_line}");
+            // node spans point at lines, so the spans of a line are merged
+            self.spans
+                .entry(span_str.clone())
+                .and_modify(|line_span| *line_span = line_span.to(span))
+                .or_insert(span);
+
+            Some(span_str)
         } else {
             None
         }
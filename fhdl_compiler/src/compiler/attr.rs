@@ -6,21 +6,32 @@ use rustc_ast::{
 };
 use rustc_hir::def_id::DefId;
 use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
 
 use super::Compiler;
+use crate::error::{Error, SpanError, SpanErrorKind};
 
 const FHDL_TOOL: &str = "fhdl_tool";
 const SYNTH_ATTR: &str = "synth";
 const BLACKBOX_ATTR: &str = "blackbox";
 const BLACKBOX_TY_ATTR: &str = "blackbox_ty";
 const LANG_ITEM_ATTR: &str = "lang_item";
+const EXTERN_VERILOG_ATTR: &str = "extern_verilog";
+const BITPACK_ATTR: &str = "bitpack";
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct SynthAttrs {
     pub inline: bool,
+    pub no_inline: bool,
     pub top: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternVerilog {
+    pub module: String,
+    pub file: String,
+}
+
 pub fn find_fhdl_tool_attr<T>(
     tcx: TyCtxt<'_>,
     attr_kind: &str,
@@ -74,6 +85,117 @@ fn extract_str_from_args(args: &AttrArgs) -> Option<&str> {
     None
 }
 
+// parses `key = "value", ...` arguments
+fn extract_key_values_from_args(args: &AttrArgs) -> Option<Vec<(&str, &str)>> {
+    let AttrArgs::Delimited(DelimArgs { tokens, .. }) = args else {
+        return None;
+    };
+
+    let mut key_values = vec![];
+    let mut trees = tokens.trees();
+    loop {
+        let key = match trees.next() {
+            Some(TokenTree::Token(
+                Token {
+                    kind: TokenKind::Ident(key, _),
+                    ..
+                },
+                _,
+            )) => key.as_str(),
+            None => break,
+            _ => return None,
+        };
+
+        match trees.next() {
+            Some(TokenTree::Token(
+                Token {
+                    kind: TokenKind::Eq,
+                    ..
+                },
+                _,
+            )) => {}
+            _ => return None,
+        }
+
+        let value = match trees.next() {
+            Some(TokenTree::Token(
+                Token {
+                    kind:
+                        TokenKind::Literal(Lit {
+                            kind: LitKind::Str,
+                            symbol,
+                            ..
+                        }),
+                    ..
+                },
+                _,
+            )) => symbol.as_str(),
+            _ => return None,
+        };
+
+        key_values.push((key, value));
+
+        match trees.next() {
+            Some(TokenTree::Token(
+                Token {
+                    kind: TokenKind::Comma,
+                    ..
+                },
+                _,
+            )) => {}
+            None => break,
+            _ => return None,
+        }
+    }
+
+    Some(key_values)
+}
+
+// Bit size of enum discriminants specified by `#[bitpack(bits = N)]` of `BitPack` derive
+pub fn find_bitpack_bits(tcx: TyCtxt<'_>, def_id: DefId) -> Option<u128> {
+    tcx.get_attrs_unchecked(def_id).iter().find_map(|attr| {
+        let AttrKind::Normal(attr) = &attr.kind else {
+            return None;
+        };
+        let segments = &attr.item.path.segments;
+        if segments.len() != 1 || segments[0].ident.as_str() != BITPACK_ATTR {
+            return None;
+        }
+        let AttrArgs::Delimited(DelimArgs { tokens, .. }) = &attr.item.args else {
+            return None;
+        };
+
+        let trees = tokens.trees().collect::<Vec<_>>();
+        trees.windows(3).find_map(|trees| match trees {
+            [TokenTree::Token(
+                Token {
+                    kind: TokenKind::Ident(key, _),
+                    ..
+                },
+                _,
+            ), TokenTree::Token(
+                Token {
+                    kind: TokenKind::Eq,
+                    ..
+                },
+                _,
+            ), TokenTree::Token(
+                Token {
+                    kind:
+                        TokenKind::Literal(Lit {
+                            kind: LitKind::Integer,
+                            symbol,
+                            ..
+                        }),
+                    ..
+                },
+                _,
+            )] if key.as_str() == "bits" => symbol.as_str().parse().ok(),
+            _ => None,
+        })
+    })
+}
+
 impl<'tcx> Compiler<'tcx> {
     fn find_fhdl_tool_attr<T>(
         &self,
@@ -105,6 +227,15 @@ impl<'tcx> Compiler<'tcx> {
     }
 
     pub fn find_synth(&self, def_id: DefId) -> Option<SynthAttrs> {
+        self.find_synth_(def_id).or_else(|| {
+            // methods of trait impls inherit synth attributes from the trait declaration
+            let trait_item_did =
+                self.tcx.opt_associated_item(def_id)?.trait_item_def_id?;
+            self.find_synth_(trait_item_did)
+        })
+    }
+
+    fn find_synth_(&self, def_id: DefId) -> Option<SynthAttrs> {
         self.find_fhdl_tool_attr(SYNTH_ATTR, def_id, |args| {
             let mut attrs = SynthAttrs::default();
 
@@ -121,6 +252,9 @@ impl<'tcx> Compiler<'tcx> {
                         if symbol.as_str() == "inline" {
                             attrs.inline = true;
                         }
+                        if symbol.as_str() == "no_inline" {
+                            attrs.no_inline = true;
+                        }
                         if symbol.as_str() == "top" {
                             attrs.top = true;
                         }
@@ -132,6 +266,31 @@ impl<'tcx> Compiler<'tcx> {
         })
     }
 
+    pub fn find_extern_verilog(
+        &self,
+        def_id: DefId,
+        span: Span,
+    ) -> Result<Option<ExternVerilog>, Error> {
+        self.find_fhdl_tool_attr(EXTERN_VERILOG_ATTR, def_id, |args| {
+            let key_values = extract_key_values_from_args(args);
+            let value = |key: &str| {
+                key_values
+                    .as_ref()?
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, value)| value.to_string())
+            };
+
+            Some(match (value("module"), value("file")) {
+                (Some(module), Some(file)) => Ok(ExternVerilog { module, file }),
+                _ => {
+                    Err(SpanError::new(SpanErrorKind::InvalidExternVerilog, span).into())
+                }
+            })
+        })
+        .transpose()
+    }
+
     pub fn is_synth(&self, def_id: DefId) -> bool {
         self.find_synth(def_id).is_some()
     }
@@ -1,9 +1,10 @@
-use fhdl_netlist::node_ty::NodeTy;
+use fhdl_netlist::{const_val::ConstVal, node_ty::NodeTy};
 use rustc_abi::Size;
-use rustc_const_eval::interpret::{alloc_range, Scalar};
+use rustc_const_eval::interpret::Scalar;
+use rustc_hir::def_id::DefId;
 use rustc_middle::{
-    mir::{ConstValue, UnevaluatedConst},
-    ty::{Const, ParamEnv, ScalarInt, Ty},
+    mir::{ConstValue, DestructuredConstant, UnevaluatedConst},
+    ty::{Const, ParamEnv, ScalarInt, Ty, TyKind},
 };
 use rustc_span::Span;
 use tracing::{debug, error};
@@ -79,50 +80,98 @@ impl<'tcx> Compiler<'tcx> {
 
                 None
             }
-            ItemTyKind::Array(array_ty) => {
-                if let ItemTyKind::Node(node_ty @ NodeTy::Unsigned(8)) =
-                    array_ty.ty().kind()
-                {
-                    let value = self.const_eval_resolve(unevaluated)?;
-                    if let ConstValue::Indirect {
-                        alloc_id,
-                        mut offset,
-                    } = value
-                    {
-                        let alloc =
-                            self.tcx.global_alloc(alloc_id).unwrap_memory().inner();
-
-                        let count = array_ty.count() as usize;
-                        if alloc.len() == count {
-                            let item_ty = array_ty.ty();
-
-                            let byte_size = Size::from_bytes(1);
-                            return Some(Item::new(
-                                const_ty,
-                                Group::new_opt((0 .. count).map(|_| {
-                                    let val = alloc
-                                        .read_scalar(
-                                            &self.tcx,
-                                            alloc_range(offset, byte_size),
-                                            false,
-                                        )
-                                        .ok();
-                                    debug!("resolve_unevaluated: read val = {val:?}");
-
-                                    let val = val?.to_u8().ok()? as u128;
-                                    offset += byte_size;
-
-                                    Some(Item::new(
-                                        item_ty,
-                                        ctx.module.const_val(*node_ty, val),
-                                    ))
-                                }))?,
-                            ));
-                        }
-                    }
-                }
+            ItemTyKind::Array(_) => {
+                let value = self.const_eval_resolve(unevaluated)?;
+                let ty = ctx.instantiate(self.tcx, ty);
 
-                None
+                self.const_value_to_item(value, ty, ctx, span)
+            }
+            _ => None,
+        }
+    }
+
+    // Arrays of scalars in `static` items (e.g. lookup tables) become groups of
+    // constants like arrays in `const` items, so indexing them by a signal lowers
+    // to a ROM
+    pub fn resolve_static(
+        &mut self,
+        static_did: DefId,
+        ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Option<Item<'tcx>> {
+        if self.tcx.is_mutable_static(static_did) {
+            return None;
+        }
+
+        let alloc = self.tcx.eval_static_initializer(static_did).ok()?;
+        let value = ConstValue::Indirect {
+            alloc_id: self.tcx.reserve_and_set_memory_alloc(alloc),
+            offset: Size::ZERO,
+        };
+        debug!("resolve_static: static = {static_did:?} ty = {ty:?}");
+
+        self.const_value_to_item(value, ty, ctx, span)
+    }
+
+    fn const_value_to_item(
+        &mut self,
+        value: ConstValue<'tcx>,
+        ty: Ty<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Option<Item<'tcx>> {
+        let item_ty = self.resolve_ty(ty, ctx.generic_args, span).ok()?;
+
+        match item_ty.kind() {
+            ItemTyKind::Array(_) => {
+                let fields = self
+                    .tcx
+                    .try_destructure_mir_constant_for_user_output(value, ty)?
+                    .fields;
+
+                Some(Item::new(
+                    item_ty,
+                    Group::new_opt(
+                        fields
+                            .iter()
+                            .map(|(value, ty)| {
+                                self.const_value_to_item(*value, *ty, ctx, span)
+                            })
+                            .collect::<Vec<_>>(),
+                    )?,
+                ))
+            }
+            ItemTyKind::Node(
+                node_ty @ (NodeTy::Bit | NodeTy::Unsigned(_) | NodeTy::Signed(_)),
+            ) if node_ty.width() <= 128 => {
+                let value = self.const_value_to_u128(value, ty)?;
+                let value = ConstVal::new(value, node_ty.width());
+
+                Some(Item::new(
+                    item_ty,
+                    ctx.module.const_val(*node_ty, value.val()),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    // Value of primitive types and of the short variants of `U<N>`/`S<N>`
+    fn const_value_to_u128(&self, value: ConstValue<'tcx>, ty: Ty<'tcx>) -> Option<u128> {
+        if !matches!(ty.kind(), TyKind::Adt(..)) {
+            return const_val_to_u128(value);
+        }
+
+        match self
+            .tcx
+            .try_destructure_mir_constant_for_user_output(value, ty)?
+        {
+            DestructuredConstant {
+                variant,
+                fields: [(value, ty)],
+            } if variant.map(|variant| variant.as_u32()).unwrap_or_default() == 0 => {
+                self.const_value_to_u128(*value, *ty)
             }
             _ => None,
         }
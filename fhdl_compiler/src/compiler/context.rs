@@ -2,11 +2,13 @@ use fhdl_netlist::netlist::Module;
 use rustc_data_structures::fx::FxHashMap;
 use rustc_hir::def_id::DefId;
 use rustc_middle::{
-    mir::{Body, Const as MirConst},
+    mir::{Body, Const as MirConst, Local, Place},
     ty::{EarlyBinder, GenericArgsRef, Ty, TyCtxt},
 };
 use rustc_span::Span;
+use rustc_target::abi::FieldIdx;
 use rustc_type_ir::fold::TypeFoldable;
+use smallvec::SmallVec;
 
 use super::{locals::Locals, Compiler};
 use crate::{compiler::item::Item, error::Error};
@@ -20,6 +22,10 @@ pub struct Context<'tcx> {
     pub fn_did: DefId,
     pub in_switch_tuple: bool,
     consts: FxHashMap<MirConst<'tcx>, Item<'tcx>>,
+    // `local = &mut place`
+    pub mut_borrows: FxHashMap<Local, Place<'tcx>>,
+    // Closure captures borrowed mutably, they are written back after each closure call
+    pub closure_borrows: FxHashMap<Local, SmallVec<[(FieldIdx, Place<'tcx>); 1]>>,
 }
 
 impl<'tcx> Context<'tcx> {
@@ -37,6 +43,8 @@ impl<'tcx> Context<'tcx> {
             fn_did,
             in_switch_tuple: false,
             consts: Default::default(),
+            mut_borrows: Default::default(),
+            closure_borrows: Default::default(),
         }
     }
 
@@ -1,11 +1,13 @@
 use ferrum_hdl::domain::{Polarity, SyncKind};
+use fhdl_common::BlackboxTy;
 use itertools::Itertools;
 use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def_id::DefId;
 use rustc_middle::{
     mir::UnevaluatedConst,
     ty::{
         fast_reject::{simplify_type, TreatParams},
-        AssocKind, GenericArgsRef, Ty,
+        AssocKind, GenericArgsRef, Ty, TyKind,
     },
 };
 
@@ -22,7 +24,7 @@ impl DomainId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Domain {
     pub freq: usize,
     pub rst_kind: SyncKind,
@@ -56,6 +58,66 @@ impl<'tcx> Compiler<'tcx> {
         &self.domains.domains[dom_id.0 as usize]
     }
 
+    // Domain of the first clock in the signature of the top module, otherwise the
+    // domain of the first register
+    pub fn top_domain(
+        &self,
+        top: DefId,
+        generics: GenericArgsRef<'tcx>,
+    ) -> Option<Domain> {
+        let fn_sig = self.fn_sig(top, generics);
+        let clock = fn_sig
+            .inputs_and_output
+            .iter()
+            .flat_map(|ty| ty.walk())
+            .find_map(|arg| match arg.as_type()?.kind() {
+                TyKind::Adt(adt, args)
+                    if self.find_blackbox_ty(adt.did()) == Some(BlackboxTy::Clock) =>
+                {
+                    Some((adt.did(), args.type_at(0)))
+                }
+                _ => None,
+            });
+
+        match clock {
+            Some((clock, dom_ty)) => self.clock_domain(clock, dom_ty),
+            None => self.domains.domains.first().copied(),
+        }
+    }
+
+    // `Clock<D>` is bound by `domain::ClockDomain` which has no lang items, so its
+    // constants are looked up by name
+    fn clock_domain(&self, clock: DefId, dom_ty: Ty<'tcx>) -> Option<Domain> {
+        let dom_trait =
+            self.tcx
+                .predicates_of(clock)
+                .predicates
+                .iter()
+                .find_map(|(pred, _)| {
+                    let trait_id = pred.as_trait_clause()?.def_id();
+                    (self.tcx.item_name(trait_id).as_str() == "ClockDomain")
+                        .then_some(trait_id)
+                })?;
+
+        let args = self.tcx.mk_args(&[dom_ty.into()]);
+        let assoc_items = self.tcx.associated_items(dom_trait);
+        let eval = |name: &str| {
+            assoc_items
+                .in_definition_order()
+                .find(|item| item.kind == AssocKind::Const && item.name.as_str() == name)
+                .and_then(|item| {
+                    self.const_eval_resolve(UnevaluatedConst::new(item.def_id, args))
+                })
+                .and_then(const_val_to_u128)
+        };
+
+        Some(Domain {
+            freq: eval("FREQ")? as usize,
+            rst_kind: SyncKind::from_val(eval("RESET_KIND")?)?,
+            rst_pol: Polarity::from_val(eval("RESET_POLARITY")?)?,
+        })
+    }
+
     fn create_domain(&self, dom_ty: Ty<'tcx>, generics: GenericArgsRef<'tcx>) -> Domain {
         let key = match simplify_type(self.tcx, dom_ty, TreatParams::AsCandidateKey) {
             Some(key) => key,
@@ -1,7 +1,7 @@
-use std::iter;
+use std::{env, fs, io, iter, path::Path};
 
 use fhdl_common::BlackboxKind;
-use fhdl_data_structures::graph::NodeId;
+use fhdl_data_structures::{graph::NodeId, FxHashMap, FxHashSet};
 use fhdl_netlist::{
     netlist::{Module, ModuleId},
     node::{Input, InputArgs, ModInst, ModInstArgs},
@@ -14,11 +14,15 @@ use rustc_hir::{
 };
 use rustc_middle::{
     mir::Local,
-    ty::{ClosureArgs, FnSig, GenericArgsRef, Ty},
+    ty::{ClosureArgs, FnSig, GenericArgsRef, ParamEnv, Ty, TypeVisitableExt},
+};
+use rustc_span::{
+    hygiene::{ExpnKind, MacroKind},
+    Span, Symbol as RustSymbol,
 };
-use rustc_span::{Span, Symbol as RustSymbol};
 
 use super::{
+    attr::ExternVerilog,
     item::{Item, ModuleExt},
     item_ty::ItemTy,
     Compiler, Context,
@@ -31,6 +35,16 @@ use crate::{
 
 const IMPL: &str = "impl";
 
+const NON_STATIC_LEN_ADAPTERS: &[&str] = &[
+    "filter",
+    "filter_map",
+    "flat_map",
+    "flatten",
+    "map_while",
+    "skip_while",
+    "take_while",
+];
+
 static STD_FUNCTIONS: Lazy<Trie> = Lazy::new(|| {
     let std_func: &[&[&str]] = &[
         &["default", "Default", "default"],
@@ -48,6 +62,7 @@ static STD_FUNCTIONS: Lazy<Trie> = Lazy::new(|| {
         &["ops", "bit", "Shl", "shl"],
         &["ops", "bit", "Shr", "shr"],
         &["ops", "bit", "Not", "not"],
+        &["ops", "index", "Index", "index"],
         // Option
         &["option", IMPL, "and"],
         &["option", IMPL, "and_then"],
@@ -124,6 +139,182 @@ fn def_path_data(def_path_data: &DefPathData) -> Option<SymOrStr> {
     }
 }
 
+// Splits the verilog source into identifiers, numbers and punctuation, comments are
+// skipped
+fn verilog_tokens(source: &str) -> Vec<&str> {
+    let is_ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+
+    let mut tokens = vec![];
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("/*") {
+            rest.find("*/").map(|end| end + 2).unwrap_or(rest.len())
+        } else if c.is_whitespace() {
+            c.len_utf8()
+        } else if is_ident_char(c) {
+            let len = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+            tokens.push(&rest[.. len]);
+            len
+        } else {
+            tokens.push(&rest[.. c.len_utf8()]);
+            c.len_utf8()
+        };
+
+        rest = &rest[len ..];
+    }
+
+    tokens
+}
+
+// Returns the index of the token after the parenthesis closing the one at `open`
+fn skip_parens(tokens: &[&str], open: usize) -> usize {
+    let mut depth = 0;
+    for (idx, token) in tokens.iter().enumerate().skip(open) {
+        match *token {
+            "(" => depth += 1,
+            ")" => {
+                depth -= 1;
+                if depth == 0 {
+                    return idx + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tokens.len()
+}
+
+// Parses `input wire [7:0] a, b, output c` declarations into the names of ports and
+// their widths (`None` if the range depends on parameters)
+fn port_decls<'a>(
+    tokens: &[&'a str],
+    names: &mut Vec<&'a str>,
+    widths: &mut FxHashMap<&'a str, Option<u128>>,
+) {
+    let mut is_decl = false;
+    let mut width = Some(1);
+    let mut idx = 0;
+    while idx < tokens.len() {
+        match tokens[idx] {
+            "input" | "output" | "inout" => {
+                is_decl = true;
+                width = Some(1);
+            }
+            "wire" | "reg" | "logic" | "signed" | "unsigned" | "var" | "," => {}
+            "[" => {
+                let end = tokens[idx ..]
+                    .iter()
+                    .position(|token| *token == "]")
+                    .map(|end| idx + end)
+                    .unwrap_or(tokens.len());
+                width = match tokens[idx + 1 .. end] {
+                    [msb, ":", lsb] => msb
+                        .parse::<u128>()
+                        .ok()
+                        .zip(lsb.parse::<u128>().ok())
+                        .map(|(msb, lsb)| msb.abs_diff(lsb) + 1),
+                    _ => None,
+                };
+                idx = end;
+            }
+            // a default value of the output port
+            "=" => {
+                idx = tokens[idx ..]
+                    .iter()
+                    .position(|token| *token == ",")
+                    .map(|end| idx + end)
+                    .unwrap_or(tokens.len());
+                continue;
+            }
+            name => {
+                names.push(name);
+                if is_decl {
+                    widths.insert(name, width);
+                }
+            }
+        }
+        idx += 1;
+    }
+}
+
+// Returns the names and widths of the ports of `module <name>` in the order of its port
+// list (ANSI or non-ANSI style), or `None` if the source doesn't declare the module
+fn extern_module_ports<'a>(
+    source: &'a str,
+    name: &str,
+) -> Option<Vec<(&'a str, Option<u128>)>> {
+    let tokens = verilog_tokens(source);
+    let start = tokens
+        .windows(2)
+        .position(|tokens| tokens[0] == "module" && tokens[1] == name)?
+        + 2;
+    let end = tokens[start ..]
+        .iter()
+        .position(|token| *token == "endmodule")
+        .map(|end| start + end)
+        .unwrap_or(tokens.len());
+    let tokens = &tokens[start .. end];
+
+    let mut idx = 0;
+    if tokens.first() == Some(&"#") {
+        idx = skip_parens(tokens, 1);
+    }
+
+    let mut names = vec![];
+    let mut widths = FxHashMap::default();
+    if tokens.get(idx) == Some(&"(") {
+        let close = skip_parens(tokens, idx);
+        port_decls(&tokens[idx + 1 .. close - 1], &mut names, &mut widths);
+        idx = close;
+    }
+
+    // ports declared in the body of the module
+    for stmt in tokens[idx ..].split(|token| *token == ";") {
+        if let Some(&("input" | "output" | "inout")) = stmt.first() {
+            port_decls(stmt, &mut vec![], &mut widths);
+        }
+    }
+
+    Some(
+        names
+            .into_iter()
+            .map(|name| (name, widths.get(name).copied().flatten()))
+            .collect(),
+    )
+}
+
+fn copy_files<'a>(
+    paths: impl IntoIterator<Item = &'a Path>,
+    out_dir: &Path,
+) -> io::Result<()> {
+    let mut copied = FxHashSet::default();
+    for path in paths {
+        if !copied.insert(path) {
+            continue;
+        }
+
+        if let Some(file_name) = path.file_name() {
+            fs::copy(path, out_dir.join(file_name))?;
+        }
+    }
+
+    Ok(())
+}
+
+// The span comes from the expansion of `unreachable!()`
+pub fn is_in_unreachable_macro(span: Span) -> bool {
+    span.macro_backtrace().any(|expn| {
+        matches!(
+            expn.kind,
+            ExpnKind::Macro(MacroKind::Bang, name)
+                if name.as_str().starts_with("unreachable")
+        )
+    })
+}
+
 pub fn def_path_eq(def_path: &DefPath, items: &[&'static str]) -> bool {
     let mut def_path = def_path.data.iter();
     let mut items = items.iter();
@@ -158,8 +349,12 @@ impl<'tcx> Compiler<'tcx> {
 
             closure_args.sig().skip_binder()
         } else {
+            // late-bound regions (e.g. of `&self` in `Index::index`) are erased, so the
+            // output type doesn't have escaping bound vars
             let fn_sig = self.tcx.fn_sig(def_id);
-            fn_sig.instantiate(self.tcx, generics).skip_binder()
+            self.tcx.instantiate_bound_regions_with_erased(
+                fn_sig.instantiate(self.tcx, generics),
+            )
         }
     }
 
@@ -190,7 +385,15 @@ impl<'tcx> Compiler<'tcx> {
 
     pub fn fn_output(&self, def_id: DefId, generics: GenericArgsRef<'tcx>) -> Ty<'tcx> {
         let fn_sig = self.fn_sig(def_id, generics);
-        fn_sig.output()
+        let output = fn_sig.output();
+
+        // reveal opaque output types (`-> impl Trait`)
+        if output.has_opaque_types() {
+            self.tcx
+                .normalize_erasing_regions(ParamEnv::reveal_all(), output)
+        } else {
+            output
+        }
     }
 
     pub fn make_input(
@@ -219,6 +422,35 @@ impl<'tcx> Compiler<'tcx> {
         Ok(item)
     }
 
+    // Calls of `unreachable!()` and `unreachable_unchecked`, other panics
+    // (e.g. `panic!()`) are not treated as unreachable
+    pub fn is_unreachable_call(&self, fn_did: DefId, span: Span) -> bool {
+        if !self.crates.is_std(fn_did) {
+            return false;
+        }
+
+        let def_path = self.tcx.def_path(fn_did);
+        if def_path_eq(&def_path, &["hint", "unreachable_unchecked"]) {
+            return true;
+        }
+
+        let is_panic = def_path
+            .data
+            .first()
+            .and_then(|def_path| def_path_data(&def_path.data))
+            .map(|data| data.as_ref() == "panicking")
+            .unwrap_or_default();
+
+        is_panic && is_in_unreachable_macro(span)
+    }
+
+    // `Into::into` is inlined only if it's resolved to the blanket impl which
+    // forwards to `From::from`
+    pub fn is_std_into_call(&self, instance_did: DefId) -> bool {
+        self.crates.is_std(instance_did)
+            && def_path_eq(&self.tcx.def_path(instance_did), &["convert", IMPL, "into"])
+    }
+
     pub fn is_std_call(&self, fn_did: DefId) -> bool {
         if self.crates.is_std(fn_did) {
             tracing::debug!("is_std_call: fn_did = {fn_did:?}");
@@ -262,6 +494,101 @@ impl<'tcx> Compiler<'tcx> {
         module.add::<_, ModInst>(mod_inst)
     }
 
+    pub fn visit_extern_fn(
+        &mut self,
+        fn_did: DefId,
+        fn_generics: GenericArgsRef<'tcx>,
+        extern_verilog: ExternVerilog,
+        span: Span,
+    ) -> Result<ModuleId, Error> {
+        let ExternVerilog { module: name, file } = extern_verilog;
+
+        let root_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let path = Path::new(&root_dir).join(&file);
+        let source = fs::read_to_string(&path).map_err(|e| {
+            SpanError::new(
+                SpanErrorKind::ExternVerilogFile(file.clone(), e.to_string()),
+                span,
+            )
+        })?;
+        let Some(extern_ports) = extern_module_ports(&source, &name) else {
+            return Err(SpanError::new(
+                SpanErrorKind::MissingExternModule(name, file),
+                span,
+            )
+            .into());
+        };
+
+        let mut module = Module::new(&name, false);
+        module.external = true;
+
+        // ports are defined by the signature and connected by position
+        for input in self.fn_inputs(fn_did, fn_generics) {
+            let input = self.resolve_fn_out_ty(*input, span)?;
+            module.mk_item_from_ty(
+                input,
+                &|node_ty, module| Some(module.add_input(node_ty, None::<&str>)),
+                span,
+            )?;
+        }
+
+        let output = self.fn_output(fn_did, fn_generics);
+        let output = self.resolve_fn_out_ty(output, span)?;
+        module.mk_item_from_ty(
+            output,
+            &|node_ty, module| Some(module.add_extern_output(node_ty)),
+            span,
+        )?;
+
+        // the ports of the signature must match the ports of the extern module, the
+        // widths depending on parameters are not checked
+        let ports = module
+            .mod_inputs()
+            .iter()
+            .chain(module.mod_outputs().iter())
+            .map(|port| module[*port].ty.width())
+            .collect::<Vec<_>>();
+        if ports.len() != extern_ports.len() {
+            return Err(SpanError::new(
+                SpanErrorKind::ExternPortCount(name, extern_ports.len(), ports.len()),
+                span,
+            )
+            .into());
+        }
+        for ((port, extern_width), width) in extern_ports.into_iter().zip(ports) {
+            if let Some(extern_width) = extern_width.filter(|w| *w != width) {
+                return Err(SpanError::new(
+                    SpanErrorKind::ExternPortWidth(
+                        port.to_string(),
+                        name,
+                        extern_width,
+                        width,
+                    ),
+                    span,
+                )
+                .into());
+            }
+        }
+
+        let module_id = self.netlist.add_module(module);
+        self.extern_files.push((module_id, path));
+
+        Ok(module_id)
+    }
+
+    pub fn copy_extern_files(&self, verilog_path: &Path) -> Result<(), Error> {
+        let out_dir = verilog_path.parent().unwrap_or(Path::new(""));
+        let paths = self
+            .extern_files
+            .iter()
+            .filter(|(module_id, _)| !self.netlist[*module_id].borrow().skip)
+            .map(|(_, path)| path.as_path());
+
+        copy_files(paths, out_dir)?;
+
+        Ok(())
+    }
+
     pub fn find_blackbox(
         &mut self,
         fn_did: DefId,
@@ -294,6 +621,13 @@ impl<'tcx> Compiler<'tcx> {
                 return Some(BlackboxKind::StdClone);
             }
 
+            // identity and the reflexive `impl<T> From<T> for T` are just rewires
+            if def_path_eq(&def_path, &["convert", "identity"])
+                || def_path_eq(&def_path, &["convert", IMPL, "from"])
+            {
+                return Some(BlackboxKind::StdIdentity);
+            }
+
             if def_path_eq(&def_path, &[
                 "iter",
                 "traits",
@@ -304,7 +638,9 @@ impl<'tcx> Compiler<'tcx> {
                 return Some(BlackboxKind::StdIntoIter);
             }
 
-            if def_path_eq(&def_path, &["array", "iter", "impl", "into_iter"]) {
+            if def_path_eq(&def_path, &["array", "iter", "impl", "into_iter"])
+                || def_path_eq(&def_path, &["slice", IMPL, "iter"])
+            {
                 return Some(BlackboxKind::StdIntoIter);
             }
 
@@ -318,11 +654,53 @@ impl<'tcx> Compiler<'tcx> {
                 return Some(BlackboxKind::StdIterEnum);
             }
 
+            if def_path_eq(&def_path, &[
+                "iter", "traits", "iterator", "Iterator", "map",
+            ]) {
+                return Some(BlackboxKind::StdIterMap);
+            }
+
+            if def_path_eq(&def_path, &[
+                "iter", "traits", "iterator", "Iterator", "position",
+            ]) {
+                return Some(BlackboxKind::StdIterPosition);
+            }
+
+            if def_path_eq(&def_path, &[
+                "iter", "traits", "iterator", "Iterator", "find",
+            ]) {
+                return Some(BlackboxKind::StdIterFind);
+            }
+
+            // the number of items produced by these adapters depends on the values
+            // of the items, so they cannot be unrolled into hardware
+            if NON_STATIC_LEN_ADAPTERS.iter().any(|adapter| {
+                def_path_eq(&def_path, &[
+                    "iter", "traits", "iterator", "Iterator", adapter,
+                ])
+            }) {
+                return Some(BlackboxKind::StdIterNonStaticLen);
+            }
+
+            if def_path_eq(&def_path, &["iter", "sources", "repeat", "repeat"]) {
+                return Some(BlackboxKind::StdIterRepeat);
+            }
+
+            if def_path_eq(&def_path, &[
+                "iter", "traits", "iterator", "Iterator", "take",
+            ]) {
+                return Some(BlackboxKind::StdIterTake);
+            }
+
             if def_path_eq(&def_path, &[
                 "iter", "traits", "iterator", "Iterator", "next",
             ]) {
                 return Some(BlackboxKind::StdIterNext);
             }
+
+            if def_path_eq(&def_path, &["option", IMPL, "map"]) {
+                return Some(BlackboxKind::StdOptionMap);
+            }
         }
 
         self.find_blackbox_kind(def_id)
@@ -332,3 +710,81 @@ impl<'tcx> Compiler<'tcx> {
         self.tcx.def_path_str(fn_did)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extern_module_decl() {
+        let source = "// my_ip\nmodule my_ip\n(\n    input wire a\n);\nendmodule\n";
+
+        assert_eq!(
+            extern_module_ports(source, "my_ip"),
+            Some(vec![("a", Some(1))])
+        );
+        assert!(
+            extern_module_ports("module my_ip(input a); endmodule", "my_ip").is_some()
+        );
+        assert!(extern_module_ports(source, "my").is_none());
+        assert!(extern_module_ports("module my_ip_2 (); endmodule", "my_ip").is_none());
+        assert!(extern_module_ports("/* module my_ip */", "my_ip").is_none());
+    }
+
+    #[test]
+    fn extern_module_port_widths() {
+        // ANSI style
+        let source = "module my_ip #(parameter W = 4) (
+            input wire [7:0] a, b, // 8 bits
+            input [W-1:0] c,
+            output reg [0:3] d = 0,
+            output e
+        );
+        endmodule";
+        assert_eq!(
+            extern_module_ports(source, "my_ip"),
+            Some(vec![
+                ("a", Some(8)),
+                ("b", Some(8)),
+                ("c", None),
+                ("d", Some(4)),
+                ("e", Some(1))
+            ])
+        );
+
+        // non-ANSI style
+        let source = "module my_ip (a, b, c);
+            output [2:0] c;
+            input a;
+            input [15:0] b;
+            assign c = { a, b[1:0] };
+        endmodule";
+        assert_eq!(
+            extern_module_ports(source, "my_ip"),
+            Some(vec![("a", Some(1)), ("b", Some(16)), ("c", Some(3))])
+        );
+    }
+
+    #[test]
+    fn copy_extern_files_once() {
+        let dir = env::temp_dir().join(format!("fhdl_extern_{}", std::process::id()));
+        let src_dir = dir.join("src");
+        let out_dir = dir.join("out");
+        fs::create_dir_all(&src_dir).unwrap();
+        fs::create_dir_all(&out_dir).unwrap();
+
+        let stub = src_dir.join("my_ip.v");
+        fs::write(
+            &stub,
+            "module my_ip(input a, output b); assign b = a; endmodule\n",
+        )
+        .unwrap();
+
+        copy_files([stub.as_path(), stub.as_path()], &out_dir).unwrap();
+        let copied = fs::read_to_string(out_dir.join("my_ip.v")).unwrap();
+        assert!(extern_module_ports(&copied, "my_ip").is_some());
+        assert_eq!(fs::read_dir(&out_dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -17,7 +17,7 @@ use fhdl_netlist::{
 };
 use rustc_span::Span;
 use rustc_target::abi::{FieldIdx, VariantIdx};
-use smallvec::SmallVec;
+use smallvec::{smallvec, SmallVec};
 
 use super::{
     item_ty::{ClosureTy, EnumTy, ItemTy, ItemTyKind},
@@ -91,6 +91,11 @@ impl<'tcx> Group<'tcx> {
         &mut v[idx]
     }
 
+    // Replaces the item in the shared storage, so all clones of the group see it
+    pub fn replace(&self, idx: usize, item: Item<'tcx>) {
+        self.0.borrow_mut()[idx] = item;
+    }
+
     pub fn slice(&self, start: usize, len: usize) -> Self {
         let items = &self.0.borrow()[start .. start + len];
         let items = items.iter().cloned();
@@ -574,6 +579,12 @@ pub trait ModuleExt<'tcx> {
     #[allow(clippy::wrong_self_convention)]
     fn to_bitvec(&mut self, item: &Item<'tcx>, span: Span) -> Result<Item<'tcx>, Error>;
 
+    fn const_to_ports(
+        &mut self,
+        item: &Item<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error>;
+
     #[allow(clippy::wrong_self_convention)]
     fn from_bitvec(
         &mut self,
@@ -614,6 +625,8 @@ pub trait ModuleExt<'tcx> {
     ) -> Result<Option<Item<'tcx>>, Error>;
 
     fn to_const_val(&self, item: &Item<'tcx>) -> Option<u128>;
+
+    fn to_const_opt(&self, item: &Item<'tcx>) -> Option<ConstVal>;
 }
 
 impl<'tcx> ModuleExt<'tcx> for Module {
@@ -671,6 +684,35 @@ impl<'tcx> ModuleExt<'tcx> for Module {
         Ok(res)
     }
 
+    // Constants have no ports, so they are replaced with constant nodes wherever the
+    // item is passed through a module boundary
+    fn const_to_ports(
+        &mut self,
+        item: &Item<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        match &item.kind {
+            ItemKind::Const(val) => {
+                let node_ty = item
+                    .ty
+                    .node_ty_opt()
+                    .ok_or_else(|| SpanError::new(SpanErrorKind::NotSynthExpr, span))?;
+
+                Ok(Item::new(item.ty, self.const_val(node_ty, val.val())))
+            }
+            ItemKind::Group(group) => {
+                let items = group.items().to_vec();
+                Ok(Item::new(
+                    item.ty,
+                    Group::try_new(
+                        items.iter().map(|item| self.const_to_ports(item, span)),
+                    )?,
+                ))
+            }
+            _ => Ok(item.clone()),
+        }
+    }
+
     #[allow(clippy::wrong_self_convention)]
     fn to_bitvec(&mut self, item: &Item<'tcx>, span: Span) -> Result<Item<'tcx>, Error> {
         match &item.kind {
@@ -758,6 +800,10 @@ impl<'tcx> ModuleExt<'tcx> for Module {
                 ))
             }
             ItemTyKind::Struct(ty) | ItemTyKind::Closure(ClosureTy { ty, .. }) => {
+                if ty.len() == 0 {
+                    return Ok(Item::new(item_ty, ItemKind::Group(Group::new([]))));
+                }
+
                 let outputs = if ty.len() == 1 {
                     Either::Left(iter::once(port).zip(ty.tys()))
                 } else {
@@ -828,17 +874,42 @@ impl<'tcx> ModuleExt<'tcx> for Module {
             (self.const_val(discr_ty, discr), enum_ty.data_width())
         };
 
-        let inputs = if data_width == 0 {
-            Either::Left(iter::once(discr))
+        let inputs: SmallVec<[Port; 3]> = if data_width == 0 {
+            smallvec![discr]
         } else {
-            let data_part = match data_part {
+            // zero-sized data (e.g. `()`) has no ports
+            let data_part = match data_part.filter(|data_part| data_part.ty.width() != 0)
+            {
                 Some(data_part) => self.to_bitvec(&data_part, span)?.port(),
                 None => self.const_zero(NodeTy::BitVec(data_width)),
             };
 
-            Either::Right([discr, data_part].into_iter())
+            // the data part of the variant is placed right after the discriminant
+            // (see `enum_variant_from_bitvec`), so the narrower variants are
+            // padded with zeros from the LSB side
+            let padding_width = data_width - self[data_part].width();
+            let padding = (padding_width > 0)
+                .then(|| self.const_zero(NodeTy::BitVec(padding_width)));
+
+            [discr, data_part].into_iter().chain(padding).collect()
         };
 
+        // a variant with the constant fields is folded into a constant, so that
+        // the blackboxes can check it by `to_const`
+        let val = inputs
+            .iter()
+            .try_fold(ConstVal::default(), |mut acc, port| {
+                let val = self.to_const(*port)?;
+                (acc.width() + val.width() <= 128).then(|| {
+                    acc.shift(val);
+                    acc
+                })
+            });
+        if let Some(val) = val {
+            let node_ty = NodeTy::BitVec(enum_ty.width());
+            return Ok(Item::new(enum_ty, self.const_val(node_ty, val.val())));
+        }
+
         let merger = MergerArgs {
             inputs,
             rev: false,
@@ -868,8 +939,10 @@ impl<'tcx> ModuleExt<'tcx> for Module {
                     }),
                 ))
             }
+            // the scrutinees of a tuple switch are merged into one selector, so the
+            // first scrutinee takes the most significant bits as in `TupleCase`
+            ItemKind::Group(_) => self.to_bitvec(discr, span),
             ItemKind::Port(_)
-            | ItemKind::Group(_)
             | ItemKind::Const(_)
             | ItemKind::Reg
             | ItemKind::Option(_) => Ok(discr.clone()),
@@ -948,23 +1021,37 @@ impl<'tcx> ModuleExt<'tcx> for Module {
     fn to_const_val(&self, item: &Item<'tcx>) -> Option<u128> {
         let mut acc = ConstVal::default();
         for port in item.ports() {
-            let val = self.to_const(port);
-            let val = val?;
+            let val = self.to_const(port)?;
+            // values wider than 128 bits can't be represented
+            if acc.width() + val.width() > 128 {
+                return None;
+            }
             acc.shift(val);
         }
 
         Some(acc.val())
     }
+
+    // The value of a constant or of a port driven by a constant node
+    fn to_const_opt(&self, item: &Item<'tcx>) -> Option<ConstVal> {
+        match &item.kind {
+            ItemKind::Const(cons) => Some(*cons),
+            ItemKind::Port(port) => self.to_const(*port),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use fhdl_data_structures::index::IndexType;
+    use fhdl_netlist::netlist::NetList;
+    use rustc_span::DUMMY_SP;
 
     use super::*;
     use crate::compiler::{
         domain::DomainId,
-        item_ty::{RegTy, WithTypeInfo},
+        item_ty::{Named, RegTy, StructTy, WithTypeInfo},
     };
 
     #[test]
@@ -1055,4 +1142,170 @@ mod tests {
             &[]
         );
     }
+
+    #[test]
+    fn nested_option_round_trip() {
+        let unit = WithTypeInfo::new(ItemTyKind::Node(NodeTy::BitVec(0)), None);
+        let u4 = WithTypeInfo::new(ItemTyKind::Node(NodeTy::Unsigned(4)), None);
+        let discr_ty = WithTypeInfo::new(ItemTyKind::Node(NodeTy::Unsigned(1)), None);
+        let (unit, u4, discr_ty) =
+            (ItemTy::new(&unit), ItemTy::new(&u4), ItemTy::new(&discr_ty));
+
+        // Option<U<4>>
+        let inner_variants = [
+            Named::new(unit, Symbol::intern("None")),
+            Named::new(u4, Symbol::intern("Some")),
+        ];
+        let inner = WithTypeInfo::new(
+            ItemTyKind::Enum(EnumTy::new(&inner_variants, None, discr_ty)),
+            None,
+        );
+        let inner = ItemTy::new(&inner);
+
+        // Option<Option<U<4>>>
+        let outer_variants = [
+            Named::new(unit, Symbol::intern("None")),
+            Named::new(inner, Symbol::intern("Some")),
+        ];
+        let outer_enum = EnumTy::new(&outer_variants, None, discr_ty);
+        let outer = WithTypeInfo::new(ItemTyKind::Enum(outer_enum), None);
+        let outer = ItemTy::new(&outer);
+
+        let (none_idx, some_idx) = (VariantIdx::from_usize(0), VariantIdx::from_usize(1));
+        let mut module = Module::new("top", true);
+
+        let none = module
+            .enum_variant_to_bitvec(None, outer, none_idx, DUMMY_SP)
+            .unwrap();
+        let some_none = module
+            .enum_variant_to_bitvec(None, inner, none_idx, DUMMY_SP)
+            .unwrap();
+        let some_none = module
+            .enum_variant_to_bitvec(Some(some_none), outer, some_idx, DUMMY_SP)
+            .unwrap();
+        let some_some = module
+            .enum_variant_to_bitvec(
+                Some(Item::new(u4, ConstVal::new(5, 4))),
+                inner,
+                some_idx,
+                DUMMY_SP,
+            )
+            .unwrap();
+        let some_some = module
+            .enum_variant_to_bitvec(Some(some_some), outer, some_idx, DUMMY_SP)
+            .unwrap();
+
+        // match Some(None) { Some(opt) => opt.is_some(), None => .. }
+        let discr = module.get_discr(&some_none, DUMMY_SP).unwrap();
+        let opt = module
+            .enum_variant_from_bitvec(some_none.port(), outer_enum, some_idx, DUMMY_SP)
+            .unwrap();
+        let opt_discr = module.get_discr(&opt, DUMMY_SP).unwrap();
+
+        for item in [&none, &some_none, &some_some, &discr, &opt_discr] {
+            module.add_mod_output(item.port());
+        }
+
+        let mut netlist = NetList::default();
+        let mod_id = netlist.add_module(module);
+        netlist.run_visitors();
+
+        let module = netlist[mod_id].borrow();
+        let outputs = module
+            .mod_outputs()
+            .iter()
+            .map(|port| module.to_const(*port).unwrap().val())
+            .collect::<Vec<_>>();
+
+        // None = 0_00000, Some(None) = 1_00000, Some(Some(5)) = 1_10101
+        assert_eq!(outputs, [0b000000, 0b100000, 0b110101, 1, 0]);
+    }
+
+    #[test]
+    fn const_val_of_wide_group() {
+        let u64_ty = WithTypeInfo::new(ItemTyKind::Node(NodeTy::Unsigned(64)), None);
+        let u100_ty = WithTypeInfo::new(ItemTyKind::Node(NodeTy::Unsigned(100)), None);
+        let (u64_ty, u100_ty) = (ItemTy::new(&u64_ty), ItemTy::new(&u100_ty));
+
+        fn group<'tcx>(
+            module: &mut Module,
+            ty: ItemTy<'tcx>,
+            vals: [u128; 2],
+        ) -> Item<'tcx> {
+            let items = vals.map(|val| {
+                let port = module.const_val(ty.to_bitvec(), val);
+                Item::new(ty, ItemKind::Port(port))
+            });
+
+            Item::new(ty, ItemKind::Group(Group::new(items)))
+        }
+
+        let mut module = Module::new("top", true);
+        let item = group(&mut module, u64_ty, [1, 2]);
+        let wide_item = group(&mut module, u100_ty, [1, 2]);
+
+        assert_eq!(module.to_const_val(&item), Some((1 << 64) | 2));
+        // 200 bits don't fit into u128
+        assert_eq!(module.to_const_val(&wide_item), None);
+    }
+
+    #[test]
+    fn newtype_wrap_unwrap_is_rewire() {
+        let u4 = WithTypeInfo::new(ItemTyKind::Node(NodeTy::Unsigned(4)), None);
+        let u4 = ItemTy::new(&u4);
+
+        // struct Wrap(U<4>)
+        let fields = [Named::new(u4, Symbol::intern("0"))];
+        let wrap = WithTypeInfo::new(ItemTyKind::Struct(StructTy::new(&fields)), None);
+        let wrap = ItemTy::new(&wrap);
+
+        let mut module = Module::new("top", true);
+        let input = module.add_input(NodeTy::Unsigned(4), Some("input"));
+        let nodes = module.node_count();
+
+        let wrapped = module.from_bitvec(input, wrap, DUMMY_SP).unwrap();
+        assert_eq!(wrapped.by_field(FieldIdx::from_usize(0)).port(), input);
+        assert_eq!(module.to_bitvec(&wrapped, DUMMY_SP).unwrap().port(), input);
+        assert_eq!(module.node_count(), nodes);
+    }
+
+    #[test]
+    fn explicit_discriminants() {
+        let unit = WithTypeInfo::new(ItemTyKind::Node(NodeTy::BitVec(0)), None);
+        let discr_ty = WithTypeInfo::new(ItemTyKind::Node(NodeTy::BitVec(3)), None);
+        let (unit, discr_ty) = (ItemTy::new(&unit), ItemTy::new(&discr_ty));
+
+        // enum Op { A = 1, B = 4 }
+        let variants = [
+            Named::new(unit, Symbol::intern("A")),
+            Named::new(unit, Symbol::intern("B")),
+        ];
+        let op = WithTypeInfo::new(
+            ItemTyKind::Enum(EnumTy::new(&variants, Some(&[1, 4]), discr_ty)),
+            None,
+        );
+        let op = ItemTy::new(&op);
+
+        let mut module = Module::new("top", true);
+        for idx in 0 .. 2 {
+            let variant = module
+                .enum_variant_to_bitvec(None, op, VariantIdx::from_usize(idx), DUMMY_SP)
+                .unwrap();
+            module.add_mod_output(variant.port());
+        }
+
+        let mut netlist = NetList::default();
+        let mod_id = netlist.add_module(module);
+        netlist.run_visitors();
+
+        let module = netlist[mod_id].borrow();
+        let outputs = module
+            .mod_outputs()
+            .iter()
+            .map(|port| module.to_const(*port).unwrap().val())
+            .collect::<Vec<_>>();
+
+        // the packed bits are the explicit discriminants, not the variant indices
+        assert_eq!(outputs, [0b001, 0b100]);
+    }
 }
@@ -6,13 +6,13 @@ use std::{
 };
 
 use either::Either;
-use ferrum_hdl::const_functions::{clog2, clog2_len};
+use ferrum_hdl::const_functions::{clog2, clog2_len, mask};
 use fhdl_common::BlackboxTy;
 use fhdl_netlist::{node_ty::NodeTy, symbol::Symbol};
 use rustc_data_structures::intern::Interned;
 use rustc_hir::def_id::DefId;
 use rustc_middle::{
-    mir::{BasicBlockData, Rvalue},
+    mir::{BasicBlockData, Rvalue, UnevaluatedConst},
     query::Key,
     ty::{
         AdtDef, AliasKind, ClosureArgs, EarlyBinder, FieldDef, GenericArg,
@@ -28,9 +28,11 @@ use rustc_type_ir::{
 };
 
 use super::{
+    attr::find_bitpack_bits,
+    cons_::const_val_to_u128,
     domain::DomainId,
     func::def_path_eq,
-    utils::{TreeIter, TreeNode},
+    utils::{find_dyn_ty, TreeIter, TreeNode},
     Compiler, Context,
 };
 use crate::error::{Error, SpanError, SpanErrorKind};
@@ -202,7 +204,7 @@ impl<'tcx> Debug for EnumTy<'tcx> {
 }
 
 impl<'tcx> EnumTy<'tcx> {
-    fn new(
+    pub(crate) fn new(
         variants: &'tcx [Named<ItemTy<'tcx>>],
         discr: Option<&'tcx [u128]>,
         discr_ty: ItemTy<'tcx>,
@@ -801,6 +803,14 @@ impl<'tcx> Compiler<'tcx> {
 
         #[allow(clippy::map_entry)]
         if !self.item_ty.contains_key(&rust_ty) {
+            if let Some(dyn_ty) = find_dyn_ty(rust_ty) {
+                return Err(SpanError::new(
+                    SpanErrorKind::DynDispatch(dyn_ty.to_string()),
+                    span,
+                )
+                .into());
+            }
+
             let item_ty: Option<ItemTy<'_>> = match rust_ty.kind() {
                 TyKind::Bool => Some(self.alloc_ty(NodeTy::Bit, Some(rust_ty))),
                 TyKind::Uint(UintTy::U8) => {
@@ -855,7 +865,7 @@ impl<'tcx> Compiler<'tcx> {
                     let ty = self.resolve_enum_ty(adt, adt_generics, generics, span)?;
                     Some(self.alloc_ty(ty, Some(rust_ty)))
                 }
-                TyKind::Alias(AliasKind::Projection, alias_ty) => {
+                TyKind::Alias(AliasKind::Projection | AliasKind::Opaque, alias_ty) => {
                     let alias_ty = self
                         .tcx
                         .try_instantiate_and_normalize_erasing_regions(
@@ -876,6 +886,11 @@ impl<'tcx> Compiler<'tcx> {
                 TyKind::Ref(_, ty, Mutability::Not) => {
                     Some(self.resolve_ty(*ty, generics, span)?)
                 }
+                // `FnMut` closures (e.g. passed to `Iterator::map`) take themselves
+                // by mutable reference
+                TyKind::Ref(_, ty, Mutability::Mut) if ty.is_closure() => {
+                    Some(self.resolve_ty(*ty, generics, span)?)
+                }
                 TyKind::FnDef(fn_did, fn_generics) => {
                     let (instance_did, instance) =
                         self.resolve_instance(*fn_did, fn_generics, span)?;
@@ -896,11 +911,15 @@ impl<'tcx> Compiler<'tcx> {
                     let closure_args = ClosureArgs {
                         args: closure_generics,
                     };
-                    let struct_ty = self.resolve_tuple_ty(
-                        closure_args.upvar_tys().iter(),
-                        closure_generics,
-                        span,
-                    )?;
+                    // captures by mutable reference are kept by value, the closure
+                    // returns their new values
+                    let upvar_tys =
+                        closure_args.upvar_tys().iter().map(|ty| match ty.kind() {
+                            TyKind::Ref(_, ty, Mutability::Mut) => *ty,
+                            _ => ty,
+                        });
+                    let struct_ty =
+                        self.resolve_tuple_ty(upvar_tys, closure_generics, span)?;
 
                     Some(self.alloc_ty(
                         ItemTyKind::Closure(ClosureTy::new(
@@ -928,6 +947,8 @@ impl<'tcx> Compiler<'tcx> {
                 })?,
             };
 
+            self.check_bit_size(rust_ty, item_ty, span)?;
+
             self.item_ty.insert(rust_ty, item_ty);
         }
 
@@ -940,6 +961,33 @@ impl<'tcx> Compiler<'tcx> {
             .map_err(Into::into)
     }
 
+    fn check_bit_size(
+        &self,
+        rust_ty: Ty<'tcx>,
+        item_ty: ItemTy<'tcx>,
+        span: Span,
+    ) -> Result<(), Error> {
+        let bits = self
+            .const_eval_resolve(UnevaluatedConst::new(
+                self.lang_items.bit_size_bits,
+                self.tcx.mk_args(&[rust_ty.into()]),
+            ))
+            .and_then(const_val_to_u128);
+
+        match bits {
+            Some(bits) if bits != item_ty.width() => Err(SpanError::new(
+                SpanErrorKind::BitSizeMismatch(
+                    rust_ty.to_string(),
+                    bits,
+                    item_ty.width(),
+                ),
+                span,
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
     fn find_item_ty(
         &mut self,
         ty: Ty<'tcx>,
@@ -1196,8 +1244,38 @@ impl<'tcx> Compiler<'tcx> {
                 Ok(Named::new(item_ty, Symbol::intern(variant.name.as_str())))
             })?;
 
+        // Negative discriminants (e.g. `core::cmp::Ordering`) are stored in the minimal
+        // two's complement width, so truncated values of `SwitchInt` targets match them.
+        let neg_discrs = (!discr_seq && adt.repr().discr_type().is_signed())
+            .then(|| {
+                adt.discriminants(self.tcx)
+                    .map(|(_, discr)| {
+                        let (size, _) = discr.ty.int_size_and_signed(self.tcx);
+                        size.sign_extend(discr.val) as i128
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|discrs| discrs.iter().any(|discr| *discr < 0));
+
         let (discr_width, discr) = if discr_seq {
             (clog2_len(variants.len()) as u128, None)
+        } else if let Some(discrs) = neg_discrs {
+            let discr_width = discrs
+                .iter()
+                .map(|discr| {
+                    let discr = if *discr < 0 { !*discr } else { *discr };
+                    (i128::BITS - discr.leading_zeros() + 1) as u128
+                })
+                .max()
+                .unwrap_or_default();
+
+            let discr = self.alloc_from_iter(
+                discrs
+                    .into_iter()
+                    .map(|discr| discr as u128 & mask(discr_width)),
+            );
+
+            (discr_width, Some(discr))
         } else {
             let mut max_discr = 0;
             let discr =
@@ -1212,6 +1290,12 @@ impl<'tcx> Compiler<'tcx> {
             (clog2(max_discr as usize) as u128, Some(discr))
         };
 
+        // `BitPack` derive allows to widen discriminants
+        let discr_width = match find_bitpack_bits(self.tcx, adt.did()) {
+            Some(bits) => discr_width.max(bits),
+            None => discr_width,
+        };
+
         let discr_ty = self.alloc_ty(ItemTyKind::Node(NodeTy::BitVec(discr_width)), None);
 
         Ok(EnumTy::new(variants, discr, discr_ty))
@@ -60,6 +60,9 @@ type ScopeTree<'tcx> = Tree<LocalScopeId, LocalScope<'tcx>>;
 pub struct Locals<'tcx> {
     tree: ScopeTree<'tcx>,
     scope_id: LocalScopeId,
+    // Locals assigned only in some branches of switches, they are dropped with the
+    // branches
+    dropped: FxHashSet<Local>,
 }
 
 impl<'tcx> Default for Locals<'tcx> {
@@ -101,7 +104,11 @@ impl<'tcx> Locals<'tcx> {
         let tree = Tree::new_with_root(LocalScope::default());
         let scope_id = tree.root_id().unwrap();
 
-        Self { tree, scope_id }
+        Self {
+            tree,
+            scope_id,
+            dropped: Default::default(),
+        }
     }
 
     pub fn place(&mut self, local: Local, item: Item<'tcx>) -> Local {
@@ -235,13 +242,12 @@ impl<'tcx> Locals<'tcx> {
 
         let mut locals: FxIndexSet<Local> = Default::default();
 
-        if !inner.is_empty() {
-            locals.extend(
-                inner
-                    .into_iter()
-                    .filter(|(_, count)| *count == branches)
-                    .map(|(local, _)| local),
-            );
+        for (local, count) in inner {
+            if count == branches {
+                locals.insert(local);
+            } else {
+                self.dropped.insert(local);
+            }
         }
         locals.extend(outer);
         locals.sort_unstable();
@@ -283,6 +289,11 @@ impl<'tcx> Locals<'tcx> {
         scope.assign_branch_locals(mux);
     }
 
+    #[inline]
+    pub fn is_dropped(&self, local: Local) -> bool {
+        self.dropped.contains(&local)
+    }
+
     pub fn prune_all_branches(&mut self) {
         self.tree.prune_all_except_root()
     }
@@ -2,13 +2,16 @@ use std::{cell::RefCell, iter, rc::Rc};
 
 use derive_where::derive_where;
 use fhdl_netlist::const_val::ConstVal;
+use rustc_span::Span;
 
 use super::{
     item::{Item, ItemKind},
     item_ty::ItemTy,
-    Compiler,
+    Compiler, Context,
 };
-use crate::compiler::item::Group;
+use crate::{compiler::item::Group, error::Error};
+
+const UNBOUNDED: usize = usize::MAX;
 
 #[derive_where(Debug)]
 #[derive(Clone)]
@@ -34,6 +37,36 @@ impl<'tcx> LoopGen<'tcx> {
         })
     }
 
+    // `iter::repeat` yields copies of the item until it is limited by `take`
+    pub fn repeat(
+        compiler: &mut Compiler<'tcx>,
+        iter_item_ty: ItemTy<'tcx>,
+        item: Item<'tcx>,
+    ) -> Item<'tcx> {
+        Self::new(
+            compiler,
+            iter_item_ty,
+            iter::repeat(item).map(|item| item.deep_clone()),
+            UNBOUNDED,
+        )
+    }
+
+    #[inline]
+    pub fn is_unbounded(&self) -> bool {
+        self.len == UNBOUNDED
+    }
+
+    pub fn take(&self, compiler: &mut Compiler<'tcx>, count: usize) -> Item<'tcx> {
+        let iter = self.iter.clone();
+
+        Self::new(
+            compiler,
+            self.iter_item_ty,
+            iter::from_fn(move || iter.borrow_mut().next()).take(count),
+            self.len.min(count),
+        )
+    }
+
     pub fn enumerate(&self, compiler: &mut Compiler<'tcx>) -> Item<'tcx> {
         let iter = self.iter.clone();
 
@@ -58,6 +91,44 @@ impl<'tcx> LoopGen<'tcx> {
         )
     }
 
+    // The closure is instantiated for all remaining items at once, so chains of maps
+    // are fused into a single loop
+    pub fn map(
+        &self,
+        compiler: &mut Compiler<'tcx>,
+        closure: &Item<'tcx>,
+        iter_item_ty: ItemTy<'tcx>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Item<'tcx>, Error> {
+        let items = self
+            .items()
+            .into_iter()
+            .map(|item| compiler.instantiate_closure(closure, &[item], ctx, span))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the iterator can be partially consumed, so only the remaining items are mapped
+        let len = items.len();
+        Ok(Self::new(compiler, iter_item_ty, items.into_iter(), len))
+    }
+
+    // Takes all remaining items
+    pub fn items(&self) -> Vec<Item<'tcx>> {
+        assert!(!self.is_unbounded());
+        let mut items = Vec::with_capacity(self.len);
+        while let Some(item) = self.iter.borrow_mut().next() {
+            items.push(item);
+        }
+
+        items
+    }
+
+    // Takes at most `count` items
+    pub fn take_items(&self, count: usize) -> Vec<Item<'tcx>> {
+        let mut iter = self.iter.borrow_mut();
+        (&mut *iter).take(count).collect()
+    }
+
     pub fn next(&self, compiler: &mut Compiler<'tcx>) -> Item<'tcx> {
         let item = self.iter.borrow_mut().next();
         Item::new(
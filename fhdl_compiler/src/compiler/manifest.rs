@@ -0,0 +1,97 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use serde::Serialize;
+
+use super::Compiler;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestEntry {
+    pub module: String,
+    pub path: String,
+    pub span: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Manifest {
+    pub modules: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn new(modules: impl IntoIterator<Item = ManifestEntry>) -> Self {
+        let mut modules = modules.into_iter().collect::<Vec<_>>();
+        modules.sort_by(|lhs, rhs| lhs.module.cmp(&rhs.module));
+        modules.dedup();
+
+        Self { modules }
+    }
+
+    pub fn write_into_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(file, self).map_err(io::Error::from)
+    }
+}
+
+impl<'tcx> Compiler<'tcx> {
+    pub fn manifest(&self) -> Manifest {
+        Manifest::new(
+            self.evaluated_modules
+                .iter()
+                .filter_map(|(mono_item, mod_id)| {
+                    let module = self.netlist[*mod_id].borrow();
+                    if module.skip {
+                        return None;
+                    }
+
+                    Some(ManifestEntry {
+                        module: module.name.to_string(),
+                        path: self.fn_name(mono_item.0.did()),
+                        span: module.span().map(ToString::to_string),
+                    })
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_entries() {
+        let manifest = Manifest::new([
+            ManifestEntry {
+                module: "top".into(),
+                path: "top".into(),
+                span: Some("src/lib.rs:10".into()),
+            },
+            ManifestEntry {
+                module: "adder".into(),
+                path: "utils::adder".into(),
+                span: Some("src/utils.rs:3".into()),
+            },
+        ]);
+
+        let json = serde_json::to_value(manifest).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "modules": [
+                    {
+                        "module": "adder",
+                        "path": "utils::adder",
+                        "span": "src/utils.rs:3",
+                    },
+                    {
+                        "module": "top",
+                        "path": "top",
+                        "span": "src/lib.rs:10",
+                    },
+                ]
+            })
+        );
+    }
+}
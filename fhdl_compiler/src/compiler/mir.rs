@@ -1,8 +1,11 @@
 use std::{convert::identity, fmt::Debug, iter, ops::Deref, vec::IntoIter};
 
+use fhdl_common::{BlackboxTy, LangItem};
 use fhdl_netlist::{
+    const_val::ConstVal,
     netlist::{Module, ModuleId},
     node::{Pass, PassArgs},
+    node_ty::NodeTy,
     symbol::Symbol,
 };
 use rustc_hir::{
@@ -13,15 +16,16 @@ use rustc_hir::{
 use rustc_index::IndexVec;
 use rustc_middle::{
     mir::{
-        AggregateKind, BasicBlock, BorrowKind, Const, ConstOperand, ConstValue, Local,
-        LocalDecl, MutBorrowKind, Operand, Place, PlaceElem, Promoted, Rvalue,
-        StatementKind, TerminatorKind, UnOp, VarDebugInfoContents, RETURN_PLACE,
-        START_BLOCK,
+        interpret::{GlobalAlloc, Scalar},
+        AggregateKind, BasicBlock, BorrowKind, CastKind, Const, ConstOperand, ConstValue,
+        Local, LocalDecl, MutBorrowKind, NonDivergingIntrinsic, Operand, Place,
+        PlaceElem, Promoted, Rvalue, StatementKind, TerminatorKind, UnOp,
+        VarDebugInfoContents, RETURN_PLACE, START_BLOCK,
     },
     query::Key,
     ty::{
-        GenericArgsRef, ImplSubject, Instance, InstanceDef, List, ParamEnv, ParamEnvAnd,
-        TyCtxt, TyKind,
+        adjustment::PointerCoercion, EarlyBinder, GenericArgsRef, ImplSubject, Instance,
+        InstanceDef, List, ParamEnv, ParamEnvAnd, Ty, TyCtxt, TyKind,
     },
 };
 use rustc_span::{def_id::LOCAL_CRATE, Span};
@@ -30,12 +34,14 @@ use smallvec::SmallVec;
 use tracing::{debug, error, instrument};
 
 use super::{
-    item::{CombineOutputs, Group, Item},
+    attr::find_lang_item,
+    item::{CombineOutputs, CombineOutputsIter, Group, Item, ItemKind},
     item_ty::{ItemTy, ItemTyKind},
+    utils::find_dyn_ty,
     Compiler, Context, MonoItem,
 };
 use crate::{
-    blackbox::{bin_op::BinOp, un_op::BitNot},
+    blackbox::{bin_op::BinOp, bitvec, cast::CastFrom, un_op::BitNot},
     compiler::{cons_::scalar_to_u128, item::ModuleExt},
     error::{Error, SpanError, SpanErrorKind},
 };
@@ -53,7 +59,7 @@ impl<'tcx> Debug for DefIdOrPromoted<'tcx> {
 }
 
 impl<'tcx> DefIdOrPromoted<'tcx> {
-    fn did(&self) -> DefId {
+    pub(super) fn did(&self) -> DefId {
         match self {
             Self::DefId(did, _) => *did,
             Self::Promoted(did, _) => *did,
@@ -98,25 +104,31 @@ impl<'tcx> Compiler<'tcx> {
                 .def_ident_span(fn_did)
                 .unwrap_or_else(|| self.tcx.def_span(fn_did));
 
+            if let DefIdOrPromoted::DefId(..) = def_id_or_promoted {
+                if let Some(extern_verilog) = self.find_extern_verilog(fn_did, span)? {
+                    let module_id =
+                        self.visit_extern_fn(fn_did, fn_generics, extern_verilog, span)?;
+                    self.evaluated_modules.insert(mono_item, module_id);
+
+                    return Ok(module_id);
+                }
+            }
+
             let mut module_sym = self.module_name(fn_did);
 
-            let (mir, inline) = match def_id_or_promoted {
+            let (mir, inline, no_inline) = match def_id_or_promoted {
                 DefIdOrPromoted::DefId(fn_did, instance_def) => {
                     let mir = self.tcx.instance_mir(instance_def);
-                    let synth_attrs = self.find_synth(fn_did);
-                    let inline = synth_attrs
-                        .as_ref()
-                        .map(|synth_attrs| synth_attrs.inline)
-                        .unwrap_or_default();
+                    let synth_attrs = self.find_synth(fn_did).unwrap_or_default();
 
-                    (mir, inline)
+                    (mir, synth_attrs.inline, synth_attrs.no_inline)
                 }
                 DefIdOrPromoted::Promoted(fn_did, promoted) => {
                     let promoted_mir = self.tcx.promoted_mir(fn_did);
                     let mir = &promoted_mir[promoted];
                     module_sym =
                         Symbol::intern_args(format_args!("{}_promoted", module_sym));
-                    (mir, true)
+                    (mir, true, false)
                 }
             };
 
@@ -131,6 +143,7 @@ impl<'tcx> Compiler<'tcx> {
             if !top_module && inline {
                 module.inline = true;
             }
+            module.force_no_inline = no_inline;
 
             let mut ctx = Context::new(fn_did, module, fn_generics, mir);
 
@@ -160,15 +173,43 @@ impl<'tcx> Compiler<'tcx> {
                 }
             }
 
+            // the captures before the closure body is visited
+            let closure = match self.tcx.def_kind(fn_did) {
+                DefKind::Closure => inputs.first().map(|closure| closure.deep_clone()),
+                _ => None,
+            };
+
             self.visit_blocks(None, None, &mut ctx)?;
 
+            self.visit_zero_sized_output(&mut ctx, span)?;
             self.visit_fn_output(&mut ctx);
+            let closure_state = match closure {
+                Some(closure) => self.visit_closure_state(&closure, &mut ctx),
+                None => SmallVec::new(),
+            };
 
             for var_debug_info in &mir.var_debug_info {
                 let name = var_debug_info.name.as_str();
                 let span = var_debug_info.source_info.span;
                 match var_debug_info.value {
                     VarDebugInfoContents::Place(place) => {
+                        // locals assigned only in some branches are dropped after
+                        // switches, zero-sized locals are never assigned
+                        if ctx.locals.get_opt(place.local).is_none() {
+                            if ctx.locals.is_dropped(place.local) {
+                                continue;
+                            }
+
+                            let local_ty = self.resolve_ty(
+                                mir.local_decls[place.local].ty,
+                                ctx.generic_args,
+                                span,
+                            )?;
+                            if local_ty.width() == 0 {
+                                continue;
+                            }
+                        }
+
                         let item = self.visit_rhs_place(&place, &mut ctx, span)?;
                         ctx.module.assign_names_to_item(name, &item, true);
                     }
@@ -180,9 +221,16 @@ impl<'tcx> Compiler<'tcx> {
                 }
             }
 
+            if top_module {
+                self.name_axi_stream_ports(&inputs, &mut ctx);
+            }
+
             let module_id = self.netlist.add_module(ctx.module);
 
             self.evaluated_modules.insert(mono_item, module_id);
+            if !closure_state.is_empty() {
+                self.closure_state.insert(module_id, closure_state);
+            }
 
             debug!("end");
         }
@@ -307,6 +355,29 @@ impl<'tcx> Compiler<'tcx> {
             .collect()
     }
 
+    // Assignments of zero-sized values (e.g. `()`) are removed from MIR, so the return
+    // place of functions returning them is never assigned
+    fn visit_zero_sized_output(
+        &mut self,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<(), Error> {
+        if ctx.locals.get_opt(RETURN_PLACE).is_some() {
+            return Ok(());
+        }
+
+        let output_ty = self.resolve_ty(
+            ctx.mir.local_decls[RETURN_PLACE].ty,
+            ctx.generic_args,
+            span,
+        )?;
+        if let Some(output) = ctx.module.mk_zero_sized_val(output_ty, span)? {
+            ctx.locals.place(RETURN_PLACE, output);
+        }
+
+        Ok(())
+    }
+
     pub fn visit_fn_output(&self, ctx: &mut Context<'tcx>) {
         let module = &mut ctx.module;
 
@@ -333,6 +404,110 @@ impl<'tcx> Compiler<'tcx> {
         ctx.module.assign_names_to_item("out", &output, false);
     }
 
+    // Ports of `AxiStream` items of the top module get the standard handshake names
+    // (e.g. `m_axis_tdata`). The items are found by the types of the top function, as
+    // item types don't keep the struct definitions
+    fn name_axi_stream_ports(&self, inputs: &[Item<'tcx>], ctx: &mut Context<'tcx>) {
+        let output = ctx.locals.get(RETURN_PLACE);
+        let args = ctx.mir.args_iter().zip(inputs);
+
+        for (local, item) in iter::once((RETURN_PLACE, &output)).chain(args) {
+            let ty = EarlyBinder::bind(ctx.mir.local_decls[local].ty)
+                .instantiate(self.tcx, ctx.generic_args);
+            self.name_axi_stream_item(ty, item, &mut ctx.module);
+        }
+    }
+
+    fn name_axi_stream_item(&self, ty: Ty<'tcx>, item: &Item<'tcx>, module: &mut Module) {
+        let ItemKind::Group(group) = &item.kind else {
+            return;
+        };
+
+        match ty.kind() {
+            TyKind::Adt(adt, _)
+                if find_lang_item(self.tcx, adt.did()) == Some(LangItem::AxiStream) =>
+            {
+                // the stream is named by the item, e.g. `m_axis$tvalid` -> `m_axis`
+                let tvalid = group.by_idx(1).port();
+                let prefix = module[tvalid]
+                    .sym
+                    .map(|sym| {
+                        let sym = sym.as_str();
+                        let sym = sym.strip_suffix("$tvalid").unwrap_or(sym);
+                        sym.trim_start_matches('_').replace('$', "_")
+                    })
+                    .filter(|prefix| !prefix.is_empty())
+                    .unwrap_or_else(|| "axis".to_string());
+
+                for (field, item) in ["tdata", "tvalid"].into_iter().zip(group.to_iter())
+                {
+                    let ports = item.ports().collect::<SmallVec<[_; 1]>>();
+                    for (idx, port) in ports.iter().enumerate() {
+                        let sym = if ports.len() == 1 {
+                            Symbol::intern_args(format_args!("{prefix}_{field}"))
+                        } else {
+                            Symbol::intern_args(format_args!("{prefix}_{field}_{idx}"))
+                        };
+                        module[*port].sym = Some(sym);
+                    }
+                }
+            }
+            TyKind::Adt(adt, args)
+                if self.find_blackbox_ty(adt.did()) == Some(BlackboxTy::Signal) =>
+            {
+                self.name_axi_stream_item(args.type_at(1), item, module);
+            }
+            TyKind::Tuple(tys) if tys.len() == group.len() => {
+                for (ty, item) in tys.iter().zip(group.to_iter()) {
+                    self.name_axi_stream_item(ty, &item, module);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Captures which are assigned in the closure body keep the state of the closure
+    // between calls, their new values are added to the module outputs
+    fn visit_closure_state(
+        &self,
+        orig: &Item<'tcx>,
+        ctx: &mut Context<'tcx>,
+    ) -> SmallVec<[FieldIdx; 1]> {
+        let closure = ctx.locals.get(Local::from_u32(1));
+        let module = &mut ctx.module;
+
+        let mut state = SmallVec::new();
+        for (idx, (orig, capture)) in orig
+            .group()
+            .items()
+            .iter()
+            .zip(closure.group().items().iter())
+            .enumerate()
+        {
+            if orig.ports().eq(capture.ports()) {
+                continue;
+            }
+
+            state.push(FieldIdx::from_usize(idx));
+            for port in capture.ports() {
+                let port = if module.is_mod_output(port) || module[port.node].is_input() {
+                    let sym = module[port].sym;
+                    module.add_and_get_port::<_, Pass>(PassArgs {
+                        input: port,
+                        sym,
+                        ty: None,
+                    })
+                } else {
+                    port
+                };
+
+                module.add_mod_output(port);
+            }
+        }
+
+        state
+    }
+
     pub fn visit_blocks(
         &mut self,
         start: Option<BasicBlock>,
@@ -367,6 +542,9 @@ impl<'tcx> Compiler<'tcx> {
 
             match &statement.kind {
                 StatementKind::StorageLive(_) | StatementKind::StorageDead(_) => {}
+                // hints such as the range of a discriminant before its cast
+                StatementKind::Intrinsic(intrinsic)
+                    if matches!(**intrinsic, NonDivergingIntrinsic::Assume(_)) => {}
                 StatementKind::Assign(assign) => {
                     let rvalue = &assign.1;
                     let rvalue_ty = rvalue.ty(&mir.local_decls, self.tcx);
@@ -391,6 +569,16 @@ impl<'tcx> Compiler<'tcx> {
                             },
                             place,
                         ) => Some(self.visit_rhs_place(place, ctx, span)?),
+                        // method calls take the receiver by a two-phase borrow, only
+                        // iterators are borrowed so (e.g. `arr.iter().position(..)`)
+                        Rvalue::Ref(
+                            _,
+                            BorrowKind::Mut {
+                                kind: MutBorrowKind::TwoPhaseBorrow,
+                            },
+                            place,
+                        ) => Some(self.visit_rhs_place(place, ctx, span)?)
+                            .filter(|item| item.is_loop_gen()),
                         Rvalue::Use(operand) => {
                             Some(self.visit_operand(operand, ctx, span)?)
                         }
@@ -411,7 +599,51 @@ impl<'tcx> Compiler<'tcx> {
                                 self.resolve_ty(lhs_ty, ctx.generic_args, span)?;
                             let bin_op = BinOp::try_from_op(lhs_ty, *bin_op, span)?;
 
-                            Some(bin_op.bin_op(&lhs, &rhs, output_ty, ctx, span)?)
+                            let overflow = self.netlist.cfg().const_overflow;
+                            Some(
+                                bin_op
+                                    .bin_op(&lhs, &rhs, output_ty, overflow, ctx, span)?,
+                            )
+                        }
+                        // `&[T; N]` as `&[T]`, the array item is kept as is
+                        Rvalue::Cast(
+                            CastKind::PointerCoercion(PointerCoercion::Unsize),
+                            operand,
+                            _,
+                        ) if operand
+                            .ty(&mir.local_decls, self.tcx)
+                            .builtin_deref(true)
+                            .map(|ty| ty.ty.is_array())
+                            .unwrap_or_default() =>
+                        {
+                            Some(self.visit_operand(operand, ctx, span)?)
+                        }
+                        // coercion into a trait object, e.g. `&T` as `&dyn Trait`
+                        Rvalue::Cast(
+                            CastKind::PointerCoercion(PointerCoercion::Unsize),
+                            _,
+                            cast_ty,
+                        ) if find_dyn_ty(*cast_ty).is_some() => {
+                            let dyn_ty = find_dyn_ty(*cast_ty).unwrap();
+                            return Err(SpanError::new(
+                                SpanErrorKind::DynDispatch(dyn_ty.to_string()),
+                                span,
+                            )
+                            .into());
+                        }
+                        // e.g. `variant as usize`, the enum is converted into its
+                        // discriminant
+                        Rvalue::Cast(CastKind::IntToInt, operand, cast_ty) => {
+                            let item = self.visit_operand(operand, ctx, span)?;
+                            let item = if item.ty.is_enum_ty() {
+                                ctx.module.get_discr(&item, span)?
+                            } else {
+                                item
+                            };
+                            let cast_ty =
+                                self.resolve_ty(*cast_ty, ctx.generic_args, span)?;
+
+                            Some(CastFrom::convert(&item, cast_ty, ctx, span)?)
                         }
                         Rvalue::UnaryOp(UnOp::Not, operand) => {
                             let expr = self.visit_operand(operand, ctx, span)?;
@@ -424,14 +656,39 @@ impl<'tcx> Compiler<'tcx> {
                             let count = self.eval_const(*const_, span)? as usize;
                             let op = self.visit_operand(op, ctx, span)?;
 
-                            Some(Item::new(
-                                rvalue_ty,
-                                Group::new(
-                                    iter::repeat(op)
-                                        .take(count)
-                                        .map(|item| item.deep_clone()),
-                                ),
-                            ))
+                            // a repeated scalar constant is packed into a single constant
+                            let packed = ctx
+                                .module
+                                .to_const_opt(&op)
+                                .filter(|_| op.ty.node_ty_opt().is_some())
+                                .filter(|val| {
+                                    count > 0 && val.width() * count as u128 <= 128
+                                })
+                                .map(|val| {
+                                    let mut packed = ConstVal::default();
+                                    for _ in 0 .. count {
+                                        packed.shift(val);
+                                    }
+                                    packed
+                                });
+
+                            match packed {
+                                Some(packed) => {
+                                    let port = ctx.module.const_val(
+                                        NodeTy::BitVec(packed.width()),
+                                        packed.val(),
+                                    );
+                                    Some(ctx.module.from_bitvec(port, rvalue_ty, span)?)
+                                }
+                                None => Some(Item::new(
+                                    rvalue_ty,
+                                    Group::new(
+                                        iter::repeat(op)
+                                            .take(count)
+                                            .map(|item| item.deep_clone()),
+                                    ),
+                                )),
+                            }
                         }
                         Rvalue::Aggregate(aggregate_kind, fields) => match aggregate_kind
                             .deref()
@@ -512,6 +769,38 @@ impl<'tcx> Compiler<'tcx> {
                     })?;
 
                     self.assign(assign.0, item, ctx, span)?;
+
+                    if assign.0.projection.is_empty() {
+                        match rvalue {
+                            Rvalue::Ref(_, BorrowKind::Mut { .. }, place) => {
+                                ctx.mut_borrows.insert(assign.0.local, *place);
+                            }
+                            // `local = deref_copy place`, so `*local` is `*place`
+                            Rvalue::CopyForDeref(place) => {
+                                let place = self.tcx.mk_place_deref(*place);
+                                ctx.mut_borrows.insert(assign.0.local, place);
+                            }
+                            Rvalue::Aggregate(aggregate_kind, fields)
+                                if matches!(
+                                    aggregate_kind.deref(),
+                                    AggregateKind::Closure(..)
+                                ) =>
+                            {
+                                let borrows = fields
+                                    .iter_enumerated()
+                                    .filter_map(|(idx, field)| {
+                                        let field = field.place()?.as_local()?;
+                                        Some((idx, *ctx.mut_borrows.get(&field)?))
+                                    })
+                                    .collect::<SmallVec<_>>();
+
+                                if !borrows.is_empty() {
+                                    ctx.closure_borrows.insert(assign.0.local, borrows);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
                 }
                 _ => {
                     error!("statement: {statement:#?}");
@@ -541,8 +830,12 @@ impl<'tcx> Compiler<'tcx> {
 
                         if fn_item.ty.is_closure_ty() {
                             let inputs = self.visit_operands(inputs, ctx, span)?;
+                            let closure = fn_item.deep_clone();
                             let item =
-                                self.instantiate_closure(&fn_item, &inputs, ctx, span)?;
+                                self.instantiate_closure(&closure, &inputs, ctx, span)?;
+                            if !closure.ports().eq(fn_item.ports()) {
+                                self.assign(*place, closure, ctx, span)?;
+                            }
 
                             Some(item)
                         } else {
@@ -571,6 +864,11 @@ impl<'tcx> Compiler<'tcx> {
                 match item {
                     Some(item) => {
                         self.assign(*destination, item, ctx, span)?;
+                        self.write_closure_borrows(
+                            args.iter().map(|arg| &arg.node),
+                            ctx,
+                            span,
+                        )?;
                     }
                     None => {
                         error!(
@@ -595,8 +893,16 @@ impl<'tcx> Compiler<'tcx> {
                 }
 
                 let discr = self.visit_operand(discr, ctx, span)?;
-                if let Some(cons) = discr.const_opt() {
-                    Some(targets.target_for_value(cons.val()))
+                if let Some(cons) = ctx.module.to_const_opt(&discr) {
+                    // switch values of signed discriminants are not truncated
+                    // to the discriminant width
+                    Some(
+                        targets
+                            .iter()
+                            .find(|(val, _)| ConstVal::new(*val, cons.width()) == cons)
+                            .map(|(_, target)| target)
+                            .unwrap_or_else(|| targets.otherwise()),
+                    )
                 } else if let Some(opt) = discr.opt_opt() {
                     Some(match opt {
                         Some(_) => targets.target_for_value(1),
@@ -610,6 +916,10 @@ impl<'tcx> Compiler<'tcx> {
                     let discr_tuple = self.visit_rhs_place(&discr_tuple, ctx, span)?;
 
                     self.visit_switch(block, &discr_tuple, &*switch_tuple, ctx, span)?
+                } else if let Some(next) =
+                    self.visit_bool_switch(block, &discr, targets, ctx, span)?
+                {
+                    Some(next)
                 } else {
                     self.visit_switch(block, &discr, targets, ctx, span)?
                 }
@@ -635,6 +945,23 @@ impl<'tcx> Compiler<'tcx> {
     ) -> Result<(), Error> {
         let local = place.local;
 
+        // `*local = rhs` where `local = &mut borrowed`
+        if let (Some(PlaceElem::Deref), Some(borrowed)) =
+            (place.projection.first(), ctx.mut_borrows.get(&local))
+        {
+            let projection = borrowed
+                .projection
+                .iter()
+                .chain(place.projection.iter().skip(1))
+                .collect::<SmallVec<[_; 4]>>();
+            let place = Place {
+                local: borrowed.local,
+                projection: self.tcx.mk_place_elems(&projection),
+            };
+
+            return self.assign(place, rhs, ctx, span);
+        }
+
         if !ctx.locals.is_root() && !ctx.locals.has_local(local) {
             let rhs = if place.projection.is_empty() {
                 rhs.clone()
@@ -713,6 +1040,21 @@ impl<'tcx> Compiler<'tcx> {
                             if let Some(value) = scalar_to_u128(scalar) {
                                 return self.mk_const(ty, value, ctx, span);
                             }
+
+                            // reference to a `static` item
+                            if let Scalar::Ptr(ptr, _) = scalar {
+                                let alloc_id = ptr.provenance.alloc_id();
+                                if let (GlobalAlloc::Static(static_did), Some(pointee)) = (
+                                    self.tcx.global_alloc(alloc_id),
+                                    ty.builtin_deref(true),
+                                ) {
+                                    if let Some(item) = self
+                                        .resolve_static(static_did, pointee.ty, ctx, span)
+                                    {
+                                        return Ok(item);
+                                    }
+                                }
+                            }
                         }
                         ConstValue::ZeroSized => {
                             if let Some(item) = ctx.find_const(&value.const_) {
@@ -807,6 +1149,7 @@ impl<'tcx> Compiler<'tcx> {
     ) -> Result<(), Error> {
         for place_elem in place.projection {
             lhs = (match place_elem {
+                PlaceElem::Deref => Some(lhs),
                 PlaceElem::Field(idx, _) => Some(unsafe { lhs.by_field_mut(idx) }),
                 PlaceElem::Index(local) => {
                     let idx = ctx.locals.get(local);
@@ -856,10 +1199,16 @@ impl<'tcx> Compiler<'tcx> {
                 }
                 PlaceElem::Index(local) => {
                     let idx = ctx.locals.get(local);
-                    idx.const_opt().map(|cons| {
-                        let idx = cons.val() as usize;
-                        item.by_idx(idx)
-                    })
+                    match idx.const_opt() {
+                        Some(cons) => Some(item.by_idx(cons.val() as usize)),
+                        None => Some(bitvec::index_array(
+                            &mut ctx.module,
+                            &item,
+                            &idx,
+                            self.netlist.cfg().rom_threshold,
+                            span,
+                        )?),
+                    }
                 }
                 PlaceElem::ConstantIndex {
                     offset, from_end, ..
@@ -911,7 +1260,9 @@ impl<'tcx> Compiler<'tcx> {
         let (instance_did, instance) =
             self.resolve_instance(fn_did, fn_generics, span)?;
 
-        let is_std_call = self.is_std_call(fn_did);
+        let is_std_call = self.is_std_call(fn_did) || self.is_std_into_call(instance_did);
+        let operands = inputs.into_iter().collect::<SmallVec<[_; 2]>>();
+        let inputs = operands.iter().copied();
 
         if ((instance_did.is_local() || is_std_call)
             && !self.has_blackbox(fn_did)
@@ -933,7 +1284,34 @@ impl<'tcx> Compiler<'tcx> {
             let span_str = self.span_to_string(span, ctx.fn_did);
             ctx.module.add_span(mod_inst_id, span_str);
 
-            ctx.module.combine_from_node(mod_inst_id, output_ty, span)
+            if !self.closure_state.contains_key(&module_id) {
+                return ctx.module.combine_from_node(mod_inst_id, output_ty, span);
+            }
+
+            let (output, state) = {
+                let mut outputs = CombineOutputs::from_node(&mut ctx.module, mod_inst_id);
+                let output = outputs.next_output(output_ty, span)?;
+                let state =
+                    self.closure_state(module_id, &inputs[0], &mut outputs, span)?;
+                (output, state)
+            };
+
+            // the closure is passed by value or by mutable reference, its new state is
+            // assigned back to the place it's passed from
+            if let Some(place) = operands
+                .first()
+                .and_then(|closure| closure_place(closure, ctx))
+            {
+                let mut closure = inputs[0].deep_clone();
+                for (idx, capture) in state {
+                    unsafe {
+                        *closure.by_field_mut(idx) = capture;
+                    }
+                }
+                self.assign(place, closure, ctx, span)?;
+            }
+
+            Ok(output)
         } else {
             let blackbox = self
                 .find_blackbox(instance_did, span)
@@ -969,6 +1347,15 @@ impl<'tcx> Compiler<'tcx> {
         fn_generics: GenericArgsRef<'tcx>,
         span: Span,
     ) -> Result<(DefId, Instance<'tcx>), Error> {
+        // calls through trait objects are resolved only at runtime
+        if let Some(dyn_ty) = fn_generics.types().find_map(find_dyn_ty) {
+            return Err(SpanError::new(
+                SpanErrorKind::DynDispatch(dyn_ty.to_string()),
+                span,
+            )
+            .into());
+        }
+
         self.tcx
             .resolve_instance(ParamEnvAnd {
                 param_env: ParamEnv::reveal_all(),
@@ -1012,6 +1399,12 @@ impl<'tcx> Compiler<'tcx> {
 
         let module_id = self.visit_fn(fn_did.into(), fn_generics, false)?;
 
+        let inputs = inputs
+            .iter()
+            .map(|input| ctx.module.const_to_ports(input, span))
+            .collect::<Result<Vec<_>, _>>()?;
+        let inputs = inputs.as_slice();
+
         let mod_inst_id = if let DefKind::Closure = self.tcx.def_kind(fn_did) {
             self.netlist[module_id].borrow_mut().inline = true;
             self.instantiate_module(
@@ -1030,9 +1423,81 @@ impl<'tcx> Compiler<'tcx> {
         let output_ty = self.resolve_ty(output_ty, List::empty(), span)?;
 
         let mut outputs = CombineOutputs::from_node(&mut ctx.module, mod_inst_id);
+        let output = outputs.next_output(output_ty, span)?;
+
+        // the next calls of the closure use its new state
+        let state = self.closure_state(module_id, closure, &mut outputs, span)?;
+        for (idx, capture) in state {
+            closure.group().replace(idx.as_usize(), capture);
+        }
 
-        outputs.next_output(output_ty, span)
+        Ok(output)
     }
+
+    fn closure_state(
+        &self,
+        module_id: ModuleId,
+        closure: &Item<'tcx>,
+        outputs: &mut CombineOutputs<'_, CombineOutputsIter<'_>>,
+        span: Span,
+    ) -> Result<SmallVec<[(FieldIdx, Item<'tcx>); 1]>, Error> {
+        match self.closure_state.get(&module_id) {
+            Some(state) => state
+                .iter()
+                .map(|idx| {
+                    let ty = closure.by_field(*idx).ty;
+                    Ok((*idx, outputs.next_output(ty, span)?))
+                })
+                .collect(),
+            None => Ok(SmallVec::new()),
+        }
+    }
+
+    // Writes the new values of the captures borrowed mutably by closures passed to the call
+    fn write_closure_borrows<'a>(
+        &mut self,
+        args: impl IntoIterator<Item = &'a Operand<'tcx>>,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<(), Error>
+    where
+        'tcx: 'a,
+    {
+        for arg in args {
+            let Some(local) = closure_local(arg, ctx) else {
+                continue;
+            };
+            let Some(borrows) = ctx.closure_borrows.get(&local).cloned() else {
+                continue;
+            };
+
+            let closure = ctx.locals.get(local);
+            for (idx, place) in borrows {
+                self.assign(place, closure.by_field(idx), ctx, span)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The place of the closure passed by value or by mutable reference
+fn closure_place<'tcx>(
+    operand: &Operand<'tcx>,
+    ctx: &Context<'tcx>,
+) -> Option<Place<'tcx>> {
+    let place = operand.place()?;
+    match place
+        .as_local()
+        .and_then(|local| ctx.mut_borrows.get(&local))
+    {
+        Some(borrowed) => Some(*borrowed),
+        None => Some(place),
+    }
+}
+
+fn closure_local<'tcx>(operand: &Operand<'tcx>, ctx: &Context<'tcx>) -> Option<Local> {
+    closure_place(operand, ctx)?.as_local()
 }
 
 fn dump_rvalue_kind(rvalue: &Rvalue) -> &'static str {
@@ -1,16 +1,23 @@
 use fhdl_netlist::{
     const_val::ConstVal,
     node::{Switch, SwitchArgs, TupleCase},
+    node_ty::NodeTy,
 };
 use rustc_middle::{
-    mir::{BasicBlock, SwitchTargets},
-    ty::Ty,
+    mir::{
+        visit::{PlaceContext, Visitor},
+        BasicBlock, Local, Location, Operand, Rvalue, StatementKind, SwitchTargets,
+        TerminatorKind,
+    },
+    ty::{Ty, TyKind},
 };
 use rustc_span::Span;
 use tracing::{error, instrument};
 
 use super::{
+    func::is_in_unreachable_macro,
     item::{Group, Item, ModuleExt},
+    item_ty::ItemTyKind,
     Compiler, Context, SymIdent,
 };
 use crate::error::{Error, SpanError, SpanErrorKind};
@@ -43,6 +50,49 @@ impl SwitchTargetsExt for SwitchTargets {
 }
 
 impl<'tcx> Compiler<'tcx> {
+    // Blocks which are unreachable (e.g. the default arm of exhaustive match) or
+    // always lead to `unreachable!()` are treated as don't-care branches, a block
+    // leading to other panics is live and fails as a not synthesizable call.
+    pub fn is_dead_block(&self, mut block: BasicBlock, ctx: &Context<'tcx>) -> bool {
+        loop {
+            let block_data = &ctx.mir.basic_blocks[block];
+            if block_data.is_empty_unreachable() {
+                return true;
+            }
+
+            match &block_data.terminator().kind {
+                TerminatorKind::Goto { target } => {
+                    block = *target;
+                }
+                // the message of `unreachable!(..)` is formatted before the panic
+                TerminatorKind::Call {
+                    target: Some(target),
+                    fn_span,
+                    ..
+                } if is_in_unreachable_macro(*fn_span) => {
+                    block = *target;
+                }
+                TerminatorKind::Call {
+                    func: Operand::Constant(func),
+                    target: None,
+                    fn_span,
+                    ..
+                } => {
+                    let ty = ctx.instantiate(self.tcx, func.ty());
+                    return match ty.kind() {
+                        TyKind::FnDef(fn_did, _) => {
+                            self.is_unreachable_call(*fn_did, *fn_span)
+                        }
+                        _ => false,
+                    };
+                }
+                _ => {
+                    return false;
+                }
+            }
+        }
+    }
+
     #[instrument(level = "debug", skip(self, discr, targets, ctx, span))]
     pub fn visit_switch<Targets>(
         &mut self,
@@ -67,7 +117,7 @@ impl<'tcx> Compiler<'tcx> {
 
         if !ctx.locals.has_branches() {
             for (idx, target) in targets.variants() {
-                if !mir.basic_blocks[target].is_empty_unreachable() {
+                if !self.is_dead_block(target, ctx) {
                     ctx.locals.go_to_variant(idx);
                     self.visit_blocks(Some(target), Some(convergent_block), ctx)?;
                     ctx.locals.leave_branch();
@@ -75,7 +125,7 @@ impl<'tcx> Compiler<'tcx> {
             }
 
             let otherwise = targets.otherwise();
-            if !mir.basic_blocks[otherwise].is_empty_unreachable() {
+            if !self.is_dead_block(otherwise, ctx) {
                 ctx.locals.go_to_otherwise();
                 self.visit_blocks(Some(otherwise), Some(convergent_block), ctx)?;
                 ctx.locals.leave_branch();
@@ -135,4 +185,142 @@ impl<'tcx> Compiler<'tcx> {
 
         Ok(Some(convergent_block))
     }
+
+    // Drop flags are only read by the switches which either skip or run `drop`,
+    // and `drop` is a no-op in the synthesized code, so their values don't matter.
+    fn is_drop_flag(&self, local: Local, ctx: &Context<'tcx>) -> bool {
+        struct Reads(Local, Vec<Location>);
+
+        impl<'tcx> Visitor<'tcx> for Reads {
+            fn visit_local(&mut self, local: Local, cx: PlaceContext, loc: Location) {
+                if local == self.0 && cx.is_use() && !cx.is_place_assignment() {
+                    self.1.push(loc);
+                }
+            }
+        }
+
+        if !ctx.mir.local_decls[local].ty.is_bool() {
+            return false;
+        }
+
+        let mut reads = Reads(local, vec![]);
+        reads.visit_body(ctx.mir);
+
+        let basic_blocks = &ctx.mir.basic_blocks;
+        reads.1.into_iter().all(|loc| {
+            let block_data = &basic_blocks[loc.block];
+            if loc.statement_index != block_data.statements.len() {
+                return false;
+            }
+
+            let TerminatorKind::SwitchInt { targets, .. } = &block_data.terminator().kind
+            else {
+                return false;
+            };
+            let drop_block = &basic_blocks[targets.otherwise()];
+
+            targets.iter().count() == 1
+                && drop_block.statements.is_empty()
+                && matches!(
+                    drop_block.terminator().kind,
+                    TerminatorKind::Drop { target, .. }
+                        if target == targets.target_for_value(0)
+                )
+        })
+    }
+
+    // The branch consists of the single `local = operand` (besides drop flags)
+    // followed by `goto`.
+    fn single_assign_block(
+        &self,
+        block: BasicBlock,
+        ctx: &Context<'tcx>,
+    ) -> Option<(Local, &'tcx Operand<'tcx>, BasicBlock)> {
+        let block_data = &ctx.mir.basic_blocks[block];
+        let TerminatorKind::Goto { target } = block_data.terminator().kind else {
+            return None;
+        };
+
+        let mut assign = None;
+        for statement in &block_data.statements {
+            match &statement.kind {
+                StatementKind::StorageLive(_) | StatementKind::StorageDead(_) => {}
+                StatementKind::Assign(stmt)
+                    if matches!(stmt.1, Rvalue::Use(Operand::Constant(_)))
+                        && stmt.0.projection.is_empty()
+                        && self.is_drop_flag(stmt.0.local, ctx) => {}
+                StatementKind::Assign(stmt) if assign.is_none() => match stmt.as_ref() {
+                    (place, Rvalue::Use(operand)) if place.projection.is_empty() => {
+                        assign = Some((place.local, operand));
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+
+        assign.map(|(local, operand)| (local, operand, target))
+    }
+
+    // `if b { x } else { y }` and `match b { true => x, false => y }` are lowered
+    // into the 2-case mux directly, without collecting the branch locals.
+    pub fn visit_bool_switch(
+        &mut self,
+        switch_block: BasicBlock,
+        discr: &Item<'tcx>,
+        targets: &SwitchTargets,
+        ctx: &mut Context<'tcx>,
+        span: Span,
+    ) -> Result<Option<BasicBlock>, Error> {
+        if !matches!(discr.ty.kind(), ItemTyKind::Node(NodeTy::Bit))
+            || ctx.locals.has_branches()
+        {
+            return Ok(None);
+        }
+
+        let mut values = targets.iter();
+        let (Some((0, false_target)), None) = (values.next(), values.next()) else {
+            return Ok(None);
+        };
+        let true_target = targets.otherwise();
+
+        let (Some((local, false_op, next)), Some((true_local, true_op, true_next))) = (
+            self.single_assign_block(false_target, ctx),
+            self.single_assign_block(true_target, ctx),
+        ) else {
+            return Ok(None);
+        };
+        if local != true_local || next != true_next {
+            return Ok(None);
+        }
+
+        let output_ty =
+            self.resolve_ty(ctx.mir.local_decls[local].ty, ctx.generic_args, span)?;
+        let false_item = self.visit_operand(false_op, ctx, span)?;
+        let true_item = self.visit_operand(true_op, ctx, span)?;
+        if false_item.nodes() != output_ty.nodes()
+            || true_item.nodes() != output_ty.nodes()
+        {
+            return Err(SpanError::new(SpanErrorKind::NotSynthSwitch, span).into());
+        }
+
+        let mux = ctx.module.add::<_, Switch>(SwitchArgs {
+            outputs: output_ty.iter().map(|ty| (ty, None)),
+            sel: discr.port(),
+            variants: [(ConstVal::new(0, 1), false_item.ports())],
+            default: Some(true_item.ports()),
+        });
+        let node_span = self
+            .span_to_string(span, ctx.fn_did)
+            .map(|span| format!("{span} ({switch_block:?})"));
+        ctx.module.add_span(mux, node_span);
+
+        let mux = ctx.module.combine_from_node(mux, output_ty, span)?;
+        ctx.module
+            .assign_names_to_item(SymIdent::Mux.as_str(), &mux, false);
+
+        self.assign(local.into(), mux, ctx, span)?;
+
+        Ok(Some(next))
+    }
 }
@@ -173,7 +173,7 @@ impl<'tcx> Compiler<'tcx> {
             } else {
                 self.find_otherwise(targets.otherwise(), ctx)
             }
-        } else if bbs.is_empty_unreachable() {
+        } else if self.is_dead_block(block, ctx) {
             None
         } else {
             Some(block)
@@ -16,6 +16,7 @@ pub enum SymIdent {
     Extend,
     Cast,
     Sel,
+    Gclk,
 }
 
 impl SymIdent {
@@ -35,6 +36,7 @@ impl SymIdent {
             Self::Extend => "extend",
             Self::Cast => "cast",
             Self::Sel => "sel",
+            Self::Gclk => "gclk",
         }
     }
 }
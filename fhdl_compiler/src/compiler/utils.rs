@@ -1,5 +1,5 @@
 use rustc_hir::def_id::DefId;
-use rustc_middle::ty::{AssocItem, AssocItems, AssocKind};
+use rustc_middle::ty::{AssocItem, AssocItems, AssocKind, Ty, TyKind};
 
 pub enum TreeNode<L, N> {
     Leaf(L),
@@ -70,3 +70,10 @@ impl AssocItemsExt for AssocItems {
             .find(|item| item.trait_item_def_id == Some(lang_item) && item.kind == kind)
     }
 }
+
+// Trait object (`dyn Trait`) nested anywhere in the type
+pub fn find_dyn_ty(ty: Ty<'_>) -> Option<Ty<'_>> {
+    ty.walk()
+        .filter_map(|arg| arg.as_type())
+        .find(|ty| matches!(ty.kind(), TyKind::Dynamic(..)))
+}
@@ -45,6 +45,8 @@ impl SpanError {
 pub enum SpanErrorKind {
     #[error("unsupported conversion")]
     UnsupportedConversion,
+    #[error("cannot cast enum '{0}' into enum '{1}' with a different layout")]
+    IncompatibleEnumCast(String, String),
     #[error("invalid reset kind")]
     InvalidResetKind,
     #[error("invalid reset polarity")]
@@ -58,6 +60,61 @@ pub enum SpanErrorKind {
     NotSynthExpr,
     #[error("not synthesizable call")]
     NotSynthCall,
+    #[error("dynamic dispatch through trait object '{0}' is not synthesizable")]
+    DynDispatch(String),
     #[error("not synthesizable if-else/match expression")]
     NotSynthSwitch,
+    #[error("iterator adapter '{0}' produces an iterator of non-static length")]
+    NonStaticIterLen(String),
+    #[error("unbounded iterator, limit the number of its items with `take`")]
+    UnboundedIter,
+    #[error("iterator yields {1} items, but {0} are expected")]
+    IterLenMismatch(u128, u128),
+    #[error("'BitSize::BITS' of type '{0}' is {1}, but its synthesized width is {2}")]
+    BitSizeMismatch(String, u128, u128),
+    #[error(
+        "invalid 'extern_verilog' attribute, expected `module = \"..\", file = \"..\"`"
+    )]
+    InvalidExternVerilog,
+    #[error("cannot read extern verilog file '{0}': {1}")]
+    ExternVerilogFile(String, String),
+    #[error("module '{0}' is not declared in extern verilog file '{1}'")]
+    MissingExternModule(String, String),
+    #[error("extern module '{0}' has {1} ports, but the signature defines {2}")]
+    ExternPortCount(String, usize, usize),
+    #[error(
+        "port '{0}' of extern module '{1}' has width {2}, but the signature defines {3}"
+    )]
+    ExternPortWidth(String, String, u128, u128),
+    #[error("literal {0} does not fit into {1} bits and is truncated to {2}")]
+    LiteralOverflow(u128, u128, u128),
+    #[error("constant expression `{0}` overflows (`--const-overflow error`)")]
+    ConstOverflow(String),
+    #[error("clock gating is not enabled (`--clock-gating`), the gated clock is turned into register enables")]
+    ClockGatingDisabled,
+    #[error("{0}")]
+    NetListWarning(String),
+    #[error("missing value of top module parameter '{0}' (`--top-param {0}=..`)")]
+    MissingTopParam(String),
+    #[error("top module has no const generic parameter '{0}'")]
+    UnknownTopParam(String),
+    #[error("value {1} of top module parameter '{0}' does not fit into its type")]
+    InvalidTopParam(String, u128),
+    #[error("top module has {0} const generic parameters, but {1} values are given by `--top-param`")]
+    TopParamsCount(usize, usize),
+    #[error("`--top-param` values with and without names cannot be combined")]
+    MixedTopParams,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dyn_dispatch_message() {
+        assert_eq!(
+            SpanErrorKind::DynDispatch("dyn Op".into()).to_string(),
+            "dynamic dispatch through trait object 'dyn Op' is not synthesizable"
+        );
+    }
 }
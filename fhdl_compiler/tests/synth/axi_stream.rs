@@ -0,0 +1,62 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn handshake_ports() {
+    let verilog = Synth::new(
+        "axi_stream_ports",
+        r#"
+use ferrum_hdl::prelude::*;
+use ferrum_hdl::toolbox::{axi_stream_wrap, AxiStream};
+
+// a user struct with the same field names as `AxiStream`
+#[derive(Debug, Clone, SignalValue)]
+pub struct State {
+    pub tdata: U<4>,
+    pub tvalid: bool,
+}
+
+#[synth(top)]
+pub fn top(
+    clk: Clock<TD4>,
+    rst: Reset<TD4>,
+    m_axis_tready: Signal<TD4, bool>,
+    s_axis: Signal<TD4, AxiStream<U<4>>>,
+) -> (Signal<TD4, AxiStream<U<8>>>, Signal<TD4, State>) {
+    let m_axis = axi_stream_wrap(&clk, &rst, &m_axis_tready, |en| {
+        reg_en0(&clk, &rst, en, |data: U<8>| data + 1)
+    });
+    let state = s_axis.map(|s_axis| State {
+        tdata: s_axis.tdata,
+        tvalid: s_axis.tvalid,
+    });
+    (m_axis, state)
+}
+"#,
+    )
+    .verilog();
+
+    // the output stream is the master side, the input stream is the slave side
+    assert!(
+        verilog.contains("    input wire m_axis_tready,\n"),
+        "{verilog}"
+    );
+    assert!(verilog.contains("    input wire [3:0] s_axis_tdata,\n"));
+    assert!(verilog.contains("    input wire s_axis_tvalid,\n"));
+    assert!(verilog.contains("    output wire [7:0] m_axis_tdata,\n"));
+    assert!(verilog.contains("    output wire m_axis_tvalid,\n"));
+    assert!(verilog.contains("assign m_axis_tdata = tdata;"));
+    assert!(verilog.contains("assign m_axis_tvalid = tvalid;"));
+
+    // the fields of other structs keep their names
+    assert!(verilog.contains("    output wire [3:0] _state$tdata,\n"));
+    assert!(verilog.contains("    output wire _state$tvalid\n"));
+
+    // the pipeline and the output register are stalled while `tready` is low
+    // and the output register is full
+    assert!(
+        verilog.contains("        case (m_axis_tready)\n            1'd0: en_1 = _$1;")
+    );
+    assert!(verilog.contains("assign _$1 = ~tvalid;"));
+    assert_eq!(count(&verilog, "        else if (en_1)\n"), 2);
+    assert!(verilog.contains("        else if (en_1)\n            tdata <= _reg;"));
+}
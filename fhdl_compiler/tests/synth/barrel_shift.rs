@@ -0,0 +1,40 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn log_depth_mux() {
+    let verilog = Synth::new(
+        "barrel_shift",
+        r#"
+use ferrum_hdl::{prelude::*, toolbox::{barrel_shift, ShiftDir}};
+
+#[synth(top)]
+pub fn top(a: U<8>, amount: Idx<8>) -> (U<8>, U<8>) {
+    (
+        barrel_shift(a.clone(), amount.clone(), ShiftDir::Left),
+        barrel_shift(a, amount, ShiftDir::Right),
+    )
+}
+"#,
+    )
+    .verilog();
+
+    // log2(8) = 3 stages for each direction, one bit of the amount per stage
+    assert_eq!(count(&verilog, "always @(*)"), 6);
+    for stage in 0 .. 3 {
+        assert_eq!(count(&verilog, &format!("= amount[{stage}];")), 2);
+    }
+    assert_eq!(count(&verilog, "<<"), 0);
+    assert_eq!(count(&verilog, ">>"), 0);
+
+    // the stages shift by 1, 2 and 4 bits
+    for slice in [
+        "a[6:0];",
+        "a[7:1];",
+        "mux_2[5:0];",
+        "mux_3[7:2];",
+        "mux_4[3:0];",
+        "mux_5[7:4];",
+    ] {
+        assert!(verilog.contains(slice), "missing slice '{slice}'");
+    }
+}
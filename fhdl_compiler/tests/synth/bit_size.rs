@@ -0,0 +1,61 @@
+use crate::harness::Synth;
+
+#[test]
+fn enum_discriminants() {
+    let verilog = Synth::new(
+        "bit_size_enum",
+        r#"
+use ferrum_hdl::prelude::*;
+
+// discriminants are 2, 3 and 4
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SignalValue, BitPack)]
+pub enum Op {
+    Nop = 2,
+    Add,
+    Sub,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SignalValue, BitPack)]
+#[bitpack(bits = 4)]
+pub enum Mode {
+    A,
+    B,
+}
+
+#[synth(top)]
+pub fn top(op: Op, mode: Mode, tuple: (U<4>, Bit, Array<2, U<2>>)) -> (BitVec<3>, BitVec<4>, BitVec<9>) {
+    (op.pack(), mode.pack(), tuple.pack())
+}
+"#,
+    )
+    .verilog();
+
+    assert!(verilog.contains("input wire [2:0] op,"));
+    assert!(verilog.contains("input wire [3:0] mode,"));
+}
+
+#[test]
+fn mismatch() {
+    Synth::new(
+        "bit_size_mismatch",
+        r#"
+use ferrum_hdl::{bitpack::BitSize, prelude::*};
+
+#[derive(Debug, Clone, PartialEq, Eq, SignalValue)]
+pub struct Pair {
+    a: U<4>,
+}
+
+impl BitSize for Pair {
+    const BITS: usize = 5;
+}
+
+#[synth(top)]
+pub fn top(a: U<4>) -> Pair {
+    Pair { a }
+}
+"#,
+    )
+    .run()
+    .assert_error("'BitSize::BITS' of type 'Pair' is 5, but its synthesized width is 4");
+}
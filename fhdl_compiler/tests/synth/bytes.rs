@@ -0,0 +1,32 @@
+use crate::harness::Synth;
+
+#[test]
+fn byte_lanes() {
+    let verilog = Synth::new(
+        "bytes",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(
+    x: U<16>,
+    le: [U<8>; 2],
+    be: [U<8>; 2],
+) -> ([U<8>; 2], [U<8>; 2], U<16>, U<16>) {
+    (
+        x.clone().to_le_bytes(),
+        x.to_be_bytes(),
+        U::from_le_bytes(le),
+        U::from_be_bytes(be),
+    )
+}
+"#,
+    )
+    .verilog();
+
+    // little-endian bytes start from the LSB, big-endian ones from the MSB
+    assert!(verilog.contains("assign slice = x[7:0];\n    assign slice_1 = x[15:8];"));
+    assert!(verilog.contains("assign slice_2 = x[15:8];\n    assign slice_3 = x[7:0];"));
+    assert!(verilog.contains("assign _out$2 = {\n        _le$1,\n        _le$0\n    };"));
+    assert!(verilog.contains("assign _out$3 = {\n        _be$0,\n        _be$1\n    };"));
+}
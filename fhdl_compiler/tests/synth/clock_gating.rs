@@ -0,0 +1,48 @@
+use crate::harness::Synth;
+
+const GATED_COUNTER: &str = r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(clk: Clock<TD4>, rst: Reset<TD4>, en: Signal<TD4, bool>) -> Signal<TD4, U<4>> {
+    let gclk = gated_clock(&clk, &en);
+    reg0(&gclk, &rst, |val: U<4>| val + 1)
+}
+"#;
+
+const CLOCK_GATING_DISABLED: &str =
+    "clock gating is not enabled (`--clock-gating`), the \
+                                     gated clock is turned into register enables";
+
+#[test]
+fn gated_clock_to_enable() {
+    let output = Synth::new("gated_clock_enable", GATED_COUNTER).run();
+
+    assert!(
+        output.has_warning(CLOCK_GATING_DISABLED),
+        "{}",
+        output.stderr
+    );
+
+    // the gate is the enable of the register and it gates the synchronous reset
+    let verilog = output.verilog();
+    assert!(!verilog.contains("fhdl_clock_gate"));
+    assert!(verilog.contains("    always @(posedge clk) begin\n        if (_$1)\n"));
+    assert!(verilog.contains("        else if (en)\n            _reg <= out;"));
+    assert!(verilog.contains("assign _$1 = rst & en;"));
+}
+
+#[test]
+fn gated_clock_cell() {
+    let output = Synth::new("gated_clock_cell", GATED_COUNTER)
+        .arg("netlist.clock_gating", true)
+        .run();
+
+    assert!(!output.has_warning(CLOCK_GATING_DISABLED));
+
+    let verilog = output.verilog();
+    assert!(
+        verilog.contains("fhdl_clock_gate gclk_cg (.clk(clk), .en(en), .gclk(gclk));")
+    );
+    assert!(verilog.contains("    always @(posedge gclk) begin\n        if (rst)\n"));
+}
@@ -0,0 +1,93 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn closure_state_is_threaded() {
+    let verilog = Synth::new(
+        "closure_state",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(arr: [U<8>; 4], threshold: U<8>) -> (U<8>, [U<8>; 4]) {
+    let mut acc: U<8> = 0_u8.cast();
+    let mut add = |x: U<8>| acc = acc.clone() + x;
+    for x in arr.clone() {
+        if x > threshold {
+            add(x);
+        }
+    }
+
+    let mut sum: U<8> = 0_u8.cast();
+    let mut prefix = arr.clone();
+    let sums = arr.into_iter().map(|x| {
+        sum = sum.clone() + x;
+        sum.clone()
+    });
+    for (idx, x) in sums.enumerate() {
+        prefix[idx] = x;
+    }
+
+    (acc, prefix)
+}
+"#,
+    )
+    .verilog();
+
+    // each call of the closure gets the captured value assigned by the previous one
+    assert_eq!(count(&verilog, "    top_closure __mod"), 4);
+    assert!(
+        verilog.contains("        ._$1(cast),\n        .x(_arr$0),"),
+        "{verilog}"
+    );
+    assert!(verilog.contains("        ._$1(_$8),\n        .x(_arr$1),"));
+    assert!(verilog.contains("        ._$1(_$9),\n        .x(_arr$2),"));
+    assert!(verilog.contains("        ._$1(_$10),\n        .x(_arr$3),"));
+    assert!(verilog.contains(
+        "            1'd0: acc = _mux$0_2;\n            default: acc = acc_4;"
+    ));
+
+    // the closure of the iterator is inlined into a chain of adders
+    assert!(verilog.contains("assign _prefix$0 = cast_1 + _arr$0;"));
+    assert!(verilog.contains("assign _prefix$1 = _prefix$0 + _arr$1;"));
+    assert!(verilog.contains("assign _prefix$2 = _prefix$1 + _arr$2;"));
+    assert!(verilog.contains("assign x = _prefix$2 + _arr$3;"));
+}
+
+#[test]
+fn captured_reg_is_threaded() {
+    let verilog = Synth::new(
+        "closure_reg",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(
+    clk: Clock<TD4>,
+    rst: Reset<TD4>,
+    arr: [Signal<TD4, U<8>>; 4],
+) -> Signal<TD4, U<8>> {
+    let mut acc = reg::<TD4, U<8>>(&clk, &rst, &0_u8.cast(), |acc| acc + 1_u8.cast::<U<8>>());
+    let mut add = |x: Signal<TD4, U<8>>| acc = acc.zip_with(x, |acc, x| acc + x);
+    for x in arr {
+        add(x);
+    }
+    acc
+}
+"#,
+    )
+    .verilog();
+
+    // the register is not duplicated by the closure instances
+    assert_eq!(count(&verilog, "always @(posedge clk)"), 1);
+    assert!(verilog.contains("_reg <= out;"));
+    assert!(verilog.contains("assign out = _reg + _$1;"));
+
+    // the first call gets the register, each next call the value of the previous one
+    assert_eq!(count(&verilog, "    top_closure __mod"), 4);
+    assert!(verilog.contains("        ._$1(_reg),\n        .x(_arr$0),"));
+    assert!(verilog.contains("        ._$1(acc_1),\n        .x(_arr$1),"));
+    assert!(verilog.contains("        ._$1(acc_2),\n        .x(_arr$2),"));
+    assert!(verilog.contains(
+        "        ._$1(acc_3),\n        .x(_arr$3),\n        // Outputs\n        .acc(acc)"
+    ));
+}
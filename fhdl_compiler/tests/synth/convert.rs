@@ -0,0 +1,34 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn into_and_identity_are_rewires() {
+    let verilog = Synth::new(
+        "convert",
+        r#"
+use std::convert::identity;
+
+use ferrum_hdl::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+pub struct Wrap(U<4>);
+
+impl From<U<4>> for Wrap {
+    fn from(value: U<4>) -> Self {
+        Self(value)
+    }
+}
+
+#[synth(top)]
+pub fn top(a: U<4>, b: U<4>) -> (Wrap, U<4>, U<4>) {
+    (a.into(), identity(b.clone()), b.into())
+}
+"#,
+    )
+    .verilog();
+
+    // the user impl, the reflexive impl and identity only connect the inputs
+    // to the outputs
+    assert_eq!(count(&verilog, "assign "), 3);
+    assert_eq!(count(&verilog, " = a;"), 1);
+    assert_eq!(count(&verilog, " = b;"), 2);
+}
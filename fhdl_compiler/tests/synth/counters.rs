@@ -0,0 +1,52 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn sample_on_enable_reset() {
+    let verilog = Synth::new(
+        "sample_on_enable",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(
+    clk: Clock<TD4>,
+    rst: Reset<TD4>,
+    en: Signal<TD4, bool>,
+    data: Signal<TD4, U<8>>,
+) -> Signal<TD4, U<8>> {
+    sample_on_enable(&clk, &rst, &en, &data)
+}
+"#,
+    )
+    .verilog();
+
+    // the register is reset by the reset of the domain
+    assert_eq!(count(&verilog, "always @(posedge clk)"), 1);
+    assert!(verilog.contains("        if (rst)\n            _reg <= cast;"));
+    assert!(verilog.contains("        else if (en)\n            _reg <= value;"));
+}
+
+#[test]
+fn decimate_passes_data_through() {
+    let verilog = Synth::new(
+        "decimate",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(
+    clk: Clock<TD4>,
+    rst: Reset<TD4>,
+    data: Signal<TD4, U<8>>,
+) -> Signal<TD4, (U<8>, Bit)> {
+    data.decimate::<4>(&clk, &rst)
+}
+"#,
+    )
+    .verilog();
+
+    // only the counter is registered and it is reset by the reset of the domain
+    assert_eq!(count(&verilog, "always @(posedge clk)"), 1);
+    assert_eq!(count(&verilog, "        if (rst)\n"), 1);
+    assert!(verilog.contains("assign data_1 = data;"), "{verilog}");
+}
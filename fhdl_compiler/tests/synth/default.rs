@@ -0,0 +1,83 @@
+use crate::harness::Synth;
+
+#[test]
+fn derived_default_reset() {
+    let verilog = Synth::new(
+        "default",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, SignalValue, BitPack)]
+pub struct State {
+    count: U<4>,
+    flag: Bit,
+    inner: Inner,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+pub struct Inner(U<3>);
+
+impl Default for Inner {
+    fn default() -> Self {
+        Inner(5_u8.cast())
+    }
+}
+
+#[synth(top)]
+pub fn top(clk: Clock<TD4>, rst: Reset<TD4>) -> Signal<TD4, State> {
+    reg::<TD4, State>(&clk, &rst, &State::default(), |s| State {
+        count: s.count + 1,
+        flag: !s.flag,
+        inner: s.inner,
+    })
+}
+"#,
+    )
+    .verilog();
+
+    // count | flag | inner = 0000 | 0 | 101
+    assert!(verilog.contains("assign _$1 = 8'd5;"));
+    assert!(verilog.contains("initial begin\n        _reg = 8'd5;\n    end"));
+    assert!(verilog.contains("if (rst)\n            _reg <= _$1;"));
+}
+
+#[test]
+fn inferred_default_init() {
+    let verilog = Synth::new(
+        "default_init",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, SignalValue, BitPack)]
+pub struct Counters {
+    lo: U<3>,
+    hi: Inner,
+    en: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+pub struct Inner(U<3>);
+
+impl Default for Inner {
+    fn default() -> Self {
+        Inner(5_u8.cast())
+    }
+}
+
+#[synth(top)]
+pub fn top(clk: Clock<TD4>, rst: Reset<TD4>) -> Signal<TD4, Counters> {
+    reg::<TD4, Counters>(&clk, &rst, &Default::default(), |s| Counters {
+        lo: s.lo + 1,
+        hi: s.hi,
+        en: !s.en,
+    })
+}
+"#,
+    )
+    .verilog();
+
+    // lo | hi | en = 000 | 101 | 0
+    assert!(verilog.contains("assign _$1 = 7'd10;"));
+    assert!(verilog.contains("initial begin\n        _reg = 7'd10;\n    end"));
+    assert!(verilog.contains("if (rst)\n            _reg <= _$1;"));
+}
@@ -0,0 +1,33 @@
+use crate::harness::Synth;
+
+#[test]
+fn freq_divider_message() {
+    let output = Synth::new(
+        "freq_divider",
+        r#"
+use ferrum_hdl::prelude::*;
+
+pub struct Fast;
+
+impl ClockDomain for Fast {
+    const FREQ: usize = usize::MAX;
+    const RESET_KIND: SyncKind = SyncKind::Sync;
+    const RESET_POLARITY: Polarity = Polarity::ActiveHigh;
+}
+
+pub const DIVIDER: usize = freq_divider::<Fast>(10_000_000_000_000_000_001);
+"#,
+    )
+    .run();
+
+    // the longest message is evaluated at compile time
+    assert!(!output.success);
+    assert!(
+        output.stderr.contains(
+            "clock domain frequency 18446744073709551615 Hz is not a multiple of \
+             10000000000000000001 Hz (ratio 1, remainder 8446744073709551614 Hz)"
+        ),
+        "{}",
+        output.stderr
+    );
+}
@@ -0,0 +1,21 @@
+use crate::harness::Synth;
+
+#[test]
+fn zero_and_sign_extend() {
+    let verilog = Synth::new(
+        "extend",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: U<4>) -> (U<8>, U<8>) {
+    (a.zero_extend::<8, U<8>>(), a.sign_extend::<8, U<8>>())
+}
+"#,
+    )
+    .verilog();
+
+    // the sign extension replicates the MSB of the input
+    assert!(verilog.contains("assign cast = { 0, _self };"), "{verilog}");
+    assert!(verilog.contains("assign cast_1 = { { 4{ _self_1[3] } }, _self_1 };"));
+}
@@ -0,0 +1,62 @@
+use crate::harness::Synth;
+
+const MY_IP: &str = "\
+// hand-written adder
+module my_ip
+(
+    input wire [7:0] a,
+    input wire [7:0] b,
+    output wire [7:0] sum,
+    output wire carry
+);
+    assign { carry, sum } = a + b;
+endmodule
+";
+
+fn synth(name: &'static str, ret_ty: &str) -> Synth {
+    Synth::new(
+        name,
+        &format!(
+            r#"
+use ferrum_hdl::prelude::*;
+
+#[fhdl_tool::extern_verilog(module = "my_ip", file = "my_ip.v")]
+pub fn my_ip(a: U<8>, b: U<8>) -> {ret_ty} {{
+    todo!()
+}}
+
+#[synth(top)]
+pub fn top(a: U<8>, b: U<8>) -> {ret_ty} {{
+    my_ip(a, b)
+}}
+"#
+        ),
+    )
+    .file("my_ip.v", MY_IP)
+}
+
+#[test]
+fn extern_module_instance() {
+    let output = synth("extern_verilog", "(U<8>, Bit)").run();
+
+    // the ports are connected by position
+    let verilog = output.verilog();
+    assert!(verilog.contains("    my_ip __mod (\n        a,\n        b,\n        _out$0,\n        _out$1\n    );"));
+    assert!(!verilog.contains("module my_ip"));
+
+    // the extern file is copied next to the generated verilog
+    assert_eq!(output.read("synth/verilog/my_ip.v"), MY_IP);
+}
+
+#[test]
+fn extern_module_port_mismatch() {
+    synth("extern_verilog_width", "(U<4>, Bit)")
+        .run()
+        .assert_error(
+        "port 'sum' of extern module 'my_ip' has width 8, but the signature defines 4",
+    );
+
+    synth("extern_verilog_count", "U<8>")
+        .run()
+        .assert_error("extern module 'my_ip' has 4 ports, but the signature defines 3");
+}
@@ -0,0 +1,169 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use fhdl_netlist::cfg::NetListCfg;
+use serde_json::{json, Value};
+
+const HEADER: &str = "\
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+";
+
+// Synthesizes a fixture crate by `fhdl-driver`. All fixtures share the target
+// directory, so `ferrum_hdl` is built only once.
+pub struct Synth {
+    name: &'static str,
+    src: String,
+    args: Value,
+    files: Vec<(&'static str, String)>,
+}
+
+pub struct Output {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+    dir: PathBuf,
+}
+
+impl Synth {
+    pub fn new(name: &'static str, src: &str) -> Self {
+        Self {
+            name,
+            src: format!("{HEADER}{src}"),
+            args: json!({
+                "color": "Never",
+                "dump_netlist": false,
+                "dump_tr_netlist": false,
+                "dump_mir": false,
+                "netlist": NetListCfg::default(),
+            }),
+            files: vec![],
+        }
+    }
+
+    // Sets a compiler argument, netlist arguments are set by `netlist.<name>`
+    pub fn arg(mut self, name: &str, value: impl Into<Value>) -> Self {
+        let (args, name) = match name.strip_prefix("netlist.") {
+            Some(name) => (&mut self.args["netlist"], name),
+            None => (&mut self.args, name),
+        };
+        args[name] = value.into();
+        self
+    }
+
+    // Adds a file to the fixture crate, the path is relative to the crate root
+    pub fn file(mut self, path: &'static str, content: &str) -> Self {
+        self.files.push((path, content.to_string()));
+        self
+    }
+
+    pub fn run(self) -> Output {
+        let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("synth");
+        let dir = root.join(self.name);
+        let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+
+        let _ = fs::remove_dir_all(dir.join("synth"));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\nferrum_hdl = {{ path = {:?} }}\n\n[workspace]\n",
+                self.name, workspace
+            ),
+        )
+        .unwrap();
+        fs::copy(
+            workspace.join("rust-toolchain.toml"),
+            dir.join("rust-toolchain.toml"),
+        )
+        .unwrap();
+        fs::write(dir.join("src").join("lib.rs"), &self.src).unwrap();
+        for (path, content) in &self.files {
+            fs::write(dir.join(path), content).unwrap();
+        }
+
+        let output = Command::new(env!("CARGO"))
+            .current_dir(&dir)
+            .args(["build", "--lib", "--offline", "--target-dir"])
+            .arg(root.join("target"))
+            .env("RUSTC_WRAPPER", env!("CARGO_BIN_EXE_fhdl-driver"))
+            .env("RUSTFLAGS", "-Z always-encode-mir=yes")
+            .env_remove("CARGO_ENCODED_RUSTFLAGS")
+            .env("FHDL_ARGS", self.args.to_string())
+            .envs([
+                ("CARGO_PROFILE_DEV_OPT_LEVEL", "0"),
+                ("CARGO_PROFILE_DEV_DEBUG", "full"),
+                ("CARGO_PROFILE_DEV_STRIP", "none"),
+                ("CARGO_PROFILE_DEV_DEBUG_ASSERTIONS", "false"),
+                ("CARGO_PROFILE_DEV_OVERFLOW_CHECKS", "false"),
+                ("CARGO_PROFILE_DEV_LTO", "off"),
+                ("CARGO_PROFILE_DEV_PANIC", "abort"),
+                ("CARGO_PROFILE_DEV_INCREMENTAL", "true"),
+            ])
+            .output()
+            .unwrap();
+
+        Output {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            dir,
+        }
+    }
+
+    // Synthesizes the fixture and returns the generated verilog
+    pub fn verilog(self) -> String {
+        self.run().verilog()
+    }
+}
+
+impl Output {
+    pub fn read(&self, path: impl AsRef<Path>) -> String {
+        assert!(self.success, "synthesis failed:\n{}", self.stderr);
+        let path = self.dir.join(path);
+        fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("cannot read '{}': {e}", path.display()))
+    }
+
+    pub fn exists(&self, path: impl AsRef<Path>) -> bool {
+        self.dir.join(path).exists()
+    }
+
+    pub fn verilog(&self) -> String {
+        self.read("synth/verilog/top.v")
+    }
+
+    // Returns the error messages (or the warning messages if `warn`) of the compiler
+    pub fn diagnostics(&self, warn: bool) -> Vec<&str> {
+        let prefix = if warn { "warning: " } else { "error: " };
+        self.stderr
+            .lines()
+            .filter_map(|line| line.strip_prefix(prefix))
+            .collect()
+    }
+
+    pub fn has_warning(&self, msg: &str) -> bool {
+        assert!(self.success, "synthesis failed:\n{}", self.stderr);
+        self.diagnostics(true).contains(&msg)
+    }
+
+    pub fn assert_error(&self, msg: &str) {
+        assert!(!self.success, "synthesis succeeded");
+        assert!(
+            self.diagnostics(false).contains(&msg),
+            "missing error '{msg}':\n{}",
+            self.stderr
+        );
+    }
+}
+
+// Counts the occurrences of `pat` in the generated verilog
+pub fn count(verilog: &str, pat: &str) -> usize {
+    verilog.matches(pat).count()
+}
@@ -0,0 +1,179 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn runtime_index_is_mux() {
+    let verilog = Synth::new(
+        "index_runtime",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(arr: [U<4>; 4], idx: Idx<4>, i: usize) -> (U<4>, U<4>) {
+    (arr[idx.cast::<usize>()].clone(), arr[i % 4].clone())
+}
+"#,
+    )
+    .verilog();
+
+    // the `usize` index is truncated to the width of the element index
+    assert!(verilog.contains("assign sel = _$2[1:0];"));
+    assert!(verilog.contains("assign sel_1 = cast[1:0];"));
+    assert_eq!(count(&verilog, "always @(*)"), 2);
+    for (sel, mux) in [("sel", "mux_1"), ("sel_1", "mux")] {
+        assert!(verilog.contains(&format!(
+            "        case ({sel})
+            2'd0: {mux} = _arr$0;
+            2'd1: {mux} = _arr$1;
+            2'd2: {mux} = _arr$2;
+            2'd3: {mux} = _arr$3;
+        endcase"
+        )));
+    }
+}
+
+const LOOKUP_TABLE: &str = r#"
+use ferrum_hdl::prelude::*;
+
+const TABLE: [u8; 5] = [3, 1, 4, 1, 5];
+
+#[synth(top)]
+pub fn top(idx: usize) -> u8 {
+    TABLE[idx]
+}
+"#;
+
+#[test]
+fn const_table_is_rom() {
+    let verilog = Synth::new("index_rom", LOOKUP_TABLE)
+        .arg("netlist.rom_threshold", 4)
+        .verilog();
+
+    // the ROM is padded with zero words up to the range of the address
+    assert!(verilog.contains("assign sel = idx[2:0];"));
+    assert!(verilog.contains("reg [7:0] rom [0:7];"));
+    assert!(verilog.contains(
+        "for (rom_i = 0; rom_i < 8; rom_i = rom_i + 1) rom[rom_i] = 8'd0;\n        \
+         rom[0] = 8'd3;\n        rom[1] = 8'd1;\n        rom[2] = 8'd4;\n        rom[3] = \
+         8'd1;\n        rom[4] = 8'd5;\n    end"
+    ));
+    assert!(verilog.contains("assign out = rom[sel];"));
+    assert!(!verilog.contains("case"));
+
+    // smaller tables stay muxes
+    let verilog = Synth::new("index_rom_mux", LOOKUP_TABLE).verilog();
+    assert!(!verilog.contains("rom"));
+    assert_eq!(count(&verilog, "case (sel)"), 1);
+}
+
+#[test]
+fn index_user_type() {
+    let verilog = Synth::new(
+        "index_user_type",
+        r#"
+use std::ops::Index;
+
+use ferrum_hdl::prelude::*;
+
+#[derive(Clone)]
+pub struct Regs([U<8>; 4]);
+
+impl Index<usize> for Regs {
+    type Output = U<8>;
+
+    fn index(&self, idx: usize) -> &U<8> {
+        &self.0[idx]
+    }
+}
+
+#[synth(top)]
+pub fn top(regs: [U<8>; 4], idx: usize) -> (U<8>, U<8>) {
+    let regs = Regs(regs);
+    (regs[2].clone() + regs[0].clone(), regs[idx].clone())
+}
+"#,
+    )
+    .verilog();
+
+    // `index` is inlined: constant indices select the inputs directly, a runtime
+    // index becomes a mux
+    assert!(!verilog.contains("Regs"));
+    assert!(verilog.contains("assign _out$0 = _regs$2 + _regs$0;"));
+    assert_eq!(count(&verilog, "case (sel)"), 1);
+    assert!(verilog.contains("2'd3: mux = _regs$3;"));
+}
+
+const ENUM_TABLE: &str = r#"
+use ferrum_hdl::prelude::*;
+
+#[derive(Debug, Clone, Copy, BitPack, SignalValue)]
+pub enum Op {
+    Add,
+    Sub,
+    Nop,
+}
+
+const OPCODES: [u8; 3] = [0x33, 0x3b, 0x13];
+
+#[synth(top)]
+pub fn top(op: Op) -> u8 {
+    OPCODES[op as usize]
+}
+"#;
+
+#[test]
+fn index_const_table_by_enum_discr() {
+    let verilog = Synth::new("index_enum_discr", ENUM_TABLE).verilog();
+
+    // the discriminant of the enum is the selector of the mux
+    assert!(verilog.contains("assign sel = cast[1:0];"));
+    assert!(verilog.contains(
+        "        case (sel)
+            2'd0: mux = _$1;
+            2'd1: mux = _$2;
+            2'd2: mux = _$3;
+        endcase"
+    ));
+    assert!(verilog.contains("assign _$1 = 8'd51;"));
+    assert!(verilog.contains("assign _$3 = 8'd19;"));
+
+    // or the address of the ROM
+    let verilog = Synth::new("index_enum_discr_rom", ENUM_TABLE)
+        .arg("netlist.rom_threshold", 3)
+        .verilog();
+    assert!(verilog.contains("reg [7:0] rom [0:3];"));
+    assert!(verilog
+        .contains("rom[0] = 8'd51;\n        rom[1] = 8'd59;\n        rom[2] = 8'd19;"));
+    assert!(verilog.contains("assign out = rom[sel];"));
+    assert!(!verilog.contains("case"));
+}
+
+#[test]
+fn index_const_table_by_explicit_discr() {
+    let verilog = Synth::new(
+        "index_explicit_discr",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[derive(Debug, Clone, Copy, BitPack, SignalValue)]
+pub enum Op {
+    Add = 1,
+    Sub,
+    Nop = 3,
+}
+
+const OPCODES: [u8; 4] = [0x00, 0x33, 0x3b, 0x13];
+
+#[synth(top)]
+pub fn top(op: Op) -> u8 {
+    OPCODES[op as usize]
+}
+"#,
+    )
+    .verilog();
+
+    // the mixed explicit and implicit discriminants select the words 1..=3
+    assert!(verilog.contains("input wire [1:0] op,"));
+    assert!(verilog.contains("assign _$2 = 8'd51;"));
+    assert!(verilog.contains("assign _$4 = 8'd19;"));
+    assert!(verilog.contains("            2'd3: mux = _$4;\n"));
+}
@@ -0,0 +1,227 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn map_chain_is_fused() {
+    let verilog = Synth::new(
+        "iter_map_chain",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: [U<8>; 4]) -> U<8> {
+    let mut iter = a.into_iter();
+    iter.next();
+    let mut sum: U<8> = 0_u8.cast();
+    for x in iter.map(|x| x + 1_u8.cast::<U<8>>()).map(|x| x << 1_usize) {
+        sum = sum + x;
+    }
+    sum
+}
+"#,
+    )
+    .verilog();
+
+    // only the 3 remaining items are mapped, each one by both closures, the consumed
+    // item is unused
+    assert_eq!(count(&verilog, " + _$1;"), 3);
+    assert_eq!(count(&verilog, " << _$1;"), 3);
+    assert_eq!(count(&verilog, "_a$0"), 1);
+    assert!(!verilog.contains("always"));
+}
+
+#[test]
+fn filter_is_rejected() {
+    Synth::new(
+        "iter_filter",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: [U<8>; 4]) -> U<8> {
+    let mut sum: U<8> = 0_u8.cast();
+    for x in a.into_iter().filter(|x| *x > 1_u8.cast::<U<8>>()) {
+        sum = sum + x;
+    }
+    sum
+}
+"#,
+    )
+    .run()
+    .assert_error("iterator adapter 'Filter' produces an iterator of non-static length");
+}
+
+#[test]
+fn position_and_find_are_priority_selects() {
+    let verilog = Synth::new(
+        "iter_position_find",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(arr: [U<4>; 4], target: U<4>) -> (Option<usize>, Option<U<4>>) {
+    (
+        arr.iter().position(|x| *x == target),
+        arr.into_iter().find(|x| *x > target),
+    )
+}
+"#,
+    )
+    .verilog();
+
+    // a comparator per item and a chain of 2-way muxes per adapter, the first item
+    // has the highest priority, so it selects the output
+    assert_eq!(count(&verilog, " == target;"), 4);
+    assert_eq!(count(&verilog, " > target;"), 4);
+    assert_eq!(count(&verilog, "always @(*)"), 8);
+    // Some(0_usize)
+    assert!(verilog.contains("assign _$1 = 65'd18446744073709551616;"));
+    assert!(verilog.contains(
+        "        case (out)\n            1'd1: mux = _$1;\n            default: mux = mux_6;"
+    ));
+    // Some(arr[0])
+    assert!(verilog.contains("assign _$8 = {\n        _$6,\n        _arr$0\n    };"));
+    assert!(verilog.contains(
+        "        case (out_4)\n            1'd1: mux_1 = _$8;\n            default: mux_1 = mux_7;"
+    ));
+}
+
+#[test]
+fn two_phase_borrow_of_non_iterator() {
+    Synth::new(
+        "iter_two_phase_borrow",
+        r#"
+use std::ops::AddAssign;
+
+use ferrum_hdl::prelude::*;
+
+pub struct Acc(U<8>);
+
+impl AddAssign<U<8>> for Acc {
+    #[synth(inline)]
+    fn add_assign(&mut self, x: U<8>) {
+        self.0 = self.0.clone() + x;
+    }
+}
+
+#[synth(top)]
+pub fn top(a: U<8>, b: U<8>) -> U<8> {
+    let mut acc = Acc(a);
+    acc += b;
+    acc.0
+}
+"#,
+    )
+    .run()
+    .assert_error("not synthesizable expression");
+}
+
+#[test]
+fn nested_loops_labeled_break() {
+    let verilog = Synth::new(
+        "iter_labeled_break",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: [[U<8>; 3]; 3]) -> U<8> {
+    let mut sum: U<8> = 0_u8.cast();
+    'outer: for (i, row) in a.into_iter().enumerate() {
+        for (j, x) in row.into_iter().enumerate() {
+            if j == 2 {
+                continue 'outer;
+            }
+            if i == 2 && j == 1 {
+                break 'outer;
+            }
+            sum = sum + x;
+        }
+    }
+    sum
+}
+"#,
+    )
+    .verilog();
+
+    // only a[0][0..2], a[1][0..2] and a[2][0] are summed, without muxes
+    assert_eq!(count(&verilog, " + "), 5);
+    for item in ["_a$0$0;", "_a$0$1;", "_a$1$0;", "_a$1$1;", "_a$2$0;"] {
+        assert_eq!(count(&verilog, item), 1, "{item}");
+    }
+    for item in ["_a$0$2;", "_a$1$2;", "_a$2$1;", "_a$2$2;"] {
+        assert_eq!(count(&verilog, item), 0, "{item}");
+    }
+    assert!(!verilog.contains("always"));
+}
+
+#[test]
+fn enumerate_map_indices_are_consts() {
+    let verilog = Synth::new(
+        "iter_enumerate_map",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(arr: [U<4>; 4]) -> [U<4>; 4] {
+    <[U<4>; 4]>::from_iter_exact(
+        arr.into_iter()
+            .enumerate()
+            .map(|(i, x)| x + i.cast::<U<4>>()),
+    )
+}
+"#,
+    )
+    .verilog();
+
+    // each item is added to its own constant index
+    assert!(verilog.contains("assign cast = 4'd0;"));
+    assert!(verilog.contains("assign out = _arr$0 + cast;"));
+    for i in 1 .. 4 {
+        assert!(verilog.contains(&format!("assign cast_{i} = 4'd{i};")));
+        assert!(verilog.contains(&format!("assign out_{i} = _arr${i} + cast_{i};")));
+    }
+    assert_eq!(count(&verilog, " + "), 4);
+}
+
+#[test]
+fn try_fold_is_priority_select() {
+    let verilog = Synth::new(
+        "iter_try_fold",
+        r#"
+use std::ops::ControlFlow;
+
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(arr: [U<4>; 4], target: U<4>) -> Option<U<4>> {
+    let res = arr.try_fold((), |(), x| {
+        if x.clone() > target {
+            ControlFlow::Break(x)
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    match res {
+        ControlFlow::Break(x) => Some(x),
+        ControlFlow::Continue(()) => None,
+    }
+}
+"#,
+    )
+    .verilog();
+
+    // all items are compared in parallel
+    assert_eq!(count(&verilog, " > target;"), 4);
+
+    // each stage keeps the result of the previous ones once it breaks, so the first
+    // break wins
+    assert!(verilog.contains("assign discr = _mux$0[4];"));
+    assert!(verilog.contains("assign discr_1 = _mux$0_4[4];"));
+    assert!(verilog.contains("assign discr_2 = _mux$0_5[4];"));
+    assert!(verilog.contains("_mux$0_4 = _mux$0_1;"));
+    assert!(verilog.contains("default: begin\n                _mux$0_4 = _mux$0;"));
+    assert!(verilog.contains("default: begin\n                _mux$0_5 = _mux$0_4;"));
+    assert!(verilog.contains(
+        "case (discr_2)\n            1'd0: res = _mux$0_3;\n            default: res = \
+         _mux$0_5;"
+    ));
+}
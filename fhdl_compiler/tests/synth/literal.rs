@@ -0,0 +1,32 @@
+use crate::harness::Synth;
+
+#[test]
+fn literal_overflow() {
+    let output = Synth::new(
+        "literal",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: U<4>) -> (U<4>, U<4>, U<4>) {
+    (a.clone() + 20, a + 15, 20_u8.cast::<U<4>>())
+}
+"#,
+    )
+    .run();
+
+    // the literal converted implicitly is reported, the explicit cast is not
+    let warnings = output
+        .diagnostics(true)
+        .into_iter()
+        .filter(|warning| warning.starts_with("literal"))
+        .collect::<Vec<_>>();
+    assert_eq!(warnings, [
+        "literal 20 does not fit into 4 bits and is truncated to 4"
+    ]);
+    assert!(output.stderr.contains("--> src/lib.rs:10:6"));
+
+    let verilog = output.verilog();
+    assert!(verilog.contains("assign cast = 4'd4;"));
+    assert!(verilog.contains("assign cast_1 = 4'd4;"));
+}
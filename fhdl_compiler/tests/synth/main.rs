@@ -0,0 +1,33 @@
+// Checks the netlist lowering by synthesizing small crates and inspecting the
+// generated verilog
+mod harness;
+
+mod axi_stream;
+mod barrel_shift;
+mod bit_size;
+mod bytes;
+mod clock_gating;
+mod closure;
+mod convert;
+mod counters;
+mod default;
+mod domain;
+mod extend;
+mod extern_verilog;
+mod index;
+mod iter;
+mod literal;
+mod manifest;
+mod option;
+mod ordering;
+mod output;
+mod pow;
+mod repeat;
+mod reset;
+mod rotate;
+mod signal;
+mod switch;
+mod top_params;
+mod traits;
+mod unreachable;
+mod zero_sized;
@@ -0,0 +1,49 @@
+use serde_json::{json, Value};
+
+use crate::harness::Synth;
+
+#[test]
+fn modules_of_hierarchy() {
+    let output = Synth::new(
+        "manifest",
+        r#"
+use ferrum_hdl::prelude::*;
+
+pub mod utils {
+    use ferrum_hdl::prelude::*;
+
+    #[synth]
+    pub fn add(a: U<8>, b: U<8>) -> U<8> {
+        a + b
+    }
+}
+
+#[synth(top)]
+pub fn top(a: U<8>, b: U<8>) -> U<8> {
+    utils::add(a, b)
+}
+"#,
+    )
+    .arg("netlist.inline_mod", "None")
+    .run();
+
+    let manifest: Value =
+        serde_json::from_str(&output.read("synth/manifest.json")).unwrap();
+    assert_eq!(
+        manifest,
+        json!({
+            "modules": [
+                {
+                    "module": "top",
+                    "path": "top",
+                    "span": "src/lib.rs: 18",
+                },
+                {
+                    "module": "utils_add",
+                    "path": "utils::add",
+                    "span": "src/lib.rs: 12",
+                },
+            ]
+        })
+    );
+}
@@ -0,0 +1,50 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn option_map() {
+    let verilog = Synth::new(
+        "option",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: Option<U<4>>) -> (Option<U<4>>, Option<U<4>>) {
+    (a.map(|a| a + 1), None::<U<4>>.map(|a| a + 2))
+}
+"#,
+    )
+    .verilog();
+
+    // the discriminant of the input is passed to the output as is
+    assert!(verilog.contains("assign discr = a[4];"));
+    assert!(verilog.contains("assign enum_part = a[3:0];"));
+    assert!(verilog.contains("assign out = enum_part + cast;"));
+    assert!(verilog.contains("assign _out$0 = {\n        discr,\n        out\n    };"));
+
+    // the closure is not instantiated for a constant None
+    assert_eq!(count(&verilog, " + "), 1);
+    assert!(verilog.contains("assign _out$1 = 5'd0;"));
+}
+
+#[test]
+fn match_binding_of_one_branch() {
+    let verilog = Synth::new(
+        "option_match",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: Option<U<4>>) -> U<4> {
+    match a {
+        Some(x) => x,
+        None => 0_u8.cast(),
+    }
+}
+"#,
+    )
+    .verilog();
+
+    // `x` is dropped after the match, only the mux is named
+    assert!(verilog.contains("output reg [3:0] mux"));
+    assert!(verilog.contains("1'd0: mux = cast;\n            1'd1: mux = enum_part;"));
+}
@@ -0,0 +1,32 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn match_on_cmp() {
+    let verilog = Synth::new(
+        "ordering",
+        r#"
+use core::cmp::Ordering;
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: U<8>, b: U<8>, x: U<4>, y: U<4>, z: U<4>) -> U<4> {
+    match a.cmp(&b) {
+        Ordering::Less => x,
+        Ordering::Equal => y,
+        Ordering::Greater => z,
+    }
+}
+"#,
+    )
+    .verilog();
+
+    // a single comparator
+    assert_eq!(count(&verilog, " - "), 1);
+    assert_eq!(count(&verilog, " < "), 0);
+    assert_eq!(count(&verilog, " != "), 0);
+    // feeds a 3-case mux
+    assert_eq!(count(&verilog, "case (discr)"), 1);
+    assert!(verilog.contains("2'd3: _mux$0 = x;"));
+    assert!(verilog.contains("2'd0: _mux$0 = y;"));
+    assert!(verilog.contains("2'd1: _mux$0 = z;"));
+}
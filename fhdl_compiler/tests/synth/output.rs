@@ -0,0 +1,30 @@
+use crate::harness::Synth;
+
+#[test]
+fn out_dir_and_name() {
+    let output = Synth::new(
+        "output",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(clk: Clock<TD4>, rst: Reset<TD4>, a: Signal<TD4, U<8>>) -> Signal<TD4, U<8>> {
+    a.reg(&clk, &rst, |a| a + 1_u8.cast::<U<8>>())
+}
+"#,
+    )
+    .arg("out_dir", "synth/gen")
+    .arg("out_name", "counter")
+    .arg("wrapper", true)
+    .run();
+
+    // all the generated files land in the output directory
+    assert!(output.read("synth/gen/counter.v").contains("module top"));
+    assert!(output
+        .read("synth/gen/counter_wrapper.sv")
+        .contains("    top dut ("));
+    assert!(output
+        .read("synth/gen/manifest.json")
+        .contains("\"module\": \"top\""));
+    assert!(!output.exists("synth/verilog"));
+}
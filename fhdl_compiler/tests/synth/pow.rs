@@ -0,0 +1,25 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn pow_is_multiplier_chain() {
+    let verilog = Synth::new(
+        "pow",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(x: U<8>) -> (U<8>, U<8>, U<8>) {
+    (x.clone().pow::<0>(), x.clone().pow::<1>(), x.pow::<4>())
+}
+"#,
+    )
+    .verilog();
+
+    // x^0 is a constant, x^1 is the input itself and x^4 takes 3 multipliers
+    assert!(verilog.contains("assign _out$0 = 8'd1;"));
+    assert!(verilog.contains("assign x_1 = x;"));
+    assert_eq!(count(&verilog, " * "), 3);
+    assert!(verilog.contains("assign _$1 = x * x;"));
+    assert!(verilog.contains("assign _$2 = _$1 * x;"));
+    assert!(verilog.contains("assign _out$2 = _$2 * x;"));
+}
@@ -0,0 +1,95 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn repeat_of_const_is_packed() {
+    let output = Synth::new(
+        "repeat_const",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(sel: Idx<4>) -> (Array<4, u8>, u8) {
+    let arr = [3_u8; 4];
+    (arr, arr.idx(sel))
+}
+"#,
+    )
+    .arg("dump_netlist", true)
+    .run();
+    assert!(output.success, "synthesis failed:\n{}", output.stderr);
+
+    // the netlist is dumped before the transform deduplicates constants: the array is
+    // split out of a single packed constant (0x03030303), the repeated element is the
+    // only other constant
+    let netlist = &output.stdout;
+    assert_eq!(count(netlist, "value = 32'd50529027"), 1, "{netlist}");
+    assert_eq!(count(netlist, "Splitter ("), 1, "{netlist}");
+    assert_eq!(count(netlist, "value = 8'd3"), 1, "{netlist}");
+
+    let verilog = output.verilog();
+    assert_eq!(count(&verilog, " = 8'd3;"), 4);
+    assert_eq!(count(&verilog, "case (sel)"), 1);
+}
+
+// Drops the span comments of the verilog
+fn strip_spans(verilog: &str) -> String {
+    verilog
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn repeat_take_as_array_literal() {
+    let repeat_take = Synth::new(
+        "repeat_take",
+        r#"
+use std::iter;
+
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(x: u8) -> [u8; 4] {
+    <[u8; 4]>::from_iter_exact(iter::repeat(x).take(4))
+}
+"#,
+    )
+    .verilog();
+
+    let literal = Synth::new(
+        "repeat_literal",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(x: u8) -> [u8; 4] {
+    [x; 4]
+}
+"#,
+    )
+    .verilog();
+
+    // each item is the input itself
+    assert_eq!(count(&repeat_take, "= x;"), 4, "{repeat_take}");
+    assert_eq!(strip_spans(&repeat_take), strip_spans(&literal));
+}
+
+#[test]
+fn repeat_take_len_mismatch() {
+    Synth::new(
+        "repeat_take_len",
+        r#"
+use std::iter;
+
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(x: u8) -> [u8; 4] {
+    <[u8; 4]>::from_iter_exact(iter::repeat(x).take(3))
+}
+"#,
+    )
+    .run()
+    .assert_error("iterator yields 3 items, but 4 are expected");
+}
@@ -0,0 +1,110 @@
+use crate::harness::Synth;
+
+#[test]
+fn with_reset_combines_resets() {
+    let verilog = Synth::new(
+        "with_reset",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(
+    clk: Clock<TD4>,
+    rst: Reset<TD4>,
+    soft_rst: Signal<TD4, bool>,
+    outer_rst: Signal<TD4, bool>,
+) -> Signal<TD4, U<4>> {
+    with_reset(&rst, &outer_rst, |rst| {
+        with_reset(rst, &soft_rst, |rst| reg0(&clk, rst, |val: U<4>| val + 1))
+    })
+}
+"#,
+    )
+    .verilog();
+
+    // the register is reset by the domain reset and the resets of both scopes
+    assert!(verilog.contains("assign rst_1 = rst | outer_rst;"));
+    assert!(verilog.contains("assign rst_2 = rst_1 | soft_rst;"));
+    assert!(verilog.contains("    always @(posedge clk) begin\n        if (rst_2)\n"));
+}
+
+#[test]
+fn with_reset_active_low() {
+    let verilog = Synth::new(
+        "with_reset_active_low",
+        r#"
+use ferrum_hdl::prelude::*;
+
+pub struct Low;
+
+impl ClockDomain for Low {
+    const FREQ: usize = 4;
+    const RESET_KIND: SyncKind = SyncKind::Sync;
+    const RESET_POLARITY: Polarity = Polarity::ActiveLow;
+}
+
+#[synth(top)]
+pub fn top(
+    clk: Clock<Low>,
+    rst: Reset<Low>,
+    soft_rst: Signal<Low, bool>,
+) -> Signal<Low, U<4>> {
+    with_reset(&rst, &soft_rst, |rst| reg0(&clk, rst, |val: U<4>| val + 1))
+}
+"#,
+    )
+    .verilog();
+
+    // the active-high local reset is inverted into the active-low domain reset
+    assert!(verilog.contains("assign out = ~soft_rst;"));
+    assert!(verilog.contains("assign rst_1 = rst & out;"));
+    assert!(verilog.contains("    always @(posedge clk) begin\n        if (!rst_1)\n"));
+}
+
+const DEAD_ASYNC_RST: &str = r#"
+use ferrum_hdl::prelude::*;
+
+pub struct Async;
+
+impl ClockDomain for Async {
+    const FREQ: usize = 4;
+    const RESET_KIND: SyncKind = SyncKind::Async;
+    const RESET_POLARITY: Polarity = Polarity::ActiveHigh;
+}
+
+#[synth(top)]
+pub fn top(clk: Clock<Async>) -> Signal<Async, U<4>> {
+    let counter = reg0(&clk, &Reset::reset(), |val: U<4>| val + 1);
+    counter
+}
+"#;
+
+#[test]
+fn dead_async_reset_is_reported() {
+    let output = Synth::new("dead_async_rst", DEAD_ASYNC_RST)
+        .arg("netlist.lint_async_rst", true)
+        .run();
+
+    // the warning points at the register in the user crate
+    assert!(
+        output.has_warning(
+            "reset of register `counter` in async-reset domain is never asserted and \
+             is removed"
+        ),
+        "{}",
+        output.stderr
+    );
+    assert!(
+        output.stderr.contains("--> src/lib.rs:18:19"),
+        "{}",
+        output.stderr
+    );
+    assert!(!output.verilog().contains("posedge rst"));
+}
+
+#[test]
+fn dead_async_reset_lint_is_disabled() {
+    let output = Synth::new("dead_async_rst_no_lint", DEAD_ASYNC_RST).run();
+
+    assert!(output.diagnostics(true).is_empty(), "{}", output.stderr);
+}
@@ -0,0 +1,70 @@
+use crate::harness::Synth;
+
+// Returns the right-hand sides of `assign` statements
+fn assigns(verilog: &str) -> Vec<&str> {
+    verilog
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("assign "))
+        .map(|assign| assign.split_once(" = ").unwrap().1)
+        .collect()
+}
+
+#[test]
+fn const_rotate_is_rewiring() {
+    let verilog = Synth::new(
+        "rotate_const",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: Array<4, U<8>>) -> Array<4, U<8>> {
+    a.rotate_left(1)
+}
+"#,
+    )
+    .verilog();
+
+    assert_eq!(assigns(&verilog), ["_a$1;", "_a$2;", "_a$3;", "_a$0;"]);
+    assert!(!verilog.contains("always"));
+}
+
+const RUNTIME_ROTATE: &str = r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: Array<3, U<8>>, k: usize) -> Array<3, U<8>> {
+    a.rotate_left(k)
+}
+"#;
+
+#[test]
+fn runtime_rotate_is_mux() {
+    let verilog = Synth::new("rotate_runtime", RUNTIME_ROTATE).verilog();
+
+    // the amount is reduced modulo the length and selects one of the rewirings
+    assert!(verilog.contains("assign _$1 = 64'd3;"));
+    assert!(verilog.contains("assign _$2 = k % _$1;"));
+    assert!(verilog.contains(
+        "        case (_$2)\n            64'd1: mux = _$3;\n            64'd2: mux = \
+         _$4;\n            default: mux = _$5;\n        endcase"
+    ));
+    assert!(verilog.contains("assign _mux$0 = mux[23:16];"));
+    assert!(verilog
+        .contains("assign _$3 = {\n        _a$1,\n        _a$2,\n        _a$0\n    };"));
+    assert!(verilog
+        .contains("assign _$4 = {\n        _a$2,\n        _a$0,\n        _a$1\n    };"));
+    assert!(verilog
+        .contains("assign _$5 = {\n        _a$0,\n        _a$1,\n        _a$2\n    };"));
+
+    let verilog = Synth::new(
+        "rotate_runtime_right",
+        &RUNTIME_ROTATE.replace("rotate_left", "rotate_right"),
+    )
+    .verilog();
+
+    assert!(verilog.contains("assign _$2 = k % _$1;"));
+    assert!(verilog
+        .contains("assign _$3 = {\n        _a$2,\n        _a$0,\n        _a$1\n    };"));
+    assert!(verilog
+        .contains("assign _$4 = {\n        _a$1,\n        _a$2,\n        _a$0\n    };"));
+}
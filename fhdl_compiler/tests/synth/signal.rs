@@ -0,0 +1,110 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn zip_is_wiring() {
+    let verilog = Synth::new(
+        "signal_zip",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: Signal<TD4, U<4>>, b: Signal<TD4, Bit>) -> Signal<TD4, (U<4>, Bit)> {
+    a.zip(b)
+}
+"#,
+    )
+    .verilog();
+
+    // the outputs are connected to the inputs directly
+    assert_eq!(count(&verilog, "assign "), 2);
+    assert!(verilog.contains("assign a_1 = a;"));
+    assert!(verilog.contains("assign b_1 = b;"));
+    assert!(!verilog.contains("always"));
+}
+
+#[test]
+fn zip_with_is_comb() {
+    let verilog = Synth::new(
+        "signal_zip_with",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(
+    a: Signal<TD4, U<8>>,
+    b: Signal<TD4, U<8>>,
+    c: Signal<TD4, U<8>>,
+) -> (Signal<TD4, U<8>>, Signal<TD4, U<8>>) {
+    (a.zip3_with(&b, &c, |a, b, c| a * b + c), a.zip_with(b, |a, b| a - b))
+}
+"#,
+    )
+    .verilog();
+
+    // the closures are inlined into a single combinational cone
+    assert!(verilog.contains("assign _$1 = a_1 * b_1;"));
+    assert!(verilog.contains("assign out = _$1 + c;"));
+    assert!(verilog.contains("assign out_1 = a - b;"));
+    assert!(!verilog.contains("always"));
+    assert!(!verilog.contains("top_"));
+}
+
+#[test]
+fn constant_is_const() {
+    let output = Synth::new(
+        "signal_constant",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(x: Signal<TD4, U<8>>) -> (Signal<TD4, U<8>>, Signal<TD4, U<8>>) {
+    let c = Signal::constant(U::<8>::cast_from(42_u8));
+    (c.map(|c| c + 1), x.zip_with(c, |x, c| x + c))
+}
+"#,
+    )
+    .arg("dump_netlist", true)
+    .run();
+    assert!(output.success, "synthesis failed:\n{}", output.stderr);
+
+    // the constant is a Const node of the netlist, not a register
+    let netlist = &output.stdout;
+    assert_eq!(count(netlist, "value = 8'd42"), 1, "{netlist}");
+    assert_eq!(count(netlist, "DFF ("), 0, "{netlist}");
+
+    // so it is folded into the mapped value
+    let verilog = output.verilog();
+    assert!(verilog.contains("assign out = 8'd43;"));
+    assert!(verilog.contains("assign c = 8'd42;"));
+    assert!(verilog.contains("assign out_1 = x + c;"));
+    assert!(!verilog.contains("always"));
+}
+
+#[test]
+fn impl_eval_output() {
+    let verilog = Synth::new(
+        "signal_impl_eval",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(no_inline)]
+pub fn counter(clk: &Clock<TD4>, rst: &Reset<TD4>) -> impl Eval<TD4, Value = U<4>> {
+    reg0(clk, rst, |val: U<4>| val + 1)
+}
+
+#[synth(top)]
+pub fn top(clk: Clock<TD4>, rst: Reset<TD4>) -> impl Eval<TD4, Value = U<4>> {
+    counter(&clk, &rst)
+}
+"#,
+    )
+    .verilog();
+
+    // the opaque outputs are resolved into the 4-bit register
+    assert!(verilog.contains("module counter"));
+    assert!(verilog.contains("    output wire [3:0] _reg\n"));
+    assert!(verilog.contains("    output reg [3:0] _reg\n"));
+    assert!(verilog.contains("        ._reg(_reg)\n"));
+    assert!(verilog.contains("            _reg <= out;"));
+    assert!(verilog.contains("assign out = _reg + cast_1;"));
+}
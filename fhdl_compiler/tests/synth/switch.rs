@@ -0,0 +1,62 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn tuple_scrutinee_with_wildcards() {
+    let verilog = Synth::new(
+        "switch_tuple",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(a: u8, b: u8, c: u8, x: [U<4>; 4]) -> U<4> {
+    match (a, b, c) {
+        (0, _, 1) => x[0].clone(),
+        (1, 2, _) => x[1].clone(),
+        (_, 3, 3) => x[2].clone(),
+        _ => x[3].clone(),
+    }
+}
+"#,
+    )
+    .verilog();
+
+    // the scrutinees are merged into one selector, the first one takes the MSBs
+    assert!(verilog.contains("assign _$1 = {\n        a,\n        b,\n        c\n    };"));
+    assert!(verilog.contains(
+        "        casez (_$1)
+            { 8'd0, 8'd?, 8'd1 }: mux = _x$0;
+            { 8'd1, 8'd2, 8'd? }: mux = _x$1;
+            { 8'd?, 8'd3, 8'd3 }: mux = _x$2;
+            default: mux = _x$3;
+        endcase"
+    ));
+}
+
+#[test]
+fn bool_match_is_single_mux() {
+    let verilog = Synth::new(
+        "bool_match",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top(b: bool, x: U<8>, y: U<8>) -> U<8> {
+    match b {
+        true => x,
+        false => y,
+    }
+}
+"#,
+    )
+    .verilog();
+
+    // the match is a single 2-case mux without intermediate nodes (e.g. drop flags)
+    assert_eq!(count(&verilog, "case ("), 1);
+    assert!(verilog.contains(
+        "        case (b)
+            1'd0: mux = y;
+            default: mux = x;
+        endcase"
+    ));
+    assert_eq!(count(&verilog, "assign"), 0);
+}
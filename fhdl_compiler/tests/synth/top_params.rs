@@ -0,0 +1,127 @@
+use serde_json::json;
+
+use crate::harness::Synth;
+
+const TOP_MUT: &str = r#"
+use ferrum_hdl::{
+    new_hdl::{Module, StateMut},
+    unsigned::U,
+};
+
+pub struct TopMut<const W: usize>;
+
+impl<const W: usize> StateMut for TopMut<W> {}
+
+impl<const W: usize> TopMut<W> {
+    const OFFSET: u128 = (W / 2) as u128;
+}
+
+impl<const W: usize> Module for TopMut<W> {
+    type Input = (U<W>, U<W>);
+    type Output = U<W>;
+
+    fn logic(self, (a, b): Self::Input) -> Self::Output {
+        a + b + Self::OFFSET
+    }
+}
+"#;
+
+#[test]
+fn module_impl_at_two_widths() {
+    let verilog = Synth::new("top_param_w4", TOP_MUT)
+        .arg("top_params", json!([["W", 4]]))
+        .verilog();
+
+    assert!(verilog.contains("module TopMut_logic"));
+    assert!(verilog.contains("input wire [3:0] a,"));
+    assert!(verilog.contains("output wire [3:0] out"));
+    // `OFFSET` is evaluated for `W = 4`
+    assert!(verilog.contains("assign cast = 4'd2;"));
+
+    let verilog = Synth::new("top_param_w8", TOP_MUT)
+        .arg("top_params", json!([["W", 8]]))
+        .verilog();
+
+    assert!(verilog.contains("input wire [7:0] a,"));
+    assert!(verilog.contains("output wire [7:0] out"));
+    assert!(verilog.contains("assign cast = 8'd4;"));
+}
+
+#[test]
+fn missing_top_param() {
+    Synth::new("top_param_missing", TOP_MUT)
+        .run()
+        .assert_error("missing value of top module parameter 'W' (`--top-param W=..`)");
+}
+
+const ADDER: &str = r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn adder<const W: usize>(a: U<W>, b: U<W>) -> U<W> {
+    a + b
+}
+"#;
+
+#[test]
+fn positional_top_param() {
+    let verilog = Synth::new("top_param_adder", ADDER)
+        .arg("top_params", json!([[null, 8]]))
+        .verilog();
+
+    assert!(verilog.contains("module adder"));
+    assert!(verilog.contains("input wire [7:0] a,"));
+    assert!(verilog.contains("input wire [7:0] b,"));
+    assert!(verilog.contains("output wire [7:0] out"));
+    assert!(verilog.contains("assign out = a + b;"));
+}
+
+#[test]
+fn top_params_count() {
+    Synth::new("top_param_count", ADDER)
+        .arg("top_params", json!([[null, 8], [null, 2]]))
+        .run()
+        .assert_error(
+            "top module has 1 const generic parameters, but 2 values are given by \
+             `--top-param`",
+        );
+}
+
+#[test]
+fn mixed_top_params() {
+    Synth::new("top_param_mixed", ADDER)
+        .arg("top_params", json!([["W", 8], [null, 8]]))
+        .run()
+        .assert_error("`--top-param` values with and without names cannot be combined");
+}
+
+const TOP_U8_BOOL: &str = r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(top)]
+pub fn top<const W: u8, const B: bool>(a: U<8>) -> U<8> {
+    if B {
+        a + U::<8>::cast_from(W)
+    } else {
+        a
+    }
+}
+"#;
+
+#[test]
+fn invalid_top_param() {
+    Synth::new("top_param_invalid", TOP_U8_BOOL)
+        .arg("top_params", json!([["W", 300], ["B", 1]]))
+        .run()
+        .assert_error("value 300 of top module parameter 'W' does not fit into its type");
+
+    Synth::new("top_param_invalid_bool", TOP_U8_BOOL)
+        .arg("top_params", json!([["W", 255], ["B", 2]]))
+        .run()
+        .assert_error("value 2 of top module parameter 'B' does not fit into its type");
+
+    let verilog = Synth::new("top_param_valid", TOP_U8_BOOL)
+        .arg("top_params", json!([["W", 255], ["B", 1]]))
+        .verilog();
+    assert!(verilog.contains("8'd255"));
+}
@@ -0,0 +1,110 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn trait_methods() {
+    let verilog = Synth::new(
+        "traits",
+        r#"
+use ferrum_hdl::prelude::*;
+
+pub trait Step {
+    #[synth(inline)]
+    fn step(&self, val: U<4>) -> U<4>;
+
+    #[synth(inline)]
+    fn counter(&self, clk: &Clock<TD4>, rst: &Reset<TD4>) -> Signal<TD4, U<4>>
+    where
+        Self: Clone + 'static,
+    {
+        let this = self.clone();
+        reg0(clk, rst, move |val| this.step(val))
+    }
+}
+
+#[derive(Clone)]
+pub struct Inc;
+
+impl Step for Inc {
+    fn step(&self, val: U<4>) -> U<4> {
+        val + 1
+    }
+}
+
+#[derive(Clone)]
+pub struct Dec;
+
+impl Step for Dec {
+    fn step(&self, val: U<4>) -> U<4> {
+        val - 1
+    }
+
+    fn counter(&self, clk: &Clock<TD4>, rst: &Reset<TD4>) -> Signal<TD4, U<4>> {
+        reg0(clk, rst, |val: U<4>| val - 1)
+    }
+}
+
+#[synth(top)]
+pub fn top(clk: Clock<TD4>, rst: Reset<TD4>) -> (Signal<TD4, U<4>>, Signal<TD4, U<4>>) {
+    (Inc.counter(&clk, &rst), Dec.counter(&clk, &rst))
+}
+"#,
+    )
+    .verilog();
+
+    // the default and the overridden methods are inlined into the top module
+    assert_eq!(count(&verilog, "module "), 1);
+    assert_eq!(count(&verilog, "always @(posedge clk)"), 2);
+    assert!(verilog.contains("assign out = _reg + cast_1;"));
+    assert!(verilog.contains("assign out_1 = _reg_1 - cast_1;"));
+}
+
+const DYN_OP: &str = r#"
+use ferrum_hdl::prelude::*;
+
+pub trait Op {
+    fn apply(&self, x: U<8>) -> U<8>;
+}
+
+pub struct Inc;
+
+impl Op for Inc {
+    fn apply(&self, x: U<8>) -> U<8> {
+        x + 1
+    }
+}
+"#;
+
+const DYN_DISPATCH: &str =
+    "dynamic dispatch through trait object 'dyn Op' is not synthesizable";
+
+#[test]
+fn dyn_dispatch_is_rejected() {
+    Synth::new(
+        "dyn_coercion",
+        &format!(
+            "{DYN_OP}
+#[synth(top)]
+pub fn top(x: U<8>) -> U<8> {{
+    let op: &dyn Op = &Inc;
+    op.apply(x)
+}}
+"
+        ),
+    )
+    .run()
+    .assert_error(DYN_DISPATCH);
+
+    Synth::new(
+        "dyn_input",
+        &format!(
+            "{DYN_OP}
+#[synth(top)]
+pub fn top(op: &dyn Op, x: U<8>) -> U<8> {{
+    op.apply(x)
+}}
+"
+        ),
+    )
+    .run()
+    .assert_error(DYN_DISPATCH);
+}
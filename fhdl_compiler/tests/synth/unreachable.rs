@@ -0,0 +1,44 @@
+use crate::harness::{count, Synth};
+
+const OP: &str = r#"
+use ferrum_hdl::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SignalValue, BitPack)]
+pub enum Op {
+    Add,
+    Sub,
+    Nop,
+}
+
+#[synth(top)]
+pub fn top(op: Op, a: U<4>, b: U<4>) -> U<4> {
+    match op {
+        Op::Add => a + b,
+        Op::Sub => a - b,
+        Op::Nop => NOP,
+    }
+}
+"#;
+
+#[test]
+fn unreachable_arm() {
+    for (name, nop) in [
+        ("unreachable", "unreachable!()"),
+        ("unreachable_msg", "unreachable!(\"nop\")"),
+    ] {
+        let verilog = Synth::new(name, &OP.replace("NOP", nop)).verilog();
+
+        // the arm of `Op::Nop` is a don't-care branch, so it has no case
+        assert!(verilog.contains("case (op)"));
+        assert_eq!(count(&verilog, ": mux = "), 2);
+        assert!(verilog.contains("2'd0: mux = _$1;"));
+        assert!(verilog.contains("2'd1: mux = _$2;"));
+    }
+}
+
+#[test]
+fn live_panic_arm() {
+    Synth::new("live_panic", &OP.replace("NOP", "panic!()"))
+        .run()
+        .assert_error("not synthesizable type '!'");
+}
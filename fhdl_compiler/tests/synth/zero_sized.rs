@@ -0,0 +1,65 @@
+use crate::harness::{count, Synth};
+
+#[test]
+fn unit_output() {
+    let verilog = Synth::new(
+        "zero_sized_output",
+        r#"
+use ferrum_hdl::prelude::*;
+
+#[synth(inline)]
+fn nothing(_x: U<8>) {}
+
+#[synth(top)]
+pub fn top(a: U<8>) -> U<8> {
+    nothing(a.clone());
+    a
+}
+"#,
+    )
+    .verilog();
+
+    // the return place of `nothing` is never assigned in MIR, it has no ports
+    assert!(verilog.contains("    output wire [7:0] a_1\n);\n\n    assign a_1 = a;\n"));
+    assert!(!verilog.contains("nothing"));
+}
+
+#[test]
+fn phantom_data_marker() {
+    let verilog = Synth::new(
+        "phantom_marker",
+        r#"
+use std::marker::PhantomData;
+
+use ferrum_hdl::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rx;
+
+#[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+#[signal_value(bound = "D: Clone + 'static")]
+#[bitpack(bound = "D:")]
+pub struct Tagged<D> {
+    data: U<4>,
+    _d: PhantomData<D>,
+}
+
+#[synth(top)]
+pub fn top(tagged: Tagged<Rx>) -> Tagged<Rx> {
+    Tagged {
+        data: tagged.data + 1_u8.cast::<U<4>>(),
+        _d: PhantomData,
+    }
+}
+"#,
+    )
+    .verilog();
+
+    // the marker field creates no ports or wires
+    assert_eq!(count(&verilog, "input wire"), 1);
+    assert_eq!(count(&verilog, "output wire"), 1);
+    assert!(verilog.contains("input wire [3:0] tagged,"));
+    assert!(verilog.contains("output wire [3:0] _out$data"));
+    assert!(verilog.contains("assign _out$data = tagged + cast;"));
+    assert!(!verilog.contains("_d"));
+}
@@ -5,7 +5,7 @@ use either::Either;
 use fhdl_const_func::{clog2, clog2_len};
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use syn::{GenericParam, Generics, Ident, Index};
+use syn::{GenericParam, Generics, Ident, Index, Path};
 
 use crate::utils::{self, AdtData, Bounds, Field, TEither};
 
@@ -18,6 +18,8 @@ pub struct BitPack {
     #[darling(default, multiple)]
     bound: Bounds,
     bits: Option<usize>,
+    #[darling(default, multiple)]
+    cast_from: Vec<Path>,
 }
 
 impl Field {
@@ -43,32 +45,32 @@ impl BitPack {
         let discr_width = self.discr_width()?;
         let impl_bit_size = self.impl_bit_size(discr_width);
         let impl_bit_pack = self.impl_bit_pack(discr_width);
+        let impl_cast_from = self.impl_cast_from()?;
 
         Ok(quote! {
             #impl_bit_size
 
             #impl_bit_pack
+
+            #impl_cast_from
         })
     }
 
     fn discr_width(&self) -> Result<usize, darling::Error> {
         match &self.data {
             AdtData::Enum(variants) => {
-                let max_discr =
-                    variants
-                        .iter()
-                        .fold(0, |max_discr, variant| match variant.discr() {
-                            Some(discr) => {
-                                if max_discr <= discr {
-                                    discr
-                                } else {
-                                    max_discr
-                                }
-                            }
-                            None => max_discr,
-                        });
+                // the same as the compiler computes the width of discriminants: implicit
+                // discriminants following explicit ones are also taken into account
+                let has_explicit =
+                    variants.iter().any(|variant| variant.discr().is_some());
+                let mut idx = 0;
+                let max_discr = variants
+                    .iter()
+                    .map(|variant| variant.branch(&mut idx))
+                    .max()
+                    .unwrap_or_default();
 
-                let bits = if max_discr == 0 {
+                let bits = if !has_explicit {
                     clog2_len(variants.len())
                 } else {
                     clog2(max_discr)
@@ -97,6 +99,46 @@ impl BitPack {
         }
     }
 
+    // Enums with the same layout are cast into each other without any logic
+    fn impl_cast_from(&self) -> Result<TokenStream, darling::Error> {
+        if self.cast_from.is_empty() {
+            return Ok(TokenStream::new());
+        }
+        if !matches!(self.data, AdtData::Enum(_)) {
+            return Err(darling::Error::custom("Cast can be derived only for enums"));
+        }
+
+        let ident = &self.ident;
+        let (impl_generics, ty_generics, predicates) =
+            utils::split_generics_for_impl(&self.generics);
+        let predicates = predicates.collect::<Vec<_>>();
+
+        Ok(self
+            .cast_from
+            .iter()
+            .map(|from| {
+                let where_clause = utils::into_where_clause(
+                    predicates.iter().cloned().chain(iter::once(TEither::TS(quote! {
+                        #from: ::ferrum_hdl::bitpack::BitPack<
+                            Packed = <#ident #ty_generics as ::ferrum_hdl::bitpack::BitPack>::Packed
+                        >
+                    }))),
+                );
+
+                quote! {
+                    #[automatically_derived]
+                    impl #impl_generics ::ferrum_hdl::cast::CastFrom<#from> for #ident #ty_generics
+                    #where_clause
+                    {
+                        fn cast_from(from: #from) -> Self {
+                            ::ferrum_hdl::cast::cast_enum(from)
+                        }
+                    }
+                }
+            })
+            .collect())
+    }
+
     pub fn impl_bit_size(&self, discr_width: usize) -> TokenStream {
         let ident = &self.ident;
         let bit_size = match &self.data {
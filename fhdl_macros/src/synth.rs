@@ -11,6 +11,7 @@ use syn::{
 pub struct SynthAttrs {
     top: Flag,
     inline: Flag,
+    no_inline: Flag,
 }
 
 impl Parse for SynthAttrs {
@@ -20,7 +21,15 @@ impl Parse for SynthAttrs {
                 .into_iter()
                 .collect();
 
-        Ok(Self::from_list(&attrs)?)
+        let attrs = Self::from_list(&attrs)?;
+        if attrs.inline.is_present() && attrs.no_inline.is_present() {
+            return Err(syn::Error::new(
+                attrs.no_inline.span(),
+                "`inline` and `no_inline` cannot be used together",
+            ));
+        }
+
+        Ok(attrs)
     }
 }
 
@@ -30,6 +39,9 @@ impl ToTokens for SynthAttrs {
         if self.inline.is_present() {
             attrs.push(quote! { inline });
         }
+        if self.no_inline.is_present() {
+            attrs.push(quote! { no_inline });
+        }
         if self.top.is_present() {
             attrs.push(quote! { top });
         }
@@ -14,7 +14,7 @@ use syn::{
     WherePredicate,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TEither<T> {
     AsIs(T),
     TS(TokenStream),
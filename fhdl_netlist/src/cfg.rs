@@ -11,9 +11,38 @@ pub enum InlineMod {
     None,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Args)]
+// Number format of constants in Verilog
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize,
+)]
+pub enum ConstFmt {
+    #[default]
+    Dec,
+    // Single-bit constants are emitted in binary
+    Hex,
+}
+
+// Evaluation of constant `+`, `-` and `*` whose result doesn't fit into its width
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize,
+)]
+pub enum ConstOverflow {
+    // The same as in hardware
+    #[default]
+    Wrap,
+    Saturate,
+    Error,
+}
+
+pub const DEFAULT_ROM_THRESHOLD: usize = 64;
+
+fn default_rom_threshold() -> usize {
+    DEFAULT_ROM_THRESHOLD
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Args)]
 pub struct NetListCfg {
-    /// Inline modules
+    /// Inline modules (`none` keeps the full hierarchy, including closures and std calls)
     #[arg(long, value_enum, default_value_t = InlineMod::Auto)]
     pub inline_mod: InlineMod,
     /// Do not embed nested multiplexers
@@ -22,7 +51,66 @@ pub struct NetListCfg {
     /// Do not eliminate constants
     #[arg(long)]
     pub no_eliminate_const: bool,
+    /// Do not reconnect pass nodes
+    #[arg(long)]
+    #[serde(default)]
+    pub no_reconnect_pass: bool,
+    /// Do not fuse splitter/merger pairs
+    #[arg(long)]
+    #[serde(default)]
+    pub no_fuse_splitter_merger: bool,
+    /// Do not remove constant reset and enable of DFFs
+    #[arg(long)]
+    #[serde(default)]
+    pub no_simplify_dff: bool,
+    /// Emit clock-gating cells for DFFs with enable instead of recirculation muxes
+    /// (except for DFFs with a sync reset)
+    #[arg(long)]
+    #[serde(default)]
+    pub clock_gating: bool,
+    /// Keep user-assigned signal names, renaming only on collision
+    #[arg(long)]
+    #[serde(default)]
+    pub preserve_names: bool,
+    /// Number format of constants
+    #[arg(long, value_enum, default_value_t = ConstFmt::Dec)]
+    #[serde(default)]
+    pub const_fmt: ConstFmt,
+    /// Overflow policy of constant arithmetic
+    #[arg(long, value_enum, default_value_t = ConstOverflow::Wrap)]
+    #[serde(default)]
+    pub const_overflow: ConstOverflow,
+    /// Min number of entries of a constant array indexed by a signal to lower it
+    /// to a ROM instead of a multiplexer
+    #[arg(long, default_value_t = DEFAULT_ROM_THRESHOLD)]
+    #[serde(default = "default_rom_threshold")]
+    pub rom_threshold: usize,
+    /// Warn about registers of async-reset domains whose reset is removed as
+    /// constantly inactive
+    #[arg(long)]
+    #[serde(default)]
+    pub lint_async_rst: bool,
     /// Max inlines (for debugging purposes)
     #[arg(long)]
     pub max_inlines: Option<usize>,
 }
+
+impl Default for NetListCfg {
+    fn default() -> Self {
+        Self {
+            inline_mod: Default::default(),
+            no_embed_muxs: false,
+            no_eliminate_const: false,
+            no_reconnect_pass: false,
+            no_fuse_splitter_merger: false,
+            no_simplify_dff: false,
+            clock_gating: false,
+            preserve_names: false,
+            const_fmt: Default::default(),
+            const_overflow: Default::default(),
+            rom_threshold: DEFAULT_ROM_THRESHOLD,
+            lint_async_rst: false,
+            max_inlines: None,
+        }
+    }
+}
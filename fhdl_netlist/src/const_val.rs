@@ -7,7 +7,10 @@ use std::{
 
 use fhdl_const_func::mask;
 
-use crate::node::BinOp;
+use crate::{
+    cfg::{ConstFmt, ConstOverflow},
+    node::BinOp,
+};
 
 // TODO: use long arithmetic
 #[derive(Clone, Copy)]
@@ -22,6 +25,23 @@ impl Display for ConstVal {
     }
 }
 
+// Verilog literal of the constant in the given number format
+pub struct ConstLit {
+    val: ConstVal,
+    fmt: ConstFmt,
+}
+
+impl Display for ConstLit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ConstVal { val, width } = self.val;
+        match self.fmt {
+            ConstFmt::Dec => write!(f, "{width}'d{val}"),
+            ConstFmt::Hex if width == 1 => write!(f, "{width}'b{val}"),
+            ConstFmt::Hex => write!(f, "{width}'h{val:x}"),
+        }
+    }
+}
+
 impl Debug for ConstVal {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -49,6 +69,11 @@ impl ConstVal {
         Self { val, width }
     }
 
+    #[inline]
+    pub fn lit(self, fmt: ConstFmt) -> ConstLit {
+        ConstLit { val: self, fmt }
+    }
+
     pub fn zero(width: u128) -> Self {
         Self { val: 0, width }
     }
@@ -84,12 +109,22 @@ impl ConstVal {
     }
 
     pub fn sra(self, rhs: ConstVal) -> ConstVal {
-        let width = op_width(&self, &rhs);
-        bin_op(
-            ((val_(self.val, width) as i128) >> val_(rhs.val, width)) as u128,
-            self,
-            rhs,
-        )
+        let width = self.width;
+        if width == 0 {
+            return self;
+        }
+
+        // shifts by `width` or more bits fill the value with the sign bit
+        let val = self.sext();
+        let amount = cmp::min(rhs.val(), width - 1) as u32;
+
+        ConstVal::new((val >> amount) as u128, width)
+    }
+
+    // Sign extends the value to 128 bits
+    fn sext(&self) -> i128 {
+        let ext = 128 - self.width as u32;
+        ((self.val() << ext) as i128) >> ext
     }
 
     pub fn slice(&self, start: u128, width: u128) -> ConstVal {
@@ -117,7 +152,7 @@ impl ConstVal {
             BinOp::BitOr => self | other,
             BinOp::BitXor => self ^ other,
             BinOp::And => self & other,
-            BinOp::Or => self & other,
+            BinOp::Or => self | other,
             BinOp::Sll => self << other,
             BinOp::Slr => self >> other,
             BinOp::Sra => self.sra(other),
@@ -129,6 +164,84 @@ impl ConstVal {
             BinOp::Lt => (self < other).into(),
         }
     }
+
+    // The same as `eval_bin_op`, but `+`, `-` and `*` follow the overflow policy.
+    // Returns `None` if the result overflows with `ConstOverflow::Error`.
+    pub fn eval_bin_op_with(
+        self,
+        other: Self,
+        bin_op: BinOp,
+        signed: bool,
+        overflow: ConstOverflow,
+    ) -> Option<ConstVal> {
+        match (bin_op, overflow) {
+            (BinOp::Add | BinOp::Sub | BinOp::Mul, ConstOverflow::Saturate) => Some(
+                self.checked_arith(other, bin_op, signed)
+                    .unwrap_or_else(|sat| sat),
+            ),
+            (BinOp::Add | BinOp::Sub | BinOp::Mul, ConstOverflow::Error) => {
+                self.checked_arith(other, bin_op, signed).ok()
+            }
+            _ => Some(self.eval_bin_op(other, bin_op)),
+        }
+    }
+
+    // Returns the saturated result as an error if the result doesn't fit into the width
+    fn checked_arith(
+        self,
+        rhs: Self,
+        bin_op: BinOp,
+        signed: bool,
+    ) -> Result<ConstVal, ConstVal> {
+        let width = op_width(&self, &rhs);
+        if width == 0 {
+            return Ok(self);
+        }
+
+        if signed {
+            let (lhs, rhs) = (self.sext(), rhs.sext());
+            let min = i128::MIN >> (128 - width);
+            let max = i128::MAX >> (128 - width);
+
+            // `neg` is the sign of the result if it overflows 128 bits
+            let (res, neg) = match bin_op {
+                BinOp::Add => (lhs.checked_add(rhs), rhs < 0),
+                BinOp::Sub => (lhs.checked_sub(rhs), rhs > 0),
+                BinOp::Mul => (lhs.checked_mul(rhs), (lhs < 0) != (rhs < 0)),
+                _ => unreachable!(),
+            };
+
+            match res {
+                Some(res) if (min ..= max).contains(&res) => {
+                    Ok(ConstVal::new(res as u128, width))
+                }
+                Some(res) if res < 0 => Err(ConstVal::new(min as u128, width)),
+                Some(_) => Err(ConstVal::new(max as u128, width)),
+                None if neg => Err(ConstVal::new(min as u128, width)),
+                None => Err(ConstVal::new(max as u128, width)),
+            }
+        } else {
+            let (lhs, rhs) = (self.val(), rhs.val());
+            let max = mask(width);
+
+            let res = match bin_op {
+                BinOp::Add => lhs.checked_add(rhs),
+                BinOp::Sub => {
+                    return lhs
+                        .checked_sub(rhs)
+                        .map(|res| ConstVal::new(res, width))
+                        .ok_or(ConstVal::zero(width));
+                }
+                BinOp::Mul => lhs.checked_mul(rhs),
+                _ => unreachable!(),
+            };
+
+            match res {
+                Some(res) if res <= max => Ok(ConstVal::new(res, width)),
+                _ => Err(ConstVal::new(max, width)),
+            }
+        }
+    }
 }
 
 fn bin_op(val: u128, lhs: ConstVal, rhs: ConstVal) -> ConstVal {
@@ -157,10 +270,10 @@ impl From<bool> for ConstVal {
     }
 }
 
+// Constants of different widths are not equal (consistently with `Hash`)
 impl PartialEq for ConstVal {
     fn eq(&self, other: &Self) -> bool {
-        let width = op_width(self, other);
-        val_(self.val, width) == val_(other.val, width)
+        self.width == other.width && self.val() == other.val()
     }
 }
 
@@ -198,13 +311,7 @@ impl Add for ConstVal {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        bin_op(
-            self.val
-                .checked_add(rhs.val)
-                .expect("attempt to add with overflow"),
-            self,
-            rhs,
-        )
+        bin_op(self.val.wrapping_add(rhs.val), self, rhs)
     }
 }
 
@@ -212,13 +319,7 @@ impl Sub for ConstVal {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        bin_op(
-            self.val
-                .checked_sub(rhs.val)
-                .expect("attempt to subtract with overflow"),
-            self,
-            rhs,
-        )
+        bin_op(self.val.wrapping_sub(rhs.val), self, rhs)
     }
 }
 
@@ -226,13 +327,7 @@ impl Mul for ConstVal {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        bin_op(
-            self.val
-                .checked_mul(rhs.val)
-                .expect("attempt to multiply with overflow"),
-            self,
-            rhs,
-        )
+        bin_op(self.val.wrapping_mul(rhs.val), self, rhs)
     }
 }
 
@@ -252,12 +347,16 @@ impl Rem for ConstVal {
     }
 }
 
+// The amount of shift can have any width. Shifts by `width` or more bits produce zero
+// (as in Verilog).
 impl Shl for ConstVal {
     type Output = Self;
 
     fn shl(self, rhs: Self) -> Self::Output {
-        let width = op_width(&self, &rhs);
-        bin_op(val_(self.val, width) << val_(rhs.val, width), self, rhs)
+        match rhs.val() {
+            amount if amount >= self.width => ConstVal::zero(self.width),
+            amount => ConstVal::new(self.val() << amount, self.width),
+        }
     }
 }
 
@@ -265,8 +364,10 @@ impl Shr for ConstVal {
     type Output = Self;
 
     fn shr(self, rhs: Self) -> Self::Output {
-        let width = op_width(&self, &rhs);
-        bin_op(val_(self.val, width) >> val_(rhs.val, width), self, rhs)
+        match rhs.val() {
+            amount if amount >= self.width => ConstVal::zero(self.width),
+            amount => ConstVal::new(self.val() >> amount, self.width),
+        }
     }
 }
 
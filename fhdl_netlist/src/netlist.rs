@@ -1,6 +1,10 @@
 mod module;
 
-use std::{cell::RefCell, ops::Index};
+use std::{
+    cell::RefCell,
+    fmt::{self, Display},
+    ops::Index,
+};
 
 use fhdl_data_structures::{
     graph::NodeId, index::IndexType, index_storage::IndexStorage,
@@ -17,6 +21,26 @@ pub struct NetList {
     pub top: Option<ModuleId>,
     modules: IndexStorage<ModuleId, RefCell<Module>>,
     cfg: NetListCfg,
+    pub(crate) warnings: Vec<Warning>,
+    pub(crate) errors: Vec<String>,
+}
+
+// Lint warning with the span of the node it is reported for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub msg: String,
+    pub span: Option<String>,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (at {})",
+            self.msg,
+            self.span.as_deref().unwrap_or("unknown")
+        )
+    }
 }
 
 impl Index<ModuleId> for NetList {
@@ -33,6 +57,8 @@ impl NetList {
             top: None,
             modules: Default::default(),
             cfg,
+            warnings: Default::default(),
+            errors: Default::default(),
         }
     }
 
@@ -40,6 +66,16 @@ impl NetList {
         &self.cfg
     }
 
+    // Lint warnings collected by the visitors
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    // Errors collected by the visitors, e.g. overflows of constant arithmetic
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
     #[inline]
     pub fn add_module(&mut self, module: Module) -> ModuleId {
         let mod_id = self.modules.last_idx();
@@ -71,6 +107,12 @@ impl NetList {
         }
     }
 
+    pub fn module_by_name(&self, name: &str) -> Option<ModuleId> {
+        self.modules()
+            .find(|module| module.borrow().name.as_str() == name)
+            .map(|module| module.id)
+    }
+
     pub fn inline_mod(
         &self,
         mut target_mod: WithId<ModuleId, &mut Module>,
@@ -88,3 +130,20 @@ impl NetList {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_by_name() {
+        let mut netlist = NetList::default();
+        let adder = netlist.add_module(Module::new("adder", false));
+        let top = netlist.add_module(Module::new("top", true));
+        netlist.add_module(Module::new("adder", false));
+
+        assert_eq!(netlist.module_by_name("adder"), Some(adder));
+        assert_eq!(netlist.module_by_name("top"), Some(top));
+        assert_eq!(netlist.module_by_name("mul"), None);
+    }
+}
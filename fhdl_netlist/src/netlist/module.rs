@@ -1,4 +1,5 @@
 use std::{
+    collections::VecDeque,
     fmt::{self, Write},
     ops::{Index, IndexMut},
     rc::Rc,
@@ -10,7 +11,7 @@ use fhdl_data_structures::{
     idx_ty,
     index::IndexType,
     list::{List, ListCursor, ListItem},
-    FxHashMap, FxIndexSet,
+    FxHashMap, FxHashSet, FxIndexSet,
 };
 use indexmap::set::Slice;
 
@@ -54,6 +55,9 @@ pub struct Module {
     pub is_top: bool,
     pub skip: bool,
     pub inline: bool,
+    pub force_no_inline: bool,
+    // module is defined in an external verilog file
+    pub external: bool,
     gl_signals: GlobalSignals,
     span: Option<Rc<String>>,
     graph: Graph<Node>,
@@ -176,6 +180,8 @@ impl Module {
             is_top,
             skip: true,
             inline: false,
+            force_no_inline: false,
+            external: false,
             gl_signals: Default::default(),
             span: None,
             graph: Default::default(),
@@ -258,6 +264,16 @@ impl Module {
         })
     }
 
+    // outputs of external modules are driven by the external verilog,
+    // so they are represented by input nodes which are not module inputs
+    pub fn add_extern_output(&mut self, ty: NodeTy) -> Port {
+        let port = self.add_input(ty, None::<&str>);
+        self.inputs.shift_remove(&port);
+        self.add_mod_output(port);
+
+        port
+    }
+
     pub fn clk(&mut self) -> Port {
         if self.gl_signals.clk.is_none() {
             let clk = self.add_input(NodeTy::ClockDomain, Some("clk"));
@@ -361,6 +377,107 @@ impl Module {
         self.incoming(node_id).into_iter_(self)
     }
 
+    // Returns the first input port driven by more than one edge
+    pub fn find_multi_driven(&self) -> Option<Port> {
+        let mut driven = FxHashSet::default();
+        for node_id in self.nodes().into_iter_(self) {
+            driven.clear();
+
+            let mut incoming = self.graph.incoming(node_id);
+            while let Some(edge_id) = incoming.next_(&self.graph) {
+                let port_in = self.graph[edge_id].port_in;
+                if !driven.insert(port_in.port) {
+                    return Some(port_in);
+                }
+            }
+        }
+
+        None
+    }
+
+    // Returns nodes in a topological order (drivers first). The order follows the graph
+    // only, so it doesn't depend on how the node list was edited by transforms: nodes
+    // are grouped by the length of the longest path from their sources, and the nodes
+    // of a group are ordered by a depth-first visit from the module inputs and outputs.
+    // Loops are broken at registers, their inputs depending on the register itself are
+    // visited after it. Nodes driving no outputs are visited in the node list order
+    pub fn topo_nodes(&self) -> Vec<NodeId> {
+        let deps = |node_id: NodeId, roots: &mut VecDeque<NodeId>| -> Vec<NodeId> {
+            let deps = self.incoming_iter(node_id).map(|port| port.node);
+            if self[node_id].dff().is_some() {
+                let fanout = self.fanout(node_id);
+                let (loop_deps, deps) =
+                    deps.partition::<Vec<_>, _>(|dep| fanout.contains(dep));
+                roots.extend(loop_deps);
+                deps
+            } else {
+                deps.collect()
+            }
+        };
+
+        let mut order = Vec::with_capacity(self.node_count());
+        let mut levels = FxHashMap::default();
+        let mut visited = FxHashSet::default();
+        let mut stack = Vec::new();
+        let mut roots = self
+            .mod_inputs()
+            .iter()
+            .chain(self.mod_outputs())
+            .map(|port| port.node)
+            .collect::<VecDeque<_>>();
+        let mut nodes = self.nodes().into_iter_(self);
+
+        while let Some(node_id) = roots.pop_front().or_else(|| nodes.next()) {
+            if !visited.insert(node_id) {
+                continue;
+            }
+            stack.push((node_id, deps(node_id, &mut roots), 0));
+
+            while let Some((node_id, node_deps, idx)) = stack.last_mut() {
+                match node_deps.get(*idx) {
+                    Some(&dep) => {
+                        *idx += 1;
+                        // a visited dependency is either ordered or on the stack (a loop)
+                        if visited.insert(dep) {
+                            stack.push((dep, deps(dep, &mut roots), 0));
+                        }
+                    }
+                    None => {
+                        let level = node_deps
+                            .iter()
+                            .filter_map(|dep| levels.get(dep).map(|level| level + 1))
+                            .max()
+                            .unwrap_or(0);
+                        levels.insert(*node_id, level);
+                        order.push(*node_id);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        order.sort_by_key(|node_id| levels[node_id]);
+        order
+    }
+
+    // Returns the nodes driven by the node directly or through other nodes
+    fn fanout(&self, node_id: NodeId) -> FxHashSet<NodeId> {
+        let mut fanout = FxHashSet::default();
+        let mut queue = vec![node_id];
+
+        while let Some(node_id) = queue.pop() {
+            for port in self.node_out_ports(node_id) {
+                for node_id in self.outgoing(port).into_iter_(self) {
+                    if fanout.insert(node_id) {
+                        queue.push(node_id);
+                    }
+                }
+            }
+        }
+
+        fanout
+    }
+
     #[inline]
     pub fn outgoing(&self, port: Port) -> Outgoing {
         Outgoing(self.graph.outgoing(port))
@@ -674,6 +791,8 @@ impl Module {
         self.graph.reserve_nodes(source_mod.graph.node_count());
         self.graph.reserve_edges(source_mod.graph.edge_count());
 
+        let inst_span = self.graph[mod_inst_id].span_rc();
+
         let mut prev_node_id = mod_inst_id;
         for node_id in source_mod.nodes().into_iter_(&source_mod) {
             let node = &source_mod[node_id];
@@ -682,7 +801,16 @@ impl Module {
             }
 
             let node_id = calc_node_id(node_id);
-            let node = node.new_from();
+            let mut node = node.new_from();
+
+            // keep the instantiation span next to the span of the inlined node,
+            // nodes without a span get the span of the instantiation
+            if let Some(inst_span) = inst_span.as_deref() {
+                node.set_span(Some(match node.span() {
+                    Some(span) => format!("{span} <- {inst_span}"),
+                    None => inst_span.to_string(),
+                }));
+            }
 
             self.insert_node(node_id, prev_node_id, node);
             prev_node_id = node_id;
@@ -751,3 +879,209 @@ impl WithId<ModuleId, &'_ Module> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use ferrum_hdl::domain::{Polarity, SyncKind};
+
+    use super::*;
+    use crate::{
+        netlist::NetList,
+        node::{
+            BinOp, BinOpArgs, BinOpNode, BitNot, BitNotArgs, DFFArgs, ModInstArgs,
+            TyOrData, DFF,
+        },
+    };
+
+    fn pass_module(name: &str, span: Option<&str>) -> Module {
+        let mut module = Module::new(name, false);
+        let input = module.add_input(NodeTy::Unsigned(4), Some("input"));
+        let pass = module.add::<_, Pass>(PassArgs {
+            input,
+            sym: None,
+            ty: None,
+        });
+        module.add_span(pass, span.map(ToString::to_string));
+        module.add_mod_outputs(pass);
+
+        module
+    }
+
+    fn inst_module(name: &str, inst: WithId<ModuleId, &Module>, span: &str) -> Module {
+        let mut module = Module::new(name, false);
+        let input = module.add_input(NodeTy::Unsigned(4), Some("input"));
+        let mod_inst = module.add::<_, ModInst>(ModInstArgs {
+            module: inst,
+            inputs: iter::once(input),
+            outputs: iter::once(None),
+        });
+        module.add_span(mod_inst, Some(span.to_string()));
+        module.add_mod_outputs(mod_inst);
+
+        module
+    }
+
+    fn add_inst_module(
+        netlist: &mut NetList,
+        name: &str,
+        inst: ModuleId,
+        span: &str,
+    ) -> ModuleId {
+        let inst = netlist.module(inst).map(|module| module.borrow());
+        let module = inst_module(name, inst.as_deref(), span);
+        drop(inst);
+
+        netlist.add_module(module)
+    }
+
+    fn inline(netlist: &NetList, mod_id: ModuleId) {
+        let mut module = netlist.module(mod_id).map(|module| module.borrow_mut());
+        let mod_inst_id = module
+            .nodes()
+            .into_iter_(&module)
+            .find(|node_id| module[*node_id].is_mod_inst())
+            .unwrap();
+
+        netlist.inline_mod(module.as_deref_mut(), mod_inst_id);
+    }
+
+    #[test]
+    fn inline_mod_spans() {
+        let mut netlist = NetList::default();
+        let leaf = netlist.add_module(pass_module("leaf", Some("src/leaf.rs:1")));
+        let middle = add_inst_module(&mut netlist, "middle", leaf, "src/middle.rs:2");
+        let top = add_inst_module(&mut netlist, "top", middle, "src/top.rs:3");
+
+        inline(&netlist, middle);
+        inline(&netlist, top);
+
+        let module = netlist[top].borrow();
+        let spans = module
+            .nodes()
+            .into_iter_(&module)
+            .filter_map(|node_id| module[node_id].span())
+            .collect::<Vec<_>>();
+
+        assert_eq!(spans, ["src/leaf.rs:1 <- src/middle.rs:2 <- src/top.rs:3"]);
+    }
+
+    #[test]
+    fn inline_mod_without_span() {
+        let mut netlist = NetList::default();
+        let leaf = netlist.add_module(pass_module("leaf", None));
+        let top = add_inst_module(&mut netlist, "top", leaf, "src/top.rs:3");
+
+        inline(&netlist, top);
+
+        let module = netlist[top].borrow();
+        let spans = module
+            .nodes()
+            .into_iter_(&module)
+            .filter_map(|node_id| module[node_id].span())
+            .collect::<Vec<_>>();
+
+        assert_eq!(spans, ["src/top.rs:3"]);
+    }
+
+    fn double_driven_module() -> (Module, Port) {
+        let mut module = pass_module("double", Some("src/double.rs:1"));
+        let other = module.add_input(NodeTy::Unsigned(4), Some("other"));
+        let pass = module.mod_outputs()[0];
+        module.add_edge(other, Port::new(pass.node, 0));
+
+        (module, Port::new(pass.node, 0))
+    }
+
+    #[test]
+    fn find_multi_driven() {
+        assert_eq!(
+            pass_module("pass", Some("src/pass.rs:1")).find_multi_driven(),
+            None
+        );
+
+        let (module, port) = double_driven_module();
+        assert_eq!(module.find_multi_driven(), Some(port));
+    }
+
+    #[test]
+    fn check_single_drivers() {
+        let mut netlist = NetList::default();
+        netlist.add_module(pass_module("pass", Some("src/pass.rs:1")));
+        assert!(netlist.check_single_drivers());
+        assert!(netlist.errors().is_empty());
+
+        let mod_id = netlist.add_module(double_driven_module().0);
+        netlist.run_visitors();
+
+        let module = netlist[mod_id].borrow();
+        let port = module.mod_outputs()[0];
+        assert_eq!(netlist.errors(), [format!(
+            "input 0 of node {} in module 'double' has multiple drivers (at \
+             src/double.rs:1)",
+            port.node
+        )]);
+    }
+
+    #[test]
+    fn topo_nodes() {
+        let mut module = Module::new("topo", false);
+        let a = module.add_input(NodeTy::Unsigned(4), Some("a"));
+        let pass = module.add_and_get_port::<_, Pass>(PassArgs {
+            input: a,
+            ty: None,
+            sym: None,
+        });
+        let inv = module.add_and_get_port::<_, BitNot>(BitNotArgs {
+            ty: NodeTy::Unsigned(4),
+            input: a,
+            sym: None,
+        });
+        module.add_mod_output(pass);
+
+        // the pass node is rewired to the inverter inserted after it
+        let edge_id = module
+            .graph
+            .incoming(pass.node)
+            .next_(&module.graph)
+            .unwrap();
+        module.graph.remove_edge(edge_id);
+        module.add_edge(inv, Port::new(pass.node, 0));
+
+        assert_eq!(module.topo_nodes(), [a.node, inv.node, pass.node]);
+    }
+
+    #[test]
+    fn topo_nodes_breaks_loops_at_dff() {
+        // reg <= reg + 1
+        let mut module = Module::new("counter", false);
+        let clk = module.add_input(NodeTy::Clock, Some("clk"));
+        let init = module.const_val(NodeTy::Unsigned(4), 0);
+        let dff = module.add::<_, DFF>(DFFArgs {
+            clk,
+            rst: None,
+            rst_kind: SyncKind::Sync,
+            rst_pol: Polarity::ActiveHigh,
+            en: None,
+            init,
+            rst_val: None,
+            data: TyOrData::Ty(NodeTy::Unsigned(4)),
+            sym: None,
+        });
+        let one = module.const_val(NodeTy::Unsigned(4), 1);
+        let next = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Unsigned(4),
+            bin_op: BinOp::Add,
+            lhs: Port::new(dff, 0),
+            rhs: one,
+            sym: None,
+        });
+        DFF::set_data(&mut module, dff, next);
+        module.add_mod_output(Port::new(dff, 0));
+
+        assert_eq!(module.topo_nodes(), [
+            clk.node, init.node, one.node, dff, next.node
+        ]);
+    }
+}
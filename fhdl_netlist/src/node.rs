@@ -1,5 +1,6 @@
 mod bin_op;
 mod bit_not;
+mod clock_gate;
 mod cons;
 mod dff;
 mod input;
@@ -7,6 +8,7 @@ mod memory;
 mod merger;
 mod mod_inst;
 mod pass;
+mod reduce;
 mod splitter;
 mod switch;
 mod zero_extend;
@@ -27,6 +29,7 @@ pub(crate) use self::cons::MultiConst;
 pub use self::{
     bin_op::{BinOp, BinOpArgs, BinOpInputs, BinOpNode},
     bit_not::{BitNot, BitNotArgs},
+    clock_gate::{ClockGate, ClockGateArgs, ClockGateInputs},
     cons::{Const, ConstArgs},
     dff::{DFFArgs, DFFInputs, TyOrData, DFF},
     input::{GlSignalKind, Input, InputArgs},
@@ -34,7 +37,8 @@ pub use self::{
     merger::{Merger, MergerArgs},
     mod_inst::{ModInst, ModInstArgs},
     pass::{Pass, PassArgs},
-    splitter::{Indices, Splitter, SplitterArgs},
+    reduce::{Reduce, ReduceArgs, ReduceOp},
+    splitter::{Indices, Offsets, SlicesArgs, Splitter, SplitterArgs},
     switch::{Case, Switch, SwitchArgs, SwitchInputs, TupleCase},
     zero_extend::{Extend, ExtendArgs},
 };
@@ -200,6 +204,10 @@ impl Node {
         matches!(&*self.kind, NodeKind::BitNot(_) | NodeKind::BinOp(_))
     }
 
+    pub fn is_bit_not(&self) -> bool {
+        matches!(&*self.kind, NodeKind::BitNot(_))
+    }
+
     pub fn is_const(&self) -> bool {
         matches!(&*self.kind, NodeKind::Const(_) | NodeKind::MultiConst(_))
     }
@@ -224,6 +232,13 @@ impl Node {
         matches!(&*self.kind, NodeKind::ModInst(_))
     }
 
+    pub fn clock_gate(&self) -> Option<&ClockGate> {
+        match &*self.kind {
+            NodeKind::ClockGate(clock_gate) => Some(clock_gate),
+            _ => None,
+        }
+    }
+
     pub fn mod_inst(&self) -> Option<&ModInst> {
         match &*self.kind {
             NodeKind::ModInst(mod_inst) => Some(mod_inst),
@@ -414,27 +429,34 @@ impl WithId<NodeId, &'_ Node> {
                     netlist[mod_inst.mod_id].borrow().name
                 )?;
             }
-            NodeKind::Splitter(splitter) => {
-                writeln!(buf, "{}start = {}", tab, splitter.start.dump())?;
-            }
+            NodeKind::Splitter(splitter) => match &splitter.offsets {
+                Offsets::Adjacent { start, .. } => {
+                    writeln!(buf, "{}start = {}", tab, start.dump())?;
+                }
+                Offsets::Explicit(offsets) => {
+                    writeln!(buf, "{}offsets = {:?}", tab, offsets)?;
+                }
+            },
             NodeKind::DFF(dff) => {
                 let DFFInputs {
                     clk,
                     rst,
                     en,
                     init,
+                    rst_val,
                     data,
                 } = self.with(dff).inputs(module);
 
                 show_inputs = false;
                 writeln!(
                     buf,
-                    "{}clk = {}, rst = {}, en = {}, init = {}, data = {}",
+                    "{}clk = {}, rst = {}, en = {}, init = {}, rst_val = {}, data = {}",
                     tab,
                     clk,
                     rst.dump(),
                     en.dump(),
                     init,
+                    rst_val.dump(),
                     data
                 )?;
             }
@@ -564,6 +586,7 @@ macro_rules! define_nodes {
 define_nodes!(
     BinOp => BinOpNode,
     BitNot => BitNot,
+    ClockGate => ClockGate,
     Const => Const,
     DFF => DFF,
     Input => Input,
@@ -572,6 +595,7 @@ define_nodes!(
     MultiConst => MultiConst,
     Switch => Switch,
     Pass => Pass,
+    Reduce => Reduce,
     Splitter => Splitter,
     Extend => Extend,
     Memory => Memory,
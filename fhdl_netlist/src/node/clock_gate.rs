@@ -0,0 +1,78 @@
+use fhdl_data_structures::{
+    cursor::Cursor,
+    graph::{NodeId, Port},
+};
+
+use super::{IsNode, MakeNode, NodeOutput};
+use crate::{netlist::Module, node_ty::NodeTy, symbol::Symbol, with_id::WithId};
+
+// Gated clock, lowered to a latch-based clock-gating cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockGate {
+    pub output: [NodeOutput; 1],
+}
+
+#[derive(Debug)]
+pub struct ClockGateArgs {
+    pub clk: Port,
+    pub en: Port,
+    pub sym: Option<Symbol>,
+}
+
+impl ClockGateArgs {
+    fn assert(&self, module: &Module) {
+        assert_eq!(module[self.clk].ty, NodeTy::Clock);
+        assert_eq!(module[self.en].width(), 1);
+    }
+}
+
+impl MakeNode<ClockGateArgs> for ClockGate {
+    fn make(module: &mut Module, args: ClockGateArgs) -> NodeId {
+        args.assert(module);
+
+        let ClockGateArgs { clk, en, sym } = args;
+
+        let node_id = module.add_node(ClockGate {
+            output: [NodeOutput::wire(NodeTy::Clock, sym)],
+        });
+
+        module.add_edge(clk, Port::new(node_id, 0));
+        module.add_edge(en, Port::new(node_id, 1));
+
+        node_id
+    }
+}
+
+impl IsNode for ClockGate {
+    #[inline]
+    fn in_count(&self) -> usize {
+        2
+    }
+
+    #[inline]
+    fn outputs(&self) -> &[NodeOutput] {
+        &self.output
+    }
+
+    #[inline]
+    fn outputs_mut(&mut self) -> &mut [NodeOutput] {
+        &mut self.output
+    }
+}
+
+#[derive(Debug)]
+pub struct ClockGateInputs {
+    pub clk: Port,
+    pub en: Port,
+}
+
+impl WithId<NodeId, &'_ ClockGate> {
+    pub fn inputs(&self, module: &Module) -> ClockGateInputs {
+        let mut incoming = module.incoming(self.id);
+
+        ClockGateInputs {
+            clk: incoming.next_(module).unwrap(),
+            en: incoming.next_(module).unwrap(),
+        }
+    }
+}
@@ -13,6 +13,7 @@ pub struct DFF {
     pub rst_pol: Polarity,
     pub has_rst: bool,
     pub has_en: bool,
+    pub has_rst_val: bool,
     pub has_data: bool,
     pub inputs: u8,
     pub output: [NodeOutput; 1],
@@ -32,6 +33,8 @@ pub struct DFFArgs {
     pub rst_pol: Polarity,
     pub en: Option<Port>,
     pub init: Port,
+    // reset value if it differs from the initial one
+    pub rst_val: Option<Port>,
     pub data: TyOrData,
     pub sym: Option<Symbol>,
 }
@@ -44,6 +47,9 @@ impl DFFArgs {
             TyOrData::Data(data) => module[data].ty,
         };
         assert_eq!(init.ty, ty);
+        if let Some(rst_val) = self.rst_val {
+            assert_eq!(module[rst_val].ty, ty);
+        }
     }
 }
 
@@ -78,6 +84,7 @@ impl MakeNode<DFFArgs> for DFF {
             rst_pol,
             en,
             init,
+            rst_val,
             data,
             sym,
         } = args;
@@ -92,6 +99,7 @@ impl MakeNode<DFFArgs> for DFF {
             rst_pol,
             has_rst: rst.is_some(),
             has_en: en.is_some(),
+            has_rst_val: rst_val.is_some(),
             has_data,
             inputs: 0,
             output: [NodeOutput::reg(ty, sym)],
@@ -115,6 +123,11 @@ impl MakeNode<DFFArgs> for DFF {
         module.add_edge(init, Port::new(node_id, port));
         port += 1;
 
+        if let Some(rst_val) = rst_val {
+            module.add_edge(rst_val, Port::new(node_id, port));
+            port += 1;
+        }
+
         if let TyOrData::Data(data) = data {
             module.add_edge(data, Port::new(node_id, port));
             port += 1;
@@ -157,6 +170,7 @@ pub struct DFFInputs {
     pub rst: Option<Port>,
     pub en: Option<Port>,
     pub init: Port,
+    pub rst_val: Option<Port>,
     pub data: Port,
 }
 
@@ -178,6 +192,11 @@ impl WithId<NodeId, &'_ DFF> {
                 None
             },
             init: incoming.next_(module).unwrap(),
+            rst_val: if self.has_rst_val {
+                Some(incoming.next_(module).unwrap())
+            } else {
+                None
+            },
             data: incoming.next_(module).unwrap(),
         }
     }
@@ -1,10 +1,18 @@
 use std::{num::NonZeroU128, rc::Rc};
 
-use fhdl_data_structures::graph::NodeId;
+use fhdl_data_structures::{
+    cursor::Cursor,
+    graph::{NodeId, Port},
+};
 
 use super::{IsNode, MakeNode, NodeOutput};
-use crate::{const_val::ConstVal, netlist::Module, node_ty::NodeTy, symbol::Symbol};
+use crate::{
+    const_val::ConstVal, netlist::Module, node_ty::NodeTy, symbol::Symbol,
+    with_id::WithId,
+};
 
+// Memory initialized with constants, the output is the word at the address input
+// (an asynchronous read port, e.g. ROMs of lookup tables)
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Memory {
     pub dim: NonZeroU128,
@@ -17,6 +25,7 @@ pub struct Memory {
 pub struct MemoryArgs<V> {
     pub ty: NodeTy,
     pub dim: NonZeroU128,
+    pub addr: Port,
     pub init: V,
     pub name: Option<Symbol>,
     pub data_sym: Option<Symbol>,
@@ -30,6 +39,7 @@ where
         let MemoryArgs {
             ty,
             dim,
+            addr,
             init,
             name,
             data_sym,
@@ -46,13 +56,17 @@ where
             })
             .collect::<Vec<_>>();
 
-        module.add_node(Memory {
+        let node_id = module.add_node(Memory {
             dim,
-            output: [NodeOutput::reg(ty, data_sym); 1],
+            output: [NodeOutput::wire(ty, data_sym); 1],
             name,
             gen_i: None,
             init: Rc::new(init),
-        })
+        });
+
+        module.add_edge(addr, Port::new(node_id, 0));
+
+        node_id
     }
 }
 
@@ -69,3 +83,22 @@ impl IsNode for Memory {
         &mut self.output
     }
 }
+
+impl Memory {
+    // Initial value of the word at `addr`, words without an initial value are zero
+    pub fn word(&self, addr: u128) -> ConstVal {
+        self.init
+            .iter()
+            .rev()
+            .find(|(idx, _)| *idx == addr % self.dim.get())
+            .map(|(_, val)| *val)
+            .unwrap_or_else(|| ConstVal::zero(self.output[0].width()))
+    }
+}
+
+impl WithId<NodeId, &'_ Memory> {
+    pub fn addr(&self, module: &Module) -> Port {
+        let mut incoming = module.incoming(self.id);
+        incoming.next_(module).unwrap()
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt::{self, Display};
+
+use fhdl_data_structures::{
+    cursor::Cursor,
+    graph::{NodeId, Port},
+};
+
+use super::{IsNode, MakeNode, NodeOutput};
+use crate::{netlist::Module, node_ty::NodeTy, symbol::Symbol, with_id::WithId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReduceOp {
+    And,
+    Or,
+    Nand,
+    Nor,
+}
+
+impl Display for ReduceOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::And => "&",
+            Self::Or => "|",
+            Self::Nand => "~&",
+            Self::Nor => "~|",
+        })
+    }
+}
+
+// Reduces all the bits of the input into a single bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reduce {
+    pub op: ReduceOp,
+    pub output: [NodeOutput; 1],
+}
+
+#[derive(Debug)]
+pub struct ReduceArgs {
+    pub op: ReduceOp,
+    pub input: Port,
+    pub sym: Option<Symbol>,
+}
+
+impl MakeNode<ReduceArgs> for Reduce {
+    fn make(module: &mut Module, args: ReduceArgs) -> NodeId {
+        let ReduceArgs { op, input, sym } = args;
+
+        let node_id = module.add_node(Reduce {
+            op,
+            output: [NodeOutput::wire(NodeTy::Bit, sym)],
+        });
+
+        module.add_edge(input, Port::new(node_id, 0));
+
+        node_id
+    }
+}
+
+impl IsNode for Reduce {
+    #[inline]
+    fn in_count(&self) -> usize {
+        1
+    }
+
+    #[inline]
+    fn outputs(&self) -> &[NodeOutput] {
+        &self.output
+    }
+
+    #[inline]
+    fn outputs_mut(&mut self) -> &mut [NodeOutput] {
+        &mut self.output
+    }
+}
+
+impl WithId<NodeId, &'_ Reduce> {
+    pub fn input(&self, module: &Module) -> Port {
+        let mut incoming = module.incoming(self.id);
+        incoming.next_(module).unwrap()
+    }
+}
@@ -12,8 +12,16 @@ use crate::{netlist::Module, node_ty::NodeTy, symbol::Symbol, with_id::WithId};
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Splitter {
     pub outputs: SmallVec<[NodeOutput; 1]>,
-    pub start: Option<u128>,
-    pub rev: bool,
+    pub offsets: Offsets,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Offsets {
+    // Outputs follow each other from `start` (by default, from the LSB or from the MSB
+    // if `rev`)
+    Adjacent { start: Option<u128>, rev: bool },
+    // Each output starts at its own offset, so outputs can overlap
+    Explicit(SmallVec<[u128; 1]>),
 }
 
 #[derive(Debug)]
@@ -24,6 +32,13 @@ pub struct SplitterArgs<O> {
     pub rev: bool,
 }
 
+// Splits the input into the outputs at explicit offsets
+#[derive(Debug)]
+pub struct SlicesArgs<O> {
+    pub input: Port,
+    pub outputs: O,
+}
+
 fn eval_start(rev: bool, width: u128) -> u128 {
     if !rev {
         0
@@ -62,8 +77,42 @@ where
 
         let node_id = module.add_node(Splitter {
             outputs,
-            start: args.start,
-            rev: args.rev,
+            offsets: Offsets::Adjacent {
+                start: args.start,
+                rev: args.rev,
+            },
+        });
+
+        module.add_edge(args.input, Port::new(node_id, 0));
+
+        node_id
+    }
+}
+
+impl<O> MakeNode<SlicesArgs<O>> for Splitter
+where
+    O: IntoIterator<Item = (u128, NodeTy, Option<Symbol>)>,
+{
+    fn make(module: &mut Module, args: SlicesArgs<O>) -> NodeId {
+        let width = module[args.input].width();
+
+        let (offsets, outputs): (SmallVec<_>, SmallVec<_>) = args
+            .outputs
+            .into_iter()
+            .map(|(offset, ty, sym)| {
+                assert!(
+                    offset + ty.width() <= width,
+                    "Invalid inputs/outputs for splitter"
+                );
+
+                (offset, NodeOutput::wire(ty, sym))
+            })
+            .unzip();
+        assert!(!outputs.is_empty());
+
+        let node_id = module.add_node(Splitter {
+            outputs,
+            offsets: Offsets::Explicit(offsets),
         });
 
         module.add_edge(args.input, Port::new(node_id, 0));
@@ -101,13 +150,19 @@ impl WithId<NodeId, &'_ Splitter> {
         let input = self.input(module);
         let width = module[input].width();
 
-        let rev = self.rev;
-        let mut start = self.start.unwrap_or_else(|| eval_start(rev, width));
+        let (mut start, rev, offsets) = match &self.offsets {
+            Offsets::Adjacent { start, rev } => {
+                (start.unwrap_or_else(|| eval_start(*rev, width)), *rev, None)
+            }
+            Offsets::Explicit(offsets) => (0, false, Some(offsets)),
+        };
 
-        self.outputs().map(move |output| {
+        self.outputs().enumerate().map(move |(idx, output)| {
             let width = output.width();
 
-            if !rev {
+            if let Some(offsets) = offsets {
+                (offsets[idx], output.inner)
+            } else if !rev {
                 let res = (start, output.inner);
                 start += width;
                 res
@@ -127,6 +182,7 @@ impl WithId<NodeId, &'_ Splitter> {
         let in_width = module[input].width();
         let out_width = self.outputs[0].ty.width();
 
+        // an output of the same width as the input can start only at 0
         in_width == out_width
     }
 }
@@ -82,10 +82,7 @@ impl NodeTy {
         match (lhs, rhs) {
             (Bit, Bit) => Some(Bit),
             (Unsigned(n), Unsigned(m)) => Some(Unsigned(cmp::max(n, m))),
-            _ => {
-                println!("ty_for_bin_expr: lhs = {lhs} rhs = {rhs}");
-                None
-            }
+            _ => None,
         }
     }
 }
@@ -10,7 +10,8 @@ use std::{
     path::Path,
 };
 
-use codegen::Verilog;
+pub use codegen::WrapperCfg;
+use codegen::{Verilog, Wrapper};
 use reachability::Reachability;
 use set_names::SetNames;
 use transform::Transform;
@@ -29,7 +30,9 @@ pub enum ParamKind {
 
 impl NetList {
     pub fn transform(&mut self) {
-        Transform::new(self).run();
+        let (warnings, errors) = Transform::new(self).run();
+        self.warnings.extend(warnings);
+        self.errors.extend(errors);
     }
 
     pub fn reachability(&mut self) {
@@ -50,6 +53,20 @@ impl NetList {
         Verilog::new(self, writer).synth()
     }
 
+    pub fn synth_wrapper_into_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        cfg: WrapperCfg,
+    ) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        self.synth_wrapper(file, cfg)
+    }
+
+    #[inline]
+    pub fn synth_wrapper<W: Write>(&self, writer: W, cfg: WrapperCfg) -> io::Result<()> {
+        Wrapper::new(self, writer, cfg).synth()
+    }
+
     pub fn dump(&self, skip: bool) {
         Dump::new(self, skip).run()
     }
@@ -63,8 +80,39 @@ impl NetList {
         Dump::new(self, skip).visit_module(module);
     }
 
+    // Every input port should be driven by one edge at most, multiple drivers are
+    // caused by bugs in the netlist construction or transformations. Returns false if
+    // such ports are found, they are reported as errors
+    pub fn check_single_drivers(&mut self) -> bool {
+        let mut errors = Vec::new();
+        for module in self.modules() {
+            let module = module.borrow();
+            if let Some(port) = module.find_multi_driven() {
+                let at = module[port.node]
+                    .span()
+                    .map(|span| format!(" (at {span})"))
+                    .unwrap_or_default();
+                errors.push(format!(
+                    "input {} of node {} in module '{}' has multiple drivers{at}",
+                    port.port, port.node, module.name,
+                ));
+            }
+        }
+
+        let single = errors.is_empty();
+        self.errors.extend(errors);
+        single
+    }
+
     pub fn run_visitors(&mut self) {
+        // the passes expect single drivers of input ports
+        if !self.check_single_drivers() {
+            return;
+        }
         self.transform();
+        if !self.check_single_drivers() {
+            return;
+        }
         self.reachability();
         self.set_names();
     }
@@ -1,3 +1,5 @@
 mod verilog;
+mod wrapper;
 
 pub use verilog::Verilog;
+pub use wrapper::{Wrapper, WrapperCfg};
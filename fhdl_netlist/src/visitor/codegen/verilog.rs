@@ -1,14 +1,22 @@
-use std::io::{Result, Write};
+use std::{
+    fmt::Display,
+    io::{Result, Write},
+};
 
 use ferrum_hdl::domain::{Polarity, SyncKind};
-use fhdl_data_structures::{cursor::Cursor, graph::NodeId, FxHashSet};
+use fhdl_data_structures::{
+    cursor::Cursor,
+    graph::{NodeId, Port},
+    FxHashSet,
+};
 
 use crate::{
     buffer::Buffer,
     const_val::ConstVal,
     netlist::{Module, NetList},
     node::{
-        BinOpInputs, Case, DFFInputs, NetKind, Node, NodeKind, NodeOutput, SwitchInputs,
+        BinOpInputs, Case, ClockGateInputs, DFFInputs, IsNode, NetKind, Node, NodeKind,
+        NodeOutput, SwitchInputs,
     },
     symbol::Symbol,
     visitor::ParamKind,
@@ -47,10 +55,51 @@ fn write_out<W: Write>(buffer: &mut Buffer<W>, out: &NodeOutput) -> Result<()> {
 
 const SEP: &str = ",\n";
 
+// Minimal number of consecutive instances of the same module which are emitted
+// as a generate loop. The loop costs two lines per bus and about ten lines of
+// the loop itself, while an instance costs a line per port, so shorter runs are
+// more readable as separate instances.
+const GENERATE_MIN_COUNT: usize = 4;
+
+// behavioral model of an integrated clock-gating cell (latch-based, glitch-free),
+// can be replaced by the library cell with the same ports
+const CLOCK_GATE: &str = "fhdl_clock_gate";
+const CLOCK_GATE_MOD: &str = "\
+module fhdl_clock_gate
+(
+    input clk,
+    input en,
+    output gclk
+);
+
+    reg en_latch;
+    always @(clk or en)
+        if (!clk)
+            en_latch <= en;
+
+    assign gclk = clk & en_latch;
+
+endmodule
+
+";
+
+struct GeneratePort {
+    orig_sym: Symbol,
+    width: u128,
+    ports: Vec<Port>,
+    syms: Vec<Symbol>,
+    // the input is connected to the same port in all instances
+    shared: bool,
+    bus: Option<Symbol>,
+}
+
 pub struct Verilog<'n, W> {
     pub buffer: Buffer<W>,
     pub locals: FxHashSet<Symbol>,
+    // names of the nodes of the current module
+    syms: FxHashSet<Symbol>,
     pub netlist: &'n NetList,
+    has_clock_gate: bool,
 }
 
 impl<'n, W: Write> Verilog<'n, W> {
@@ -58,7 +107,9 @@ impl<'n, W: Write> Verilog<'n, W> {
         Self {
             buffer: Buffer::new(writer),
             locals: Default::default(),
+            syms: Default::default(),
             netlist: net_list,
+            has_clock_gate: false,
         }
     }
 
@@ -67,24 +118,24 @@ impl<'n, W: Write> Verilog<'n, W> {
         module: &Module,
         node: WithId<NodeId, &Node>,
     ) -> Result<()> {
-        let can_skip = !node.is_mod_inst();
+        // instance outputs are kept while the instantiated module still drives them
+        let orig_mod = node
+            .mod_inst()
+            .map(|mod_inst| self.netlist.module(mod_inst.mod_id).map(|m| m.borrow()));
 
-        for node_out in node.outputs() {
+        for (idx, node_out) in node.outputs().enumerate() {
             let port = node_out.id;
+            let can_skip = match orig_mod.as_deref() {
+                Some(orig_mod) => orig_mod
+                    .mod_outputs()
+                    .get_index(idx)
+                    .map(|orig_port| orig_mod[*orig_port].skip)
+                    .unwrap_or(true),
+                None => true,
+            };
             let is_input = module.is_mod_input(port);
             let is_output = module.is_mod_output(port);
-            match node.kind() {
-                NodeKind::Memory(memory) => {
-                    self.write_local(
-                        *node_out,
-                        is_input,
-                        is_output,
-                        memory.dim.get() as usize,
-                        can_skip,
-                    )?;
-                }
-                _ => self.write_local(*node_out, is_input, is_output, 1, can_skip)?,
-            }
+            self.write_local(*node_out, is_input, is_output, can_skip)?;
         }
 
         Ok(())
@@ -95,7 +146,6 @@ impl<'n, W: Write> Verilog<'n, W> {
         node_out: &NodeOutput,
         is_input: bool,
         is_output: bool,
-        count: usize,
         can_skip: bool,
     ) -> Result<()> {
         if can_skip && node_out.skip {
@@ -109,9 +159,6 @@ impl<'n, W: Write> Verilog<'n, W> {
                 b.write_tab()?;
                 write_out(b, node_out)?;
                 b.write_fmt(format_args!(" {}", sym))?;
-                if count > 1 {
-                    b.write_fmt(format_args!("[{}:0]", count - 1))?;
-                }
                 b.write_str(";\n")?;
             }
 
@@ -153,13 +200,17 @@ impl<'n, W: Write> Verilog<'n, W> {
 
         for module in self.netlist.modules().rev() {
             let module = module.borrow();
-            if module.skip {
+            if module.skip || module.external {
                 continue;
             }
 
             self.visit_module(&module)?;
         }
 
+        if self.has_clock_gate {
+            self.buffer.write_str(CLOCK_GATE_MOD)?;
+        }
+
         self.buffer.flush()?;
 
         Ok(())
@@ -167,6 +218,7 @@ impl<'n, W: Write> Verilog<'n, W> {
 
     fn visit_module(&mut self, module: &Module) -> Result<()> {
         self.locals.clear();
+        self.syms = module_syms(module);
         let is_top = module.is_top;
 
         self.write_mod_span(module)?;
@@ -226,14 +278,27 @@ impl<'n, W: Write> Verilog<'n, W> {
 
         b.push_tab();
 
-        let mut nodes = module.nodes();
-        while let Some(node_id) = nodes.next_(module) {
-            let node = &module[node_id];
-            if node.skip {
-                continue;
+        let node_ids = module
+            .topo_nodes()
+            .into_iter()
+            .filter(|node_id| !module[*node_id].skip)
+            .collect::<Vec<_>>();
+
+        let mut idx = 0;
+        while idx < node_ids.len() {
+            let (len, count) = mod_inst_run(module, &node_ids[idx ..]);
+            let run = &node_ids[idx .. idx + len];
+            if count >= GENERATE_MIN_COUNT && !self.is_external_run(module, run) {
+                if let Some(ports) = self.generate_ports(module, run) {
+                    self.visit_mod_inst_run(module, run, ports)?;
+                    idx += len;
+                    continue;
+                }
             }
 
-            self.visit_node(module, WithId::new(node_id, node))?;
+            let node_id = node_ids[idx];
+            self.visit_node(module, WithId::new(node_id, &module[node_id]))?;
+            idx += 1;
         }
 
         let b = &mut self.buffer;
@@ -244,10 +309,204 @@ impl<'n, W: Write> Verilog<'n, W> {
         Ok(())
     }
 
+    // instances of external modules are connected by position, so they are not
+    // emitted as a generate loop
+    fn is_external_run(&self, module: &Module, node_ids: &[NodeId]) -> bool {
+        node_ids
+            .iter()
+            .find_map(|node_id| module[*node_id].mod_inst())
+            .map(|mod_inst| self.netlist[mod_inst.mod_id].borrow().external)
+            .unwrap_or_default()
+    }
+
+    // Collects the ports of the instances in the run, returns `None` if the
+    // connections of some input don't have a regular stride
+    fn generate_ports(
+        &self,
+        module: &Module,
+        node_ids: &[NodeId],
+    ) -> Option<(Vec<GeneratePort>, Vec<GeneratePort>)> {
+        let mut inputs = Vec::<GeneratePort>::new();
+        let mut outputs = Vec::<GeneratePort>::new();
+        for node_id in node_ids {
+            let node = module.node(*node_id);
+            let Some(mod_inst) = node.mod_inst() else {
+                continue;
+            };
+            let mod_inst = node.with(mod_inst);
+            let orig_mod = self
+                .netlist
+                .module(mod_inst.mod_id)
+                .map(|orig_mod| orig_mod.borrow());
+
+            let mod_inst_inputs = module
+                .mod_inst_inputs(mod_inst, orig_mod.as_deref())
+                .filter(|(_, orig_mod_input)| !orig_mod_input.skip)
+                .map(|(mod_inst_input, orig_mod_input)| {
+                    (mod_inst_input, orig_mod_input.inner)
+                });
+            add_generate_ports(&mut inputs, mod_inst_inputs);
+
+            let mod_inst_outputs = module
+                .mod_inst_outputs(mod_inst, orig_mod.as_deref())
+                .filter(|(_, orig_mod_output)| !orig_mod_output.skip)
+                .map(|(mod_inst_output, orig_mod_output)| {
+                    (mod_inst_output, orig_mod_output.inner)
+                });
+            add_generate_ports(&mut outputs, mod_inst_outputs);
+        }
+
+        for input in &mut inputs {
+            input.shared = input.ports.iter().all(|port| *port == input.ports[0]);
+            if !input.shared && !has_regular_stride(&input.ports) {
+                return None;
+            }
+        }
+
+        Some((inputs, outputs))
+    }
+
+    // Returns a name for the item generated by codegen which doesn't clash with
+    // the names of the module nodes and the previously generated items
+    fn gen_local(&mut self, sym: impl Display) -> Symbol {
+        let mut new_sym = Symbol::intern_args(format_args!("{sym}"));
+        let mut count = 0;
+        while self.syms.contains(&new_sym) || self.locals.contains(&new_sym) {
+            count += 1;
+            new_sym = Symbol::intern_args(format_args!("{sym}_{count}"));
+        }
+
+        self.locals.insert(new_sym);
+        new_sym
+    }
+
+    fn visit_mod_inst_run(
+        &mut self,
+        module: &Module,
+        node_ids: &[NodeId],
+        (mut inputs, mut outputs): (Vec<GeneratePort>, Vec<GeneratePort>),
+    ) -> Result<()> {
+        let (input_ids, node_ids): (Vec<_>, Vec<_>) = node_ids
+            .iter()
+            .partition(|node_id| module[**node_id].is_input());
+        for node_id in input_ids {
+            self.visit_node(module, module.node(node_id))?;
+        }
+
+        for node_id in &node_ids {
+            let node = module.node(*node_id);
+            self.write_span(*node)?;
+            self.write_locals(module, node)?;
+        }
+
+        let first = module.node(node_ids[0]);
+        let first = first.with(first.mod_inst().unwrap());
+        let orig_mod = self
+            .netlist
+            .module(first.mod_id)
+            .map(|orig_mod| orig_mod.borrow());
+        let name = first.name.unwrap();
+        let count = node_ids.len();
+
+        // instance ports are packed into buses which are sliced inside the loop,
+        // the input shared by all instances is connected directly
+        for (ports, is_input) in [(&mut inputs, true), (&mut outputs, false)] {
+            for port in ports.iter_mut().filter(|port| !port.shared) {
+                let bus = self.gen_local(format_args!("{name}_{}", port.orig_sym));
+                let bus_width = port.width * count as u128;
+                port.bus = Some(bus);
+
+                let b = &mut self.buffer;
+                b.write_tab()?;
+                b.write_fmt(format_args!("wire [{}:0] {bus};\n", bus_width - 1))?;
+
+                b.write_tab()?;
+                let syms = port
+                    .syms
+                    .iter()
+                    .rev()
+                    .map(|sym| sym.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if is_input {
+                    b.write_fmt(format_args!("assign {bus} = {{ {syms} }};\n\n"))?;
+                } else {
+                    b.write_fmt(format_args!("assign {{ {syms} }} = {bus};\n\n"))?;
+                }
+            }
+        }
+
+        let idx = self.gen_local(format_args!("{name}_idx"));
+        let label = self.gen_local(format_args!("{name}_gen"));
+
+        let b = &mut self.buffer;
+        b.write_tab()?;
+        b.write_fmt(format_args!("genvar {idx};\n"))?;
+        b.write_tab()?;
+        b.write_str("generate\n")?;
+        b.push_tab();
+
+        b.write_tab()?;
+        b.write_fmt(format_args!(
+            "for ({idx} = 0; {idx} < {count}; {idx} = {idx} + 1) begin : {label}\n"
+        ))?;
+        b.push_tab();
+
+        b.write_tab()?;
+        b.write_fmt(format_args!("{} {name} (\n", orig_mod.name))?;
+
+        let write_port = |buffer: &mut Buffer<_>, port: &GeneratePort| {
+            let orig_sym = port.orig_sym;
+            let width = port.width;
+
+            buffer.write_tab()?;
+            match port.bus {
+                Some(bus) => buffer.write_fmt(format_args!(
+                    ".{orig_sym}({bus}[{idx} * {width} +: {width}])"
+                )),
+                None => buffer.write_fmt(format_args!(".{orig_sym}({})", port.syms[0])),
+            }
+        };
+
+        b.push_tab();
+        if !inputs.is_empty() {
+            b.write_tab()?;
+            b.write_str("// Inputs\n")?;
+
+            b.intersperse(SEP, inputs.iter(), write_port)?;
+        }
+
+        if !outputs.is_empty() {
+            if !inputs.is_empty() {
+                b.write_str(SEP)?;
+            }
+            b.write_tab()?;
+            b.write_str("// Outputs\n")?;
+
+            b.intersperse(SEP, outputs.iter(), write_port)?;
+        }
+        b.write_eol()?;
+        b.pop_tab();
+
+        b.write_tab()?;
+        b.write_str(");\n")?;
+
+        b.pop_tab();
+        b.write_tab()?;
+        b.write_str("end\n")?;
+
+        b.pop_tab();
+        b.write_tab()?;
+        b.write_str("endgenerate\n\n")?;
+
+        Ok(())
+    }
+
     fn visit_node(&mut self, module: &Module, node: WithId<NodeId, &Node>) -> Result<()> {
         self.write_span(*node)?;
         self.write_locals(module, node)?;
 
+        let fmt = self.netlist.cfg().const_fmt;
         let b = &mut self.buffer;
 
         match node.kind() {
@@ -273,6 +532,28 @@ impl<'n, W: Write> Verilog<'n, W> {
                 b.write_fmt(format_args!("{} {} (\n", orig_mod.name, name.unwrap()))?;
 
                 b.push_tab();
+                if orig_mod.external {
+                    // port names of external modules are unknown, so ports are connected by position
+                    let inputs = module
+                        .mod_inst_inputs(mod_inst, orig_mod.as_deref())
+                        .map(|(mod_inst_input, _)| mod_inst_input.sym.unwrap());
+                    let outputs = module
+                        .mod_inst_outputs(mod_inst, orig_mod.as_deref())
+                        .map(|(mod_inst_output, _)| mod_inst_output.sym.unwrap());
+
+                    b.intersperse(SEP, inputs.chain(outputs), |buffer, sym| {
+                        buffer.write_tab()?;
+                        buffer.write_fmt(format_args!("{sym}"))
+                    })?;
+                    b.write_eol()?;
+
+                    b.pop_tab();
+                    b.write_tab()?;
+                    b.write_str(");\n\n")?;
+
+                    return Ok(());
+                }
+
                 if mod_inst.has_inputs() {
                     b.write_tab()?;
                     b.write_str("// Inputs\n")?;
@@ -326,7 +607,7 @@ impl<'n, W: Write> Verilog<'n, W> {
             }
             NodeKind::Const(cons) => {
                 let output = cons.output[0].sym.unwrap();
-                let value = cons.value;
+                let value = ConstVal::new(cons.value, cons.output[0].width()).lit(fmt);
 
                 b.write_tab()?;
                 b.write_fmt(format_args!("assign {output} = {value};\n\n"))?;
@@ -337,6 +618,7 @@ impl<'n, W: Write> Verilog<'n, W> {
                         continue;
                     }
 
+                    let value = ConstVal::new(value, output.width()).lit(fmt);
                     let output = output.sym.unwrap();
 
                     b.write_tab()?;
@@ -458,6 +740,7 @@ impl<'n, W: Write> Verilog<'n, W> {
                             if case.0.len() == 1 {
                                 match case.0[0] {
                                     Case::Val(case) => {
+                                        let case = case.lit(fmt);
                                         b.write_fmt(format_args!("{case}: "))?;
                                     }
                                     Case::Default(_) => {
@@ -471,7 +754,7 @@ impl<'n, W: Write> Verilog<'n, W> {
                                     case.0.iter(),
                                     |b, case| match case {
                                         Case::Val(case) => {
-                                            b.write_fmt(format_args!("{case}"))
+                                            b.write_fmt(format_args!("{}", case.lit(fmt)))
                                         }
                                         Case::Default(width) => {
                                             b.write_fmt(format_args!("{width}'d?"))
@@ -530,6 +813,31 @@ impl<'n, W: Write> Verilog<'n, W> {
                 b.write_tab()?;
                 b.write_fmt(format_args!("assign {output} = ~{input};\n\n"))?;
             }
+            NodeKind::ClockGate(clock_gate) => {
+                let clock_gate = node.with(clock_gate);
+                let ClockGateInputs { clk, en } = clock_gate.inputs(module);
+                let clk = module[clk].sym.unwrap();
+                let en = module[en].sym.unwrap();
+                let output = clock_gate.output[0].sym.unwrap();
+                let cg = self.gen_local(format_args!("{output}_cg"));
+
+                let b = &mut self.buffer;
+                b.write_tab()?;
+                b.write_fmt(format_args!(
+                    "{CLOCK_GATE} {cg} (.clk({clk}), .en({en}), .gclk({output}));\n\n"
+                ))?;
+
+                self.has_clock_gate = true;
+            }
+            NodeKind::Reduce(reduce) => {
+                let reduce = node.with(reduce);
+                let input = module[reduce.input(module)].sym.unwrap();
+                let output = reduce.output[0].sym.unwrap();
+                let op = reduce.op;
+
+                b.write_tab()?;
+                b.write_fmt(format_args!("assign {output} = {op}{input};\n\n"))?;
+            }
             NodeKind::BinOp(bin_op) => {
                 let bin_op = node.with(bin_op);
                 let BinOpInputs { lhs, rhs } = bin_op.inputs(module);
@@ -548,22 +856,49 @@ impl<'n, W: Write> Verilog<'n, W> {
                     rst,
                     en,
                     init,
+                    rst_val,
                     data,
                 } = dff.inputs(module);
 
-                let clk = module[clk].sym.unwrap();
+                let mut clk = module[clk].sym.unwrap();
                 let data = module[data].sym.unwrap();
                 let rst = rst.map(|rst| module[rst].sym.unwrap());
-                let en = en.map(|en| module[en].sym.unwrap());
+                let mut en = en.map(|en| module[en].sym.unwrap());
                 let init_val = module.to_const(init);
                 let output = dff.output[0].sym.unwrap();
 
+                // a sync reset is sampled on the clock edges, so it would be ignored
+                // while the clock is gated
+                let sync_rst = rst.is_some() && matches!(dff.rst_kind, SyncKind::Sync);
+                if self.netlist.cfg().clock_gating && !sync_rst {
+                    if let Some(en) = en.take() {
+                        let gclk = self.gen_local(format_args!("{output}_gclk"));
+                        let cg = self.gen_local(format_args!("{gclk}_cg"));
+
+                        let b = &mut self.buffer;
+                        b.write_tab()?;
+                        b.write_str("// clock gate\n")?;
+                        b.write_tab()?;
+                        b.write_fmt(format_args!("wire {gclk};\n"))?;
+                        b.write_tab()?;
+                        b.write_fmt(format_args!(
+                            "{CLOCK_GATE} {cg} (.clk({clk}), .en({en}), .gclk({gclk}));\n\n"
+                        ))?;
+
+                        self.has_clock_gate = true;
+                        clk = gclk;
+                    }
+                }
+
+                let b = &mut self.buffer;
+
                 if let Some(init_val) = init_val {
                     b.write_tab()?;
                     b.write_str("initial begin\n")?;
 
                     b.push_tab();
                     b.write_tab()?;
+                    let init_val = init_val.lit(fmt);
                     b.write_fmt(format_args!("{output} = {init_val};\n"))?;
                     b.pop_tab();
 
@@ -599,8 +934,8 @@ impl<'n, W: Write> Verilog<'n, W> {
                         b.push_tab();
 
                         b.write_tab()?;
-                        let init = module[init].sym.unwrap();
-                        b.write_fmt(format_args!("{output} <= {init};\n"))?;
+                        let rst_val = module[rst_val.unwrap_or(init)].sym.unwrap();
+                        b.write_fmt(format_args!("{output} <= {rst_val};\n"))?;
 
                         b.pop_tab();
 
@@ -644,27 +979,1096 @@ impl<'n, W: Write> Verilog<'n, W> {
                 let dim = memory.dim;
                 let name = memory.name.unwrap();
                 let gen_i = memory.gen_i.unwrap();
+                let addr = module[memory.addr(module)].sym.unwrap();
+                let output = memory.output[0].sym.unwrap();
+                let width = memory.output[0].width();
 
                 b.write_tab()?;
-                b.write_fmt(format_args!("integer {gen_i};\n"))?;
+                b.write_str("reg ")?;
+                if width > 1 {
+                    b.write_fmt(format_args!("[{}:0] ", width - 1))?;
+                }
+                b.write_fmt(format_args!("{name} [0:{}];\n", dim.get() - 1))?;
 
+                // words without an initial value are zeroed
+                let all_init = (0 .. dim.get())
+                    .all(|addr| memory.init.iter().any(|(idx, _)| *idx == addr));
+                if !all_init {
+                    b.write_tab()?;
+                    b.write_fmt(format_args!("integer {gen_i};\n"))?;
+                }
+
+                b.write_tab()?;
                 b.write_str("initial begin\n")?;
                 b.push_tab();
 
-                b.write_tab()?;
-                let zero = ConstVal::zero(memory.output[0].width());
-                b.write_fmt(format_args!("for ({gen_i} = 0; {gen_i} < {dim}; {gen_i} = {gen_i} + 1) {name}[{gen_i}] = {zero};\n"))?;
+                if !all_init {
+                    b.write_tab()?;
+                    let zero = ConstVal::zero(width).lit(fmt);
+                    b.write_fmt(format_args!("for ({gen_i} = 0; {gen_i} < {dim}; {gen_i} = {gen_i} + 1) {name}[{gen_i}] = {zero};\n"))?;
+                }
 
                 for (idx, val) in memory.init.iter() {
                     b.write_tab()?;
+                    let val = val.lit(fmt);
                     b.write_fmt(format_args!("{name}[{idx}] = {val};\n"))?;
                 }
 
                 b.pop_tab();
+                b.write_tab()?;
                 b.write_str("end\n")?;
+
+                b.write_tab()?;
+                b.write_fmt(format_args!("assign {output} = {name}[{addr}];\n\n"))?;
             }
         }
 
         Ok(())
     }
 }
+
+// Returns the number of nodes in the run of the instances of the same module
+// (input nodes between instances are included, as they don't produce any code)
+// and the number of instances in this run.
+// The run stops at the instance that consumes an output of the previous ones
+// (e.g. chained array maps), so every stage gets its own loop.
+fn mod_inst_run(module: &Module, node_ids: &[NodeId]) -> (usize, usize) {
+    let mod_id = match module[node_ids[0]].mod_inst() {
+        Some(mod_inst) => mod_inst.mod_id,
+        None => return (1, 0),
+    };
+
+    let mut len = 0;
+    let mut count = 0;
+    for (idx, node_id) in node_ids.iter().enumerate() {
+        let node = &module[*node_id];
+        match node.mod_inst() {
+            Some(mod_inst)
+                if mod_inst.mod_id == mod_id
+                    && !module.incoming_iter(*node_id).any(|port| {
+                        module[port.node].is_mod_inst()
+                            && node_ids[.. idx].contains(&port.node)
+                    }) =>
+            {
+                len = idx + 1;
+                count += 1;
+            }
+            None if node.is_input() => {}
+            _ => break,
+        }
+    }
+
+    (len, count)
+}
+
+fn module_syms(module: &Module) -> FxHashSet<Symbol> {
+    let mut syms = FxHashSet::default();
+    let mut nodes = module.nodes();
+    while let Some(node_id) = nodes.next_(module) {
+        let node = &module[node_id];
+        let mod_inst = node.mod_inst().and_then(|mod_inst| mod_inst.name);
+        let memory = node
+            .memory()
+            .into_iter()
+            .flat_map(|memory| [memory.name, memory.gen_i])
+            .flatten();
+        let outputs = node.outputs().iter().filter_map(|out| out.sym);
+
+        syms.extend(mod_inst.into_iter().chain(memory).chain(outputs));
+    }
+
+    syms
+}
+
+fn add_generate_ports<'a>(
+    ports: &mut Vec<GeneratePort>,
+    mod_inst_ports: impl Iterator<Item = (WithId<Port, &'a NodeOutput>, &'a NodeOutput)>,
+) {
+    let is_empty = ports.is_empty();
+    for (idx, (port, orig_port)) in mod_inst_ports.enumerate() {
+        if is_empty {
+            ports.push(GeneratePort {
+                orig_sym: orig_port.sym.unwrap(),
+                width: orig_port.width(),
+                ports: vec![port.id],
+                syms: vec![port.sym.unwrap()],
+                shared: false,
+                bus: None,
+            });
+        } else {
+            ports[idx].ports.push(port.id);
+            ports[idx].syms.push(port.sym.unwrap());
+        }
+    }
+}
+
+// The instances are connected to the distinct ports, and the outputs of the same
+// node (e.g. the elements of an array) are connected with the constant step
+fn has_regular_stride(ports: &[Port]) -> bool {
+    let distinct = ports.iter().collect::<FxHashSet<_>>().len() == ports.len();
+    if !distinct {
+        return false;
+    }
+
+    let node_id = ports[0].node;
+    if !ports.iter().all(|port| port.node == node_id) {
+        return true;
+    }
+
+    let step = ports[1].port as i64 - ports[0].port as i64;
+    ports
+        .windows(2)
+        .all(|pair| pair[1].port as i64 - pair[0].port as i64 == step)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{iter, num::NonZeroU128};
+
+    use super::*;
+    use crate::{
+        cfg::{ConstFmt, NetListCfg},
+        netlist::ModuleId,
+        node::{
+            BinOp, BinOpArgs, BinOpNode, BitNot, BitNotArgs, ClockGate, ClockGateArgs,
+            DFFArgs, Memory, MemoryArgs, ModInst, ModInstArgs, Switch, SwitchArgs,
+            TyOrData, DFF,
+        },
+        node_ty::NodeTy,
+    };
+
+    fn inv_module() -> Module {
+        let mut module = Module::new("inv", false);
+        let input = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let inv = module.add_and_get_port::<_, BitNot>(BitNotArgs {
+            ty: NodeTy::Unsigned(4),
+            input,
+            sym: Some(Symbol::intern("out")),
+        });
+        module.add_mod_output(inv);
+
+        module
+    }
+
+    // The instance `idx` is connected to the input `a{inputs[idx]}`
+    fn top_module(netlist: &NetList, inv: ModuleId, inputs: &[usize]) -> Module {
+        let inv = netlist.module(inv).map(|module| module.borrow());
+        let mut module = Module::new("top", true);
+
+        let count = inputs.iter().max().map(|max| max + 1).unwrap_or_default();
+        let top_inputs = (0 .. count)
+            .map(|idx| {
+                module.add_input(
+                    NodeTy::Unsigned(4),
+                    Some(Symbol::intern_args(format_args!("a{idx}"))),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for (idx, input) in inputs.iter().enumerate() {
+            let mod_inst = module.add::<_, ModInst>(ModInstArgs {
+                module: inv.as_deref(),
+                inputs: iter::once(top_inputs[*input]),
+                outputs: iter::once(Some(Symbol::intern_args(format_args!("out{idx}")))),
+            });
+            module.add_mod_outputs(mod_inst);
+        }
+
+        module
+    }
+
+    #[test]
+    fn mod_inst_generate_loop() {
+        let mut netlist = NetList::default();
+        let inv = netlist.add_module(inv_module());
+        let top = top_module(&netlist, inv, &[0, 1, 2, 3, 4, 5, 6, 7]);
+        netlist.add_module(top);
+
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+        assert!(verilog.contains("generate\n"));
+        assert!(verilog.contains("for (__mod_idx = 0; __mod_idx < 8;"));
+        assert_eq!(verilog.matches("inv __mod").count(), 1);
+        assert!(verilog.contains(".a(__mod_a[__mod_idx * 4 +: 4])"));
+        assert!(verilog.contains("assign __mod_a = { a7, a6, a5, a4, a3, a2, a1, a0 };"));
+    }
+
+    #[test]
+    fn mod_inst_irregular_stride() {
+        let mut netlist = NetList::default();
+        let mut inv = inv_module();
+        inv.force_no_inline = true;
+        let inv = netlist.add_module(inv);
+        let top = top_module(&netlist, inv, &[0, 0, 1, 2, 3, 4, 5, 6]);
+        netlist.add_module(top);
+
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+        // the first instance shares the input with the second one, so it's
+        // emitted separately and the loop starts from the second instance
+        assert!(verilog.contains(".a(a0),"));
+        assert!(verilog.contains("assign __mod_1_a = { a6, a5, a4, a3, a2, a1, a0 };"));
+        assert!(verilog.contains("for (__mod_1_idx = 0; __mod_1_idx < 7;"));
+        assert_eq!(verilog.matches("inv __mod").count(), 2);
+    }
+
+    #[test]
+    fn mod_inst_shared_input() {
+        let mut netlist = NetList::default();
+        let mut inv = inv_module();
+        inv.force_no_inline = true;
+        let inv = netlist.add_module(inv);
+        let top = top_module(&netlist, inv, &[0; 8]);
+        netlist.add_module(top);
+
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+        assert!(verilog.contains("for (__mod_idx = 0; __mod_idx < 8;"));
+        assert!(verilog.contains(".a(a0)"));
+        assert!(!verilog.contains("__mod_a"));
+    }
+
+    #[test]
+    fn mod_inst_generate_names() {
+        let mut netlist = NetList::default();
+        let mut inv = inv_module();
+        inv.force_no_inline = true;
+        let inv = netlist.add_module(inv);
+        let mut top = top_module(&netlist, inv, &[0, 1, 2, 3]);
+        for sym in ["__mod_a", "__mod_idx"] {
+            let input = top.add_input(NodeTy::Unsigned(4), Some(Symbol::intern(sym)));
+            top.add_mod_output(input);
+        }
+        netlist.add_module(top);
+
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+        assert!(verilog.contains("assign __mod_a_1 = { a3, a2, a1, a0 };"));
+        assert!(verilog.contains("genvar __mod_idx_1;"));
+        assert!(verilog.contains(".a(__mod_a_1[__mod_idx_1 * 4 +: 4])"));
+    }
+
+    #[test]
+    fn mod_inst_chained_generate_loops() {
+        let mut netlist = NetList::default();
+        let inv_id = netlist.add_module(inv_module());
+        let inv = netlist.module(inv_id).map(|module| module.borrow());
+        let mut module = Module::new("top", true);
+
+        let mut ports = (0 .. 8)
+            .map(|idx| {
+                module.add_input(
+                    NodeTy::Unsigned(4),
+                    Some(Symbol::intern_args(format_args!("a{idx}"))),
+                )
+            })
+            .collect::<Vec<_>>();
+        // a.map(inv).map(inv)
+        for _ in 0 .. 2 {
+            ports = ports
+                .into_iter()
+                .map(|input| {
+                    let mod_inst = module.add::<_, ModInst>(ModInstArgs {
+                        module: inv.as_deref(),
+                        inputs: iter::once(input),
+                        outputs: iter::once(None),
+                    });
+                    Port::new(mod_inst, 0)
+                })
+                .collect();
+        }
+        for port in ports {
+            module.add_mod_output(port);
+        }
+        drop(inv);
+        netlist.add_module(module);
+
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+        assert_eq!(verilog.matches("genvar").count(), 2);
+        assert!(verilog.contains(
+            "assign __mod_a = { out_15, out_14, out_13, out_12, out_11, out_10, out_9, out_8 };"
+        ));
+    }
+
+    #[test]
+    fn mod_inst_unused_output_pruned() {
+        // helper(a, b) -> (U<4>, U<8>)
+        let mut helper = Module::new("helper", false);
+        helper.force_no_inline = true;
+        let a = helper.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let b = helper.add_input(NodeTy::Unsigned(8), Some(Symbol::intern("b")));
+        for (input, sym) in [(a, "lo"), (b, "hi")] {
+            let ty = helper[input].ty;
+            let out = helper.add_and_get_port::<_, BitNot>(BitNotArgs {
+                ty,
+                input,
+                sym: Some(Symbol::intern(sym)),
+            });
+            helper.add_mod_output(out);
+        }
+
+        let mut netlist = NetList::default();
+        let helper_id = netlist.add_module(helper);
+        let helper = netlist.module(helper_id).map(|module| module.borrow());
+
+        // helper(a, b).1
+        let mut module = Module::new("top", true);
+        let a = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let b = module.add_input(NodeTy::Unsigned(8), Some(Symbol::intern("b")));
+        let mod_inst = module.add::<_, ModInst>(ModInstArgs {
+            module: helper.as_deref(),
+            inputs: [a, b].into_iter(),
+            outputs: [None, None].into_iter(),
+        });
+        module.add_mod_output(Port::new(mod_inst, 1));
+        drop(helper);
+        netlist.add_module(module);
+
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+
+        // `.0` and its cone are eliminated, so is the input feeding only `.0`
+        assert!(!verilog.contains("lo"));
+        assert!(!verilog.contains(".a("));
+        assert!(verilog.contains("assign hi = ~b;"));
+    }
+
+    fn synth_counter() -> String {
+        // reg <= rst ? 0 : ~(reg + inc)
+        let mut module = Module::new("top", true);
+        let clk = module.add_input(NodeTy::Clock, Some(Symbol::intern("clk")));
+        let rst = module.add_input(NodeTy::Bit, Some(Symbol::intern("rst")));
+        let inc = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("inc")));
+        let init = module.const_val(NodeTy::Unsigned(4), 0);
+        let dff = module.add::<_, DFF>(DFFArgs {
+            clk,
+            rst: Some(rst),
+            rst_kind: SyncKind::Sync,
+            rst_pol: Polarity::ActiveHigh,
+            en: None,
+            init,
+            rst_val: None,
+            data: TyOrData::Ty(NodeTy::Unsigned(4)),
+            sym: Some(Symbol::intern("reg")),
+        });
+        let sum = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Unsigned(4),
+            bin_op: BinOp::Add,
+            lhs: Port::new(dff, 0),
+            rhs: inc,
+            sym: None,
+        });
+        let next = module.add_and_get_port::<_, BitNot>(BitNotArgs {
+            ty: NodeTy::Unsigned(4),
+            input: sum,
+            sym: None,
+        });
+        DFF::set_data(&mut module, dff, next);
+        module.add_mod_output(Port::new(dff, 0));
+
+        let mut netlist = NetList::default();
+        netlist.add_module(module);
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        String::from_utf8(verilog).unwrap()
+    }
+
+    // out1 = a + b, out2 = ~a; the independent nodes are added in the given order and the
+    // dead node is removed from the node list
+    fn synth_reordered(sum_first: bool, dead_node: bool) -> String {
+        let mut module = Module::new("top", true);
+        let a = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let b = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("b")));
+        let dead = dead_node.then(|| module.const_val(NodeTy::Unsigned(4), 7));
+
+        let sum = |module: &mut Module| {
+            module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                ty: NodeTy::Unsigned(4),
+                bin_op: BinOp::Add,
+                lhs: a,
+                rhs: b,
+                sym: None,
+            })
+        };
+        let inv = |module: &mut Module| {
+            module.add_and_get_port::<_, BitNot>(BitNotArgs {
+                ty: NodeTy::Unsigned(4),
+                input: a,
+                sym: None,
+            })
+        };
+        let (sum, inv) = if sum_first {
+            let sum = sum(&mut module);
+            (sum, inv(&mut module))
+        } else {
+            let inv = inv(&mut module);
+            (sum(&mut module), inv)
+        };
+        module.add_mod_output(sum);
+        module.add_mod_output(inv);
+
+        if let Some(dead) = dead {
+            module.remove(dead.node);
+        }
+
+        let mut netlist = NetList::default();
+        netlist.add_module(module);
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        String::from_utf8(verilog).unwrap()
+    }
+
+    #[test]
+    fn node_list_order_does_not_change_verilog() {
+        let verilog = synth_reordered(true, false);
+
+        assert_eq!(synth_reordered(false, false), verilog);
+        assert_eq!(synth_reordered(false, true), verilog);
+        assert_eq!(synth_reordered(true, true), verilog);
+        assert!(verilog.contains("assign _$1 = a + b;"));
+    }
+
+    #[test]
+    fn dff_distinct_init_and_rst_val() {
+        let mut module = Module::new("top", true);
+        let clk = module.add_input(NodeTy::Clock, Some(Symbol::intern("clk")));
+        let rst = module.add_input(NodeTy::Bit, Some(Symbol::intern("rst")));
+        let data = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("data")));
+        let init = module.const_val(NodeTy::Unsigned(4), 3);
+        let rst_val = module.const_val(NodeTy::Unsigned(4), 5);
+
+        let dff = module.add_and_get_port::<_, DFF>(DFFArgs {
+            clk,
+            rst: Some(rst),
+            rst_kind: SyncKind::Sync,
+            rst_pol: Polarity::ActiveHigh,
+            en: None,
+            init,
+            rst_val: Some(rst_val),
+            data: TyOrData::Data(data),
+            sym: Some(Symbol::intern("reg")),
+        });
+        module.add_mod_output(dff);
+
+        let mut netlist = NetList::default();
+        netlist.add_module(module);
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+
+        // the initial block gets `init`, the reset branch gets `rst_val`
+        assert!(verilog.contains("_reg = 4'd3;"));
+        assert!(verilog.contains("assign _$2 = 4'd5;"));
+        assert!(verilog.contains("if (rst)\n            _reg <= _$2;"));
+    }
+
+    fn dff_with_en_verilog(
+        clock_gating: bool,
+        rst_kind: Option<SyncKind>,
+        data_sym: &str,
+    ) -> String {
+        let mut module = Module::new("top", true);
+        let clk = module.add_input(NodeTy::Clock, Some(Symbol::intern("clk")));
+        let rst =
+            rst_kind.map(|_| module.add_input(NodeTy::Bit, Some(Symbol::intern("rst"))));
+        let en = module.add_input(NodeTy::Bit, Some(Symbol::intern("en")));
+        let data = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern(data_sym)));
+        let init = module.const_val(NodeTy::Unsigned(4), 0);
+
+        let dff = module.add_and_get_port::<_, DFF>(DFFArgs {
+            clk,
+            rst,
+            rst_kind: rst_kind.unwrap_or(SyncKind::Sync),
+            rst_pol: Polarity::ActiveHigh,
+            en: Some(en),
+            init,
+            rst_val: None,
+            data: TyOrData::Data(data),
+            sym: Some(Symbol::intern("reg")),
+        });
+        module.add_mod_output(dff);
+
+        let mut netlist = NetList::new(NetListCfg {
+            clock_gating,
+            ..Default::default()
+        });
+        netlist.add_module(module);
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        String::from_utf8(verilog).unwrap()
+    }
+
+    fn gated_clock_verilog(clock_gating: bool, data: &str) -> String {
+        let mut module = Module::new("top", true);
+        let clk = module.add_input(NodeTy::Clock, Some(Symbol::intern("clk")));
+        let en = module.add_input(NodeTy::Bit, Some(Symbol::intern("en")));
+        let data = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern(data)));
+        let init = module.const_val(NodeTy::Unsigned(4), 0);
+
+        let gclk = module.add_and_get_port::<_, ClockGate>(ClockGateArgs {
+            clk,
+            en,
+            sym: Some(Symbol::intern("gclk")),
+        });
+        let dff = module.add_and_get_port::<_, DFF>(DFFArgs {
+            clk: gclk,
+            rst: None,
+            rst_kind: SyncKind::Sync,
+            rst_pol: Polarity::ActiveHigh,
+            en: None,
+            init,
+            rst_val: None,
+            data: TyOrData::Data(data),
+            sym: Some(Symbol::intern("reg")),
+        });
+        module.add_mod_output(dff);
+
+        let mut netlist = NetList::new(NetListCfg {
+            clock_gating,
+            ..Default::default()
+        });
+        netlist.add_module(module);
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        String::from_utf8(verilog).unwrap()
+    }
+
+    #[test]
+    fn gated_clock() {
+        let verilog = gated_clock_verilog(true, "data");
+        assert!(verilog
+            .contains("fhdl_clock_gate gclk_cg (.clk(clk), .en(en), .gclk(gclk));"));
+        assert!(verilog.contains("always @(posedge gclk) begin\n        _reg <= data;"));
+        assert_eq!(verilog.matches("module fhdl_clock_gate").count(), 1);
+        // the latch of the cell is non-blocking like the registers
+        assert!(verilog.contains("en_latch <= en;"));
+        // never gated with a plain AND
+        assert!(!verilog.contains("clk & en;"));
+
+        // without opt-in the enable is moved to the register
+        let verilog = gated_clock_verilog(false, "data");
+        assert!(!verilog.contains("fhdl_clock_gate"));
+        assert!(verilog.contains(
+            "always @(posedge clk) begin\n        if (en)\n            _reg <= data;"
+        ));
+    }
+
+    #[test]
+    fn gated_clock_names() {
+        // the clock gate instance doesn't clash with the names of the module
+        let verilog = gated_clock_verilog(true, "gclk_cg");
+        assert!(verilog
+            .contains("fhdl_clock_gate gclk_cg_1 (.clk(clk), .en(en), .gclk(gclk));"));
+        assert!(
+            verilog.contains("always @(posedge gclk) begin\n        _reg <= gclk_cg;")
+        );
+    }
+
+    #[test]
+    fn dff_clock_gating() {
+        let verilog = dff_with_en_verilog(true, None, "data");
+        assert!(verilog.contains("// clock gate\n"));
+        assert!(verilog.contains(
+            "fhdl_clock_gate _reg_gclk_cg (.clk(clk), .en(en), .gclk(_reg_gclk));"
+        ));
+        assert!(
+            verilog.contains("always @(posedge _reg_gclk) begin\n        _reg <= data;")
+        );
+        assert!(!verilog.contains("if (en)"));
+        assert_eq!(verilog.matches("module fhdl_clock_gate").count(), 1);
+
+        let verilog = dff_with_en_verilog(false, None, "data");
+        assert!(!verilog.contains("fhdl_clock_gate"));
+        assert!(verilog.contains(
+            "always @(posedge clk) begin\n        if (en)\n            _reg <= data;"
+        ));
+    }
+
+    #[test]
+    fn dff_clock_gating_with_reset() {
+        // an async reset doesn't depend on the clock
+        let verilog = dff_with_en_verilog(true, Some(SyncKind::Async), "data");
+        assert!(verilog.contains("always @(posedge _reg_gclk or posedge rst) begin"));
+
+        // a sync reset must be applied even if the register is disabled
+        let verilog = dff_with_en_verilog(true, Some(SyncKind::Sync), "data");
+        assert!(!verilog.contains("fhdl_clock_gate"));
+        assert!(verilog.contains("always @(posedge clk) begin"));
+        assert!(verilog.contains("else if (en)\n            _reg <= data;"));
+    }
+
+    #[test]
+    fn dff_clock_gating_names() {
+        // the gated clock doesn't clash with the names of the module
+        let verilog = dff_with_en_verilog(true, None, "_reg_gclk");
+        assert!(verilog.contains(
+            "fhdl_clock_gate _reg_gclk_1_cg (.clk(clk), .en(en), .gclk(_reg_gclk_1));"
+        ));
+        assert!(verilog
+            .contains("always @(posedge _reg_gclk_1) begin\n        _reg <= _reg_gclk;"));
+    }
+
+    #[test]
+    fn extern_mod_inst() {
+        let mut netlist = NetList::default();
+
+        let mut my_ip = Module::new("my_ip", false);
+        my_ip.external = true;
+        my_ip.add_input(NodeTy::Unsigned(8), None::<&str>);
+        my_ip.add_input(NodeTy::Unsigned(8), None::<&str>);
+        my_ip.add_extern_output(NodeTy::Unsigned(8));
+        my_ip.add_extern_output(NodeTy::Bit);
+        let my_ip = netlist.add_module(my_ip);
+
+        let mut module = Module::new("top", true);
+        let a = module.add_input(NodeTy::Unsigned(8), Some(Symbol::intern("a")));
+        let b = module.add_input(NodeTy::Unsigned(8), Some(Symbol::intern("b")));
+        let my_ip = netlist.module(my_ip).map(|module| module.borrow());
+        let mod_inst = module.add::<_, ModInst>(ModInstArgs {
+            module: my_ip.as_deref(),
+            inputs: [a, b],
+            outputs: [Some(Symbol::intern("sum")), Some(Symbol::intern("carry"))],
+        });
+        drop(my_ip);
+        // carry is unused, but is still connected
+        module.add_mod_output(Port::new(mod_inst, 0));
+        netlist.add_module(module);
+
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        let verilog = String::from_utf8(verilog).unwrap();
+
+        assert!(!verilog.contains("module my_ip"));
+        assert!(verilog.contains(
+            "my_ip __mod (\n        a,\n        b,\n        sum,\n        carry\n    );"
+        ));
+    }
+
+    fn synth_top(module: Module) -> String {
+        let mut netlist = NetList::default();
+        netlist.add_module(module);
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        String::from_utf8(verilog).unwrap()
+    }
+
+    #[test]
+    fn dff_non_blocking_golden() {
+        let mut module = Module::new("top", true);
+        let clk = module.add_input(NodeTy::Clock, Some(Symbol::intern("clk")));
+        let rst = module.add_input(NodeTy::Bit, Some(Symbol::intern("rst")));
+        let data = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("data")));
+        let init = module.const_val(NodeTy::Unsigned(4), 0);
+        let dff = module.add_and_get_port::<_, DFF>(DFFArgs {
+            clk,
+            rst: Some(rst),
+            rst_kind: SyncKind::Sync,
+            rst_pol: Polarity::ActiveHigh,
+            en: None,
+            init,
+            rst_val: None,
+            data: TyOrData::Data(data),
+            sym: Some(Symbol::intern("out")),
+        });
+        module.add_mod_output(dff);
+
+        // the register is assigned with non-blocking assignments in the clocked block,
+        // only the power-up value in the initial block is blocking
+        assert_eq!(
+            synth_top(module),
+            "\
+/* Automatically generated by Ferrum HDL. */
+
+module top
+(
+    // Inputs
+    input wire clk,
+    input wire rst,
+    input wire [3:0] data,
+    // Outputs
+    output reg [3:0] out
+);
+
+    wire [3:0] _$1;
+    assign _$1 = 4'd0;
+
+    initial begin
+        out = 4'd0;
+    end
+    always @(posedge clk) begin
+        if (rst)
+            out <= _$1;
+        else
+            out <= data;
+    end
+
+endmodule
+
+"
+        );
+    }
+
+    #[test]
+    fn mux_continuous_assign_golden() {
+        // out = (sel ? a : b) + a
+        let mut module = Module::new("top", true);
+        let sel = module.add_input(NodeTy::Bit, Some(Symbol::intern("sel")));
+        let a = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let b = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("b")));
+        let mux = module.add_and_get_port::<_, Switch>(SwitchArgs {
+            outputs: iter::once((NodeTy::Unsigned(4), Some(Symbol::intern("mux")))),
+            sel,
+            variants: iter::once((ConstVal::new(1, 1), iter::once(a))),
+            default: Some(iter::once(b)),
+        });
+        let out = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Unsigned(4),
+            bin_op: BinOp::Add,
+            lhs: mux,
+            rhs: a,
+            sym: Some(Symbol::intern("out")),
+        });
+        module.add_mod_output(out);
+
+        // combinational logic is either a continuous assignment or blocking
+        // assignments in a combinational block
+        assert_eq!(
+            synth_top(module),
+            "\
+/* Automatically generated by Ferrum HDL. */
+
+module top
+(
+    // Inputs
+    input wire sel,
+    input wire [3:0] a,
+    input wire [3:0] b,
+    // Outputs
+    output wire [3:0] out
+);
+
+    reg [3:0] mux;
+    always @(*) begin
+        case (sel)
+            1'd1: mux = a;
+            default: mux = b;
+        endcase
+    end
+
+    assign out = mux + a;
+
+endmodule
+
+"
+        );
+    }
+
+    // Checks that clocked blocks use only non-blocking assignments and combinational
+    // blocks only blocking ones
+    fn check_assignments(verilog: &str) {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum Block {
+            Seq,
+            Comb,
+        }
+
+        let mut block = None;
+        for line in verilog.lines() {
+            let trimmed = line.trim();
+            match block {
+                None if trimmed.starts_with("always @(posedge") => {
+                    block = Some(Block::Seq)
+                }
+                None if trimmed.starts_with("always @(*)") => block = Some(Block::Comb),
+                Some(_) if line == "    end" => block = None,
+                Some(Block::Seq) => {
+                    assert!(
+                        !trimmed.contains(" = "),
+                        "blocking in clocked block: {line}"
+                    );
+                }
+                Some(Block::Comb) => {
+                    assert!(
+                        !trimmed.contains("<="),
+                        "non-blocking in comb block: {line}"
+                    );
+                }
+                None => {}
+            }
+        }
+    }
+
+    #[test]
+    fn no_blocking_in_clocked_blocks() {
+        // reg <= !rst ? 0 : en ? (sel ? a : reg + 1) : reg
+        let mut module = Module::new("top", true);
+        let clk = module.add_input(NodeTy::Clock, Some(Symbol::intern("clk")));
+        let rst = module.add_input(NodeTy::Bit, Some(Symbol::intern("rst")));
+        let en = module.add_input(NodeTy::Bit, Some(Symbol::intern("en")));
+        let sel = module.add_input(NodeTy::Bit, Some(Symbol::intern("sel")));
+        let a = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let init = module.const_val(NodeTy::Unsigned(4), 0);
+        let dff = module.add::<_, DFF>(DFFArgs {
+            clk,
+            rst: Some(rst),
+            rst_kind: SyncKind::Async,
+            rst_pol: Polarity::ActiveLow,
+            en: Some(en),
+            init,
+            rst_val: None,
+            data: TyOrData::Ty(NodeTy::Unsigned(4)),
+            sym: Some(Symbol::intern("reg")),
+        });
+        let one = module.const_val(NodeTy::Unsigned(4), 1);
+        let inc = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Unsigned(4),
+            bin_op: BinOp::Add,
+            lhs: Port::new(dff, 0),
+            rhs: one,
+            sym: None,
+        });
+        let mux = module.add_and_get_port::<_, Switch>(SwitchArgs {
+            outputs: iter::once((NodeTy::Unsigned(4), Some(Symbol::intern("mux")))),
+            sel,
+            variants: iter::once((ConstVal::new(1, 1), iter::once(a))),
+            default: Some(iter::once(inc)),
+        });
+        DFF::set_data(&mut module, dff, mux);
+        module.add_mod_output(Port::new(dff, 0));
+
+        let verilog = synth_top(module);
+        assert!(verilog.contains("always @(posedge clk or negedge rst) begin"));
+        assert!(verilog.contains("always @(*) begin"));
+
+        for verilog in [
+            verilog,
+            synth_counter(),
+            dff_with_en_verilog(false, None, "data"),
+            gated_clock_verilog(true, "data"),
+        ] {
+            check_assignments(&verilog);
+        }
+    }
+
+    fn masked_verilog(const_fmt: ConstFmt) -> String {
+        // out = en ? a & 8'hff : 0
+        let mut module = Module::new("top", true);
+        let en = module.add_input(NodeTy::Bit, Some(Symbol::intern("en")));
+        let a = module.add_input(NodeTy::Unsigned(8), Some(Symbol::intern("a")));
+        let mask = module.const_val(NodeTy::Unsigned(8), 0xff);
+        let masked = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Unsigned(8),
+            bin_op: BinOp::BitAnd,
+            lhs: a,
+            rhs: mask,
+            sym: Some(Symbol::intern("masked")),
+        });
+        let zero = module.const_val(NodeTy::Unsigned(8), 0);
+        let out = module.add_and_get_port::<_, Switch>(SwitchArgs {
+            outputs: iter::once((NodeTy::Unsigned(8), Some(Symbol::intern("out")))),
+            sel: en,
+            variants: iter::once((ConstVal::new(1, 1), iter::once(masked))),
+            default: Some(iter::once(zero)),
+        });
+        module.add_mod_output(out);
+
+        let mut netlist = NetList::new(NetListCfg {
+            const_fmt,
+            ..Default::default()
+        });
+        netlist.add_module(module);
+        netlist.run_visitors();
+
+        let mut verilog = vec![];
+        netlist.synth_verilog(&mut verilog).unwrap();
+        String::from_utf8(verilog).unwrap()
+    }
+
+    #[test]
+    fn const_fmt_dec() {
+        assert_eq!(
+            masked_verilog(ConstFmt::Dec),
+            "\
+/* Automatically generated by Ferrum HDL. */
+
+module top
+(
+    // Inputs
+    input wire en,
+    input wire [7:0] a,
+    // Outputs
+    output reg [7:0] out
+);
+
+    wire [7:0] _$1;
+    assign _$1 = 8'd255;
+
+    wire [7:0] _$2;
+    assign _$2 = 8'd0;
+
+    wire [7:0] masked;
+    assign masked = a & _$1;
+
+    always @(*) begin
+        case (en)
+            1'd1: out = masked;
+            default: out = _$2;
+        endcase
+    end
+
+endmodule
+
+"
+        );
+    }
+
+    #[test]
+    fn const_fmt_hex() {
+        // single-bit constants are binary
+        assert_eq!(
+            masked_verilog(ConstFmt::Hex),
+            "\
+/* Automatically generated by Ferrum HDL. */
+
+module top
+(
+    // Inputs
+    input wire en,
+    input wire [7:0] a,
+    // Outputs
+    output reg [7:0] out
+);
+
+    wire [7:0] _$1;
+    assign _$1 = 8'hff;
+
+    wire [7:0] _$2;
+    assign _$2 = 8'h0;
+
+    wire [7:0] masked;
+    assign masked = a & _$1;
+
+    always @(*) begin
+        case (en)
+            1'b1: out = masked;
+            default: out = _$2;
+        endcase
+    end
+
+endmodule
+
+"
+        );
+    }
+
+    #[test]
+    fn rom_golden() {
+        let mut module = Module::new("top", true);
+        let addr = module.add_input(NodeTy::Unsigned(2), Some(Symbol::intern("addr")));
+        let rom = module.add_and_get_port::<_, Memory>(MemoryArgs {
+            ty: NodeTy::Unsigned(4),
+            dim: NonZeroU128::new(4).unwrap(),
+            addr,
+            init: [1, 2, 4, 8]
+                .into_iter()
+                .enumerate()
+                .map(|(idx, val)| (idx as u128, ConstVal::new(val, 4))),
+            name: Some(Symbol::intern("rom")),
+            data_sym: Some(Symbol::intern("out")),
+        });
+        module.add_mod_output(rom);
+
+        assert_eq!(
+            synth_top(module),
+            "\
+/* Automatically generated by Ferrum HDL. */
+
+module top
+(
+    // Inputs
+    input wire [1:0] addr,
+    // Outputs
+    output wire [3:0] out
+);
+
+    reg [3:0] rom [0:3];
+    initial begin
+        rom[0] = 4'd1;
+        rom[1] = 4'd2;
+        rom[2] = 4'd4;
+        rom[3] = 4'd8;
+    end
+    assign out = rom[addr];
+
+endmodule
+
+"
+        );
+    }
+
+    #[test]
+    fn rom_partial_init_and_const_addr() {
+        let mut module = Module::new("top", true);
+        let addr = module.add_input(NodeTy::Unsigned(2), Some(Symbol::intern("addr")));
+        let const_addr = module.const_val(NodeTy::Unsigned(2), 1);
+        let rom = |module: &mut Module, addr, sym| {
+            module.add_and_get_port::<_, Memory>(MemoryArgs {
+                ty: NodeTy::Unsigned(4),
+                dim: NonZeroU128::new(4).unwrap(),
+                addr,
+                init: [(1, ConstVal::new(7, 4))],
+                name: None,
+                data_sym: Some(Symbol::intern(sym)),
+            })
+        };
+        let out = rom(&mut module, addr, "out");
+        let folded = rom(&mut module, const_addr, "folded");
+        module.add_mod_output(out);
+        module.add_mod_output(folded);
+
+        let verilog = synth_top(module);
+        // the words without an initial value are zeroed
+        assert!(verilog.contains("    integer __mem_i;\n"));
+        assert!(verilog.contains(
+            "for (__mem_i = 0; __mem_i < 4; __mem_i = __mem_i + 1) __mem[__mem_i] = 4'd0;\n"
+        ));
+        assert!(verilog.contains("        __mem[1] = 4'd7;\n"));
+        assert!(verilog.contains("    assign out = __mem[addr];\n"));
+        // the word at a constant address is read at synthesis time
+        assert!(verilog.contains("    assign folded = 4'd7;\n"));
+        assert!(!verilog.contains("__mem_1"));
+    }
+}
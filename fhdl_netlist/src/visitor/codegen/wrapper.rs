@@ -0,0 +1,317 @@
+use std::io::{Result, Write};
+
+use ferrum_hdl::domain::{hz_to_period, Polarity};
+use fhdl_data_structures::{cursor::Cursor, graph::Port};
+
+use crate::{
+    buffer::Buffer,
+    netlist::{Module, NetList},
+    node::{GlSignalKind, NodeOutput},
+    node_ty::NodeTy,
+    with_id::WithId,
+};
+
+// Clock of the simulated domain and polarity of resets not driving any register
+#[derive(Debug, Clone, Copy)]
+pub struct WrapperCfg {
+    // In hertz
+    pub freq: usize,
+    pub rst_pol: Polarity,
+    // Number of clock cycles the reset is held asserted
+    pub rst_cycles: usize,
+}
+
+impl Default for WrapperCfg {
+    fn default() -> Self {
+        Self {
+            freq: 100_000_000,
+            rst_pol: Polarity::ActiveHigh,
+            rst_cycles: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputKind {
+    Clk,
+    Rst { active_high: bool },
+    Data,
+}
+
+// Ports of the module driving reset inputs of registers, directly or through
+// module instances
+fn find_resets(netlist: &NetList, module: &Module) -> Vec<(Port, Polarity)> {
+    let mut resets = Vec::new();
+    for node_id in module.nodes().into_iter_(module) {
+        let node = &module[node_id];
+        if node.skip {
+            continue;
+        }
+
+        if let Some(dff) = node.dff() {
+            if let Some(rst) = WithId::new(node_id, dff).inputs(module).rst {
+                resets.push((rst, dff.rst_pol));
+            }
+        } else if let Some(mod_inst) = node.mod_inst() {
+            let orig_mod = netlist
+                .module(mod_inst.mod_id)
+                .map(|module| module.borrow());
+            let orig_resets = find_resets(netlist, &orig_mod);
+
+            for (port, orig_port) in module
+                .mod_inst_inputs(WithId::new(node_id, mod_inst), orig_mod.as_deref())
+            {
+                if let Some((_, rst_pol)) =
+                    orig_resets.iter().find(|(rst, _)| *rst == orig_port.id)
+                {
+                    resets.push((port.id, *rst_pol));
+                }
+            }
+        }
+    }
+
+    resets
+}
+
+fn input_kind(
+    port: Port,
+    node_out: &NodeOutput,
+    global: GlSignalKind,
+    resets: &[(Port, Polarity)],
+    cfg: &WrapperCfg,
+) -> InputKind {
+    if let Some((_, rst_pol)) = resets.iter().find(|(rst, _)| *rst == port) {
+        return InputKind::Rst {
+            active_high: *rst_pol == Polarity::ActiveHigh,
+        };
+    }
+
+    match (global, node_out.ty) {
+        (GlSignalKind::Clk, _) | (_, NodeTy::Clock | NodeTy::ClockDomain) => {
+            InputKind::Clk
+        }
+        (GlSignalKind::Rst, _) => InputKind::Rst {
+            active_high: cfg.rst_pol == Polarity::ActiveHigh,
+        },
+        (GlSignalKind::None, _) => InputKind::Data,
+    }
+}
+
+fn write_decl<W: Write>(
+    buffer: &mut Buffer<W>,
+    kind: &str,
+    node_out: &NodeOutput,
+    init: Option<u8>,
+) -> Result<()> {
+    buffer.write_tab()?;
+    buffer.write_str(kind)?;
+    if node_out.ty.width() > 1 {
+        buffer.write_fmt(format_args!(" [{}:0]", node_out.ty.width() - 1))?;
+    }
+    buffer.write_fmt(format_args!(" {}", node_out.sym.unwrap()))?;
+    if let Some(init) = init {
+        buffer.write_fmt(format_args!(" = {init}"))?;
+    }
+    buffer.write_str(";\n")
+}
+
+// Simulation-only testbench shell around the top module: clock inputs toggle at the
+// domain frequency, resets are held asserted for `rst_cycles` cycles, other inputs
+// are tied to zero
+pub struct Wrapper<'n, W: Write> {
+    buffer: Buffer<W>,
+    netlist: &'n NetList,
+    cfg: WrapperCfg,
+}
+
+impl<'n, W: Write> Wrapper<'n, W> {
+    pub fn new(netlist: &'n NetList, writer: W, cfg: WrapperCfg) -> Self {
+        Self {
+            buffer: Buffer::new(writer),
+            netlist,
+            cfg,
+        }
+    }
+
+    pub fn synth(&mut self) -> Result<()> {
+        let top = match self.netlist.top {
+            Some(top) => self.netlist[top].borrow(),
+            None => return Ok(()),
+        };
+
+        let resets = find_resets(self.netlist, &top);
+        let inputs = top
+            .mod_inputs()
+            .iter()
+            .map(|&port| {
+                let node_out = &top[port];
+                let global = top[port.node].input().unwrap().global;
+                (
+                    node_out,
+                    input_kind(port, node_out, global, &resets, &self.cfg),
+                )
+            })
+            .collect::<Vec<_>>();
+        let outputs = top
+            .mod_outputs()
+            .iter()
+            .map(|&port| &top[port])
+            .collect::<Vec<_>>();
+
+        let b = &mut self.buffer;
+        b.write_str(
+            "/* Automatically generated by Ferrum HDL. For simulation only. */\n\n",
+        )?;
+        b.write_str("`timescale 1ps / 1ps\n\n")?;
+        b.write_str("module wrapper;\n")?;
+
+        b.push_tab();
+        for &(node_out, kind) in &inputs {
+            let init = match kind {
+                InputKind::Clk | InputKind::Data => 0,
+                InputKind::Rst { active_high } => active_high as u8,
+            };
+            write_decl(b, "reg", node_out, Some(init))?;
+        }
+        for &node_out in &outputs {
+            write_decl(b, "wire", node_out, None)?;
+        }
+
+        let clks = inputs
+            .iter()
+            .filter(|(_, kind)| *kind == InputKind::Clk)
+            .map(|(node_out, _)| node_out.sym.unwrap())
+            .collect::<Vec<_>>();
+        let half_period = (hz_to_period(self.cfg.freq) / 2).max(1);
+
+        if !clks.is_empty() {
+            b.write_eol()?;
+            for clk in &clks {
+                b.write_tab()?;
+                b.write_fmt(format_args!("always #{half_period} {clk} = ~{clk};\n"))?;
+            }
+        }
+
+        let rsts = inputs
+            .iter()
+            .filter_map(|(node_out, kind)| match kind {
+                InputKind::Rst { active_high } => {
+                    Some((node_out.sym.unwrap(), *active_high))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if !rsts.is_empty() {
+            b.write_eol()?;
+            b.write_tab()?;
+            b.write_str("initial begin\n")?;
+            b.push_tab();
+
+            b.write_tab()?;
+            match clks.first() {
+                Some(clk) => b.write_fmt(format_args!(
+                    "repeat ({}) @(posedge {clk});\n",
+                    self.cfg.rst_cycles
+                ))?,
+                None => b.write_fmt(format_args!(
+                    "#{};\n",
+                    self.cfg.rst_cycles * half_period * 2
+                ))?,
+            }
+            for (rst, active_high) in &rsts {
+                b.write_tab()?;
+                b.write_fmt(format_args!("{rst} <= {};\n", !active_high as u8))?;
+            }
+
+            b.pop_tab();
+            b.write_tab()?;
+            b.write_str("end\n")?;
+        }
+
+        b.write_eol()?;
+        b.write_tab()?;
+        b.write_fmt(format_args!("{} dut (\n", top.name))?;
+        b.push_tab();
+        let ports = inputs
+            .iter()
+            .map(|(node_out, _)| *node_out)
+            .chain(outputs.iter().copied());
+        b.intersperse(",\n", ports, |buffer, node_out| {
+            let sym = node_out.sym.unwrap();
+            buffer.write_tab()?;
+            buffer.write_fmt(format_args!(".{sym}({sym})"))
+        })?;
+        b.pop_tab();
+        b.write_str("\n")?;
+        b.write_tab()?;
+        b.write_str(");\n")?;
+        b.pop_tab();
+
+        b.write_str("endmodule\n")?;
+        b.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ferrum_hdl::domain::SyncKind;
+
+    use super::*;
+    use crate::{
+        node::{DFFArgs, TyOrData, DFF},
+        symbol::Symbol,
+    };
+
+    #[test]
+    fn wrapper_drives_clk_and_rst() {
+        let mut module = Module::new("top", true);
+        let clk = module.add_input(NodeTy::Clock, Some(Symbol::intern("clk")));
+        let rst = module.add_input(NodeTy::Bit, Some(Symbol::intern("rst")));
+        let data = module.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("data")));
+        let init = module.const_val(NodeTy::Unsigned(4), 0);
+        let dff = module.add_and_get_port::<_, DFF>(DFFArgs {
+            clk,
+            rst: Some(rst),
+            rst_kind: SyncKind::Sync,
+            rst_pol: Polarity::ActiveLow,
+            en: None,
+            init,
+            rst_val: None,
+            data: TyOrData::Data(data),
+            sym: Some(Symbol::intern("out")),
+        });
+        module.add_mod_output(dff);
+
+        let mut netlist = NetList::default();
+        netlist.add_module(module);
+        netlist.run_visitors();
+
+        let mut wrapper = vec![];
+        netlist
+            .synth_wrapper(&mut wrapper, WrapperCfg {
+                freq: 50_000_000,
+                rst_pol: Polarity::ActiveHigh,
+                rst_cycles: 3,
+            })
+            .unwrap();
+        let wrapper = String::from_utf8(wrapper).unwrap();
+
+        assert!(wrapper.contains("reg clk = 0;"));
+        // Polarity is taken from the register: the reset starts low and is released
+        // after 3 cycles
+        assert!(wrapper.contains("reg rst = 0;"));
+        assert!(wrapper.contains("reg [3:0] data = 0;"));
+        assert!(wrapper.contains("wire [3:0] out;"));
+        // 50 MHz is a 20 ns period
+        assert!(wrapper.contains("always #10000 clk = ~clk;"));
+        assert!(wrapper.contains("repeat (3) @(posedge clk);\n        rst <= 1;"));
+        assert!(wrapper.contains("top dut (\n"));
+        assert!(wrapper.contains(".clk(clk),\n"));
+        assert!(wrapper.contains(".rst(rst),\n"));
+        assert!(wrapper.contains(".data(data),\n"));
+        assert!(wrapper.contains(".out(out)\n"));
+    }
+}
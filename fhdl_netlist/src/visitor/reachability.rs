@@ -7,8 +7,9 @@ use crate::netlist::{Module, ModuleId, NetList};
 pub struct Reachability<'n> {
     netlist: &'n NetList,
     ports: Vec<Port>,
-    modules: VecDeque<ModuleId>,
-    handled: FxHashSet<ModuleId>,
+    // `None` requests all outputs of the module, `Some(idx)` only the output `idx`
+    modules: VecDeque<(ModuleId, Option<usize>)>,
+    handled: FxHashSet<(ModuleId, Option<usize>)>,
 }
 
 impl<'n> Reachability<'n> {
@@ -23,23 +24,63 @@ impl<'n> Reachability<'n> {
 
     pub fn run(mut self) {
         if let Some(top) = self.netlist.top {
-            self.modules.push_back(top);
+            self.modules.push_back((top, None));
         }
 
-        while let Some(module_id) = self.modules.pop_front() {
-            if !self.handled.contains(&module_id) {
+        while let Some(req) = self.modules.pop_front() {
+            if self.handled.insert(req) {
+                let (module_id, output) = req;
                 let mut module = self.netlist[module_id].borrow_mut();
-                self.visit_module(&mut module);
-
-                self.handled.insert(module_id);
+                match output {
+                    None => self.visit_module(&mut module),
+                    Some(idx) => self.visit_output(&mut module, idx),
+                }
             }
         }
     }
 
     pub(super) fn visit_module(&mut self, module: &mut Module) {
+        if self.visit_external(module) {
+            return;
+        }
+
         self.ports.clear();
         self.ports.extend(module.mod_outputs().iter().rev());
+        self.visit_ports(module);
+    }
+
+    fn visit_output(&mut self, module: &mut Module, idx: usize) {
+        if self.visit_external(module) {
+            return;
+        }
+
+        // only outputs used by some instance are kept, the rest is pruned
+        self.ports.clear();
+        self.ports
+            .extend(module.mod_outputs().get_index(idx).copied());
+        self.visit_ports(module);
+    }
+
+    fn visit_external(&mut self, module: &mut Module) -> bool {
+        if !module.external {
+            return false;
+        }
+
+        // all ports of external modules are kept, as they are instantiated by position
+        let ports = module
+            .mod_inputs()
+            .iter()
+            .chain(module.mod_outputs().iter());
+        for port in ports.copied().collect::<Vec<_>>() {
+            module[port].skip = false;
+            module[port.node].skip = false;
+        }
+        module.skip = false;
+
+        true
+    }
 
+    fn visit_ports(&mut self, module: &mut Module) {
         while let Some(port) = self.ports.pop() {
             let node_out = &module[port];
             if !node_out.skip || node_out.ty.width() == 0 {
@@ -51,7 +92,8 @@ impl<'n> Reachability<'n> {
                     continue;
                 }
 
-                self.modules.push_back(mod_inst.mod_id);
+                self.modules
+                    .push_back((mod_inst.mod_id, Some(port.port as usize)));
             }
 
             module[port].skip = false;
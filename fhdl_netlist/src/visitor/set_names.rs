@@ -1,4 +1,4 @@
-use fhdl_data_structures::{cursor::Cursor, FxHashMap};
+use fhdl_data_structures::{cursor::Cursor, FxHashMap, FxHashSet};
 
 use crate::{
     netlist::{Module, ModuleId, NetList},
@@ -11,6 +11,9 @@ pub struct SetNames<'n> {
     netlist: &'n NetList,
     idents: FxHashMap<(ModuleId, Symbol), usize>,
     module_idents: FxHashMap<Symbol, usize>,
+    // Names assigned before renaming, generated names must not shadow them
+    reserved: FxHashSet<Symbol>,
+    preserve_names: bool,
 }
 
 impl<'n> SetNames<'n> {
@@ -19,6 +22,8 @@ impl<'n> SetNames<'n> {
             netlist,
             idents: Default::default(),
             module_idents: Default::default(),
+            reserved: Default::default(),
+            preserve_names: netlist.cfg().preserve_names,
         }
     }
 
@@ -37,10 +42,27 @@ impl<'n> SetNames<'n> {
         let mod_id = module.id;
         self.set_module_name(*module);
 
-        let mut nodes = module.nodes();
-        while let Some(node_id) = nodes.next_(*module) {
-            let node = &mut module[node_id];
+        if self.preserve_names {
+            self.reserve_names(*module);
+        }
 
+        // the ports are named in the port order, the rest of the names are numbered in the
+        // order of the generated code
+        let ports = module
+            .mod_inputs()
+            .iter()
+            .chain(module.mod_outputs())
+            .map(|port| port.node)
+            .collect::<Vec<_>>();
+        let topo_nodes = module.topo_nodes();
+        let mut named = FxHashSet::default();
+
+        for node_id in ports.into_iter().chain(topo_nodes) {
+            if !named.insert(node_id) {
+                continue;
+            }
+
+            let node = &mut module[node_id];
             if !node.skip {
                 self.set_node_out_names(mod_id, node);
             }
@@ -49,6 +71,11 @@ impl<'n> SetNames<'n> {
 
     fn set_module_name(&mut self, module: &mut Module) {
         let sym = module.name;
+        if module.external {
+            // the name of an external module is defined by its verilog
+            self.module_idents.entry(sym).or_insert(0);
+            return;
+        }
 
         let count = self.module_idents.get(&sym).copied();
         let (new_sym, count) = ident(sym, count);
@@ -57,6 +84,25 @@ impl<'n> SetNames<'n> {
         module.name = make_sym(new_sym, count);
     }
 
+    fn reserve_names(&mut self, module: &Module) {
+        self.reserved.clear();
+
+        let mut nodes = module.nodes();
+        while let Some(node_id) = nodes.next_(module) {
+            let node = &module[node_id];
+            if node.skip {
+                continue;
+            }
+
+            let mod_inst = node.mod_inst().and_then(|mod_inst| mod_inst.name);
+            let memory = node.memory().and_then(|memory| memory.name);
+            let outputs = node.outputs().iter().filter_map(|out| out.sym);
+
+            self.reserved
+                .extend(mod_inst.into_iter().chain(memory).chain(outputs));
+        }
+    }
+
     fn set_node_out_names(&mut self, mod_id: ModuleId, node: &mut Node) {
         if let Some(mod_inst) = node.mod_inst_mut() {
             let sym = mod_inst.name.unwrap_or_else(|| Symbol::intern("__mod"));
@@ -79,11 +125,17 @@ impl<'n> SetNames<'n> {
     }
 
     fn handle_sym(&mut self, mod_id: ModuleId, sym: Symbol) -> Symbol {
-        let count = self.idents.get(&(mod_id, sym)).copied();
-        let (new_sym, count) = ident(sym, count);
-        self.idents.insert((mod_id, sym), count);
-
-        make_sym(new_sym, count)
+        let mut count = self.idents.get(&(mod_id, sym)).copied();
+        loop {
+            let (new_sym, new_count) = ident(sym, count);
+            self.idents.insert((mod_id, sym), new_count);
+
+            let new_sym = make_sym(new_sym, new_count);
+            if new_count == 0 || !self.reserved.contains(&new_sym) {
+                return new_sym;
+            }
+            count = Some(new_count);
+        }
     }
 }
 
@@ -106,3 +158,79 @@ fn make_sym(sym: Symbol, count: usize) -> Symbol {
         Symbol::intern_args(format_args!("{}_{}", sym, count))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cfg::NetListCfg,
+        node::{BitNot, BitNotArgs},
+        node_ty::NodeTy,
+    };
+
+    #[test]
+    fn preserve_user_names() {
+        let mut netlist = NetList::new(NetListCfg {
+            preserve_names: true,
+            ..Default::default()
+        });
+
+        let mut module = Module::new("top", true);
+        let input = module.add_input(NodeTy::Unsigned(4), Some("counter_1"));
+        let mut outputs = vec![];
+        for sym in ["counter", "counter", "wire"] {
+            let port = module.add_and_get_port::<_, BitNot>(BitNotArgs {
+                ty: NodeTy::Unsigned(4),
+                input,
+                sym: Some(Symbol::intern(sym)),
+            });
+            module.add_mod_output(port);
+            outputs.push(port);
+        }
+        let mod_id = netlist.add_module(module);
+        netlist.reachability();
+        netlist.set_names();
+
+        let module = netlist[mod_id].borrow();
+        let names = outputs
+            .into_iter()
+            .map(|port| module[port].sym.unwrap().as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(module[input].sym.unwrap().as_str(), "counter_1");
+        assert_eq!(names, ["counter", "counter_2", "_wire"]);
+    }
+
+    #[test]
+    fn mangle_verilog_keywords() {
+        let mut netlist = NetList::new(NetListCfg {
+            preserve_names: true,
+            ..Default::default()
+        });
+
+        let mut module = Module::new("top", true);
+        let input = module.add_input(NodeTy::Unsigned(4), Some("time"));
+        let mut outputs = vec![];
+        for sym in ["event", "signed", "counter"] {
+            let port = module.add_and_get_port::<_, BitNot>(BitNotArgs {
+                ty: NodeTy::Unsigned(4),
+                input,
+                sym: Some(Symbol::intern(sym)),
+            });
+            module.add_mod_output(port);
+            outputs.push(port);
+        }
+        let mod_id = netlist.add_module(module);
+        netlist.reachability();
+        netlist.set_names();
+
+        let module = netlist[mod_id].borrow();
+        let names = outputs
+            .into_iter()
+            .map(|port| module[port].sym.unwrap().as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(module[input].sym.unwrap().as_str(), "_time");
+        assert_eq!(names, ["_event", "_signed", "counter"]);
+    }
+}
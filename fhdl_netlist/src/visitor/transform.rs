@@ -1,5 +1,7 @@
 use std::iter;
 
+use ferrum_hdl::domain::{Polarity, SyncKind};
+use fhdl_const_func::mask;
 use fhdl_data_structures::{
     cursor::Cursor,
     graph::{NodeId, Port},
@@ -10,11 +12,14 @@ use smallvec::SmallVec;
 use crate::{
     cfg::InlineMod,
     const_val::ConstVal,
-    netlist::{Module, ModuleId, NetList},
+    netlist::{Module, ModuleId, NetList, Warning},
     node::{
-        BinOpInputs, Const, ConstArgs, DFFArgs, DFFInputs, IsNode, MultiConst, NodeKind,
-        SwitchInputs, TyOrData, DFF,
+        BinOp, BinOpArgs, BinOpInputs, BinOpNode, BitNot, BitNotArgs, ClockGateInputs,
+        Const, ConstArgs, DFFArgs, DFFInputs, IsNode, Merger, MergerArgs, MultiConst,
+        NodeKind, NodeOutput, Offsets, Reduce, ReduceArgs, ReduceOp, SwitchInputs,
+        TyOrData, DFF,
     },
+    node_ty::NodeTy,
     with_id::WithId,
 };
 
@@ -24,6 +29,8 @@ pub struct Transform<'n> {
     netlist: &'n NetList,
     cons: FxHashMap<(ModuleId, ConstVal), Port>,
     max_inlines: Option<MaxInlines>,
+    warnings: Vec<Warning>,
+    errors: Vec<String>,
 }
 
 pub struct MaxInlines {
@@ -54,13 +61,17 @@ impl<'n> Transform<'n> {
             netlist,
             cons: Default::default(),
             max_inlines: netlist.cfg().max_inlines.map(MaxInlines::new),
+            warnings: Default::default(),
+            errors: Default::default(),
         }
     }
 
-    pub fn run(mut self) {
+    pub fn run(mut self) -> (Vec<Warning>, Vec<String>) {
         if let Some(top) = self.netlist.top {
             self.visit_module(top);
         }
+
+        (self.warnings, self.errors)
     }
 
     fn should_inline(&self) -> bool {
@@ -97,6 +108,8 @@ impl<'n> Transform<'n> {
                 let node_id = self.netlist.inline_mod(module.as_deref_mut(), node_id);
                 self.inc_inlines();
 
+                // revisit the inlined nodes, so their constants are deduplicated
+                // within this module
                 if let Some(node_id) = node_id {
                     nodes.set_next(node_id);
                 }
@@ -126,7 +139,8 @@ impl<'n> Transform<'n> {
                         });
                     }
                     None => {
-                        if !(module.is_mod_output(Port::new(node_id, 0))
+                        if !(self.netlist.cfg().no_reconnect_pass
+                            || module.is_mod_output(Port::new(node_id, 0))
                             || module.is_mod_input(pass.input(&module)))
                         {
                             let pass = node.with(pass);
@@ -161,17 +175,18 @@ impl<'n> Transform<'n> {
                     });
 
                     self.replace_with_multi_const(node_id, module, const_args);
-                } else {
+                } else if !orig_module.external {
                     match self.netlist.cfg().inline_mod {
                         InlineMod::All => {
                             inline = true;
                         }
                         InlineMod::Auto => {
-                            inline = orig_module.inline
-                                || module.mod_in_count() == 0
-                                || module.mod_out_count() == 0
-                                || module.node_count() <= NODES_LIMIT_TO_INLINE
-                                || module.node_has_const_inputs(node_id)
+                            inline = !orig_module.force_no_inline
+                                && (orig_module.inline
+                                    || module.mod_in_count() == 0
+                                    || module.mod_out_count() == 0
+                                    || module.node_count() <= NODES_LIMIT_TO_INLINE
+                                    || module.node_has_const_inputs(node_id))
                         }
                         InlineMod::None => {
                             inline = false;
@@ -180,9 +195,8 @@ impl<'n> Transform<'n> {
                 }
             }
             NodeKind::BitNot(bit_not) => {
-                if let Some(const_val) =
-                    module.to_const(node.with(bit_not).input(&module))
-                {
+                let input = node.with(bit_not).input(&module);
+                if let Some(const_val) = module.to_const(input) {
                     let const_val = !const_val;
                     let output = bit_not.output[0];
                     self.replace_with_const(node_id, module, ConstArgs {
@@ -190,6 +204,25 @@ impl<'n> Transform<'n> {
                         value: const_val.val(),
                         sym: output.sym,
                     });
+                } else if module[input.node].is_bit_not()
+                    && !module.is_mod_output(input)
+                    && module.outgoing(input).into_iter_(&module).count() == 1
+                {
+                    // !!x => x, if the inner negation is not used anywhere else
+                    module.reconnect_from_inputs_to_outputs(input.node, node_id);
+                }
+            }
+
+            NodeKind::Memory(memory) => {
+                let addr = node.with(memory).addr(&module);
+                if let Some(addr) = module.to_const(addr) {
+                    let output = memory.output[0];
+                    let value = memory.word(addr.val()).val();
+                    self.replace_with_const(node_id, module, ConstArgs {
+                        ty: output.ty,
+                        value,
+                        sym: output.sym,
+                    });
                 }
             }
 
@@ -199,21 +232,65 @@ impl<'n> Transform<'n> {
                 if let (Some(left), Some(right)) =
                     (module.to_const(lhs), module.to_const(rhs))
                 {
-                    let const_val = left.eval_bin_op(right, bin_op.bin_op);
                     let output = bin_op.output[0];
+                    let signed = matches!(module[lhs].ty, NodeTy::Signed(_));
+
+                    match left.eval_bin_op_with(
+                        right,
+                        bin_op.bin_op,
+                        signed,
+                        self.netlist.cfg().const_overflow,
+                    ) {
+                        Some(const_val) => {
+                            self.replace_with_const(node_id, module, ConstArgs {
+                                ty: output.ty,
+                                value: const_val.val(),
+                                sym: output.sym,
+                            });
+                        }
+                        None => {
+                            let at = node
+                                .span()
+                                .map(|span| format!(" (at {span})"))
+                                .unwrap_or_default();
+                            self.errors.push(format!(
+                                "constant expression `{left} {} {right}` overflows \
+                                 (`--const-overflow error`){at}",
+                                bin_op.bin_op
+                            ));
+                        }
+                    }
+                } else if matches!(bin_op.bin_op, BinOp::Eq | BinOp::Ne) {
+                    let var_cons = match (module.to_const(lhs), module.to_const(rhs)) {
+                        (Some(cons), None) => Some((rhs, cons)),
+                        (None, Some(cons)) => Some((lhs, cons)),
+                        _ => None,
+                    };
 
-                    self.replace_with_const(node_id, module, ConstArgs {
-                        ty: output.ty,
-                        value: const_val.val(),
-                        sym: output.sym,
-                    });
+                    if let Some((var, cons)) = var_cons {
+                        if module[var].width() == cons.width() {
+                            let eq = bin_op.bin_op == BinOp::Eq;
+                            let output = bin_op.output[0];
+                            self.fold_cmp_with_const(
+                                node_id, module, eq, var, cons, output,
+                            );
+                        }
+                    }
                 }
             }
             NodeKind::Splitter(splitter) => {
                 let splitter = node.with(splitter);
 
                 if splitter.pass_all_bits(&module) {
+                    let input = splitter.input(&module);
+                    let sym = splitter.outputs[0].sym;
+
                     module.reconnect(node_id);
+
+                    // keep the user-assigned name of the splitter output
+                    if module[input].sym.is_none() {
+                        module[input].sym = sym;
+                    }
                 } else {
                     let indices = splitter.eval_indices(&module);
                     let input = splitter.input(&module);
@@ -244,7 +321,11 @@ impl<'n> Transform<'n> {
                         let input = &module[input_id];
 
                         if let NodeKind::Merger(merger) = input.kind() {
-                            if splitter.rev != merger.rev
+                            if !self.netlist.cfg().no_fuse_splitter_merger
+                                && matches!(
+                                    splitter.offsets,
+                                    Offsets::Adjacent { rev, .. } if rev != merger.rev
+                                )
                                 && module.is_reversible(input_id, node_id)
                             {
                                 module
@@ -255,27 +336,11 @@ impl<'n> Transform<'n> {
                 }
             }
             NodeKind::Merger(merger) => {
-                // let sym = output.sym;
-
-                // if !*rev {
-                //     // for case:
-                //     // ```verilog
-                //     // wire [3:0] in;
-                //     // wire [3:0] out;
-                //     // assing out = {in[3], in[2], in[1], in[0]};
-                //     // ```
-                //     //
-                //     // after transform:
-                //     // ```verilog
-                //     // wire [3:0] in;
-                //     // wire [3:0] out;
-                //     // assign out = in;
-                //     // ```
-                //     if let Some(node_out_id) = self.is_merger_eq_input(inputs) {
-                //         let out = &self[node_out_id];
-                //         return Pass::new(out.ty, node_out_id, sym).into();
-                //     }
-                // }
+                // `{x[7:4], x[3:0]}` is just `x`
+                if let Some(source) = reassembled_source(&module, node.with(merger)) {
+                    module.reconnect_all_outgoing(node_id, iter::once(source));
+                    return inline;
+                }
 
                 let mut val = Some(ConstVal::new(0, 0));
                 node.with(merger).inputs(&module).for_each(|input| {
@@ -355,18 +420,108 @@ impl<'n> Transform<'n> {
             NodeKind::DFF(dff) => {
                 let dff = node.with(dff);
                 let DFFInputs {
-                    clk,
+                    mut clk,
                     mut rst,
                     mut en,
                     init,
+                    rst_val,
                     data,
                 } = dff.inputs(&module);
+                let rst_kind = dff.rst_kind;
+                let rst_pol = dff.rst_pol;
+                let sym = dff.output[0].sym;
+                let span = node.span().map(str::to_string);
 
                 let mut replace = false;
 
+                // without clock gating the gated clock is turned into the enable
+                // of the register. The closed gate blocks the synchronous reset too,
+                // as there are no edges of the gated clock
+                if !self.netlist.cfg().clock_gating {
+                    if let Some(clock_gate) = module[clk.node].clock_gate() {
+                        let ClockGateInputs {
+                            clk: gate_clk,
+                            en: gate_en,
+                        } = module.node(clk.node).with(clock_gate).inputs(&module);
+
+                        clk = gate_clk;
+                        en = Some(match en {
+                            Some(en) => module.insert_and_get_port::<_, BinOpNode>(
+                                node_id,
+                                BinOpArgs {
+                                    ty: NodeTy::Bit,
+                                    bin_op: BinOp::BitAnd,
+                                    lhs: gate_en,
+                                    rhs: en,
+                                    sym: None,
+                                },
+                            ),
+                            None => gate_en,
+                        });
+                        if let Some(sync_rst) = rst.filter(|_| rst_kind == SyncKind::Sync)
+                        {
+                            rst = Some(match rst_pol {
+                                Polarity::ActiveHigh => module
+                                    .insert_and_get_port::<_, BinOpNode>(
+                                        node_id,
+                                        BinOpArgs {
+                                            ty: NodeTy::Bit,
+                                            bin_op: BinOp::BitAnd,
+                                            lhs: sync_rst,
+                                            rhs: gate_en,
+                                            sym: None,
+                                        },
+                                    ),
+                                Polarity::ActiveLow => {
+                                    let gate_closed = module
+                                        .insert_and_get_port::<_, BitNot>(
+                                            node_id,
+                                            BitNotArgs {
+                                                ty: NodeTy::Bit,
+                                                input: gate_en,
+                                                sym: None,
+                                            },
+                                        );
+                                    module.insert_and_get_port::<_, BinOpNode>(
+                                        node_id,
+                                        BinOpArgs {
+                                            ty: NodeTy::Bit,
+                                            bin_op: BinOp::BitOr,
+                                            lhs: sync_rst,
+                                            rhs: gate_closed,
+                                            sym: None,
+                                        },
+                                    )
+                                }
+                            });
+                        }
+                        replace = true;
+                    }
+                }
+
+                let simplify = !self.netlist.cfg().no_simplify_dff;
+
                 let mut true_rst = false;
-                if let Some(const_val) = rst.and_then(|rst| module.to_const(rst)) {
-                    if dff.rst_pol.bool(const_val.val() == 0) {
+                if let Some(const_val) = rst
+                    .filter(|_| simplify)
+                    .and_then(|rst| module.to_const(rst))
+                {
+                    if rst_pol.bool(const_val.val() == 0) {
+                        // an async reset that never fires is often a logic error
+                        if rst_kind == SyncKind::Async
+                            && self.netlist.cfg().lint_async_rst
+                        {
+                            let name =
+                                sym.map(|sym| format!(" `{sym}`")).unwrap_or_default();
+                            self.warnings.push(Warning {
+                                msg: format!(
+                                    "reset of register{name} in async-reset domain \
+                                     is never asserted and is removed"
+                                ),
+                                span: span.clone(),
+                            });
+                        }
+
                         rst = None;
                         replace = true;
                     } else {
@@ -375,7 +530,9 @@ impl<'n> Transform<'n> {
                 }
 
                 let mut false_en = false;
-                if let Some(const_val) = en.and_then(|en| module.to_const(en)) {
+                if let Some(const_val) =
+                    en.filter(|_| simplify).and_then(|en| module.to_const(en))
+                {
                     if const_val.val() > 0 {
                         en = None;
                         replace = true;
@@ -385,10 +542,6 @@ impl<'n> Transform<'n> {
                 };
 
                 if replace {
-                    let rst_kind = dff.rst_kind;
-                    let rst_pol = dff.rst_pol;
-                    let sym = dff.output[0].sym;
-
                     module.replace::<_, DFF>(node_id, DFFArgs {
                         rst_kind,
                         rst_pol,
@@ -396,10 +549,17 @@ impl<'n> Transform<'n> {
                         rst,
                         en,
                         init,
+                        // the reset value is not needed without the reset
+                        rst_val: rst.and(rst_val),
                         data: TyOrData::Data(data),
                         sym,
                     });
-                } else if true_rst || false_en {
+                } else if true_rst {
+                    module.reconnect_all_outgoing(
+                        node_id,
+                        iter::once(rst_val.unwrap_or(init)),
+                    );
+                } else if false_en {
                     module.reconnect_all_outgoing(node_id, iter::once(init));
                 }
             }
@@ -429,6 +589,115 @@ impl<'n> Transform<'n> {
         self.transform(module, node_id);
     }
 
+    // Rewrites `x == const` (or `x != const`) into a reduce over the bits of `x`
+    // that are not known. If `x` is a merger, its constant inputs are compared
+    // in place and dropped from the comparison.
+    fn fold_cmp_with_const(
+        &mut self,
+        node_id: NodeId,
+        mut module: WithId<ModuleId, &mut Module>,
+        eq: bool,
+        var: Port,
+        cons: ConstVal,
+        output: NodeOutput,
+    ) {
+        let chunks = match module[var.node].kind() {
+            NodeKind::Merger(merger) => module
+                .node(var.node)
+                .with(merger)
+                .inputs(&module)
+                .map(|input| (input, module.to_const(input)))
+                .collect::<SmallVec<[_; 4]>>(),
+            _ => SmallVec::from_iter([(var, None)]),
+        };
+
+        let mut offset = cons.width();
+        let mut unknown = SmallVec::<[Port; 4]>::new();
+        let mut unknown_val = ConstVal::new(0, 0);
+        for (input, val) in &chunks {
+            let width = module[*input].width();
+            offset -= width;
+            let expected = ConstVal::new(cons.val() >> offset, width);
+
+            match val {
+                Some(val) => {
+                    if val.val() != expected.val() {
+                        return self.replace_with_const(node_id, module, ConstArgs {
+                            ty: output.ty,
+                            value: !eq as u128,
+                            sym: output.sym,
+                        });
+                    }
+                }
+                None => {
+                    unknown.push(*input);
+                    unknown_val.shift(expected);
+                }
+            }
+        }
+
+        if unknown.is_empty() {
+            return self.replace_with_const(node_id, module, ConstArgs {
+                ty: output.ty,
+                value: eq as u128,
+                sym: output.sym,
+            });
+        }
+
+        let width = unknown_val.width();
+        let op = if unknown_val.val() == 0 {
+            if eq {
+                ReduceOp::Nor
+            } else {
+                ReduceOp::Or
+            }
+        } else if unknown_val.val() == mask(width) {
+            if eq {
+                ReduceOp::And
+            } else {
+                ReduceOp::Nand
+            }
+        } else if unknown.len() < chunks.len() {
+            let input = self.merge_unknown(node_id, module.reborrow(), &unknown);
+            let cons = module.const_val(NodeTy::BitVec(width), unknown_val.val());
+
+            module.replace::<_, BinOpNode>(node_id, BinOpArgs {
+                ty: output.ty,
+                bin_op: if eq { BinOp::Eq } else { BinOp::Ne },
+                lhs: input,
+                rhs: cons,
+                sym: output.sym,
+            });
+            return;
+        } else {
+            return;
+        };
+
+        let input = self.merge_unknown(node_id, module.reborrow(), &unknown);
+        module.replace::<_, Reduce>(node_id, ReduceArgs {
+            op,
+            input,
+            sym: output.sym,
+        });
+    }
+
+    fn merge_unknown(
+        &mut self,
+        node_id: NodeId,
+        mut module: WithId<ModuleId, &mut Module>,
+        unknown: &[Port],
+    ) -> Port {
+        if unknown.len() == 1 {
+            unknown[0]
+        } else {
+            module.insert_and_get_port::<_, Merger>(node_id, MergerArgs {
+                inputs: unknown.iter().copied(),
+                rev: false,
+                sym: None,
+            })
+        }
+    }
+
     fn eliminate_const(
         &mut self,
         val: ConstVal,
@@ -464,15 +733,49 @@ impl<'n> Transform<'n> {
     }
 }
 
+// Returns the source of a splitter if the merger reassembles all its bits in order
+fn reassembled_source(module: &Module, merger: WithId<NodeId, &Merger>) -> Option<Port> {
+    let mut inputs = merger.inputs(module).collect::<SmallVec<[_; 4]>>();
+    let splitter_id = inputs.first()?.node;
+    let NodeKind::Splitter(splitter) = module[splitter_id].kind() else {
+        return None;
+    };
+    let splitter = module.node(splitter_id).with(splitter);
+    let source = splitter.input(module);
+    if module[source].ty != merger.output[0].ty {
+        return None;
+    }
+
+    let starts = splitter
+        .eval_indices(module)
+        .map(|(start, _)| start)
+        .collect::<SmallVec<[_; 4]>>();
+
+    // inputs of merger go from the MSB
+    inputs.reverse();
+    let mut offset = 0;
+    for input in inputs {
+        if input.node != splitter_id || starts[input.port as usize] != offset {
+            return None;
+        }
+        offset += module[input].width();
+    }
+
+    (offset == module[source].width()).then_some(source)
+}
+
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
 
     use super::*;
     use crate::{
+        cfg::{ConstOverflow, NetListCfg},
         netlist::NodeWithInputs,
-        node::{Merger, MergerArgs, Splitter, SplitterArgs},
-        node_ty::NodeTy,
+        node::{
+            Case, ClockGate, ClockGateArgs, Extend, ExtendArgs, ModInst, ModInstArgs,
+            SlicesArgs, Splitter, SplitterArgs, Switch, SwitchArgs, TupleCase,
+        },
         symbol::Symbol,
         visitor::reachability::Reachability,
     };
@@ -484,8 +787,7 @@ mod tests {
         Reachability::new(netlist).visit_module(&mut module);
     }
 
-    #[test]
-    fn merger_splitter_pattern() {
+    fn merger_splitter_module() -> Module {
         let mut module = Module::new("test", false);
 
         const IN1: u128 = 1;
@@ -527,6 +829,23 @@ mod tests {
 
         module.add_mod_outputs(splitter);
 
+        module
+    }
+
+    #[test]
+    fn merger_splitter_pattern() {
+        let module = merger_splitter_module();
+        let input1 = module.mod_inputs()[0];
+        let input2 = module.mod_inputs()[1];
+        let input3 = module.mod_inputs()[2];
+
+        let input1_ty = module[input1].ty;
+        let input1_sym = module[input1].sym;
+        let input2_ty = module[input2].ty;
+        let input2_sym = module[input2].sym;
+        let input3_ty = module[input3].ty;
+        let input3_sym = module[input3].sym;
+
         let mut netlist = NetList::default();
         let mod_id = netlist.add_module(module);
 
@@ -563,4 +882,820 @@ mod tests {
 
         assert_eq!(module.mod_outputs_vec(true), [pass1, pass2, pass3]);
     }
+
+    #[test]
+    fn merger_splitter_pattern_no_fuse() {
+        let module = merger_splitter_module();
+
+        let mut netlist = NetList::new(NetListCfg {
+            no_fuse_splitter_merger: true,
+            ..Default::default()
+        });
+        let mod_id = netlist.add_module(module);
+
+        transform(&netlist, mod_id);
+
+        let module = netlist[mod_id].borrow();
+        let nodes = module
+            .nodes()
+            .into_iter_(&module)
+            .filter(|node_id| !module[*node_id].skip)
+            .collect::<Vec<_>>();
+
+        assert!(nodes.iter().any(|node_id| module[*node_id].is_merger()));
+        assert!(nodes.iter().any(|node_id| module[*node_id].is_splitter()));
+    }
+
+    #[test]
+    fn pass_all_bits_splitter_keeps_sym() {
+        let mut module = Module::new("test", false);
+
+        let ty = NodeTy::Unsigned(8);
+        let a = module.add_input(ty, Some("a"));
+        let b = module.add_input(ty, Some("b"));
+        let sum = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty,
+            bin_op: BinOp::Add,
+            lhs: a,
+            rhs: b,
+            sym: None,
+        });
+
+        let split = |module: &mut Module, input, sym| {
+            module.add_and_get_port::<_, Splitter>(SplitterArgs {
+                input,
+                outputs: [(ty, Some(Symbol::intern(sym)))].into_iter(),
+                start: None,
+                rev: false,
+            })
+        };
+        let named_sum = split(&mut module, sum, "sum");
+        let named_a = split(&mut module, a, "named_a");
+
+        let output = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty,
+            bin_op: BinOp::Sub,
+            lhs: named_sum,
+            rhs: named_a,
+            sym: Some(Symbol::intern("out")),
+        });
+        module.add_mod_output(output);
+
+        let mut netlist = NetList::default();
+        let mod_id = netlist.add_module(module);
+
+        transform(&netlist, mod_id);
+
+        let module = netlist[mod_id].borrow();
+        assert_eq!(module.incoming_iter(output.node).collect::<Vec<_>>(), [
+            sum, a
+        ]);
+        assert_eq!(module[sum].sym, Some(Symbol::intern("sum")));
+        // the existing name is not overwritten
+        assert_eq!(module[a].sym, Some(Symbol::intern("a")));
+    }
+
+    fn reassembly_module(permuted: bool) -> (Module, Port, Port) {
+        let mut module = Module::new("test", false);
+        let x = module.add_input(NodeTy::Unsigned(8), Some("x"));
+        let splitter = module.add::<_, Splitter>(SplitterArgs {
+            input: x,
+            outputs: [(NodeTy::Unsigned(4), None), (NodeTy::Unsigned(4), None)]
+                .into_iter(),
+            start: None,
+            rev: false,
+        });
+        let (lo, hi) = (Port::new(splitter, 0), Port::new(splitter, 1));
+        let inputs = if permuted { [lo, hi] } else { [hi, lo] };
+        let merger = module.add_and_get_port::<_, Merger>(MergerArgs {
+            inputs: inputs.into_iter(),
+            rev: false,
+            sym: None,
+        });
+        let output = module.add_and_get_port::<_, BitNot>(BitNotArgs {
+            ty: NodeTy::Unsigned(8),
+            input: merger,
+            sym: Some(Symbol::intern("out")),
+        });
+        module.add_mod_output(output);
+
+        (module, x, output)
+    }
+
+    #[test]
+    fn merger_of_splitter_in_order() {
+        for permuted in [false, true] {
+            let (module, x, output) = reassembly_module(permuted);
+
+            let mut netlist = NetList::default();
+            let mod_id = netlist.add_module(module);
+
+            transform(&netlist, mod_id);
+
+            let module = netlist[mod_id].borrow();
+            let input = module.incoming_iter(output.node).next().unwrap();
+            // `{x[7:4], x[3:0]}` folds into `x`, `{x[3:0], x[7:4]}` doesn't
+            assert_eq!(input == x, !permuted);
+            assert_eq!(module[input.node].is_merger(), permuted);
+        }
+    }
+
+    #[test]
+    fn mask_const_folding() {
+        let mut module = Module::new("test", false);
+
+        let ty = NodeTy::Unsigned(12);
+        let input = module.add_and_get_port::<_, Const>(ConstArgs {
+            ty,
+            value: 0b1010_1101_0110,
+            sym: None,
+        });
+
+        let slice = module.add_and_get_port::<_, Splitter>(SplitterArgs {
+            input,
+            outputs: [(NodeTy::Unsigned(5), None)].into_iter(),
+            start: Some(0),
+            rev: false,
+        });
+
+        let extend = module.add_and_get_port::<_, Extend>(ExtendArgs {
+            ty,
+            input: slice,
+            sym: None,
+            is_sign: false,
+        });
+
+        module.add_mod_output(extend);
+
+        let mut netlist = NetList::default();
+        let mod_id = netlist.add_module(module);
+
+        transform(&netlist, mod_id);
+
+        let module = netlist[mod_id].borrow();
+        let output = module.mod_outputs()[0];
+
+        assert_eq!(module.to_const(output), Some(ConstVal::new(0b10110, 12)));
+    }
+
+    #[test]
+    fn overlapping_splitter_const_folding() {
+        let mut module = Module::new("test", false);
+
+        let input = module.add_and_get_port::<_, Const>(ConstArgs {
+            ty: NodeTy::Unsigned(8),
+            value: 0b1011_0110,
+            sym: None,
+        });
+
+        let splitter = module.add::<_, Splitter>(SlicesArgs {
+            input,
+            outputs: [
+                (0, NodeTy::Unsigned(4), None),
+                (2, NodeTy::Unsigned(4), None),
+            ]
+            .into_iter(),
+        });
+
+        let node = module.node(splitter);
+        let NodeKind::Splitter(splitter_node) = node.kind() else {
+            unreachable!()
+        };
+        let indices = node
+            .with(splitter_node)
+            .eval_indices(&module)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(indices, [0, 2]);
+
+        module.add_mod_outputs(splitter);
+
+        let mut netlist = NetList::default();
+        let mod_id = netlist.add_module(module);
+
+        transform(&netlist, mod_id);
+
+        let module = netlist[mod_id].borrow();
+        let outputs = module
+            .mod_outputs()
+            .iter()
+            .map(|output| module.to_const(*output))
+            .collect::<Vec<_>>();
+
+        assert_eq!(outputs, [
+            Some(ConstVal::new(0b0110, 4)),
+            Some(ConstVal::new(0b1101, 4))
+        ]);
+    }
+
+    #[test]
+    fn force_no_inline() {
+        let mut netlist = NetList::default();
+
+        let mut helper = Module::new("helper", false);
+        let input = helper.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let output = helper.add_and_get_port::<_, BitNot>(BitNotArgs {
+            ty: NodeTy::Unsigned(4),
+            input,
+            sym: Some(Symbol::intern("out")),
+        });
+        helper.add_mod_output(output);
+        helper.force_no_inline = true;
+        let helper = netlist.add_module(helper);
+
+        let mut top = Module::new("top", true);
+        let input = top.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let mod_inst = top.add::<_, ModInst>(ModInstArgs {
+            module: netlist
+                .module(helper)
+                .map(|module| module.borrow())
+                .as_deref(),
+            inputs: iter::once(input),
+            outputs: iter::once(Some(Symbol::intern("out"))),
+        });
+        top.add_mod_outputs(mod_inst);
+        let top = netlist.add_module(top);
+
+        transform(&netlist, top);
+
+        let module = netlist[top].borrow();
+        assert!(module
+            .nodes()
+            .into_iter_(&module)
+            .any(|node_id| !module[node_id].skip && module[node_id].is_mod_inst()));
+    }
+
+    #[test]
+    fn inline_mod_none_keeps_closure_modules() {
+        let mut netlist = NetList::new(NetListCfg {
+            inline_mod: InlineMod::None,
+            ..Default::default()
+        });
+
+        // closures and std calls are marked to be inlined by the compiler
+        let mut closure = Module::new("top_closure", false);
+        let input = closure.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let output = closure.add_and_get_port::<_, BitNot>(BitNotArgs {
+            ty: NodeTy::Unsigned(4),
+            input,
+            sym: Some(Symbol::intern("out")),
+        });
+        closure.add_mod_output(output);
+        closure.inline = true;
+        let closure = netlist.add_module(closure);
+
+        let mut top = Module::new("top", true);
+        let input = top.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let mod_inst = top.add::<_, ModInst>(ModInstArgs {
+            module: netlist
+                .module(closure)
+                .map(|module| module.borrow())
+                .as_deref(),
+            inputs: iter::once(input),
+            outputs: iter::once(Some(Symbol::intern("out"))),
+        });
+        top.add_mod_outputs(mod_inst);
+        let top = netlist.add_module(top);
+
+        transform(&netlist, top);
+
+        let module = netlist[top].borrow();
+        assert!(!module[mod_inst].skip);
+        assert!(module[mod_inst].is_mod_inst());
+    }
+
+    #[test]
+    fn dedup_consts_after_inlining() {
+        let mut netlist = NetList::default();
+
+        let mut helper = Module::new("helper", false);
+        let input = helper.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+        let cons = helper.const_val(NodeTy::Unsigned(4), 5);
+        let output = helper.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Unsigned(4),
+            bin_op: BinOp::Add,
+            lhs: input,
+            rhs: cons,
+            sym: Some(Symbol::intern("out")),
+        });
+        helper.add_mod_output(output);
+        helper.inline = true;
+        let helper = netlist.add_module(helper);
+
+        let mut top = Module::new("top", true);
+        let input = top.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("b")));
+        let cons = top.const_val(NodeTy::Unsigned(4), 5);
+        let output = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Unsigned(4),
+            bin_op: BinOp::Sub,
+            lhs: input,
+            rhs: cons,
+            sym: Some(Symbol::intern("out")),
+        });
+        top.add_mod_output(output);
+        for idx in 0 .. 2 {
+            let input = top.add_input(
+                NodeTy::Unsigned(4),
+                Some(Symbol::intern_args(format_args!("a{idx}"))),
+            );
+            let mod_inst = top.add::<_, ModInst>(ModInstArgs {
+                module: netlist
+                    .module(helper)
+                    .map(|module| module.borrow())
+                    .as_deref(),
+                inputs: iter::once(input),
+                outputs: iter::once(Some(Symbol::intern_args(format_args!("out{idx}")))),
+            });
+            top.add_mod_outputs(mod_inst);
+        }
+        let top = netlist.add_module(top);
+
+        transform(&netlist, top);
+
+        let module = netlist[top].borrow();
+        let consts = module
+            .nodes()
+            .into_iter_(&module)
+            .filter(|node_id| !module[*node_id].skip && module[*node_id].is_const())
+            .count();
+        assert_eq!(consts, 1);
+    }
+
+    #[test]
+    fn dedup_consts_of_different_widths() {
+        let mut netlist = NetList::default();
+
+        let mut top = Module::new("top", true);
+        for width in 1 ..= 16 {
+            let ty = NodeTy::Unsigned(width);
+            let input =
+                top.add_input(ty, Some(Symbol::intern_args(format_args!("a{width}"))));
+            // every value is added twice, so there is one duplicate per (width, value)
+            for val in (0 .. 8).chain(0 .. 8) {
+                let cons = top.const_val(ty, val);
+                let output = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                    ty,
+                    bin_op: BinOp::Add,
+                    lhs: input,
+                    rhs: cons,
+                    sym: None,
+                });
+                top.add_mod_output(output);
+            }
+        }
+        let top = netlist.add_module(top);
+
+        transform(&netlist, top);
+
+        let module = netlist[top].borrow();
+        let consts = module
+            .nodes()
+            .into_iter_(&module)
+            .filter(|node_id| !module[*node_id].skip && module[*node_id].is_const())
+            .count();
+        // values are truncated to 1 and 2 bits for the narrowest constants
+        assert_eq!(consts, 2 + 4 + 14 * 8);
+    }
+
+    fn cmp_with_const_module(
+        bin_op: BinOp,
+        var: impl Fn(&mut Module) -> Port,
+        val: u128,
+    ) -> (NetList, ModuleId, Port) {
+        let mut netlist = NetList::default();
+
+        let mut top = Module::new("top", true);
+        let var = var(&mut top);
+        let cons = top.const_val(top[var].ty, val);
+        let output = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Bit,
+            bin_op,
+            lhs: var,
+            rhs: cons,
+            sym: Some(Symbol::intern("out")),
+        });
+        top.add_mod_output(output);
+        let top = netlist.add_module(top);
+
+        transform(&netlist, top);
+
+        let output = netlist[top].borrow().mod_outputs()[0];
+        (netlist, top, output)
+    }
+
+    #[test]
+    fn fold_eq_zero_to_nor() {
+        let (netlist, top, output) = cmp_with_const_module(
+            BinOp::Eq,
+            |top| top.add_input(NodeTy::Unsigned(8), Some(Symbol::intern("a"))),
+            0,
+        );
+
+        let module = netlist[top].borrow();
+        match module[output.node].kind() {
+            NodeKind::Reduce(reduce) => assert_eq!(reduce.op, ReduceOp::Nor),
+            kind => panic!("expected reduce, got {kind:?}"),
+        }
+        assert_eq!(module.incoming_iter(output.node).collect::<Vec<_>>(), [
+            module.mod_inputs()[0]
+        ]);
+    }
+
+    #[test]
+    fn fold_cmp_with_known_bits() {
+        // {a, 2'b10} == 6'b111110 => &a
+        let known_bits = |top: &mut Module| {
+            let a = top.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("a")));
+            let cons = top.const_val(NodeTy::Unsigned(2), 0b10);
+            top.add_and_get_port::<_, Merger>(MergerArgs {
+                inputs: [a, cons],
+                rev: false,
+                sym: None,
+            })
+        };
+
+        let (netlist, top, output) =
+            cmp_with_const_module(BinOp::Eq, known_bits, 0b111110);
+        let module = netlist[top].borrow();
+        match module[output.node].kind() {
+            NodeKind::Reduce(reduce) => assert_eq!(reduce.op, ReduceOp::And),
+            kind => panic!("expected reduce, got {kind:?}"),
+        }
+        assert_eq!(module.incoming_iter(output.node).collect::<Vec<_>>(), [
+            module.mod_inputs()[0]
+        ]);
+
+        // known bits don't match
+        let (netlist, top, output) =
+            cmp_with_const_module(BinOp::Ne, known_bits, 0b111111);
+        let module = netlist[top].borrow();
+        assert_eq!(module.to_const(output), Some(ConstVal::new(1, 1)));
+    }
+
+    #[test]
+    fn fold_out_of_range_shifts() {
+        for bin_op in [BinOp::Sll, BinOp::Slr, BinOp::Sra] {
+            for amount in [8, 100] {
+                let mut netlist = NetList::default();
+
+                let mut top = Module::new("top", true);
+                let b = top.add_input(NodeTy::Unsigned(8), Some(Symbol::intern("b")));
+                let lhs = top.const_val(NodeTy::Unsigned(8), 0x25);
+                let rhs = top.const_val(NodeTy::Unsigned(7), amount);
+                let shift = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                    ty: NodeTy::Unsigned(8),
+                    bin_op,
+                    lhs,
+                    rhs,
+                    sym: None,
+                });
+                let output = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                    ty: NodeTy::Unsigned(8),
+                    bin_op: BinOp::Add,
+                    lhs: shift,
+                    rhs: b,
+                    sym: Some(Symbol::intern("out")),
+                });
+                top.add_mod_output(output);
+                let top = netlist.add_module(top);
+
+                transform(&netlist, top);
+
+                let module = netlist[top].borrow();
+                let lhs = module.incoming_iter(output.node).next().unwrap();
+                assert_eq!(module.to_const(lhs), Some(ConstVal::new(0, 8)));
+            }
+        }
+    }
+
+    #[test]
+    fn fold_logical_ops() {
+        for (bin_op, lhs, rhs, res) in [
+            (BinOp::And, 1, 0, 0),
+            (BinOp::And, 1, 1, 1),
+            (BinOp::Or, 1, 0, 1),
+            (BinOp::Or, 0, 0, 0),
+        ] {
+            let mut netlist = NetList::default();
+
+            let mut top = Module::new("top", true);
+            let b = top.add_input(NodeTy::Bit, Some(Symbol::intern("b")));
+            let lhs = top.const_val(NodeTy::Bit, lhs);
+            let rhs = top.const_val(NodeTy::Bit, rhs);
+            let op = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                ty: NodeTy::Bit,
+                bin_op,
+                lhs,
+                rhs,
+                sym: None,
+            });
+            let output = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                ty: NodeTy::Bit,
+                bin_op: BinOp::BitXor,
+                lhs: op,
+                rhs: b,
+                sym: Some(Symbol::intern("out")),
+            });
+            top.add_mod_output(output);
+            let top = netlist.add_module(top);
+
+            transform(&netlist, top);
+
+            let module = netlist[top].borrow();
+            let lhs = module.incoming_iter(output.node).next().unwrap();
+            assert_eq!(
+                module.to_const(lhs),
+                Some(ConstVal::new(res, 1)),
+                "{bin_op:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn fold_double_negation() {
+        let mut netlist = NetList::default();
+
+        // !!x + b
+        let mut top = Module::new("top", true);
+        let x = top.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("x")));
+        let b = top.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("b")));
+        let inner = top.add_and_get_port::<_, BitNot>(BitNotArgs {
+            ty: NodeTy::Unsigned(4),
+            input: x,
+            sym: None,
+        });
+        let outer = top.add_and_get_port::<_, BitNot>(BitNotArgs {
+            ty: NodeTy::Unsigned(4),
+            input: inner,
+            sym: None,
+        });
+        let output = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty: NodeTy::Unsigned(4),
+            bin_op: BinOp::Add,
+            lhs: outer,
+            rhs: b,
+            sym: Some(Symbol::intern("out")),
+        });
+        top.add_mod_output(output);
+        let top = netlist.add_module(top);
+
+        transform(&netlist, top);
+
+        let module = netlist[top].borrow();
+        assert!(module[inner.node].skip);
+        assert!(module[outer.node].skip);
+        assert_eq!(module.incoming_iter(output.node).collect::<Vec<_>>(), [
+            x, b
+        ]);
+    }
+
+    #[test]
+    fn select_tuple_case_with_wildcards() {
+        // match (a, b, c) { (0, _, 1) => x0, (1, 2, _) => x1, (_, 3, 3) => x2, _ => x3 }
+        let val = |val| Case::Val(ConstVal::new(val, 4));
+        let any = Case::Default(4);
+        let cases = [
+            TupleCase([val(0), any, val(1)].into_iter().collect()),
+            TupleCase([val(1), val(2), any].into_iter().collect()),
+            TupleCase([any, val(3), val(3)].into_iter().collect()),
+        ];
+
+        for ((a, b_val, c), expected) in [
+            ((0, 9, 1), 0),
+            ((1, 2, 7), 1),
+            ((5, 3, 3), 2),
+            ((0, 3, 3), 2),
+            ((0, 2, 2), 3),
+        ] {
+            let mut netlist = NetList::default();
+
+            let mut top = Module::new("top", true);
+            let inputs = (0 .. 4)
+                .map(|idx| {
+                    top.add_input(
+                        NodeTy::Unsigned(4),
+                        Some(Symbol::intern_args(format_args!("x{idx}"))),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let b = top.add_input(NodeTy::Unsigned(4), Some(Symbol::intern("b")));
+            let sel = top.const_val(NodeTy::BitVec(12), (a << 8) | (b_val << 4) | c);
+            let mux = top.add_and_get_port::<_, Switch>(SwitchArgs {
+                outputs: iter::once((NodeTy::Unsigned(4), None)),
+                sel,
+                variants: cases
+                    .iter()
+                    .cloned()
+                    .zip(inputs.iter().map(|input| iter::once(*input))),
+                default: Some(iter::once(inputs[3])),
+            });
+            let output = top.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+                ty: NodeTy::Unsigned(4),
+                bin_op: BinOp::Add,
+                lhs: mux,
+                rhs: b,
+                sym: Some(Symbol::intern("out")),
+            });
+            top.add_mod_output(output);
+            let top = netlist.add_module(top);
+
+            transform(&netlist, top);
+
+            let module = netlist[top].borrow();
+            assert_eq!(module.incoming_iter(output.node).collect::<Vec<_>>(), [
+                inputs[expected],
+                b
+            ]);
+        }
+    }
+
+    fn dead_async_rst_module() -> Module {
+        let mut module = Module::new("top", true);
+        let clk = module.add_input(NodeTy::Clock, Some("clk"));
+        let rst = module.add_input(NodeTy::Bit, Some("rst"));
+        let dead_rst = module.const_val(NodeTy::Bit, 0);
+        let init = module.const_val(NodeTy::Unsigned(4), 0);
+
+        for (sym, rst, rst_kind, line) in [
+            ("dead", dead_rst, SyncKind::Async, 4),
+            ("live", rst, SyncKind::Async, 5),
+            ("sync", dead_rst, SyncKind::Sync, 6),
+        ] {
+            let data = module.add_input(NodeTy::Unsigned(4), None::<Symbol>);
+            let dff = module.add_and_get_port::<_, DFF>(DFFArgs {
+                clk,
+                rst: Some(rst),
+                rst_kind,
+                rst_pol: Polarity::ActiveHigh,
+                en: None,
+                init,
+                rst_val: None,
+                data: TyOrData::Data(data),
+                sym: Some(Symbol::intern(sym)),
+            });
+            module.add_span(dff.node, Some(format!("src/lib.rs: {line}")));
+            module.add_mod_output(dff);
+        }
+
+        module
+    }
+
+    #[test]
+    fn lint_dead_async_rst() {
+        let mut netlist = NetList::new(NetListCfg {
+            lint_async_rst: true,
+            ..Default::default()
+        });
+        netlist.add_module(dead_async_rst_module());
+        netlist.transform();
+
+        // only the async reset folded away is reported, at the span of the register
+        assert_eq!(netlist.warnings(), [Warning {
+            msg: "reset of register `dead` in async-reset domain is never asserted and \
+                  is removed"
+                .into(),
+            span: Some("src/lib.rs: 4".into()),
+        }]);
+
+        let mut netlist = NetList::default();
+        netlist.add_module(dead_async_rst_module());
+        netlist.transform();
+
+        assert!(netlist.warnings().is_empty());
+    }
+
+    #[test]
+    fn gated_clock_to_enable() {
+        let mut netlist = NetList::default();
+
+        let mut top = Module::new("top", true);
+        let clk = top.add_input(NodeTy::Clock, Some("clk"));
+        let rst = top.add_input(NodeTy::Bit, Some("rst"));
+        let en = top.add_input(NodeTy::Bit, Some("en"));
+        let init = top.const_val(NodeTy::Unsigned(4), 0);
+        let gclk = top.add_and_get_port::<_, ClockGate>(ClockGateArgs {
+            clk,
+            en,
+            sym: Some(Symbol::intern("gclk")),
+        });
+
+        let mut dffs = vec![];
+        for (rst_kind, rst_pol) in [
+            (SyncKind::Sync, Polarity::ActiveHigh),
+            (SyncKind::Sync, Polarity::ActiveLow),
+            (SyncKind::Async, Polarity::ActiveHigh),
+        ] {
+            let data = top.add_input(NodeTy::Unsigned(4), None::<Symbol>);
+            let dff = top.add_and_get_port::<_, DFF>(DFFArgs {
+                clk: gclk,
+                rst: Some(rst),
+                rst_kind,
+                rst_pol,
+                en: None,
+                init,
+                rst_val: None,
+                data: TyOrData::Data(data),
+                sym: None,
+            });
+            top.add_mod_output(dff);
+            dffs.push(dff);
+        }
+        let top = netlist.add_module(top);
+
+        transform(&netlist, top);
+
+        let module = netlist[top].borrow();
+        let dffs = module
+            .mod_outputs()
+            .iter()
+            .map(|dff| {
+                let node = module.node(dff.node);
+                node.with(node.dff().unwrap()).inputs(&module)
+            })
+            .collect::<Vec<_>>();
+
+        for dff in &dffs {
+            assert_eq!(dff.clk, clk);
+            assert_eq!(dff.en, Some(en));
+        }
+
+        // the closed gate blocks the synchronous resets only
+        let rst_and = dffs[0].rst.unwrap();
+        assert!(matches!(
+            module[rst_and.node].kind(),
+            NodeKind::BinOp(bin_op) if bin_op.bin_op == BinOp::BitAnd
+        ));
+        assert_eq!(module.incoming_iter(rst_and.node).collect::<Vec<_>>(), [
+            rst, en
+        ]);
+
+        let rst_or = dffs[1].rst.unwrap();
+        assert!(matches!(
+            module[rst_or.node].kind(),
+            NodeKind::BinOp(bin_op) if bin_op.bin_op == BinOp::BitOr
+        ));
+        let gate_closed = module.incoming_iter(rst_or.node).nth(1).unwrap();
+        assert!(matches!(
+            module[gate_closed.node].kind(),
+            NodeKind::BitNot(_)
+        ));
+        assert_eq!(
+            module.incoming_iter(gate_closed.node).collect::<Vec<_>>(),
+            [en]
+        );
+
+        assert_eq!(dffs[2].rst, Some(rst));
+    }
+
+    fn max_plus_one(ty: NodeTy, max: u128) -> Module {
+        let mut module = Module::new("top", true);
+        let lhs = module.const_val(ty, max);
+        let rhs = module.const_val(ty, 1);
+        let sum = module.add_and_get_port::<_, BinOpNode>(BinOpArgs {
+            ty,
+            bin_op: BinOp::Add,
+            lhs,
+            rhs,
+            sym: None,
+        });
+        module.add_mod_output(sum);
+
+        module
+    }
+
+    #[test]
+    fn const_overflow_policy() {
+        for (const_overflow, ty, max, expected) in [
+            (ConstOverflow::Wrap, NodeTy::Unsigned(8), 255, Some(0)),
+            (ConstOverflow::Wrap, NodeTy::Signed(8), 127, Some(128)),
+            (ConstOverflow::Saturate, NodeTy::Unsigned(8), 255, Some(255)),
+            (ConstOverflow::Saturate, NodeTy::Signed(8), 127, Some(127)),
+            (ConstOverflow::Error, NodeTy::Unsigned(8), 255, None),
+        ] {
+            let mut netlist = NetList::new(NetListCfg {
+                const_overflow,
+                ..Default::default()
+            });
+            let mod_id = netlist.add_module(max_plus_one(ty, max));
+            netlist.transform();
+
+            let module = netlist[mod_id].borrow();
+            let output = module.mod_outputs()[0];
+            assert_eq!(
+                module.to_const(output).map(|cons| cons.val()),
+                expected,
+                "{const_overflow:?} {ty:?}"
+            );
+
+            if expected.is_none() {
+                assert!(matches!(module[output.node].kind(), NodeKind::BinOp(_)));
+                assert_eq!(netlist.errors(), [
+                    "constant expression `8'd255 + 8'd1` overflows (`--const-overflow \
+                     error`)"
+                ]);
+            } else {
+                assert!(netlist.errors().is_empty());
+            }
+        }
+    }
 }
@@ -1,20 +1,27 @@
-use std::{array, io};
+use core::{array, ops::ControlFlow};
+#[cfg(feature = "std")]
+use std::io;
 
 use fhdl_macros::{blackbox, synth};
-use smallvec::SmallVec;
+#[cfg(feature = "std")]
 use vcd::IdCode;
 
 use crate::{
+    bit::Bit,
     bitpack::{BitPack, BitSize, BitVec, IsPacked},
-    bundle::{Bundle, Unbundle},
     cast::{Cast, CastFrom},
     const_functions::idx_range_len,
     const_helpers::{Assert, ConstConstr, IsTrue},
+    index::{idx_constr, Idx},
+    signal::SignalValue,
+};
+#[cfg(feature = "std")]
+use crate::{
+    bundle::{Bundle, Unbundle},
     domain::ClockDomain,
     eval::{Eval, EvalCtx},
-    index::{idx_constr, Idx},
     new_hdl::{State, StateMut},
-    signal::{Signal, SignalValue},
+    signal::Signal,
     trace::{TraceVars, Traceable, Tracer},
 };
 
@@ -50,12 +57,12 @@ where
         let mask: <T as BitPack>::Packed = ((1_usize << width) - 1_usize).cast();
         let mut offset = (N - 1) * width;
 
-        array_from_iter((0 .. N).map(|_| {
+        array::from_fn(|_| {
             let slice = (bitvec.clone() >> offset).cast::<<T as BitPack>::Packed>()
                 & mask.clone();
             offset = offset.saturating_sub(width);
             T::unpack(slice)
-        }))
+        })
     }
 }
 
@@ -102,6 +109,14 @@ pub trait ArrayExt<const N: usize, T>: Sized {
         Self::make(move || val.clone())
     }
 
+    #[synth(inline)]
+    fn default_() -> [T; N]
+    where
+        T: Default,
+    {
+        Self::make(T::default)
+    }
+
     #[blackbox(ArrayMap)]
     fn map_<U>(self, f: impl Fn(T) -> U) -> [U; N];
 
@@ -110,6 +125,60 @@ pub trait ArrayExt<const N: usize, T>: Sized {
     where
         ConstConstr<{ idx_constr(N) }>:;
 
+    // A constant `k` is a rewiring of items, a runtime one is lowered to a mux
+    // over the `N` possible rotations
+    #[blackbox(ArrayRotateLeft)]
+    fn rotate_left(self, k: usize) -> [T; N];
+
+    #[blackbox(ArrayRotateRight)]
+    fn rotate_right(self, k: usize) -> [T; N];
+
+    // Splits the array into the first item and the rest (empty for a single-item array)
+    #[blackbox(ArraySplitFirst)]
+    fn split_first(self) -> (T, [T; N - 1])
+    where
+        Assert<{ N > 0 }>: IsTrue;
+
+    // Splits the array into the last item and the rest (empty for a single-item array)
+    #[blackbox(ArraySplitLast)]
+    fn split_last(self) -> ([T; N - 1], T)
+    where
+        Assert<{ N > 0 }>: IsTrue;
+
+    // Returns the index of the first (lowest-index) item satisfying `pred`
+    #[blackbox(ArrayPosition)]
+    fn position<F: Fn(T) -> bool>(self, pred: F) -> Option<Idx<N>>
+    where
+        ConstConstr<{ idx_constr(N) }>:;
+
+    // Folds the items while `f` returns `Continue`, the first `Break` is the result.
+    // Only the behavioral model stops at the first break: hardware evaluates `f` for
+    // all items in parallel and the first break wins in a priority select
+    #[synth(inline)]
+    fn try_fold<B, Acc>(
+        self,
+        init: Acc,
+        f: impl Fn(Acc, T) -> ControlFlow<B, Acc>,
+    ) -> ControlFlow<B, Acc>
+    where
+        Self: IntoIterator<Item = T>,
+    {
+        let mut res = ControlFlow::Continue(init);
+        for item in self {
+            res = match res {
+                ControlFlow::Continue(acc) => f(acc, item),
+                res => res,
+            };
+        }
+        res
+    }
+
+    #[blackbox(ArrayAll)]
+    fn all<F: Fn(T) -> Bit>(self, pred: F) -> Bit;
+
+    #[blackbox(ArrayAny)]
+    fn any<F: Fn(T) -> Bit>(self, pred: F) -> Bit;
+
     #[blackbox(ArrayMake)]
     fn make(f: impl Fn() -> T) -> [T; N];
 
@@ -117,6 +186,11 @@ pub trait ArrayExt<const N: usize, T>: Sized {
     fn make_idx(f: impl Fn(Idx<N>) -> T) -> [T; N]
     where
         ConstConstr<{ idx_constr(N) }>:;
+
+    // Collects exactly `N` items of the iterator into an array (panics if the iterator yields
+    // fewer or more items)
+    #[blackbox(ArrayFromIterExact)]
+    fn from_iter_exact(iter: impl IntoIterator<Item = T>) -> [T; N];
 }
 
 impl<const N: usize, T> ArrayExt<N, T> for [T; N] {
@@ -136,66 +210,137 @@ impl<const N: usize, T> ArrayExt<N, T> for [T; N] {
     {
         let idx = idx.val().cast::<usize>();
 
-        array_from_iter::<T, M>(self[idx .. (idx + M)].iter().cloned())
+        array::from_fn(|offset| self[idx + offset].clone())
     }
 
     fn map_<U>(self, f: impl Fn(T) -> U) -> [U; N] {
-        array_from_iter(self.into_iter().map(f))
+        self.map(f)
     }
 
     fn map_idx<U>(self, f: impl Fn(Idx<N>, T) -> U) -> [U; N]
     where
         ConstConstr<{ idx_constr(N) }>:,
     {
-        array_from_iter(self.into_iter().enumerate().map(|(idx, item)| {
-            let idx = unsafe { Idx::from_usize(idx) };
-            f(idx, item)
-        }))
+        let mut idx = 0;
+        self.map(|item| {
+            let res = f(unsafe { Idx::from_usize(idx) }, item);
+            idx += 1;
+            res
+        })
+    }
+
+    fn rotate_left(mut self, k: usize) -> [T; N] {
+        if N != 0 {
+            self.as_mut_slice().rotate_left(k % N);
+        }
+        self
+    }
+
+    fn rotate_right(mut self, k: usize) -> [T; N] {
+        if N != 0 {
+            self.as_mut_slice().rotate_right(k % N);
+        }
+        self
+    }
+
+    fn split_first(self) -> (T, [T; N - 1])
+    where
+        Assert<{ N > 0 }>: IsTrue,
+    {
+        let mut items = self.into_iter();
+        let first = items.next().unwrap();
+        (first, array::from_fn(|_| items.next().unwrap()))
+    }
+
+    fn split_last(self) -> ([T; N - 1], T)
+    where
+        Assert<{ N > 0 }>: IsTrue,
+    {
+        let mut items = self.into_iter();
+        let rest = array::from_fn(|_| items.next().unwrap());
+        (rest, items.next().unwrap())
+    }
+
+    fn position<F: Fn(T) -> bool>(self, pred: F) -> Option<Idx<N>>
+    where
+        ConstConstr<{ idx_constr(N) }>:,
+    {
+        self.into_iter()
+            .position(pred)
+            .map(|idx| unsafe { Idx::from_usize(idx) })
+    }
+
+    fn all<F: Fn(T) -> Bit>(self, pred: F) -> Bit {
+        self.into_iter().all(pred)
+    }
+
+    fn any<F: Fn(T) -> Bit>(self, pred: F) -> Bit {
+        self.into_iter().any(pred)
     }
 
     fn make(f: impl Fn() -> T) -> [T; N] {
-        array_from_iter((0 .. N).map(|_| f()))
+        array::from_fn(|_| f())
     }
 
     fn make_idx(f: impl Fn(Idx<N>) -> T) -> [T; N]
     where
         ConstConstr<{ idx_constr(N) }>:,
     {
-        array_from_iter((0 .. N).map(|idx| {
+        array::from_fn(|idx| {
             let idx = unsafe { Idx::from_usize(idx) };
             f(idx)
-        }))
+        })
+    }
+    fn from_iter_exact(iter: impl IntoIterator<Item = T>) -> [T; N] {
+        let mut iter = iter.into_iter();
+        let mut len = 0;
+        let items = array::from_fn(|_| match iter.next() {
+            Some(item) => {
+                len += 1;
+                item
+            }
+            None => panic!("iterator yields {len} items, but {N} are expected"),
+        });
+        let rest = iter.count();
+        if rest > 0 {
+            panic!("iterator yields {} items, but {N} are expected", N + rest);
+        }
+
+        items
     }
 }
 
+#[cfg(feature = "std")]
 impl<const N: usize, D: ClockDomain, T: SignalValue> Unbundle for Signal<D, [T; N]> {
     type Unbundled = [Signal<D, T>; N];
 
     fn unbundle(self) -> Self::Unbundled {
-        array_from_iter((0 .. N).map(|ind| self.clone().map(move |s| s[ind].clone())))
+        array::from_fn(|ind| self.clone().map(move |s| s[ind].clone()))
     }
 }
 
+#[cfg(feature = "std")]
 impl<const N: usize, D: ClockDomain, T: SignalValue> Bundle for [Signal<D, T>; N] {
     type Bundled = Signal<D, [T; N]>;
 
     fn bundle(mut self) -> Self::Bundled {
-        Signal::new(move |ctx| {
-            array_from_iter(self.iter_mut().map(|signal| signal.next(ctx)))
-        })
+        Signal::new(move |ctx| array::from_fn(|ind| self[ind].next(ctx)))
     }
 }
 
+#[cfg(feature = "std")]
 impl<const N: usize, D: ClockDomain, T: SignalValue> Eval<D> for [Signal<D, T>; N] {
     type Value = [T; N];
 
     fn next(&mut self, ctx: &mut EvalCtx) -> Self::Value {
-        array_from_iter((0 .. N).map(|ind| self[ind].next(ctx)))
+        array::from_fn(|ind| self[ind].next(ctx))
     }
 }
 
+#[cfg(feature = "std")]
 impl<T: StateMut, const N: usize> StateMut for [T; N] {}
 
+#[cfg(feature = "std")]
 impl<T: State, const N: usize> State for [T; N] {
     type Mut<'a> = [<T as State>::Mut<'a>; N]
     where
@@ -206,20 +351,11 @@ impl<T: State, const N: usize> State for [T; N] {
     }
 
     fn as_mut(&mut self) -> Self::Mut<'_> {
-        array_from_iter(self.iter_mut().map(|item| item.as_mut()))
-    }
-}
-
-fn array_from_iter<T, const N: usize>(it: impl Iterator<Item = T>) -> Array<N, T> {
-    let v = it.into_iter().collect::<SmallVec<[T; N]>>();
-    assert_eq!(v.len(), N);
-
-    match v.into_inner() {
-        Ok(a) => a,
-        Err(_) => unreachable!(),
+        self.each_mut().map(|item| item.as_mut())
     }
 }
 
+#[cfg(feature = "std")]
 impl<const N: usize, T: Traceable> Traceable for Array<N, T>
 where
     Assert<{ N > 0 }>: IsTrue,
@@ -246,9 +382,10 @@ where
 mod tests {
     use super::*;
     use crate::{
-        bit::{Bit, H, L},
+        bit::{H, L},
         domain::{Clock, TD4},
         signal::SignalIterExt,
+        unsigned::U,
     };
 
     #[test]
@@ -256,6 +393,57 @@ mod tests {
         assert_eq!([3, 2, 1, 0].slice::<2>(1.cast()), [2, 1]);
     }
 
+    #[test]
+    fn split_first_last() {
+        let a: [U<4>; 4] = [3_u8, 7, 1, 5].map(Cast::cast);
+
+        let (head, tail) = a.clone().split_first();
+        assert_eq!(head, U::<4>::cast_from(3_u8));
+        assert_eq!(tail, [7_u8, 1, 5].map(Cast::cast::<U<4>>));
+        let [b, c, d] = tail;
+        assert_eq!([head, b, c, d], a);
+
+        let (rest, last) = a.clone().split_last();
+        assert_eq!(last, U::<4>::cast_from(5_u8));
+        assert_eq!(rest, [3_u8, 7, 1].map(Cast::cast::<U<4>>));
+
+        assert_eq!([1_u8].split_first(), (1, []));
+        assert_eq!([1_u8].split_last(), ([], 1));
+    }
+
+    #[test]
+    fn position() {
+        let a: [U<4>; 5] = [3_u8, 7, 1, 7, 0].map(Cast::cast);
+
+        let pos = |v: u8| {
+            a.clone()
+                .position(|x| x == U::<4>::cast_from(v))
+                .map(|idx| idx.val().cast::<u8>())
+        };
+
+        assert_eq!(pos(3), Some(0));
+        // the lowest index wins
+        assert_eq!(pos(7), Some(1));
+        assert_eq!(pos(0), Some(4));
+        assert_eq!(pos(5), None);
+    }
+
+    #[test]
+    fn all_any() {
+        let zero = U::<4>::cast_from(0_u8);
+        let a: [U<4>; 4] = [3_u8, 7, 1, 2].map(Cast::cast);
+        let b: [U<4>; 4] = [3_u8, 0, 1, 2].map(Cast::cast);
+
+        assert!(a.clone().all(|x| x > zero));
+        assert!(!a.any(|x| x == zero));
+        assert!(!b.clone().all(|x| x > zero));
+        assert!(b.any(|x| x == zero));
+
+        let empty: [U<4>; 0] = [];
+        assert!(empty.clone().all(|x| x > zero));
+        assert!(!empty.any(|x| x == zero));
+    }
+
     #[test]
     fn unbundle() {
         let clk = Clock::<TD4>::new();
@@ -323,4 +511,19 @@ mod tests {
         let idx = idx.succ();
         assert_eq!(s.idx(idx.clone()), 4);
     }
+
+    #[test]
+    fn array_default() {
+        let s = Array::<4, U<3>>::default_();
+
+        assert_eq!(s, [0_u8, 0, 0, 0].map_(U::<3>::cast_from));
+        assert_eq!(s.pack(), BitVec::<12>::zero());
+    }
+
+    #[test]
+    fn rotate() {
+        assert_eq!([0, 1, 2, 3].rotate_left(1), [1, 2, 3, 0]);
+        assert_eq!([0, 1, 2, 3].rotate_right(1), [3, 0, 1, 2]);
+        assert_eq!([0, 1, 2, 3].rotate_left(6), [2, 3, 0, 1]);
+    }
 }
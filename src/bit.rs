@@ -1,13 +1,16 @@
+#[cfg(feature = "std")]
 use std::io;
 
 use fhdl_macros::blackbox;
+#[cfg(feature = "std")]
 use vcd::IdCode;
 
+#[cfg(feature = "std")]
+use crate::trace::{bool_to_vcd, TraceTy, TraceVars, Traceable, Tracer};
 use crate::{
     bitpack::{BitPack, BitSize, BitVec},
     cast::{Cast, CastFrom},
     signal::SignalValue,
-    trace::{bool_to_vcd, TraceTy, TraceVars, Traceable, Tracer},
 };
 
 pub type Bit = bool;
@@ -44,6 +47,7 @@ impl BitPack for Bit {
 pub const H: Bit = true;
 pub const L: Bit = false;
 
+#[cfg(feature = "std")]
 impl Traceable for Bit {
     #[inline]
     fn add_vars(vars: &mut TraceVars) {
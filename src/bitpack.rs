@@ -1,25 +1,34 @@
-use std::{
+use core::{
+    array,
     marker::PhantomData,
     ops::{BitAnd, BitOr, Shl, Shr},
 };
 
 pub use fhdl_macros::BitPack;
-use fhdl_macros::{blackbox, synth};
+use fhdl_macros::{blackbox, lang_item, synth};
 
 use crate::{
     bit::Bit,
     cast::{Cast, CastFrom},
     const_functions::{assert_extend, assert_in_range, idx_range_len},
-    const_helpers::ConstConstr,
+    const_helpers::{Assert, ConstConstr, IsTrue},
     index::{idx_constr, Idx},
     signed::S,
     unsigned::U,
 };
 
+#[lang_item(BitSize)]
 pub trait BitSize: Sized {
+    #[lang_item(BitSizeBits)]
     const BITS: usize;
 }
 
+// The width of the type in the synthesized netlist
+#[synth(inline)]
+pub const fn bit_width<T: BitSize>() -> usize {
+    T::BITS
+}
+
 pub trait IsPacked:
     Sized
     + Clone
@@ -47,6 +56,109 @@ impl<const N: usize> BitVec<N> {
     pub fn unpack<T: BitPack<Packed = Self>>(self) -> T {
         T::unpack(self)
     }
+
+    #[blackbox(BitVecMask)]
+    pub fn mask<const K: usize>(self) -> Self
+    where
+        ConstConstr<{ assert_in_range(N, 0, K) }>:,
+    {
+        self.slice_::<K>(0).cast()
+    }
+
+    // Bits of `self` go to the even positions, bits of `other` to the odd ones
+    #[blackbox(BitVecInterleave)]
+    pub fn interleave(self, other: BitVec<N>) -> BitVec<{ 2 * N }>
+    where
+        ConstConstr<{ 2 * N }>:,
+    {
+        let mut res = BitVec::<{ 2 * N }>::zero();
+        for idx in (0 .. N).rev() {
+            res = (res << 2_usize)
+                | (BitVec::cast_from(other.bit_(idx)) << 1_usize)
+                | BitVec::cast_from(self.bit_(idx));
+        }
+        res
+    }
+
+    // Splits into the even and the odd bits, the inverse of `interleave`
+    #[blackbox(BitVecDeinterleave)]
+    pub fn deinterleave(self) -> (BitVec<{ N / 2 }>, BitVec<{ N / 2 }>)
+    where
+        Assert<{ N % 2 == 0 }>: IsTrue,
+        ConstConstr<{ N / 2 }>:,
+    {
+        let mut even = BitVec::<{ N / 2 }>::zero();
+        let mut odd = BitVec::<{ N / 2 }>::zero();
+        for idx in (0 .. N / 2).rev() {
+            even = (even << 1_usize) | BitVec::cast_from(self.bit_(2 * idx));
+            odd = (odd << 1_usize) | BitVec::cast_from(self.bit_(2 * idx + 1));
+        }
+        (even, odd)
+    }
+
+    // XOR of all bits, high if the number of set bits is odd
+    #[blackbox(BitVecParity)]
+    pub fn parity(self) -> Bit {
+        (0 .. N).fold(false, |parity, idx| parity ^ self.bit_(idx))
+    }
+
+    // Advances a CRC (LFSR) register by one input bit: the register is shifted to the
+    // left, and the bits set in `POLY` (without the implicit top term) are XORed with
+    // the feedback bit, which is the shifted out MSB XOR `data_bit`
+    #[blackbox(BitVecCrcStep)]
+    pub fn crc_step<const POLY: u128>(self, data_bit: Bit) -> Self {
+        if N == 0 {
+            return self;
+        }
+
+        let fb = self.bit_(N - 1) ^ data_bit;
+        let shifted = self << 1_usize;
+        if fb {
+            shifted ^ Self::cast_from(POLY)
+        } else {
+            shifted
+        }
+    }
+
+    // Splits into bytes starting from the least significant one
+    #[blackbox(BitVecToLeBytes)]
+    pub fn to_le_bytes(self) -> [U<8>; N / 8]
+    where
+        Assert<{ N % 8 == 0 }>: IsTrue,
+    {
+        array::from_fn(|idx| self.slice_::<8>(8 * idx))
+    }
+
+    // Splits into bytes starting from the most significant one
+    #[blackbox(BitVecToBeBytes)]
+    pub fn to_be_bytes(self) -> [U<8>; N / 8]
+    where
+        Assert<{ N % 8 == 0 }>: IsTrue,
+    {
+        array::from_fn(|idx| self.slice_::<8>(N - 8 * (idx + 1)))
+    }
+
+    // The inverse of `to_le_bytes`
+    #[blackbox(BitVecFromLeBytes)]
+    pub fn from_le_bytes(bytes: [U<8>; N / 8]) -> Self
+    where
+        Assert<{ N % 8 == 0 }>: IsTrue,
+    {
+        bytes.into_iter().rev().fold(Self::zero(), |res, byte| {
+            (res << 8_usize) | byte.cast::<BitVec<N>>()
+        })
+    }
+
+    // The inverse of `to_be_bytes`
+    #[blackbox(BitVecFromBeBytes)]
+    pub fn from_be_bytes(bytes: [U<8>; N / 8]) -> Self
+    where
+        Assert<{ N % 8 == 0 }>: IsTrue,
+    {
+        bytes.into_iter().fold(Self::zero(), |res, byte| {
+            (res << 8_usize) | byte.cast::<BitVec<N>>()
+        })
+    }
 }
 
 pub trait BitPack: BitSize {
@@ -81,6 +193,37 @@ impl<T> BitPack for PhantomData<T> {
     }
 }
 
+impl<T: BitSize> BitSize for Option<T> {
+    const BITS: usize = T::BITS + 1;
+}
+
+// The valid bit is placed in the MSB, the payload of `None` is zero
+impl<T> BitPack for Option<T>
+where
+    T: BitPack<Packed = BitVec<{ T::BITS }>>,
+    [(); T::BITS + 1]:,
+{
+    type Packed = BitVec<{ T::BITS + 1 }>;
+
+    fn pack(self) -> Self::Packed {
+        match self {
+            Some(value) => {
+                (Self::Packed::cast_from(1_u8) << T::BITS)
+                    | value.pack().cast::<Self::Packed>()
+            }
+            None => Self::Packed::zero(),
+        }
+    }
+
+    fn unpack(packed: Self::Packed) -> Self {
+        if packed.bit_(T::BITS) {
+            Some(T::unpack(packed.cast()))
+        } else {
+            None
+        }
+    }
+}
+
 pub trait BitPackExt<const N: usize>: BitPack<Packed = BitVec<N>> + Clone {
     #[synth(inline)]
     #[inline]
@@ -192,7 +335,8 @@ mod tests {
     use crate::{
         array::Array,
         bit::{Bit, H, L},
-        cast::Cast,
+        bitpack::BitSize,
+        cast::{Cast, CastFrom},
         prelude::BitPack,
         unsigned::U,
     };
@@ -210,4 +354,95 @@ mod tests {
             [[[L, H, H]], [[L, H, H]]].cast::<Array<2, Array<1, Array<3, Bit>>>>()
         );
     }
+
+    #[test]
+    fn option() {
+        let some: Option<U<8>> = Some(5_u8.cast());
+        let none: Option<U<8>> = None;
+
+        assert_eq!(<Option<U<8>> as BitSize>::BITS, 9);
+        assert_eq!(some.clone().pack(), 0b1_0000_0101);
+        assert_eq!(none.clone().pack(), 0);
+
+        assert_eq!(Option::<U<8>>::unpack(some.clone().pack()), some);
+        assert_eq!(Option::<U<8>>::unpack(none.clone().pack()), none);
+        // the payload of `None` is ignored
+        assert_eq!(Option::<U<8>>::unpack(0b0_1111_0101_u16.cast()), None);
+    }
+
+    #[test]
+    fn mask() {
+        let u: U<12> = 0b1010_1101_0110_u16.cast();
+
+        assert_eq!(u.clone().mask::<5>(), 0b10110);
+        assert_eq!(u.clone().mask::<12>(), 0b1010_1101_0110);
+        assert_eq!(u.mask::<0>(), 0);
+    }
+
+    #[test]
+    fn interleave() {
+        let zeros: U<2> = 0b00_u8.cast();
+        let ones: U<2> = 0b11_u8.cast();
+        assert_eq!(zeros.interleave(ones), 0b1010);
+
+        let even: U<4> = 0b1001_u8.cast();
+        let odd: U<4> = 0b0110_u8.cast();
+        let interleaved = even.clone().interleave(odd.clone());
+        assert_eq!(interleaved, 0b0110_1001);
+        assert_eq!(interleaved.deinterleave(), (even, odd));
+    }
+
+    #[test]
+    fn bytes() {
+        let val: U<24> = 0x12_34_56_u32.cast();
+        let le = val.clone().to_le_bytes().map(|byte| byte.cast::<u8>());
+        let be = val.clone().to_be_bytes().map(|byte| byte.cast::<u8>());
+
+        assert_eq!(le, [0x56, 0x34, 0x12]);
+        assert_eq!(be, [0x12, 0x34, 0x56]);
+        assert_eq!(U::<24>::from_le_bytes(le.map(Cast::cast)), val);
+        assert_eq!(U::<24>::from_be_bytes(be.map(Cast::cast)), val);
+        assert_eq!(
+            U::<16>::from_le_bytes([0xff_u8, 0x01].map(Cast::cast)),
+            0x01ff_u128
+        );
+    }
+
+    #[test]
+    fn parity() {
+        assert!(!U::<8>::cast_from(0_u8).parity());
+        assert!(U::<8>::cast_from(0b1000_0000_u8).parity());
+        assert!(!U::<8>::cast_from(0b1010_0000_u8).parity());
+        assert!(U::<8>::cast_from(0b1110_0000_u8).parity());
+    }
+
+    // Bitwise CRC-5/USB register update with the MSB of the byte going first
+    fn crc5_ref(byte: u8, mut crc: u8) -> u8 {
+        for idx in (0 .. 8).rev() {
+            let fb = ((crc >> 4) ^ (byte >> idx)) & 1;
+            crc = (crc << 1) & 0x1f;
+            if fb == 1 {
+                crc ^= 0x05;
+            }
+        }
+        crc
+    }
+
+    #[test]
+    fn crc5() {
+        let crc5 = |byte: u8| {
+            let byte: U<8> = byte.cast();
+            (0 .. 8)
+                .rev()
+                .fold(U::<5>::cast_from(0x1f_u8), |crc, idx| {
+                    crc.crc_step::<0x05>(byte.bit_(idx))
+                })
+                .cast::<u8>()
+        };
+
+        assert_eq!(crc5(0xa5), 0x01);
+        for byte in 0 ..= 255 {
+            assert_eq!(crc5(byte), crc5_ref(byte, 0x1f));
+        }
+    }
 }
@@ -1,4 +1,6 @@
-use fhdl_macros::synth;
+use fhdl_macros::{blackbox, synth};
+
+use crate::bitpack::BitPack;
 
 pub trait CastFrom<T: Sized>: Sized {
     fn cast_from(from: T) -> Self;
@@ -30,3 +32,10 @@ pub trait Cast: Sized {
 }
 
 impl<T: Sized> Cast for T {}
+
+// Reinterprets an enum as another enum with the same layout (discriminant width,
+// discriminants and payload widths), see `#[bitpack(cast_from = "..")]`
+#[blackbox(CastFrom)]
+pub fn cast_enum<F: BitPack, T: BitPack<Packed = F::Packed>>(from: F) -> T {
+    T::unpack(from.pack())
+}
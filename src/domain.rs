@@ -4,13 +4,18 @@ use std::{
     io,
     marker::{ConstParamTy, PhantomData},
     rc::Rc,
+    slice, str,
 };
 
 use derive_where::derive_where;
 use fhdl_macros::blackbox_ty;
 use vcd::IdCode;
 
-use crate::trace::{TraceTy, TraceVars, Traceable, Tracer};
+use crate::{
+    eval::EvalCtx,
+    signal::Signal,
+    trace::{TraceTy, TraceVars, Traceable, Tracer},
+};
 
 pub const SECOND: usize = 1_000_000_000_000;
 pub const MILLISECOND: usize = 1_000_000_000;
@@ -23,9 +28,120 @@ pub const fn hz_to_period(freq: usize) -> usize {
     SECOND / freq
 }
 
+// Number of clock cycles of the domain per `ps` picoseconds, fails at compile time
+// if `ps` is not a multiple of the period of the domain
 pub const fn clk_divider<D: ClockDomain>(ps: usize) -> usize {
-    assert!(ps >= D::PERIOD);
-    ps / D::PERIOD
+    period_divider(ps, D::PERIOD)
+}
+
+pub(crate) const fn period_divider(ps: usize, period: usize) -> usize {
+    assert!(ps >= period);
+
+    if ps % period != 0 {
+        // the fixed parts of the message and 4 numbers
+        let msg = ConstMsg::<{ 79 + 4 * USIZE_DIGITS }>::new()
+            .push_str("period ")
+            .push_num(ps)
+            .push_str(" ps is not a multiple of clock domain period ")
+            .push_num(period)
+            .push_str(" ps (ratio ")
+            .push_num(ps / period)
+            .push_str(", remainder ")
+            .push_num(ps % period)
+            .push_str(" ps)");
+        panic!("{}", msg.as_str());
+    }
+
+    ps / period
+}
+
+// Number of clock cycles of the domain per one cycle of `freq`, fails at compile time
+// if the frequency of the domain is not a multiple of `freq`
+pub const fn freq_divider<D: ClockDomain>(freq: usize) -> usize {
+    assert!(freq > 0, "frequency must be non-zero");
+
+    if D::FREQ % freq != 0 {
+        // the fixed parts of the message and 4 numbers
+        let msg = ConstMsg::<{ 75 + 4 * USIZE_DIGITS }>::new()
+            .push_str("clock domain frequency ")
+            .push_num(D::FREQ)
+            .push_str(" Hz is not a multiple of ")
+            .push_num(freq)
+            .push_str(" Hz (ratio ")
+            .push_num(D::FREQ / freq)
+            .push_str(", remainder ")
+            .push_num(D::FREQ % freq)
+            .push_str(" Hz)");
+        panic!("{}", msg.as_str());
+    }
+
+    D::FREQ / freq
+}
+
+// Max number of decimal digits of `usize`
+const USIZE_DIGITS: usize = usize::MAX.ilog10() as usize + 1;
+
+// Fixed-size buffer to build panic messages in const fns, the message is truncated
+// if it doesn't fit into the buffer
+struct ConstMsg<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ConstMsg<N> {
+    const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    const fn push_byte(mut self, byte: u8) -> Self {
+        if self.len < N {
+            self.buf[self.len] = byte;
+            self.len += 1;
+        }
+
+        self
+    }
+
+    const fn push_str(mut self, s: &str) -> Self {
+        let bytes = s.as_bytes();
+        let mut idx = 0;
+        while idx < bytes.len() {
+            self = self.push_byte(bytes[idx]);
+            idx += 1;
+        }
+
+        self
+    }
+
+    const fn push_num(mut self, mut num: usize) -> Self {
+        let mut digits = [0_u8; USIZE_DIGITS];
+        let mut count = 0;
+        loop {
+            digits[count] = b'0' + (num % 10) as u8;
+            count += 1;
+            num /= 10;
+            if num == 0 {
+                break;
+            }
+        }
+
+        while count > 0 {
+            count -= 1;
+            self = self.push_byte(digits[count]);
+        }
+
+        self
+    }
+
+    const fn as_str(&self) -> &str {
+        // only ASCII is pushed into the buffer
+        unsafe {
+            str::from_utf8_unchecked(slice::from_raw_parts(self.buf.as_ptr(), self.len))
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ConstParamTy)]
@@ -106,6 +222,8 @@ impl Display for ClockState {
 #[blackbox_ty(Clock)]
 pub struct Clock<D: ClockDomain> {
     state: Rc<Cell<ClockState>>,
+    // enable of the gated clock
+    gate: Option<Signal<D, bool>>,
     _dom: PhantomData<D>,
 }
 
@@ -127,6 +245,21 @@ impl<D: ClockDomain> Clock<D> {
     pub fn new() -> Self {
         Self {
             state: Rc::new(Cell::new(ClockState::Falling)),
+            gate: None,
+            _dom: PhantomData,
+        }
+    }
+
+    // Shares the state with the source clock, but rises only if `en` is true
+    pub(crate) fn gate(&self, en: &Signal<D, bool>) -> Self {
+        let gate = match &self.gate {
+            Some(gate) => gate.apply2(en, |gate, en| gate && en),
+            None => en.clone(),
+        };
+
+        Self {
+            state: self.state.clone(),
+            gate: Some(gate),
             _dom: PhantomData,
         }
     }
@@ -139,6 +272,17 @@ impl<D: ClockDomain> Clock<D> {
         matches!(self.state.get(), ClockState::Falling)
     }
 
+    // The enable of the gated clock is latched while the clock is low, so it's
+    // the value sampled right before the rising edge
+    pub(crate) fn is_rising_gated(&mut self, ctx: &mut EvalCtx) -> bool {
+        let en = self
+            .gate
+            .as_mut()
+            .map(|gate| gate.next(ctx))
+            .unwrap_or(true);
+        self.is_rising() && en
+    }
+
     pub fn invert(&self) {
         self.state.update(|state| match state {
             ClockState::Rising => ClockState::Falling,
@@ -171,3 +315,43 @@ impl<const N: usize> ClockDomain for TestDomain<N> {
 pub type TD4 = TestDomain<4>;
 pub type TD8 = TestDomain<8>;
 pub type TD16 = TestDomain<16>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freq_divider_integer_ratio() {
+        assert_eq!(freq_divider::<TestDomain<12>>(3), 4);
+        assert_eq!(freq_divider::<TestDomain<12>>(12), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "clock domain frequency 10 Hz is not a multiple of 4 Hz (ratio 2, remainder 2 Hz)"
+    )]
+    fn freq_divider_non_integer_ratio() {
+        freq_divider::<TestDomain<10>>(4);
+    }
+
+    #[test]
+    fn clk_divider_integer_ratio() {
+        assert_eq!(clk_divider::<TestDomain<4>>(SECOND), 4);
+        assert_eq!(clk_divider::<TestDomain<4>>(SECOND / 4), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "period 300000000000 ps is not a multiple of clock domain \
+                               period 250000000000 ps (ratio 1, remainder 50000000000 ps)"
+    )]
+    fn clk_divider_non_integer_ratio() {
+        clk_divider::<TestDomain<4>>(300 * MILLISECOND);
+    }
+
+    #[test]
+    fn const_msg_truncated() {
+        let msg = ConstMsg::<8>::new().push_str("ratio ").push_num(1234);
+        assert_eq!(msg.as_str(), "ratio 12");
+    }
+}
@@ -1,4 +1,9 @@
-use crate::domain::{Clock, ClockDomain};
+use std::iter::StepBy;
+
+use crate::{
+    domain::{Clock, ClockDomain},
+    signal::{Signal, SignalValue},
+};
 
 #[derive(Debug)]
 pub struct EvalCtx {
@@ -34,23 +39,62 @@ impl Default for EvalOpts {
     }
 }
 
-pub trait Eval<D: ClockDomain>: Sized {
+// Behavioral model of a design which produces the next value on every clock edge.
+// The trait is object safe, so designs of different types can be driven through
+// `DynEval`
+pub trait Eval<D: ClockDomain> {
     type Value;
 
     fn next(&mut self, ctx: &mut EvalCtx) -> Self::Value;
 
+    // Number of clock cycles skipped before the first value
     #[inline]
-    fn eval(self, clk: &Clock<D>) -> EvalIter<D, Self> {
+    fn skipped_cycles(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn eval(self, clk: &Clock<D>) -> EvalIter<D, Self>
+    where
+        Self: Sized,
+    {
         self.eval_with_opts(clk, Default::default())
     }
 
-    fn eval_with_opts(self, clk: &Clock<D>, opts: EvalOpts) -> EvalIter<D, Self> {
-        EvalIter {
-            ctx: EvalCtx::new(),
-            source: self,
-            clk: clk.clone(),
-            opts,
+    fn eval_with_opts(self, clk: &Clock<D>, opts: EvalOpts) -> EvalIter<D, Self>
+    where
+        Self: Sized,
+    {
+        let skip = self.skipped_cycles();
+        let mut iter = EvalIter::new(self, clk, opts);
+        for _ in 0 .. 2 * skip {
+            iter.eval();
         }
+
+        iter
+    }
+
+    fn boxed<'a>(self) -> DynEval<'a, D, Self::Value>
+    where
+        Self: Sized + 'a,
+    {
+        Box::new(self)
+    }
+}
+
+pub type DynEval<'a, D, T> = Box<dyn Eval<D, Value = T> + 'a>;
+
+impl<'a, D: ClockDomain, T> Eval<D> for DynEval<'a, D, T> {
+    type Value = T;
+
+    #[inline]
+    fn next(&mut self, ctx: &mut EvalCtx) -> Self::Value {
+        (**self).next(ctx)
+    }
+
+    #[inline]
+    fn skipped_cycles(&self) -> usize {
+        (**self).skipped_cycles()
     }
 }
 
@@ -63,6 +107,15 @@ pub struct EvalIter<D: ClockDomain, S> {
 }
 
 impl<D: ClockDomain, S: Eval<D>> EvalIter<D, S> {
+    pub(crate) fn new(source: S, clk: &Clock<D>, opts: EvalOpts) -> Self {
+        Self {
+            ctx: EvalCtx::new(),
+            source,
+            clk: clk.clone(),
+            opts,
+        }
+    }
+
     pub fn eval(&mut self) -> S::Value {
         if self.opts.auto_clk {
             self.clk.invert();
@@ -86,6 +139,11 @@ impl<D: ClockDomain, S: Eval<D>> EvalIter<D, S> {
     pub fn with_time(self) -> WithTime<D, S> {
         WithTime { inner: self }
     }
+
+    // Values at the rising edges of the clock, one value per clock cycle
+    pub fn cycles(self) -> StepBy<Self> {
+        self.step_by(2)
+    }
 }
 
 impl<D: ClockDomain, S: Eval<D>> Iterator for EvalIter<D, S> {
@@ -108,3 +166,173 @@ impl<D: ClockDomain, S: Eval<D>> Iterator for WithTime<D, S> {
         self.inner.next().map(|value| (self.inner.time(), value))
     }
 }
+
+// Streams of values driving the inputs of a design, one iterator per input in the
+// order of the inputs of the design
+pub trait EvalInputs<D: ClockDomain> {
+    type Values;
+    type Signals: Clone;
+
+    fn next_values(&mut self) -> Option<Self::Values>;
+
+    fn signals(values: Self::Values) -> Self::Signals;
+
+    fn set_values(signals: &Self::Signals, values: Self::Values);
+}
+
+macro_rules! impl_eval_inputs {
+    ($($input:ident: $idx:tt),+) => {
+        impl<D: ClockDomain, $($input),+> EvalInputs<D> for ($($input,)+)
+        where
+            $($input: Iterator, $input::Item: SignalValue),+
+        {
+            type Values = ($($input::Item,)+);
+            type Signals = ($(Signal<D, $input::Item>,)+);
+
+            fn next_values(&mut self) -> Option<Self::Values> {
+                Some(($(self.$idx.next()?,)+))
+            }
+
+            fn signals(values: Self::Values) -> Self::Signals {
+                ($(Signal::lift(values.$idx),)+)
+            }
+
+            fn set_values(signals: &Self::Signals, values: Self::Values) {
+                $(signals.$idx.replace_value(|_| values.$idx);)+
+            }
+        }
+    };
+}
+
+impl_eval_inputs!(I0: 0);
+impl_eval_inputs!(I0: 0, I1: 1);
+impl_eval_inputs!(I0: 0, I1: 1, I2: 2);
+impl_eval_inputs!(I0: 0, I1: 1, I2: 2, I3: 3);
+impl_eval_inputs!(I0: 0, I1: 1, I2: 2, I3: 3, I4: 4);
+impl_eval_inputs!(I0: 0, I1: 1, I2: 2, I3: 3, I4: 4, I5: 5);
+impl_eval_inputs!(I0: 0, I1: 1, I2: 2, I3: 3, I4: 4, I5: 5, I6: 6);
+impl_eval_inputs!(I0: 0, I1: 1, I2: 2, I3: 3, I4: 4, I5: 5, I6: 6, I7: 7);
+
+// Evaluates the design built by `top` from the input signals, the inputs take the
+// next values of the streams every clock cycle before the rising edge, and the output
+// is sampled at the rising edge. The streams are zipped, so the evaluation stops at
+// the end of the shortest one
+pub fn eval_inputs<D, I, S>(
+    clk: &Clock<D>,
+    mut inputs: I,
+    top: impl FnOnce(I::Signals) -> S,
+) -> EvalInputsIter<D, I, S>
+where
+    D: ClockDomain,
+    I: EvalInputs<D>,
+    S: Eval<D>,
+{
+    let first = inputs.next_values();
+    let signals = first.map(I::signals);
+    let iter = signals.clone().map(|signals| top(signals).eval(clk));
+
+    EvalInputsIter {
+        inputs,
+        signals,
+        iter,
+        started: false,
+    }
+}
+
+pub struct EvalInputsIter<D: ClockDomain, I: EvalInputs<D>, S> {
+    inputs: I,
+    signals: Option<I::Signals>,
+    iter: Option<EvalIter<D, S>>,
+    started: bool,
+}
+
+impl<D: ClockDomain, I: EvalInputs<D>, S: Eval<D>> Iterator for EvalInputsIter<D, I, S> {
+    type Item = S::Value;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (signals, iter) = (self.signals.as_ref()?, self.iter.as_mut()?);
+
+        // the first values are set on creation of the signals
+        if self.started {
+            I::set_values(signals, self.inputs.next_values()?);
+        }
+        self.started = true;
+
+        let value = iter.eval();
+        iter.eval();
+
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cast::Cast,
+        domain::TD4,
+        signal::{reg0, Reset},
+        unsigned::U,
+    };
+
+    fn adder(a: Signal<TD4, U<8>>, b: Signal<TD4, U<8>>) -> Signal<TD4, U<8>> {
+        a.zip_with(b, |a, b| a + b)
+    }
+
+    #[test]
+    fn eval_two_input_streams() {
+        let clk = Clock::<TD4>::new();
+        let a = [1_u8, 2, 3, 4, 5].into_iter().map(Cast::cast::<U<8>>);
+        let b = [10_u8, 20, 30, 40].into_iter().map(Cast::cast::<U<8>>);
+
+        // the output follows the inputs of the same cycle, and stops with the shorter
+        // stream
+        let sums = eval_inputs(&clk, (a, b), |(a, b)| adder(a, b))
+            .map(Cast::cast::<u8>)
+            .collect::<Vec<_>>();
+        assert_eq!(sums, [11, 22, 33, 44]);
+    }
+
+    #[test]
+    fn eval_registered_input_stream() {
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::<TD4>::reset();
+        let a = [1_u8, 2, 3, 4].into_iter().map(Cast::cast::<U<8>>);
+
+        // the register captures the input at the rising edge, so it's seen at the
+        // output in the next cycle
+        let regs = eval_inputs(&clk, (a,), |(a,)| {
+            a.and_then(|a| reg0(&clk, &rst, move |_: U<8>| a.value()))
+        })
+        .map(Cast::cast::<u8>)
+        .collect::<Vec<_>>();
+        assert_eq!(regs, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn dyn_eval_per_cycle() {
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::<TD4>::reset();
+
+        // designs of different types are driven through the same interface, the
+        // skipped cycles of the delayed counter are kept behind the box
+        let designs: Vec<DynEval<'_, TD4, U<8>>> = vec![
+            reg0(&clk, &rst, |cnt: U<8>| cnt + 1).boxed(),
+            reg0(&clk, &rst, |cnt: U<8>| cnt + 1).skip(2).boxed(),
+            adder(Signal::lift(3_u8.cast()), Signal::lift(4_u8.cast())).boxed(),
+        ];
+
+        let res = designs
+            .into_iter()
+            .map(|design| {
+                design
+                    .eval(&clk)
+                    .cycles()
+                    .take(4)
+                    .map(Cast::cast::<u8>)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(res, [[0, 1, 2, 3], [2, 3, 4, 5], [7, 7, 7, 7]]);
+    }
+}
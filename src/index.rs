@@ -1,4 +1,4 @@
-use std::fmt::{Binary, Debug, Display, LowerHex};
+use core::fmt::{self, Binary, Debug, Display, LowerHex};
 
 use fhdl_const_func::clog2_len;
 use fhdl_macros::synth;
@@ -25,7 +25,7 @@ impl<const N: usize> Debug for Idx<N>
 where
     ConstConstr<{ idx_constr(N) }>:,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Debug::fmt(&self.0, f)
     }
 }
@@ -34,7 +34,7 @@ impl<const N: usize> Display for Idx<N>
 where
     ConstConstr<{ idx_constr(N) }>:,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Display::fmt(&self.0, f)
     }
 }
@@ -43,7 +43,7 @@ impl<const N: usize> Binary for Idx<N>
 where
     ConstConstr<{ idx_constr(N) }>:,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Binary::fmt(&self.0, f)
     }
 }
@@ -52,7 +52,7 @@ impl<const N: usize> LowerHex for Idx<N>
 where
     ConstConstr<{ idx_constr(N) }>:,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         LowerHex::fmt(&self.0, f)
     }
 }
@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(clippy::let_and_return)]
 #![allow(incomplete_features)]
 #![feature(generic_const_exprs)]
@@ -10,6 +11,8 @@
 #![feature(register_tool)]
 #![register_tool(fhdl_tool)]
 
+extern crate alloc;
+
 pub mod array;
 pub mod bit;
 pub mod bitpack;
@@ -17,15 +20,22 @@ pub mod bundle;
 pub mod cast;
 pub mod const_functions;
 pub mod const_helpers;
+#[cfg(feature = "std")]
 pub mod domain;
+#[cfg(feature = "std")]
 pub mod eval;
 pub mod index;
+#[cfg(feature = "std")]
 pub mod memory;
+#[cfg(feature = "std")]
 pub mod new_hdl;
 pub mod signal;
 pub mod signed;
+#[cfg(feature = "std")]
 pub mod toolbox;
+#[cfg(feature = "std")]
 pub mod trace;
+#[cfg(feature = "std")]
 mod tuples;
 pub mod unsigned;
 
@@ -35,25 +45,29 @@ pub mod prelude {
     pub use crate::{
         array::{Array, ArrayExt},
         bit::{Bit, H, L},
-        bitpack::{BitPack, BitPackExt, BitVec},
+        bitpack::{bit_width, BitPack, BitPackExt, BitVec},
         bundle::{Bundle, Unbundle},
         cast::{Cast, CastFrom},
         const_functions::{assert_in_range, clog2, idx_range_len},
         const_helpers::{Assert, ConstConstr, IsTrue},
+        index::{idx_constr, Idx},
+        signal::SignalValue,
+        signed::S,
+        unsigned::U,
+    };
+    #[cfg(feature = "std")]
+    pub use crate::{
         domain::{
-            clk_divider, hz_to_period, Clock, ClockDomain, Polarity, SyncKind,
-            TestDomain, MICROSECOND, MILLISECOND, NANOSECOND, PICOSECOND, SECOND, TD16,
-            TD4, TD8,
+            clk_divider, freq_divider, hz_to_period, Clock, ClockDomain, Polarity,
+            SyncKind, TestDomain, MICROSECOND, MILLISECOND, NANOSECOND, PICOSECOND,
+            SECOND, TD16, TD4, TD8,
         },
-        eval::{Eval, EvalIter, EvalOpts},
-        index::{idx_constr, Idx},
+        eval::{eval_inputs, DynEval, Eval, EvalInputs, EvalIter, EvalOpts},
         signal::{
-            dff, dff_comb, reg, reg0, reg0_comb, reg_comb, reg_en, reg_en0, reg_en0_comb,
-            reg_en_comb, rise_every, rise_period, rise_rate, Enable, IntoSignal, Reset,
-            Signal, SignalValue,
+            dff, dff_comb, gated_clock, pipeline, reg, reg0, reg0_comb, reg_comb, reg_en,
+            reg_en0, reg_en0_comb, reg_en_comb, reg_full, rise_every, rise_period,
+            rise_rate, sample_on_enable, with_reset, Enable, IntoSignal, Reset, Signal,
         },
-        signed::S,
         trace::{IdCode, Timescale, TraceTy, TraceValue, TraceVars, Traceable, Tracer},
-        unsigned::U,
     };
 }
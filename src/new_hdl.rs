@@ -4,7 +4,7 @@ pub use fhdl_macros::State;
 use fhdl_macros::{blackbox, blackbox_ty, lang_item, synth};
 
 use crate::{
-    domain::{hz_to_period, Polarity, SyncKind},
+    domain::{hz_to_period, period_divider, Polarity, SyncKind},
     prelude::{ConstConstr, Idx, SignalValue},
     rise_every_constr,
 };
@@ -31,8 +31,7 @@ pub trait Module: StateMut {
 }
 
 pub const fn clk_divider<D: ClockDomain>(ps: usize) -> usize {
-    assert!(ps >= D::PERIOD);
-    ps / D::PERIOD
+    period_divider(ps, D::PERIOD)
 }
 
 #[lang_item(Domain)]
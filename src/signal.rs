@@ -1,30 +1,52 @@
+#[cfg(feature = "std")]
+mod cdc;
+#[cfg(feature = "std")]
 mod counters;
+#[cfg(feature = "std")]
 mod ops;
+#[cfg(feature = "std")]
 mod reg;
+#[cfg(feature = "std")]
 mod signal_fn;
+#[cfg(feature = "std")]
 mod wrapped;
 
+#[cfg(feature = "std")]
 use std::{
     cell::RefCell,
+    error::Error,
     fmt::{self, Display},
     io,
     marker::PhantomData,
     rc::Rc,
 };
 
-pub use counters::{rise_every, rise_period, rise_rate};
+#[cfg(feature = "std")]
+pub use cdc::{dual_flop, resample};
+#[cfg(feature = "std")]
+pub use counters::{
+    changed, pipeline, rise_every, rise_period, rise_rate, sample_on_enable,
+};
+#[cfg(feature = "std")]
 use derive_where::derive_where;
 pub use fhdl_macros::SignalValue;
+#[cfg(feature = "std")]
 use fhdl_macros::{blackbox, blackbox_ty, synth};
+#[cfg(feature = "std")]
 pub use ops::IntoSignal;
+#[cfg(feature = "std")]
 pub use reg::{
-    dff, dff_comb, reg, reg0, reg0_comb, reg_comb, reg_en, reg_en0, reg_en0_comb,
-    reg_en_comb, Enable, Reset,
+    dff, dff_comb, gated_clock, reg, reg0, reg0_comb, reg_comb, reg_en, reg_en0,
+    reg_en0_comb, reg_en_comb, reg_full, with_reset, Enable, Reset,
 };
+#[cfg(feature = "std")]
 use vcd::IdCode;
+#[cfg(feature = "std")]
 pub use wrapped::Wrapped;
 
+#[cfg(feature = "std")]
 use self::signal_fn::SignalFn;
+#[cfg(feature = "std")]
 use crate::{
     bit::Bit,
     domain::{Clock, ClockDomain},
@@ -39,6 +61,7 @@ impl SignalValue for () {}
 
 impl<T: SignalValue> SignalValue for Option<T> {}
 
+#[cfg(feature = "std")]
 impl<D: ClockDomain, T: SignalValue> Eval<D> for T {
     type Value = T;
 
@@ -47,6 +70,7 @@ impl<D: ClockDomain, T: SignalValue> Eval<D> for T {
     }
 }
 
+#[cfg(feature = "std")]
 #[derive_where(Debug, Clone; T)]
 #[blackbox_ty(Signal)]
 pub struct Signal<D: ClockDomain, T: SignalValue> {
@@ -54,8 +78,10 @@ pub struct Signal<D: ClockDomain, T: SignalValue> {
     _dom: PhantomData<D>,
     next: Rc<RefCell<SignalFn<T>>>,
     value: Option<Rc<RefCell<T>>>,
+    skip: usize,
 }
 
+#[cfg(feature = "std")]
 impl<D: ClockDomain, T: SignalValue + Display> Display for Signal<D, T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.next.borrow().as_value_opt() {
@@ -65,12 +91,14 @@ impl<D: ClockDomain, T: SignalValue + Display> Display for Signal<D, T> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<D: ClockDomain, T: SignalValue> Signal<D, T> {
     pub(crate) fn new(f: impl FnMut(&mut EvalCtx) -> T + 'static) -> Self {
         Self {
             _dom: PhantomData,
             next: Rc::new(RefCell::new(SignalFn::new(f))),
             value: None,
+            skip: 0,
         }
     }
 
@@ -89,6 +117,12 @@ impl<D: ClockDomain, T: SignalValue> Signal<D, T> {
         this
     }
 
+    // Emits `value` every cycle, it's synthesized as a constant without a register
+    #[blackbox(SignalConstant)]
+    pub fn constant(value: T) -> Signal<D, T> {
+        Self::new(move |_| value.clone())
+    }
+
     pub fn replace_value(&self, f: impl FnOnce(T) -> T) {
         if let Some(inner) = self.value.as_ref() {
             inner.replace_with(|val| f(val.clone()));
@@ -105,6 +139,7 @@ impl<D: ClockDomain, T: SignalValue> Signal<D, T> {
             let val = inner.next(ctx);
             (f)(val)
         })
+        .skip(self.skip)
     }
 
     #[blackbox(SignalAndThen)]
@@ -114,10 +149,40 @@ impl<D: ClockDomain, T: SignalValue> Signal<D, T> {
     {
         let mut wrapped = Wrapped::new(self.clone());
         let mut signal = f(wrapped.clone());
+        let skip = self.skip.max(signal.skip);
         Signal::new(move |ctx| {
             wrapped.next(ctx);
             signal.next(ctx)
         })
+        .skip(skip)
+    }
+
+    // Pairs two signals into a signal of tuples
+    #[synth(inline)]
+    pub fn zip<U: IntoSignal<D>>(&self, other: U) -> Signal<D, (T, U::Value)> {
+        self.apply2(other, |a, b| (a, b))
+    }
+
+    // Combines two signals with `f`
+    #[synth(inline)]
+    pub fn zip_with<U: IntoSignal<D>, V: SignalValue>(
+        &self,
+        other: U,
+        f: impl Fn(T, U::Value) -> V + Clone + 'static,
+    ) -> Signal<D, V> {
+        self.apply2(other, f)
+    }
+
+    // Combines three signals with `f`
+    #[synth(inline)]
+    pub fn zip3_with<U: IntoSignal<D>, W: IntoSignal<D>, V: SignalValue>(
+        &self,
+        second: U,
+        third: W,
+        f: impl Fn(T, U::Value, W::Value) -> V + Clone + 'static,
+    ) -> Signal<D, V> {
+        self.zip(second)
+            .apply2(third, move |(first, second), third| f(first, second, third))
     }
 
     #[synth(inline)]
@@ -148,14 +213,61 @@ impl<D: ClockDomain, T: SignalValue> Signal<D, T> {
     {
         self.and_then(|value| reg0(clk, rst, move |_| value.value()))
     }
+
+    // Skips the first `n` clock cycles (two evaluations per cycle) when the signal is
+    // evaluated, e.g. to align the output of a pipeline with its latency in tests
+    pub fn skip(mut self, n: usize) -> Self {
+        self.skip += n;
+        self
+    }
+
+    pub fn simulate(self, clk: &Clock<D>, cycles: usize) -> Vec<T> {
+        self.eval(clk).take(cycles).collect()
+    }
+
+    // Evaluates the signal until `pred` holds for a value, fails if it doesn't hold
+    // within `max_cycles`
+    pub fn simulate_until(
+        self,
+        clk: &Clock<D>,
+        max_cycles: usize,
+        mut pred: impl FnMut(&T) -> bool,
+    ) -> Result<Vec<T>, CycleLimit> {
+        let mut values = vec![];
+        for value in self.eval(clk).take(max_cycles) {
+            let stop = pred(&value);
+            values.push(value);
+            if stop {
+                return Ok(values);
+            }
+        }
+
+        Err(CycleLimit(max_cycles))
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleLimit(pub usize);
+
+#[cfg(feature = "std")]
+impl Display for CycleLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the predicate does not hold within {} cycles", self.0)
+    }
 }
 
+#[cfg(feature = "std")]
+impl Error for CycleLimit {}
+
+#[cfg(feature = "std")]
 impl<D: ClockDomain> Signal<D, Bit> {
     pub fn invert(&self) {
         self.replace_value(|value| !value)
     }
 }
 
+#[cfg(feature = "std")]
 impl<T: SignalValue, D: ClockDomain> From<T> for Signal<D, T> {
     #[synth(inline)]
     fn from(value: T) -> Self {
@@ -163,6 +275,7 @@ impl<T: SignalValue, D: ClockDomain> From<T> for Signal<D, T> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<T: SignalValue, D: ClockDomain> From<&'_ Signal<D, T>> for Signal<D, T> {
     #[synth(inline)]
     fn from(signal: &'_ Signal<D, T>) -> Self {
@@ -170,6 +283,7 @@ impl<T: SignalValue, D: ClockDomain> From<&'_ Signal<D, T>> for Signal<D, T> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<T: SignalValue + Traceable, D: ClockDomain> Traceable for Signal<D, T> {
     #[inline]
     fn add_vars(vars: &mut TraceVars) {
@@ -185,14 +299,21 @@ impl<T: SignalValue + Traceable, D: ClockDomain> Traceable for Signal<D, T> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<D: ClockDomain, T: SignalValue> Eval<D> for Signal<D, T> {
     type Value = T;
 
     fn next(&mut self, ctx: &mut EvalCtx) -> Self::Value {
         self.next(ctx)
     }
+
+    #[inline]
+    fn skipped_cycles(&self) -> usize {
+        self.skip
+    }
 }
 
+#[cfg(feature = "std")]
 pub trait SignalIterExt: IntoIterator + Sized
 where
     Self::Item: SignalValue,
@@ -200,6 +321,7 @@ where
     fn into_signal<D: ClockDomain>(self) -> Signal<D, Self::Item>;
 }
 
+#[cfg(feature = "std")]
 impl<I> SignalIterExt for I
 where
     I: IntoIterator + Sized,
@@ -214,9 +336,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::SignalIterExt;
+    use super::{reg0, CycleLimit, Reset, SignalIterExt};
     use crate::{
-        cast::CastFrom,
+        cast::{Cast, CastFrom},
         domain::{Clock, TD4},
         prelude::Eval,
         unsigned::U,
@@ -232,4 +354,131 @@ mod tests {
 
         assert_eq!(s.eval(&clk).take(5).collect::<Vec<_>>(), [0, 4, 3, 1, 2]);
     }
+
+    #[test]
+    fn test_simulate() {
+        let clk = Clock::<TD4>::new();
+        let s = [0_u8, 4, 3, 1, 2]
+            .into_iter()
+            .map(U::<8>::cast_from)
+            .into_signal::<TD4>();
+
+        assert_eq!(s.simulate(&clk, 3), [0, 4, 3]);
+    }
+
+    #[test]
+    fn test_simulate_until() {
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::reset();
+        let counter = reg0(&clk, &rst, |val: U<4>| val + 1);
+
+        assert_eq!(
+            counter
+                .clone()
+                .simulate_until(&clk, 16, |val| *val == 3)
+                .unwrap(),
+            //R F  R  F  R  F  R
+            [0, 0, 1, 1, 2, 2, 3]
+        );
+        assert_eq!(
+            counter.simulate_until(&clk, 16, |val| *val == 15),
+            Err(CycleLimit(16))
+        );
+    }
+
+    #[test]
+    fn test_zip() {
+        let clk = Clock::<TD4>::new();
+        let a = [0_u8, 4, 3]
+            .into_iter()
+            .map(U::<4>::cast_from)
+            .into_signal::<TD4>();
+        let b = [true, false, true].into_iter().into_signal::<TD4>();
+
+        assert_eq!(a.zip(b).simulate(&clk, 3), [
+            (U::cast_from(0_u8), true),
+            (U::cast_from(4_u8), false),
+            (U::cast_from(3_u8), true)
+        ]);
+    }
+
+    #[test]
+    fn test_zip_with() {
+        let clk = Clock::<TD4>::new();
+        let signal = |values: [u8; 3]| {
+            values
+                .into_iter()
+                .map(U::<4>::cast_from)
+                .into_signal::<TD4>()
+        };
+        let a = signal([0, 4, 3]);
+        let b = signal([1, 2, 3]);
+        let c = signal([5, 1, 9]);
+
+        let sum = a.zip3_with(&b, &c, |a, b, c| a + b + c);
+        let diff = a.zip_with(&b, |a, b| a - b);
+        let out = sum.zip(diff);
+
+        assert_eq!(
+            out.simulate(&clk, 3)
+                .into_iter()
+                .map(|(sum, diff)| (sum.cast::<u8>(), diff.cast::<u8>()))
+                .collect::<Vec<_>>(),
+            [(6, 15), (7, 2), (15, 0)]
+        );
+    }
+
+    #[test]
+    fn constant() {
+        let clk = Clock::<TD4>::new();
+
+        let values = super::Signal::<TD4, U<8>>::constant(42_u8.cast())
+            .map(|val| val + 1)
+            .eval(&clk)
+            .cycles()
+            .take(4)
+            .map(|val| val.cast::<u8>())
+            .collect::<Vec<_>>();
+        assert_eq!(values, [43; 4]);
+    }
+
+    #[test]
+    fn test_skip() {
+        fn inputs(
+            clk: &Clock<TD4>,
+            rst: &Reset<TD4>,
+        ) -> super::Signal<TD4, (U<8>, U<8>)> {
+            let a = reg0(clk, rst, |val: U<8>| val + 1);
+            let b = a.map(|val| val.clone() + val);
+            a.zip(b)
+        }
+
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::reset();
+
+        // two-stage pipelined adder
+        let sum = inputs(&clk, &rst)
+            .reg(&clk, &rst, |(a, b)| a + b)
+            .into_reg(&clk, &rst);
+        let expected = inputs(&clk, &rst).map(|(a, b)| a + b);
+
+        assert_eq!(sum.skip(2).simulate(&clk, 16), expected.simulate(&clk, 16));
+
+        let a = inputs(&clk, &rst).map(|(a, _)| a);
+        assert_eq!(a.skip(0).simulate(&clk, 4), [0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_skip_is_kept_by_combinators() {
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::reset();
+
+        let counter = |step: u128| reg0(&clk, &rst, move |val: U<8>| val + step);
+
+        let doubled = counter(1).skip(1).map(|val| val.clone() + val);
+        assert_eq!(doubled.simulate(&clk, 4), [2, 2, 4, 4]);
+
+        let sum = counter(1).skip(2).zip_with(counter(2), |a, b| a + b);
+        assert_eq!(sum.simulate(&clk, 4), [6, 6, 9, 9]);
+    }
 }
@@ -0,0 +1,171 @@
+use fhdl_macros::{blackbox, synth};
+
+use super::{
+    reg::{reg0, reg_en0, Reset},
+    Signal, SignalValue,
+};
+use crate::{
+    bit::Bit,
+    domain::{Clock, ClockDomain},
+};
+
+// Reinterprets the signal in another clock domain without any synchronization
+#[blackbox(SignalCrossDomain)]
+fn cross_domain<DSrc: ClockDomain, DDst: ClockDomain, T: SignalValue>(
+    src: &Signal<DSrc, T>,
+) -> Signal<DDst, T> {
+    let mut src = src.clone();
+    Signal::new(move |ctx| src.next(ctx))
+}
+
+// Two-flop synchronizer of a single-bit signal crossing into the `DDst` domain
+#[synth(inline)]
+pub fn dual_flop<DSrc: ClockDomain, DDst: ClockDomain>(
+    clk: &Clock<DDst>,
+    rst: &Reset<DDst>,
+    src: &Signal<DSrc, Bit>,
+) -> Signal<DDst, Bit> {
+    cross_domain::<DSrc, DDst, Bit>(src)
+        .into_reg(clk, rst)
+        .into_reg(clk, rst)
+}
+
+// Multi-bit clock domain crossing: `src` is captured into a register of the source
+// domain when `valid` is high and held there, only a toggle of the capture crosses
+// through `dual_flop`. The destination valid is raised for one cycle once the toggle
+// arrives, so the held data is stable when it is sampled. `valid` must not be raised
+// again until the previous transfer is seen in the destination domain
+#[synth(inline)]
+pub fn resample<DSrc: ClockDomain, DDst: ClockDomain, T: SignalValue + Default>(
+    clk_src: &Clock<DSrc>,
+    rst_src: &Reset<DSrc>,
+    clk_dst: &Clock<DDst>,
+    rst_dst: &Reset<DDst>,
+    src: &Signal<DSrc, T>,
+    valid: &Signal<DSrc, Bit>,
+) -> Signal<DDst, (T, Bit)> {
+    let data = src.and_then(|src| reg_en0(clk_src, rst_src, valid, move |_| src.value()));
+    let toggle = valid.and_then(|valid| {
+        reg0(clk_src, rst_src, move |toggle: Bit| toggle ^ valid.value())
+    });
+
+    let synced = dual_flop::<DSrc, DDst>(clk_dst, rst_dst, &toggle);
+    let prev = synced.into_reg(clk_dst, rst_dst);
+    let valid = synced.zip_with(prev, |synced, prev| synced ^ prev);
+
+    cross_domain::<DSrc, DDst, T>(&data).zip(valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cast::Cast,
+        domain::{TestDomain, TD4},
+        eval::{Eval, EvalOpts},
+        unsigned::U,
+    };
+
+    type TD6 = TestDomain<6>;
+
+    fn set_clk<D: ClockDomain>(clk: &Clock<D>, rising: bool) {
+        if clk.is_rising() != rising {
+            clk.invert();
+        }
+    }
+
+    // The source clock rises every 2nd step, the destination clock every 3rd step (a
+    // stopped clock never rises). Inputs change between the source clock edges, returns
+    // the output at every 3rd step
+    fn run(
+        steps: usize,
+        (src_runs, dst_runs): (bool, bool),
+        mut src_at: impl FnMut(usize) -> (u8, bool),
+    ) -> Vec<(u8, bool)> {
+        let clk_src = Clock::<TD6>::new();
+        let clk_dst = Clock::<TD4>::new();
+        let rst_src = Reset::reset();
+        let rst_dst = Reset::reset();
+        let src = Signal::<TD6, U<4>>::lift(0_u8.cast());
+        let valid = Signal::<TD6, Bit>::lift(false);
+
+        let mut out = resample(&clk_src, &rst_src, &clk_dst, &rst_dst, &src, &valid)
+            .eval_with_opts(&clk_dst, EvalOpts { auto_clk: false });
+
+        let mut res = vec![];
+        for step in 0 .. steps {
+            set_clk(&clk_src, src_runs && step % 2 == 0);
+            set_clk(&clk_dst, dst_runs && step % 3 == 0);
+            if step % 2 == 1 {
+                let (data, is_valid) = src_at(step / 2);
+                src.replace_value(|_| data.cast());
+                valid.replace_value(|_| is_valid);
+            }
+
+            let (data, valid) = out.eval();
+            if step % 3 == 0 {
+                res.push((data.cast::<u8>(), valid));
+            }
+        }
+
+        res
+    }
+
+    #[test]
+    fn resample_transfers_words() {
+        // a word is sent every 8 source cycles
+        let res = run(96, (true, true), |cycle| {
+            ((cycle / 8 + 1) as u8, cycle % 8 == 0)
+        });
+
+        let received = res
+            .iter()
+            .filter(|(_, valid)| *valid)
+            .map(|(data, _)| *data)
+            .collect::<Vec<_>>();
+        assert_eq!(received, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn resample_holds_data_until_valid() {
+        // the input changes every source cycle, but only the values at the valid
+        // cycles are captured, and the destination valid follows the capture after
+        // the toggle passes the two synchronizer flops and the edge detector
+        let res = run(48, (true, true), |cycle| {
+            ((cycle % 16) as u8, cycle == 1 || cycle == 10)
+        });
+
+        assert_eq!(res, [
+            (0, false),
+            (0, false),
+            (1, false),
+            (1, true),
+            (1, false),
+            (1, false),
+            (1, false),
+            (1, false),
+            (10, false),
+            (10, true),
+            (10, false),
+            (10, false),
+            (10, false),
+            (10, false),
+            (10, false),
+            (10, false),
+        ]);
+    }
+
+    #[test]
+    fn resample_only_toggle_crosses() {
+        // the data register is clocked by the source domain: it isn't loaded without
+        // the source clock, and the destination valid isn't raised
+        let res = run(48, (false, true), |cycle| (5, cycle == 1));
+        assert!(res.iter().all(|out| *out == (0, false)));
+
+        // the held data is visible right away, but the valid is raised only after the
+        // toggle passes the synchronizer flops clocked by the destination domain
+        let res = run(48, (true, false), |cycle| (5, cycle == 1));
+        assert_eq!(res.last(), Some(&(5, false)));
+        assert!(res.iter().all(|(_, valid)| !valid));
+    }
+}
@@ -1,13 +1,13 @@
 use fhdl_macros::synth;
 
 use super::{
-    reg::{reg0, Reset},
-    Signal,
+    reg::{reg, reg0, Enable, Reset},
+    Signal, SignalValue,
 };
 use crate::{
     bit::Bit,
     const_helpers::ConstConstr,
-    domain::{clk_divider, hz_to_period, Clock, ClockDomain},
+    domain::{clk_divider, freq_divider, Clock, ClockDomain},
     index::Idx,
 };
 
@@ -53,7 +53,7 @@ where
 #[macro_export]
 macro_rules! rise_rate_constr {
     ($domain:ident, $rate:expr) => {
-        $crate::rise_period_constr!($domain, $crate::domain::hz_to_period($rate))
+        $crate::rise_every_constr!($crate::domain::freq_divider::<$domain>($rate))
     };
 }
 
@@ -65,5 +65,183 @@ pub fn rise_rate<D: ClockDomain, const RATE: usize>(
 where
     ConstConstr<{ rise_rate_constr!(D, RATE) }>:,
 {
-    rise_period::<D, { hz_to_period(RATE) }>(clk, rst)
+    rise_every::<D, { freq_divider::<D>(RATE) }>(clk, rst)
+}
+
+// Multi-rate sampling: captures `data` on the clock edges where `en` is high
+// (e.g. the output of `rise_every`) and holds the captured value otherwise.
+#[synth(inline)]
+pub fn sample_on_enable<D: ClockDomain, V: SignalValue + Default>(
+    clk: &Clock<D>,
+    rst: &Reset<D>,
+    en: &Enable<D>,
+    data: &Signal<D, V>,
+) -> Signal<D, V> {
+    data.reg_en(clk, rst, en, |value| value)
+}
+
+// A pure delay pipeline: every stage applies its function and registers the result,
+// the valid bit is registered along with the data, so the output is valid exactly
+// `STAGES` cycles after the input. There is no backpressure, the data moves forward
+// on every clock edge.
+#[synth(inline)]
+pub fn pipeline<D: ClockDomain, V: SignalValue + Default, F, const STAGES: usize>(
+    clk: &Clock<D>,
+    rst: &Reset<D>,
+    data: &Signal<D, V>,
+    valid: &Signal<D, Bit>,
+    stages: [F; STAGES],
+) -> Signal<D, (V, Bit)>
+where
+    F: Fn(V) -> V + Clone + 'static,
+{
+    let mut res = data.zip(valid);
+    for stage in stages {
+        res = res.reg(clk, rst, move |(value, valid)| (stage(value), valid));
+    }
+    res
+}
+
+// Pulses high for one cycle whenever `sig` differs from its value in the previous
+// cycle: a register holding the previous value and a full-width comparison. In the
+// first cycle `sig` is compared with `init`
+#[synth(inline)]
+pub fn changed<D: ClockDomain, V: SignalValue + PartialEq>(
+    clk: &Clock<D>,
+    init: &V,
+    sig: &Signal<D, V>,
+) -> Signal<D, Bit> {
+    let rst = Reset::reset();
+    let prev = sig.and_then(|value| reg(clk, &rst, init, move |_| value.value()));
+    sig.zip_with(prev, |value, prev| value != prev)
+}
+
+impl<D: ClockDomain, T: SignalValue> Signal<D, T> {
+    // Downsampling: passes the data through unchanged and raises the valid bit
+    // every `N`th cycle, so only a counter is synthesized (no data register).
+    #[synth(inline)]
+    pub fn decimate<const N: usize>(
+        &self,
+        clk: &Clock<D>,
+        rst: &Reset<D>,
+    ) -> Signal<D, (T, Bit)>
+    where
+        ConstConstr<{ rise_every_constr!(N) }>:,
+    {
+        let valid = rise_every::<D, N>(clk, rst);
+        self.apply2(valid, |data, valid| (data, valid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bundle::Bundle, cast::Cast, domain::TD4, unsigned::U};
+
+    #[test]
+    fn sample_every_4th_cycle() {
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::reset();
+
+        let data = reg0(&clk, &rst, |val: U<8>| val + 1);
+        let en = rise_every::<TD4, 4>(&clk, &rst);
+        let sampled = sample_on_enable(&clk, &rst, &en, &data);
+
+        // take rising edges only
+        let res = (data, en, sampled)
+            .bundle()
+            .simulate(&clk, 32)
+            .into_iter()
+            .step_by(2)
+            .collect::<Vec<_>>();
+
+        for (cycle, (data, en, sampled)) in res.into_iter().enumerate() {
+            // `en` rises every 4th cycle, at the same edge the sample is updated
+            // with the value that `data` has had before this edge
+            assert_eq!(en, cycle > 0 && cycle % 4 == 0);
+            assert_eq!(data, cycle as u128);
+            assert_eq!(sampled, (cycle / 4 * 4).saturating_sub(1) as u128);
+        }
+    }
+
+    #[test]
+    fn decimate_by_4() {
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::reset();
+
+        let data = reg0(&clk, &rst, |val: U<8>| val + 1);
+
+        let res = data
+            .decimate::<4>(&clk, &rst)
+            .simulate(&clk, 32)
+            .into_iter()
+            .step_by(2)
+            .collect::<Vec<_>>();
+
+        for (cycle, (data, valid)) in res.into_iter().enumerate() {
+            assert_eq!(valid, cycle > 0 && cycle % 4 == 0);
+            assert_eq!(data, cycle as u128);
+        }
+    }
+
+    #[test]
+    fn changed_pulses() {
+        fn pulses(init: u8) -> Vec<bool> {
+            let clk = Clock::<TD4>::new();
+            let rst = Reset::reset();
+
+            // holds 3, changes to 5 and 6, then holds 6
+            let cnt = reg0(&clk, &rst, |cnt: U<4>| cnt + 1);
+            let sig = cnt.map(|cnt| {
+                let val: u8 = match cnt.cast::<u8>() {
+                    0 ..= 2 => 3,
+                    3 => 5,
+                    _ => 6,
+                };
+                val.cast::<U<4>>()
+            });
+
+            changed(&clk, &init.cast(), &sig)
+                .simulate(&clk, 16)
+                .into_iter()
+                .step_by(2)
+                .collect()
+        }
+
+        assert_eq!(pulses(3), [
+            false, false, false, true, true, false, false, false
+        ]);
+        // the first cycle is compared with `init`
+        assert_eq!(pulses(0), [
+            true, false, false, true, true, false, false, false
+        ]);
+    }
+
+    #[test]
+    fn pipeline_delays_valid() {
+        const STAGES: usize = 3;
+
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::reset();
+
+        let data = reg0(&clk, &rst, |val: U<8>| val + 1);
+        // a single valid pulse at the 2nd cycle
+        let valid = reg0(&clk, &rst, |cnt: U<5>| cnt + 1).map(|cnt| cnt == 2);
+        let out = pipeline(&clk, &rst, &data, &valid, [|val: U<8>| val * 2; STAGES]);
+
+        let res = (valid, out)
+            .bundle()
+            .simulate(&clk, 32)
+            .into_iter()
+            .step_by(2)
+            .collect::<Vec<_>>();
+
+        for (cycle, (in_valid, (data, valid))) in res.into_iter().enumerate() {
+            assert_eq!(in_valid, cycle == 2);
+            assert_eq!(valid, cycle == 2 + STAGES);
+            if cycle >= STAGES {
+                assert_eq!(data, ((cycle - STAGES) << STAGES) as u128);
+            }
+        }
+    }
 }
@@ -68,12 +68,14 @@ impl<D: ClockDomain, T: SignalValue> Signal<D, T> {
     {
         let mut this = self.clone();
         let mut other = other.into_signal();
+        let skip = this.skip.max(other.skip);
 
         Signal::new(move |ctx| {
             let this = this.next(ctx);
             let other = other.next(ctx);
             (f)(this, other)
         })
+        .skip(skip)
     }
 
     impl_cmp!(
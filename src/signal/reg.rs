@@ -4,6 +4,7 @@ use fhdl_macros::{blackbox, synth};
 
 use super::{Signal, SignalValue};
 use crate::{
+    bit::Bit,
     domain::{Clock, ClockDomain, Polarity, SyncKind},
     prelude::Unbundle,
 };
@@ -30,6 +31,31 @@ impl<D: ClockDomain> Enable<D> {
     }
 }
 
+// Gates the clock with `en` through a latch-based clock-gating cell, so registers
+// clocked by it are updated (or reset synchronously) only if `en` is true. Requires
+// clock gating to be enabled in the netlist config, otherwise `en` becomes the enable
+// of the registers and gates their synchronous resets
+#[blackbox(ClockGate)]
+pub fn gated_clock<D: ClockDomain>(clk: &Clock<D>, en: &Enable<D>) -> Clock<D> {
+    clk.gate(en)
+}
+
+// Combines `rst` with the (active-high) `local_rst`, e.g. a soft reset, and passes
+// the combined reset to `body`, so registers created with it are reset by either of
+// them. Nested calls accumulate their resets
+#[synth(inline)]
+pub fn with_reset<D: ClockDomain, V: SignalValue>(
+    rst: &Reset<D>,
+    local_rst: &Signal<D, Bit>,
+    body: impl FnOnce(&Reset<D>) -> Signal<D, V>,
+) -> Signal<D, V> {
+    let rst = match D::RESET_POLARITY {
+        Polarity::ActiveHigh => rst | local_rst,
+        Polarity::ActiveLow => rst & !local_rst,
+    };
+    body(&rst)
+}
+
 #[synth(inline)]
 pub fn reg<D: ClockDomain, T: SignalValue>(
     clk: &Clock<D>,
@@ -130,6 +156,28 @@ pub fn reg_en0_comb<D: ClockDomain, T: SignalValue + Default>(
     reg_en_comb(clk, rst, en, &T::default(), comb_fn)
 }
 
+// `init` is the power-up value of the register, `rst_val` is loaded on reset
+#[synth(inline)]
+pub fn reg_full<D: ClockDomain, T: SignalValue>(
+    clk: &Clock<D>,
+    rst: &Reset<D>,
+    init: &T,
+    rst_val: &T,
+    comb_fn: impl Fn(T) -> T + Clone + 'static,
+) -> Signal<D, T> {
+    let en = Enable::enable();
+    dff_full_::<D, T>(
+        clk,
+        rst,
+        &en,
+        init,
+        rst_val,
+        comb_fn,
+        D::RESET_KIND,
+        D::RESET_POLARITY,
+    )
+}
+
 #[synth(inline)]
 pub fn dff<
     D: ClockDomain,
@@ -160,6 +208,23 @@ fn dff_<D: ClockDomain, T: SignalValue>(
     reg
 }
 
+#[allow(clippy::too_many_arguments)]
+#[blackbox(SignalDffFull)]
+fn dff_full_<D: ClockDomain, T: SignalValue>(
+    clk: &Clock<D>,
+    rst: &Reset<D>,
+    en: &Enable<D>,
+    init: &T,
+    rst_val: &T,
+    comb_fn: impl Fn(T) -> T + Clone + 'static,
+    rst_kind: SyncKind,
+    rst_pol: Polarity,
+) -> Signal<D, T> {
+    let (reg, _) =
+        dff_sim(clk, rst, en, init, rst_val, comb_fn, rst_kind, rst_pol).unbundle();
+    reg
+}
+
 #[synth(inline)]
 pub fn dff_comb<
     D: ClockDomain,
@@ -187,26 +252,42 @@ fn dff_comb_<D: ClockDomain, T: SignalValue>(
     rst_kind: SyncKind,
     rst_pol: Polarity,
 ) -> Signal<D, (T, T)> {
-    let clk = clk.clone();
+    dff_sim(clk, rst, en, init, init, comb_fn, rst_kind, rst_pol)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn dff_sim<D: ClockDomain, T: SignalValue>(
+    clk: &Clock<D>,
+    rst: &Reset<D>,
+    en: &Enable<D>,
+    init: &T,
+    rst_val: &T,
+    comb_fn: impl Fn(T) -> T + Clone + 'static,
+    rst_kind: SyncKind,
+    rst_pol: Polarity,
+) -> Signal<D, (T, T)> {
+    let mut clk = clk.clone();
     let mut rst = match rst_pol {
         Polarity::ActiveHigh => rst.clone(),
         Polarity::ActiveLow => !rst,
     };
     let mut en = en.clone();
     let init = init.borrow().clone();
+    let rst_val = rst_val.borrow().clone();
 
     let mut val = init.clone();
-    let mut next_val = init.clone();
+    let mut next_val = init;
 
     match rst_kind {
         SyncKind::Async => Signal::new(move |ctx| {
             let rst = rst.next(ctx);
             let en = en.next(ctx);
+            let rising = clk.is_rising_gated(ctx);
             if rst {
-                val = init.clone();
+                val = rst_val.clone();
                 next_val = (comb_fn)(val.clone());
                 (val.clone(), next_val.clone())
-            } else if clk.is_rising() && en {
+            } else if rising && en {
                 val = next_val.clone();
                 next_val = (comb_fn)(val.clone());
                 (val.clone(), next_val.clone())
@@ -218,9 +299,9 @@ fn dff_comb_<D: ClockDomain, T: SignalValue>(
         SyncKind::Sync => Signal::new(move |ctx| {
             let rst = rst.next(ctx);
             let en = en.next(ctx);
-            if clk.is_rising() {
+            if clk.is_rising_gated(ctx) {
                 if rst {
-                    val = init.clone();
+                    val = rst_val.clone();
                     next_val = (comb_fn)(val.clone());
                     return (val.clone(), next_val.clone());
                 } else if en {
@@ -527,6 +608,91 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_gated_clock() {
+        let clk = Clock::<TD4>::default();
+        let rst = Reset::reset();
+        let en = Enable::enable();
+
+        let gclk = gated_clock(&clk, &en);
+        let mut r =
+            reg_comb::<_, U<2>>(&gclk, &rst, &0_u8.cast(), |val| val + 1).eval(&clk);
+
+        assert_eq!(
+            r.take_by_ref::<(u8, u8)>(7),
+            // R  F  R  F  R  F  R
+            [(0, 1), (0, 1), (1, 2), (1, 2), (2, 3), (2, 3), (3, 0)]
+        );
+
+        en.invert();
+        assert_eq!(
+            r.take_by_ref::<(u8, u8)>(4),
+            //F       R       F       R
+            [(3, 0), (3, 0), (3, 0), (3, 0)]
+        );
+
+        en.invert();
+        assert_eq!(
+            r.take_by_ref::<(u8, u8)>(5),
+            //F       R       F       R       F
+            [(3, 0), (0, 1), (0, 1), (1, 2), (1, 2)]
+        );
+
+        // the synchronous reset waits for the gate to open
+        en.invert();
+        rst.invert();
+        assert_eq!(
+            r.take_by_ref::<(u8, u8)>(4),
+            //R       F       R       F
+            [(1, 2), (1, 2), (1, 2), (1, 2)]
+        );
+
+        en.invert();
+        assert_eq!(
+            r.take_by_ref::<(u8, u8)>(2),
+            //R       F
+            [(0, 1), (0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_with_reset() {
+        let clk = Clock::<TD4>::default();
+        let rst = Reset::reset();
+        let soft_rst = Reset::reset();
+        let outer_rst = Reset::reset();
+
+        let mut r = with_reset(&rst, &outer_rst, |rst| {
+            with_reset(rst, &soft_rst, |rst| {
+                reg::<_, U<2>>(&clk, rst, &0_u8.cast(), |val| val + 1)
+            })
+        })
+        .eval(&clk);
+
+        assert_eq!(
+            r.take_by_ref::<u8>(5),
+            //R F  R  F  R
+            [0, 0, 1, 1, 2]
+        );
+
+        // each of the resets resets the register
+        for rst in [&soft_rst, &rst, &outer_rst] {
+            rst.invert();
+            assert_eq!(
+                r.take_by_ref::<u8>(4),
+                //F R  F  R
+                [2, 0, 0, 0]
+            );
+
+            rst.invert();
+            assert_eq!(
+                r.take_by_ref::<u8>(4),
+                //F R  F  R
+                [0, 1, 1, 2]
+            );
+        }
+    }
+
     #[test]
     fn test_reg_seq() {
         let clk = Clock::<TD4>::default();
@@ -603,4 +769,53 @@ mod tests {
             (0, true, [7, 6, 5, 4]), // F
         ]);
     }
+
+    fn make_counter(clk: &Clock<TD4>, rst: &Reset<TD4>) -> impl Eval<TD4, Value = U<4>> {
+        reg0(clk, rst, |val: U<4>| val + 1)
+    }
+
+    #[test]
+    fn test_impl_eval_output() {
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::reset();
+
+        let mut r = make_counter(&clk, &rst).eval(&clk);
+
+        assert_eq!(
+            r.take_by_ref::<u8>(6),
+            //R F  R  F  R  F
+            [0, 0, 1, 1, 2, 2]
+        );
+    }
+
+    #[test]
+    fn test_reg_full() {
+        struct Test;
+
+        impl ClockDomain for Test {
+            const FREQ: usize = 4;
+            const RESET_KIND: SyncKind = SyncKind::Sync;
+            const RESET_POLARITY: Polarity = Polarity::ActiveHigh;
+        }
+
+        let clk = Clock::<_>::new();
+        let rst = Reset::reset();
+
+        let mut r =
+            reg_full::<Test, U<4>>(&clk, &rst, &3_u8.cast(), &0_u8.cast(), |val| val + 1)
+                .eval(&clk);
+
+        assert_eq!(
+            r.take_by_ref::<u8>(5),
+            //R  F  R  F  R
+            [3, 3, 4, 4, 5]
+        );
+
+        rst.invert();
+        assert_eq!(
+            r.take_by_ref::<u8>(4),
+            //F  R  F  R
+            [5, 0, 0, 0]
+        );
+    }
 }
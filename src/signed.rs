@@ -1,17 +1,19 @@
-use std::{cmp::Ordering, io};
+use core::cmp::Ordering;
+#[cfg(feature = "std")]
+use std::io;
 
 use fhdl_macros::{blackbox, blackbox_ty, synth};
 use num_bigint::{BigInt, Sign};
+#[cfg(feature = "std")]
 use vcd::IdCode;
 
+#[cfg(feature = "std")]
+use crate::trace::{TraceTy, TraceVars, Traceable, Tracer};
 use crate::{
     bit::{self, Bit},
     bitpack::BitSize,
     cast::{Cast, CastFrom},
-    prelude::{
-        Assert, BitPack, BitVec, IsTrue, SignalValue, TraceTy, TraceVars, Traceable,
-        Tracer, U,
-    },
+    prelude::{Assert, BitPack, BitVec, IsTrue, SignalValue, U},
     unsigned::U_,
 };
 
@@ -173,6 +175,11 @@ impl<const N: usize, const M: usize> CastFrom<S<M>> for S<N> {
     #[blackbox(CastFrom)]
     fn cast_from(from: S<M>) -> S<N> {
         match from {
+            // the value is kept as raw bits, so it's sign extended explicitly
+            S::<M>::Short(short) if M > 0 && M < 128 && N > M => {
+                let shift = 128 - M;
+                S::<N>::from_short((short << shift) >> shift)
+            }
             S::<M>::Short(short) => S::<N>::from_short(short),
             S::<M>::Long(long) => S::<N>::from_long(long),
         }
@@ -197,6 +204,7 @@ impl<const N: usize, const M: usize> CastFrom<U<N>> for S<M> {
     }
 }
 
+#[cfg(feature = "std")]
 macro_rules! impl_trace_for_prims {
     ($( $prim:ty => $cast:ty ),+) => {
         $(
@@ -220,8 +228,10 @@ macro_rules! impl_trace_for_prims {
     };
 }
 
+#[cfg(feature = "std")]
 impl_trace_for_prims!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
 
+#[cfg(feature = "std")]
 impl<const N: usize> Traceable for S<N> {
     #[inline]
     fn add_vars(vars: &mut TraceVars) {
@@ -1,3 +1,7 @@
+mod axi_stream;
+mod barrel_shift;
 mod shift_reg;
 
+pub use axi_stream::{axi_stream_wrap, AxiStream};
+pub use barrel_shift::{barrel_shift, ShiftDir};
 pub use shift_reg::{ShiftReg, ShiftRegInput, ShiftRegMut};
@@ -0,0 +1,83 @@
+use fhdl_macros::{lang_item, synth};
+
+use crate::{
+    domain::{Clock, ClockDomain},
+    signal::{reg0, Enable, Reset, Signal, SignalValue},
+};
+
+// Outputs of an AXI-Stream-style handshake. The ports of a stream of the top module
+// get the standard names, e.g. the master stream `m_axis` returned from the top
+// is split into the `m_axis_tdata` and `m_axis_tvalid` ports
+#[lang_item(AxiStream)]
+#[derive(Debug, Clone, PartialEq, Eq, SignalValue)]
+pub struct AxiStream<T> {
+    pub tdata: T,
+    pub tvalid: bool,
+}
+
+// Wraps the datapath built by `pipeline` in a ready/valid handshake. The pipeline
+// gets an enable which is low while the consumer is not ready (`tready` is low)
+// and the output register is full, so the pipeline and `tdata` are stalled.
+#[synth(inline)]
+pub fn axi_stream_wrap<D: ClockDomain, T: SignalValue + Default>(
+    clk: &Clock<D>,
+    rst: &Reset<D>,
+    tready: &Signal<D, bool>,
+    pipeline: impl FnOnce(&Enable<D>) -> Signal<D, T>,
+) -> Signal<D, AxiStream<T>> {
+    // the datapath always produces data, so the output register is valid
+    // after it's loaded for the first time
+    let tvalid = reg0(clk, rst, |_: bool| true);
+    let en = tready.apply2(&tvalid, |tready, tvalid| tready || !tvalid);
+
+    let tdata = pipeline(&en).reg_en(clk, rst, &en, |tdata| tdata);
+
+    tdata.apply2(tvalid, |tdata, tvalid| AxiStream { tdata, tvalid })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bundle::Bundle, cast::Cast, domain::TD4, signal::reg_en0, unsigned::U};
+
+    #[test]
+    fn stall_on_not_ready() {
+        let clk = Clock::<TD4>::new();
+        let rst = Reset::reset();
+
+        let cycle = reg0(&clk, &rst, |cycle: U<8>| cycle + 1);
+        let tready = cycle.map(|cycle| !(3 .. 6).contains(&cycle.cast::<u8>()));
+        let m_axis = axi_stream_wrap(&clk, &rst, &tready, |en| {
+            reg_en0(&clk, &rst, en, |data: U<8>| data + 1)
+        });
+
+        // take rising edges only
+        let res = (tready, m_axis)
+            .bundle()
+            .simulate(&clk, 32)
+            .into_iter()
+            .step_by(2)
+            .collect::<Vec<_>>();
+
+        assert!(!res[0].1.tvalid);
+
+        let mut data = vec![];
+        for window in res.windows(2) {
+            let (_, prev) = &window[0];
+            let (tready, m_axis) = &window[1];
+
+            assert!(m_axis.tvalid);
+            if prev.tvalid && !tready {
+                // the data is held while the consumer is not ready
+                assert_eq!(m_axis, prev);
+            }
+            if data.last() != Some(&m_axis.tdata) {
+                data.push(m_axis.tdata.clone());
+            }
+        }
+
+        // nothing is lost while the pipeline is stalled
+        assert_eq!(data.len(), 12);
+        assert_eq!(data, (0 .. data.len() as u128).collect::<Vec<_>>());
+    }
+}
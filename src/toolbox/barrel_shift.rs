@@ -0,0 +1,50 @@
+use fhdl_macros::blackbox;
+
+use crate::{
+    const_helpers::ConstConstr,
+    index::{idx_constr, Idx},
+    signal::SignalValue,
+    unsigned::U,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SignalValue)]
+pub enum ShiftDir {
+    Left,
+    Right,
+}
+
+// `dir` should be a constant for synthesis
+#[blackbox(BarrelShift)]
+pub fn barrel_shift<const N: usize>(value: U<N>, amount: Idx<N>, dir: ShiftDir) -> U<N>
+where
+    ConstConstr<{ idx_constr(N) }>:,
+{
+    match dir {
+        ShiftDir::Left => value << amount,
+        ShiftDir::Right => value >> amount,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cast::Cast;
+
+    #[test]
+    fn barrel_shift_matches_shifts() {
+        let value: U<8> = 0b1011_0110_u8.cast();
+
+        for amount in 0 .. 8 {
+            let idx: Idx<8> = amount.cast();
+
+            assert_eq!(
+                barrel_shift(value.clone(), idx.clone(), ShiftDir::Left),
+                value.clone() << amount
+            );
+            assert_eq!(
+                barrel_shift(value.clone(), idx, ShiftDir::Right),
+                value.clone() >> amount
+            );
+        }
+    }
+}
@@ -22,7 +22,7 @@ impl<D: ClockDomain, const N: usize, T: SignalValue + Default> Module
     type Output = [T; N];
 
     fn logic(self, ShiftRegInput { next, init, data }: Self::Input) -> Self::Output {
-        let mut sr = <[T; N]>::make(T::default);
+        let mut sr = <[T; N]>::default_();
         let mut prev = data;
 
         for (idx, reg) in self.regs.into_iter().enumerate() {
@@ -31,6 +31,7 @@ mod tests {
     use crate::{
         array::Array,
         bit::Bit,
+        bitpack::bit_width,
         domain::{Clock, TD4},
         signal::SignalIterExt,
         unsigned::U,
@@ -101,6 +102,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bit_size() {
+        assert_eq!(<(U<4>, Bit, Array<2, U<2>>) as BitSize>::BITS, 9);
+        assert_eq!(bit_width::<(U<4>, Bit, Array<2, U<2>>)>(), 9);
+    }
+
     #[test]
     fn pack() {
         let s: (U<4>, Bit, Array<2, U<2>>) =
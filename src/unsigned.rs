@@ -1,18 +1,22 @@
-use std::{
+use core::{
     cmp::Ordering::{self, *},
     fmt::{self, Binary, Display, LowerHex},
-    io,
     marker::StructuralPartialEq,
     ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Rem, Shl, Shr, Sub},
 };
+#[cfg(feature = "std")]
+use std::io;
 
-use fhdl_const_func::mask;
+use fhdl_const_func::{clog2, mask};
 use fhdl_macros::{blackbox, blackbox_ty, synth};
 use num_bigint::BigUint;
 use num_traits::Zero;
 use paste::paste;
+#[cfg(feature = "std")]
 use vcd::IdCode;
 
+#[cfg(feature = "std")]
+use crate::trace::{bool_to_vcd, TraceTy, TraceVars, Traceable, Tracer};
 use crate::{
     bit::Bit,
     bitpack::{BitPack, BitSize, BitVec},
@@ -20,7 +24,6 @@ use crate::{
     const_helpers::{Assert, ConstConstr, IsTrue},
     index::{idx_constr, Idx},
     signal::SignalValue,
-    trace::{bool_to_vcd, TraceTy, TraceVars, Traceable, Tracer},
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -104,6 +107,96 @@ impl<const N: usize> U<N> {
             U_::Long(long) => !long.is_zero(),
         }
     }
+
+    #[blackbox(UnsignedClog2)]
+    pub fn clog2_runtime(self) -> U<{ clog2(N + 1) }>
+    where
+        ConstConstr<{ clog2(N + 1) }>:,
+    {
+        let bits = match &self.0 {
+            U_::Short(short) => (u128::BITS - short.leading_zeros()) as u128,
+            U_::Long(long) => long.bits() as u128,
+        };
+        // the same as clog2(0) = 1
+        bits.max(1).cast()
+    }
+
+    // Shifts left by one inserting `carry_in` at the LSB, returns the old MSB as carry-out
+    #[blackbox(UnsignedRotateThroughCarry)]
+    pub fn rotate_through_carry(self, carry_in: Bit) -> (Self, Bit) {
+        if N == 0 {
+            return (self, carry_in);
+        }
+
+        let carry_out = self.bit_(N - 1);
+        ((self << 1_usize) | U::<N>::cast_from(carry_in), carry_out)
+    }
+
+    // Returns `|self - other|` without underflow
+    #[blackbox(UnsignedAbsDiff)]
+    pub fn abs_diff(self, other: Self) -> Self {
+        match (self.0, other.0) {
+            (U_::Short(lhs), U_::Short(rhs)) => U::from_short(lhs.abs_diff(rhs)),
+            (U_::Long(lhs), U_::Long(rhs)) => {
+                if lhs >= rhs {
+                    U::from_long(lhs - rhs)
+                } else {
+                    U::from_long(rhs - lhs)
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Returns `self^E` wrapping at `N` bits
+    #[blackbox(UnsignedPow)]
+    pub fn pow<const E: usize>(self) -> Self {
+        let mut res = U::<N>::cast_from(1_u8);
+        for _ in 0 .. E {
+            res = res * self.clone();
+        }
+        res
+    }
+
+    // Returns `None` if `rhs` is greater than or equal to the number of bits
+    #[synth(inline)]
+    pub fn checked_shl(self, rhs: usize) -> Option<Self> {
+        if rhs < N {
+            Some(self << rhs)
+        } else {
+            None
+        }
+    }
+
+    // Returns `None` if `rhs` is greater than or equal to the number of bits
+    #[synth(inline)]
+    pub fn checked_shr(self, rhs: usize) -> Option<Self> {
+        if rhs < N {
+            Some(self >> rhs)
+        } else {
+            None
+        }
+    }
+
+    // Returns `W` bits starting at the runtime `offset`, the bits past the MSB
+    // are filled with zeros
+    #[synth(inline)]
+    pub fn extract<const W: usize, const M: usize>(self, offset: U<M>) -> U<W> {
+        (self >> offset).cast::<U<W>>()
+    }
+
+    // Replaces `W` bits starting at the runtime `offset` with `value`, the bits
+    // of `value` past the MSB are dropped
+    #[synth(inline)]
+    pub fn insert<const W: usize, const M: usize>(
+        self,
+        offset: U<M>,
+        value: U<W>,
+    ) -> Self {
+        let mask = (!U::<W>::cast_from(0_u8)).cast::<U<N>>() << offset.clone();
+        let value = value.cast::<U<N>>() << offset;
+        (self & !mask) | value
+    }
 }
 
 impl<const N: usize> SignalValue for U<N> {}
@@ -217,6 +310,7 @@ impl<const N: usize> PartialOrd for U<N> {
 }
 
 impl<const N: usize> Ord for U<N> {
+    #[blackbox(OpCmp)]
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
         match (&self.0, &other.0) {
@@ -515,6 +609,11 @@ macro_rules! impl_shift_ops {
 
                 #[blackbox(OpShl)]
                 fn shl(self, rhs: $prim) -> Self::Output {
+                    // shifts by `N` or more bits produce zero
+                    if rhs >= N {
+                        return U::from_short(0);
+                    }
+
                     match self.0 {
                         U_::Short(short) => Self::from_short(short.shl(rhs)),
                         U_::Long(long) => Self::from_long(long.shl(rhs)),
@@ -527,6 +626,11 @@ macro_rules! impl_shift_ops {
 
                 #[blackbox(OpShl)]
                 fn shl(self, rhs: $prim) -> Self::Output {
+                    // shifts by `N` or more bits produce zero
+                    if rhs >= N {
+                        return U::from_short(0);
+                    }
+
                     match &self.0 {
                         U_::Short(short) => U::from_short((*short).shl(rhs)),
                         U_::Long(long) => U::from_long(long.shl(rhs)),
@@ -539,6 +643,11 @@ macro_rules! impl_shift_ops {
 
                 #[blackbox(OpShr)]
                 fn shr(self, rhs: $prim) -> Self::Output {
+                    // shifts by `N` or more bits produce zero
+                    if rhs >= N {
+                        return U::from_short(0);
+                    }
+
                     match self.0 {
                         U_::Short(short) => U::from_short(short.shr(rhs)),
                         U_::Long(long) => U::from_long(long.shr(rhs)),
@@ -551,6 +660,11 @@ macro_rules! impl_shift_ops {
 
                 #[blackbox(OpShr)]
                 fn shr(self, rhs: $prim) -> Self::Output {
+                    // shifts by `N` or more bits produce zero
+                    if rhs >= N {
+                        return U::from_short(0);
+                    }
+
                     match &self.0 {
                         U_::Short(short) => U::from_short((*short).shr(rhs)),
                         U_::Long(long) => U::from_long(long.shr(rhs)),
@@ -610,19 +724,21 @@ where
 impl<const N: usize> Not for U<N> {
     type Output = Self;
 
+    #[blackbox(OpNot)]
     fn not(self) -> Self::Output {
         match self.0 {
             U_::Short(short) => Self::from_short(short.not()),
             U_::Long(long) => Self::from_long(BigUint::from_slice(
                 long.iter_u32_digits()
                     .map(|digit| !digit)
-                    .collect::<Vec<u32>>()
+                    .collect::<alloc::vec::Vec<u32>>()
                     .as_slice(),
             )),
         }
     }
 }
 
+#[cfg(feature = "std")]
 macro_rules! impl_trace_for_prims {
     ($( $prim:ty ),+) => {
         $(
@@ -652,8 +768,10 @@ macro_rules! impl_trace_for_prims {
     };
 }
 
+#[cfg(feature = "std")]
 impl_trace_for_prims!(u8, u16, u32, u64, u128, usize);
 
+#[cfg(feature = "std")]
 impl<const N: usize> Traceable for U<N> {
     fn add_vars(vars: &mut TraceVars) {
         vars.add_ty(TraceTy::Bus(N as u32));
@@ -673,3 +791,140 @@ impl<const N: usize> Traceable for U<N> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitpack::BitPackExt;
+
+    fn abs_diff(a: U<4>, b: U<4>) -> U<4> {
+        match a.cmp(&b) {
+            Less => b - a,
+            Equal => U::from_short(0),
+            Greater => a - b,
+        }
+    }
+
+    #[test]
+    fn match_on_cmp() {
+        assert_eq!(abs_diff(U::from_short(3), U::from_short(7)), 4);
+        assert_eq!(abs_diff(U::from_short(5), U::from_short(5)), 0);
+        assert_eq!(abs_diff(U::from_short(9), U::from_short(2)), 7);
+    }
+
+    #[test]
+    fn abs_diff_method() {
+        assert_eq!(U::<4>::from_short(3).abs_diff(U::from_short(7)), 4);
+        assert_eq!(U::<4>::from_short(9).abs_diff(U::from_short(2)), 7);
+        assert_eq!(U::<4>::from_short(5).abs_diff(U::from_short(5)), 0);
+        assert_eq!(U::<4>::from_short(0).abs_diff(U::from_short(15)), 15);
+
+        let long = U::<200>::from_short(1) << 150_usize;
+        assert_eq!(
+            U::<200>::from_short(1).abs_diff(long.clone()),
+            long - U::<200>::from_short(1)
+        );
+    }
+
+    #[test]
+    fn pow() {
+        let x = U::<8>::from_short(3);
+        assert_eq!(x.clone().pow::<0>(), 1);
+        assert_eq!(x.clone().pow::<1>(), 3);
+        assert_eq!(x.clone().pow::<3>(), 27);
+        // 3^6 = 729 wraps at 8 bits
+        assert_eq!(x.pow::<6>(), 729 % 256);
+    }
+
+    #[test]
+    fn extend() {
+        let neg = U::<4>::from_short(0b1010);
+        let pos = U::<4>::from_short(0b0101);
+
+        assert_eq!(neg.zero_extend::<8, U<8>>(), 0b0000_1010);
+        assert_eq!(neg.sign_extend::<8, U<8>>(), 0b1111_1010);
+        assert_eq!(pos.zero_extend::<8, U<8>>(), 0b0101);
+        assert_eq!(pos.sign_extend::<8, U<8>>(), 0b0101);
+    }
+
+    #[test]
+    fn shift_out_of_range() {
+        let val = U::<8>::from_short(0xa5);
+
+        for amount in [8_usize, 100, 200] {
+            assert_eq!(val.clone() << amount, 0);
+            assert_eq!(val.clone() >> amount, 0);
+            assert_eq!(val.clone().checked_shl(amount), None);
+            assert_eq!(val.clone().checked_shr(amount), None);
+        }
+        assert_eq!(val.clone() << U::<8>::from_short(100), 0);
+        assert_eq!(val.clone().checked_shl(4), Some(U::from_short(0x50)));
+        assert_eq!(val.checked_shr(7), Some(U::from_short(1)));
+
+        let long = U::<200>::from_short(1) << 150_usize;
+        assert_eq!(long.clone() << 50_usize, 0);
+        assert_eq!(long >> 1000_usize, 0);
+    }
+
+    #[test]
+    fn extract_insert() {
+        let val = U::<16>::from_short(0xabcd);
+
+        let fields = (0 .. 16)
+            .step_by(4)
+            .map(|offset| val.clone().extract::<4, 4>(U::from_short(offset)))
+            .collect::<Vec<_>>();
+        assert_eq!(fields, [0xd, 0xc, 0xb, 0xa]);
+
+        // the bits past the MSB are zeros
+        assert_eq!(val.clone().extract::<4, 4>(U::from_short(14)), 0b10);
+        assert_eq!(val.clone().extract::<4, 8>(U::from_short(200)), 0);
+
+        let field = val.clone().extract::<4, 4>(U::from_short(4));
+        assert_eq!(
+            U::<16>::from_short(0).insert(U::<4>::from_short(8), field.clone()),
+            0x0c00
+        );
+        assert_eq!(
+            val.clone().insert(U::<4>::from_short(0), field.clone()),
+            0xabcc
+        );
+        assert_eq!(
+            val.clone().insert(U::<4>::from_short(14), field.clone()),
+            0x2bcd
+        );
+        assert_eq!(val.clone().insert(U::<8>::from_short(200), field), 0xabcd);
+    }
+
+    #[test]
+    fn clog2_runtime() {
+        for val in [0_usize, 1, 2, 3, 4, 7, 8, 100, 127, 128, 255] {
+            assert_eq!(
+                U::<8>::from_short(val as u128).clog2_runtime(),
+                clog2(val) as u128
+            );
+        }
+    }
+
+    #[test]
+    fn rotate_through_carry() {
+        // 8-bit Fibonacci LFSR with taps 8, 6, 5, 4
+        let mut state = U::<8>::from_short(1);
+        let mut expected = 1_u8;
+
+        for _ in 0 .. 300 {
+            let feedback = state.bit_(7) ^ state.bit_(5) ^ state.bit_(4) ^ state.bit_(3);
+            let msb = state.bit_(7);
+            let carry_out;
+            (state, carry_out) = state.rotate_through_carry(feedback);
+
+            let feedback =
+                ((expected >> 7) ^ (expected >> 5) ^ (expected >> 4) ^ (expected >> 3))
+                    & 1;
+            expected = (expected << 1) | feedback;
+
+            assert_eq!(carry_out, msb);
+            assert_eq!(state, expected as u128);
+        }
+    }
+}
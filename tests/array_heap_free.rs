@@ -0,0 +1,50 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ferrum_hdl::{array::ArrayExt, bitpack::BitPack, cast::Cast, unsigned::U};
+
+// counts heap allocations, so the behavioral array paths can be checked to run
+// without a heap
+struct CountingAlloc;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[test]
+fn array_ops_do_not_allocate() {
+    let before = ALLOCS.load(Ordering::SeqCst);
+
+    let a = <[U<4>; 4]>::make_idx(|idx| idx.val().cast::<U<4>>());
+    let b = a
+        .clone()
+        .map_idx(|idx, item| item + idx.val().cast::<U<4>>());
+    let packed = b.clone().pack();
+    let c = <[U<4>; 4]>::unpack(packed);
+    let d = c.slice::<2>(1_usize.cast());
+    let e = <[U<4>; 2]>::from_iter_exact(c.clone().into_iter().skip(2));
+
+    let after = ALLOCS.load(Ordering::SeqCst);
+
+    assert_eq!(b, c);
+    assert_eq!(d, [2_u8, 4].cast::<[U<4>; 2]>());
+    assert_eq!(e, [4_u8, 6].cast::<[U<4>; 2]>());
+    assert_eq!(after, before);
+}
@@ -0,0 +1,61 @@
+use std::{fs, path::Path, process::Command};
+
+const LIB: &str = r#"
+#![no_std]
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+
+use ferrum_hdl::prelude::*;
+
+pub fn offsets(arr: [U<4>; 4]) -> [U<4>; 4] {
+    arr.map_idx(|idx, item| item + idx.val().cast::<U<4>>())
+}
+
+pub fn unpacked(arr: [U<4>; 4]) -> [U<4>; 4] {
+    <[U<4>; 4]>::unpack(arr.pack())
+}
+
+pub fn from_iter(arr: [U<4>; 4]) -> [U<4>; 2] {
+    <[U<4>; 2]>::from_iter_exact(arr.into_iter().skip(2))
+}
+"#;
+
+// builds a `no_std` crate using the array paths of the behavioral model against the
+// crate without the `std` feature
+#[test]
+fn array_ops_build_without_std() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_std");
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"array_no_std\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+             [dependencies]\nferrum_hdl = {{ path = {:?}, default-features = false }}\n\n\
+             [workspace]\n",
+            workspace
+        ),
+    )
+    .unwrap();
+    fs::copy(
+        workspace.join("rust-toolchain.toml"),
+        dir.join("rust-toolchain.toml"),
+    )
+    .unwrap();
+    fs::write(dir.join("src").join("lib.rs"), LIB).unwrap();
+
+    let output = Command::new(env!("CARGO"))
+        .current_dir(&dir)
+        .args(["build", "--lib", "--offline", "--target-dir"])
+        .arg(dir.join("target"))
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "no_std build failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -493,6 +493,23 @@ mod test_enum_with_discr {
     }
 }
 
+mod test_enum_with_implicit_discr_after_explicit {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, SignalValue, BitPack)]
+    enum Test {
+        A = 2,
+        B,
+        C,
+    }
+
+    #[test]
+    fn pack() {
+        assert_eq!(Test::BITS, 3);
+        assert_eq!(Test::C.pack(), 0b100_u64.cast::<U<_>>());
+    }
+}
+
 mod test_unpack_invalid_enum_variant {
     use super::*;
 
@@ -656,3 +673,36 @@ mod test_enum_with_spec_bitsize {
         assert_eq!(Test::A.pack(), 0b011011_u64.cast::<U<_>>());
     }
 }
+
+mod test_enum_cast {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+    enum Status {
+        Idle,
+        Busy(U<4>),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+    #[bitpack(cast_from = "Status")]
+    enum MyStatus {
+        Waiting,
+        Working(U<4>),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+    #[bitpack(cast_from = "MyStatus")]
+    enum StatusBack {
+        Idle,
+        Busy(U<4>),
+    }
+
+    #[test]
+    fn round_trip() {
+        let my: MyStatus = Status::Busy(5_u8.cast()).cast();
+        assert_eq!(my, MyStatus::Working(5_u8.cast()));
+        assert_eq!(my.cast::<StatusBack>(), StatusBack::Busy(5_u8.cast()));
+
+        assert_eq!(Status::Idle.cast::<MyStatus>(), MyStatus::Waiting);
+    }
+}
@@ -0,0 +1,27 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{cast::Cast, prelude::synth, unsigned::U};
+
+// a match on a bool nested in an arm of another one, each combination of the
+// conditions selects its argument
+#[synth(inline)]
+fn select(b: bool, c: bool, x: U<8>, y: U<8>, z: U<8>) -> U<8> {
+    match b {
+        true => match c {
+            true => x,
+            false => y,
+        },
+        false => z,
+    }
+}
+
+#[test]
+fn bool_match() {
+    let res = [(false, false), (false, true), (true, false), (true, true)]
+        .map(|(b, c)| select(b, c, 1_u8.cast(), 2_u8.cast(), 3_u8.cast()).cast::<u8>());
+
+    assert_eq!(res, [3, 3, 2, 1]);
+}
@@ -0,0 +1,20 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{cast::Cast, prelude::synth, unsigned::U};
+
+// the big-endian bytes read as little-endian ones reverse the byte order
+#[synth(inline)]
+fn swap_bytes(a: U<32>) -> U<32> {
+    U::from_le_bytes(a.to_be_bytes())
+}
+
+#[test]
+fn byte_lanes_endianness() {
+    let a: U<32> = 0x1234_5678_u32.cast();
+
+    assert_eq!(swap_bytes(a.clone()).cast::<u32>(), 0x7856_3412);
+    assert_eq!(swap_bytes(swap_bytes(a.clone())), a);
+}
@@ -0,0 +1,66 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{
+    cast::Cast,
+    domain::{Clock, TD4},
+    eval::Eval,
+    prelude::synth,
+    signal::{reg, Reset, Signal},
+    unsigned::U,
+};
+
+// the captured accumulator is threaded through the closure instances of the unrolled loop
+#[synth(inline)]
+fn sum_above(arr: [U<8>; 4], threshold: U<8>) -> U<8> {
+    let mut acc: U<8> = 0_u8.cast();
+    let mut add = |x: U<8>| acc = acc.clone() + x;
+    for x in arr {
+        if x > threshold {
+            add(x);
+        }
+    }
+    acc
+}
+
+#[test]
+fn accumulate_in_captured_state() {
+    let arr = [1_u8, 5, 2, 7].map(|x| x.cast::<U<8>>());
+
+    assert_eq!(sum_above(arr.clone(), 0_u8.cast()).cast::<u8>(), 15);
+    assert_eq!(sum_above(arr, 2_u8.cast()).cast::<u8>(), 12);
+}
+
+// the captured register is updated by each call, so the output is the counter plus
+// the sum of the inputs
+#[synth(inline)]
+fn count_plus_sum(
+    clk: Clock<TD4>,
+    rst: Reset<TD4>,
+    arr: [Signal<TD4, U<8>>; 4],
+) -> Signal<TD4, U<8>> {
+    let mut acc =
+        reg::<TD4, U<8>>(&clk, &rst, &0_u8.cast(), |acc| acc + 1_u8.cast::<U<8>>());
+    let mut add = |x: Signal<TD4, U<8>>| acc = acc.zip_with(x, |acc, x| acc + x);
+    for x in arr {
+        add(x);
+    }
+    acc
+}
+
+#[test]
+fn update_captured_signal() {
+    let clk = Clock::<TD4>::new();
+    let arr = [1_u8, 5, 2, 7].map(|x| Signal::lift(x.cast::<U<8>>()));
+
+    let res = count_plus_sum(clk.clone(), Reset::reset(), arr)
+        .eval(&clk)
+        .step_by(2)
+        .take(4)
+        .map(|x| x.cast::<u8>())
+        .collect::<Vec<_>>();
+
+    assert_eq!(res, [15, 16, 17, 18]);
+}
@@ -0,0 +1,95 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{
+    bit::Bit,
+    bitpack::BitPack,
+    cast::Cast,
+    domain::{Clock, TD4},
+    eval::Eval,
+    prelude::synth,
+    signal::{reg, Reset, Signal, SignalValue},
+    unsigned::U,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, SignalValue, BitPack)]
+struct State {
+    count: U<4>,
+    flag: Bit,
+    inner: Inner,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+struct Inner(U<3>);
+
+impl Default for Inner {
+    fn default() -> Self {
+        Inner(5_u8.cast())
+    }
+}
+
+#[synth(inline)]
+fn state(clk: Clock<TD4>, rst: Reset<TD4>) -> Signal<TD4, State> {
+    reg::<TD4, State>(&clk, &rst, &State::default(), |s| State {
+        count: s.count + 1,
+        flag: !s.flag,
+        inner: s.inner,
+    })
+}
+
+#[test]
+#[allow(clippy::unusual_byte_groupings)]
+fn reset_to_derived_default() {
+    let clk = Clock::<TD4>::new();
+    let rst = Reset::reset();
+
+    let bits = |s: State| s.pack().cast::<u8>();
+    let mut s = state(clk.clone(), rst.clone()).eval(&clk);
+
+    // count | flag | inner
+    assert_eq!(s.by_ref().take(4).map(bits).collect::<Vec<_>>(), [
+        0b0000_0_101,
+        0b0000_0_101,
+        0b0001_1_101,
+        0b0001_1_101
+    ]);
+
+    rst.invert();
+    assert_eq!(s.by_ref().take(3).map(bits).collect::<Vec<_>>(), [
+        0b0000_0_101,
+        0b0000_0_101,
+        0b0000_0_101
+    ]);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, SignalValue, BitPack)]
+struct Counters {
+    lo: U<3>,
+    hi: Inner,
+    en: bool,
+}
+
+#[synth(inline)]
+fn counters(clk: Clock<TD4>, rst: Reset<TD4>) -> Signal<TD4, Counters> {
+    reg::<TD4, Counters>(&clk, &rst, &Default::default(), |s| Counters {
+        lo: s.lo + 1,
+        hi: s.hi,
+        en: !s.en,
+    })
+}
+
+#[test]
+#[allow(clippy::unusual_byte_groupings)]
+fn init_is_packed_field_defaults() {
+    let clk = Clock::<TD4>::new();
+    let rst = Reset::reset();
+
+    let init = counters(clk.clone(), rst).eval(&clk).next().unwrap();
+    let field_defaults = (U::<3>::default(), Inner::default(), bool::default());
+
+    assert_eq!(init.clone().pack(), field_defaults.pack());
+    // lo | hi | en
+    assert_eq!(init.pack().cast::<u8>(), 0b000_101_0);
+}
@@ -0,0 +1,21 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{bit::Bit, bitpack::BitPackExt, cast::Cast, unsigned::U};
+
+// synthesized as an instance of `my_ip` from `my_ip.v`, the body is the behavioral model
+#[fhdl_tool::extern_verilog(module = "my_ip", file = "my_ip.v")]
+pub fn my_ip(a: U<8>, b: U<8>) -> (U<8>, Bit) {
+    let sum = a.cast::<U<9>>() + b.cast::<U<9>>();
+    (sum.clone().cast(), sum.msb())
+}
+
+#[test]
+fn extern_verilog_model() {
+    let (sum, carry) = my_ip(200_u8.cast(), 100_u8.cast());
+
+    assert_eq!(sum.cast::<u8>(), 44);
+    assert!(carry);
+}
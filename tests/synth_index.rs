@@ -0,0 +1,173 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use std::ops::Index;
+
+use ferrum_hdl::{
+    array::ArrayExt,
+    cast::Cast,
+    domain::{Clock, TD4},
+    eval::Eval,
+    index::Idx,
+    prelude::synth,
+    signal::{reg, Reset, Signal},
+    unsigned::U,
+};
+
+// the runtime index selects any of the elements
+#[synth(inline)]
+fn select(arr: [U<4>; 4], idx: Idx<4>) -> U<4> {
+    arr[idx.cast::<usize>()].clone()
+}
+
+#[test]
+fn index_array_with_runtime_idx() {
+    let arr = [3_u8, 7, 11, 15].map(Cast::cast::<U<4>>);
+
+    for (idx, expected) in [3_u8, 7, 11, 15].into_iter().enumerate() {
+        let idx: Idx<4> = idx.cast();
+        assert_eq!(select(arr.clone(), idx).cast::<u8>(), expected);
+    }
+}
+
+// the array is rotated by the value of a counter, so it goes through all 4 rotations
+#[synth(inline)]
+fn rotating(clk: Clock<TD4>, rst: Reset<TD4>, arr: [U<4>; 4]) -> Signal<TD4, [U<4>; 4]> {
+    reg::<TD4, U<2>>(&clk, &rst, &0_u8.cast(), |cnt| cnt + 1)
+        .map(move |cnt| arr.clone().rotate_left(cnt.cast::<usize>()))
+}
+
+#[test]
+fn rotate_by_counter() {
+    let clk = Clock::<TD4>::new();
+    let arr = [0_u8, 1, 2, 3].map(Cast::cast::<U<4>>);
+
+    let rotated = rotating(clk.clone(), Reset::reset(), arr)
+        .eval(&clk)
+        .step_by(2)
+        .take(5)
+        .map(|arr| arr.map(Cast::cast::<u8>))
+        .collect::<Vec<_>>();
+
+    assert_eq!(rotated, [
+        [0, 1, 2, 3],
+        [1, 2, 3, 0],
+        [2, 3, 0, 1],
+        [3, 0, 1, 2],
+        [0, 1, 2, 3]
+    ]);
+}
+
+static SQUARES: [U<8>; 8] = [
+    U::from(0),
+    U::from(1),
+    U::from(4),
+    U::from(9),
+    U::from(16),
+    U::from(25),
+    U::from(36),
+    U::from(49),
+];
+
+// the runtime index reads the entries of a static table
+#[synth(inline)]
+fn square(x: Idx<8>) -> U<8> {
+    SQUARES[x.cast::<usize>()].clone()
+}
+
+#[test]
+fn index_static_lookup_table() {
+    for x in 0 .. 8_u8 {
+        assert_eq!(square((x as usize).cast()).cast::<u8>(), x * x);
+    }
+}
+
+#[derive(Clone)]
+pub struct Regs([U<8>; 4]);
+
+impl Index<usize> for Regs {
+    type Output = U<8>;
+
+    fn index(&self, idx: usize) -> &U<8> {
+        &self.0[idx]
+    }
+}
+
+// `Index::index` of a user type forwards the constant indices to the inner array
+#[synth(inline)]
+fn sum_regs(regs: [U<8>; 4]) -> U<8> {
+    let regs = Regs(regs);
+    regs[2].clone() + regs[0].clone()
+}
+
+#[test]
+fn index_user_type() {
+    let regs = [1_u8, 2, 3, 4].map(Cast::cast::<U<8>>);
+    assert_eq!(Regs(regs.clone())[2].clone().cast::<u8>(), 3);
+    assert_eq!(sum_regs(regs).cast::<u8>(), 4);
+}
+
+// one period of a sine in 256 samples, scaled to 0 ..= 255
+const SINE: [u8; 256] = [
+    128, 131, 134, 137, 140, 143, 146, 149, 152, 155, 158, 162, 165, 167, 170, 173, 176,
+    179, 182, 185, 188, 190, 193, 196, 198, 201, 203, 206, 208, 211, 213, 215, 218, 220,
+    222, 224, 226, 228, 230, 232, 234, 235, 237, 238, 240, 241, 243, 244, 245, 246, 248,
+    249, 250, 250, 251, 252, 253, 253, 254, 254, 254, 255, 255, 255, 255, 255, 255, 255,
+    254, 254, 254, 253, 253, 252, 251, 250, 250, 249, 248, 246, 245, 244, 243, 241, 240,
+    238, 237, 235, 234, 232, 230, 228, 226, 224, 222, 220, 218, 215, 213, 211, 208, 206,
+    203, 201, 198, 196, 193, 190, 188, 185, 182, 179, 176, 173, 170, 167, 165, 162, 158,
+    155, 152, 149, 146, 143, 140, 137, 134, 131, 128, 124, 121, 118, 115, 112, 109, 106,
+    103, 100, 97, 93, 90, 88, 85, 82, 79, 76, 73, 70, 67, 65, 62, 59, 57, 54, 52, 49, 47,
+    44, 42, 40, 37, 35, 33, 31, 29, 27, 25, 23, 21, 20, 18, 17, 15, 14, 12, 11, 10, 9, 7,
+    6, 5, 5, 4, 3, 2, 2, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 2, 2, 3, 4, 5, 5, 6, 7,
+    9, 10, 11, 12, 14, 15, 17, 18, 20, 21, 23, 25, 27, 29, 31, 33, 35, 37, 40, 42, 44,
+    47, 49, 52, 54, 57, 59, 62, 65, 67, 70, 73, 76, 79, 82, 85, 88, 90, 93, 97, 100, 103,
+    106, 109, 112, 115, 118, 121, 124,
+];
+
+// the counter wraps around, so the samples of the table are read in a loop
+#[synth(inline)]
+fn sine(clk: Clock<TD4>, rst: Reset<TD4>) -> Signal<TD4, U<8>> {
+    reg::<TD4, U<8>>(&clk, &rst, &0_u8.cast(), |cnt| cnt + 1)
+        .map(|cnt| SINE[cnt.cast::<usize>()].cast())
+}
+
+#[test]
+fn index_const_lookup_table_by_counter() {
+    let clk = Clock::<TD4>::new();
+
+    let samples = sine(clk.clone(), Reset::reset())
+        .eval(&clk)
+        .step_by(2)
+        .take(300)
+        .map(|sample| sample.cast::<u8>())
+        .collect::<Vec<_>>();
+
+    for (cycle, sample) in samples.into_iter().enumerate() {
+        assert_eq!(sample, SINE[cycle % 256]);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Add,
+    Sub,
+    Nop,
+}
+
+const OPCODES: [u8; 3] = [0x33, 0x3b, 0x13];
+
+// the discriminant of the enum is the index of its entry in the table
+#[synth(inline)]
+fn opcode(op: Op) -> u8 {
+    OPCODES[op as usize]
+}
+
+#[test]
+fn index_const_table_by_enum_discr() {
+    let res = [Op::Nop, Op::Add, Op::Sub].map(opcode);
+
+    assert_eq!(res, [0x13, 0x33, 0x3b]);
+}
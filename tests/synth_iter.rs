@@ -0,0 +1,146 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use std::{iter, ops::ControlFlow};
+
+use ferrum_hdl::{array::ArrayExt, cast::Cast, prelude::synth, unsigned::U};
+
+// the chained maps are applied to each item in order
+#[synth(inline)]
+fn sum_of_doubled_incs(a: [U<8>; 4]) -> U<8> {
+    let mut sum: U<8> = 0_u8.cast();
+    for x in a
+        .into_iter()
+        .map(|x| x + 1_u8.cast::<U<8>>())
+        .map(|x| x << 1_usize)
+    {
+        sum = sum + x;
+    }
+    sum
+}
+
+#[test]
+fn map_chain() {
+    let a = [1_u8, 2, 3, 4].map(|x| x.cast::<U<8>>());
+
+    assert_eq!(sum_of_doubled_incs(a).cast::<u8>(), 2 * (2 + 3 + 4 + 5));
+}
+
+// the first matching item wins, `None` if there is no match
+#[synth(inline)]
+fn find_target(arr: [U<4>; 4], target: U<4>) -> (Option<usize>, Option<U<4>>) {
+    (
+        arr.iter().position(|x| *x == target),
+        arr.into_iter().find(|x| *x > target),
+    )
+}
+
+#[test]
+fn position_and_find() {
+    let arr = [3_u8, 7, 5, 7].map(|x| x.cast::<U<4>>());
+
+    let (pos, found) = find_target(arr.clone(), 7_u8.cast());
+    assert_eq!(pos, Some(1));
+    assert_eq!(found, None);
+
+    let (pos, found) = find_target(arr, 4_u8.cast());
+    assert_eq!(pos, None);
+    assert_eq!(found.map(|x| x.cast::<u8>()), Some(7));
+}
+
+// `repeat(x).take(n)` builds the same array as the `[x; n]` literal
+#[synth(inline)]
+fn repeat_take(x: U<4>) -> [U<4>; 4] {
+    <[U<4>; 4]>::from_iter_exact(iter::repeat(x).take(4))
+}
+
+#[test]
+fn repeat_take_as_array() {
+    let x = 5_u8.cast::<U<4>>();
+
+    assert_eq!(repeat_take(x.clone()), [x.clone(), x.clone(), x.clone(), x]);
+}
+
+// the items are collected in order, `enumerate` yields the index of each item
+#[synth(inline)]
+fn collect_mapped(arr: [U<4>; 4]) -> ([U<4>; 4], [U<4>; 4]) {
+    (
+        <[U<4>; 4]>::from_iter_exact(
+            arr.clone().into_iter().map(|x| x + 1_u8.cast::<U<4>>()),
+        ),
+        <[U<4>; 4]>::from_iter_exact(
+            arr.into_iter()
+                .enumerate()
+                .map(|(i, x)| x + i.cast::<U<4>>()),
+        ),
+    )
+}
+
+#[test]
+fn from_iter_exact_map() {
+    let arr = [1_u8, 2, 3, 4].map(|x| x.cast::<U<4>>());
+
+    let (inc, shifted) = collect_mapped(arr);
+    assert_eq!(inc.map(|x| x.cast::<u8>()), [2, 3, 4, 5]);
+    assert_eq!(shifted.map(|x| x.cast::<u8>()), [1, 3, 5, 7]);
+}
+
+// the first item that breaks the fold is returned
+#[synth(inline)]
+fn first_greater(arr: [U<4>; 4], target: U<4>) -> Option<U<4>> {
+    let res = arr.try_fold((), |(), x| {
+        if x.clone() > target {
+            ControlFlow::Break(x)
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    match res {
+        ControlFlow::Break(x) => Some(x),
+        ControlFlow::Continue(()) => None,
+    }
+}
+
+#[test]
+fn try_fold_first_match() {
+    let arr = [3_u8, 9, 5, 12].map(|x| x.cast::<U<4>>());
+
+    assert_eq!(
+        first_greater(arr.clone(), 4_u8.cast()).map(|x| x.cast::<u8>()),
+        Some(9)
+    );
+    assert_eq!(
+        first_greater(arr.clone(), 9_u8.cast()).map(|x| x.cast::<u8>()),
+        Some(12)
+    );
+    assert_eq!(first_greater(arr, 12_u8.cast()), None);
+}
+
+// labeled `continue`/`break` end the iterations early: only a[0][0..2], a[1][0..2] and
+// a[2][0] are summed
+#[synth(inline)]
+fn sum_until_labeled_break(a: [[U<8>; 3]; 3]) -> U<8> {
+    let mut sum: U<8> = 0_u8.cast();
+    'outer: for (i, row) in a.into_iter().enumerate() {
+        for (j, x) in row.into_iter().enumerate() {
+            if j == 2 {
+                continue 'outer;
+            }
+            if i == 2 && j == 1 {
+                break 'outer;
+            }
+            sum = sum + x;
+        }
+    }
+    sum
+}
+
+#[test]
+fn nested_loops_labeled_break() {
+    let a =
+        [[1_u8, 2, 4], [8, 16, 32], [64, 128, 0]].map(|row| row.map(Cast::cast::<U<8>>));
+
+    assert_eq!(sum_until_labeled_break(a).cast::<u8>(), 1 + 2 + 8 + 16 + 64);
+}
@@ -0,0 +1,47 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use std::marker::PhantomData;
+
+use ferrum_hdl::{
+    bitpack::{BitPack, BitSize},
+    cast::Cast,
+    prelude::synth,
+    signal::SignalValue,
+    unsigned::U,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rx;
+
+// the marker field has no bits, so only `data` is packed
+#[derive(Debug, Clone, PartialEq, Eq, SignalValue, BitPack)]
+#[signal_value(bound = "D: Clone + 'static")]
+#[bitpack(bound = "D:")]
+pub struct Tagged<D> {
+    data: U<4>,
+    _d: PhantomData<D>,
+}
+
+// a top module passing the marker through
+#[synth(top)]
+pub fn inc_tagged(tagged: Tagged<Rx>) -> Tagged<Rx> {
+    Tagged {
+        data: tagged.data + 1_u8.cast::<U<4>>(),
+        _d: PhantomData,
+    }
+}
+
+#[test]
+fn marker_field_has_no_bits() {
+    assert_eq!(Tagged::<Rx>::BITS, 4);
+
+    let tagged = inc_tagged(Tagged {
+        data: 5_u8.cast(),
+        _d: PhantomData,
+    });
+    assert_eq!(tagged.clone().pack(), 6_u8.cast::<U<4>>());
+    assert_eq!(tagged.data.cast::<u8>(), 6);
+}
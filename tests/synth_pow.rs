@@ -0,0 +1,21 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{cast::Cast, prelude::synth, unsigned::U};
+
+// x^3 + 2x + 1, wrapping around at 8 bits
+#[synth(inline)]
+fn poly(x: U<8>) -> U<8> {
+    x.clone().pow::<3>() + x.clone() + x + 1
+}
+
+#[test]
+fn cube_in_polynomial() {
+    for x in [0_u8, 1, 2, 5, 7] {
+        let x3 = (x as u32).pow(3);
+        let expected = ((x3 + 2 * x as u32 + 1) % 256) as u8;
+        assert_eq!(poly(x.cast()).cast::<u8>(), expected);
+    }
+}
@@ -0,0 +1,50 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{
+    cast::Cast,
+    new_hdl::{Module, StateMut},
+    prelude::synth,
+    unsigned::U,
+};
+
+// synthesized with `--top-param W=..`
+pub struct TopMut<const W: usize>;
+
+impl<const W: usize> StateMut for TopMut<W> {}
+
+impl<const W: usize> TopMut<W> {
+    const OFFSET: u128 = (W / 2) as u128;
+}
+
+impl<const W: usize> Module for TopMut<W> {
+    type Input = (U<W>, U<W>);
+    type Output = U<W>;
+
+    fn logic(self, (a, b): Self::Input) -> Self::Output {
+        a + b + Self::OFFSET
+    }
+}
+
+#[test]
+fn top_at_two_widths() {
+    let narrow = TopMut::<4>.logic((10_u8.cast(), 3_u8.cast()));
+    assert_eq!(narrow.cast::<u8>(), 15);
+
+    let wide = TopMut::<8>.logic((250_u8.cast(), 3_u8.cast()));
+    assert_eq!(wide.cast::<u8>(), 1);
+}
+
+// synthesized with `--top-param 8`
+#[synth(top)]
+pub fn adder<const W: usize>(a: U<W>, b: U<W>) -> U<W> {
+    a + b
+}
+
+#[test]
+fn generic_adder_at_w8() {
+    let sum = adder::<8>(200_u8.cast(), 100_u8.cast());
+    assert_eq!(sum.cast::<u8>(), 44);
+}
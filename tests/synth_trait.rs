@@ -0,0 +1,65 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{
+    cast::Cast,
+    domain::{Clock, TD4},
+    prelude::synth,
+    signal::{reg0, Reset, Signal},
+    unsigned::U,
+};
+
+trait Step {
+    #[synth(inline)]
+    fn step(&self, val: U<4>) -> U<4>;
+
+    #[synth(inline)]
+    fn counter(&self, clk: &Clock<TD4>, rst: &Reset<TD4>) -> Signal<TD4, U<4>>
+    where
+        Self: Clone + 'static,
+    {
+        let this = self.clone();
+        reg0(clk, rst, move |val| this.step(val))
+    }
+}
+
+#[derive(Clone)]
+struct Inc;
+
+impl Step for Inc {
+    fn step(&self, val: U<4>) -> U<4> {
+        val + 1
+    }
+}
+
+#[derive(Clone)]
+struct Dec;
+
+impl Step for Dec {
+    fn step(&self, val: U<4>) -> U<4> {
+        val - 1
+    }
+
+    fn counter(&self, clk: &Clock<TD4>, rst: &Reset<TD4>) -> Signal<TD4, U<4>> {
+        reg0(clk, rst, |val: U<4>| val - 1)
+    }
+}
+
+fn simulate(step: impl Step + Clone + 'static) -> Vec<u8> {
+    let clk = Clock::<TD4>::new();
+    let rst = Reset::reset();
+
+    step.counter(&clk, &rst)
+        .simulate(&clk, 6)
+        .into_iter()
+        .map(Cast::cast)
+        .collect()
+}
+
+#[test]
+fn trait_methods() {
+    assert_eq!(simulate(Inc), [0, 0, 1, 1, 2, 2]);
+    assert_eq!(simulate(Dec), [0, 0, 15, 15, 14, 14]);
+}
@@ -0,0 +1,43 @@
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+#![feature(register_tool)]
+#![register_tool(fhdl_tool)]
+
+use ferrum_hdl::{
+    cast::Cast,
+    domain::{Clock, TD4},
+    prelude::synth,
+    signal::{Signal, SignalIterExt},
+    unsigned::U,
+};
+
+// the output follows the inputs in the same cycle
+#[synth(inline)]
+fn mac(
+    a: &Signal<TD4, U<8>>,
+    b: &Signal<TD4, U<8>>,
+    c: &Signal<TD4, U<8>>,
+) -> Signal<TD4, U<8>> {
+    a.zip3_with(b, c, |a, b, c| a * b + c)
+}
+
+fn signal(values: [u8; 4]) -> Signal<TD4, U<8>> {
+    values.into_iter().map(Cast::cast).into_signal()
+}
+
+#[test]
+fn combine_three_signals() {
+    let clk = Clock::new();
+    let (a, b, c) = ([1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12]);
+    let expected = (0 .. 4).map(|i| a[i] * b[i] + c[i]).collect::<Vec<u8>>();
+
+    let out = mac(&signal(a), &signal(b), &signal(c));
+
+    assert_eq!(
+        out.simulate(&clk, 4)
+            .into_iter()
+            .map(Cast::cast::<u8>)
+            .collect::<Vec<_>>(),
+        expected
+    );
+}